@@ -8,17 +8,21 @@ use crate::commands::configure::handle_configure;
 use crate::commands::info::handle_info;
 use crate::commands::mcp::run_server;
 use crate::commands::project::{handle_project_default, handle_projects_interactive};
-use crate::commands::recipe::{handle_deeplink, handle_validate};
+use crate::commands::recipe::{handle_deeplink, handle_test, handle_validate};
 // Import the new handlers from commands::schedule
 use crate::commands::schedule::{
     handle_schedule_add, handle_schedule_cron_help, handle_schedule_list, handle_schedule_remove,
     handle_schedule_run_now, handle_schedule_services_status, handle_schedule_services_stop,
     handle_schedule_sessions,
 };
-use crate::commands::session::{handle_session_list, handle_session_remove};
+use crate::commands::session::{
+    handle_session_branches, handle_session_fork, handle_session_list, handle_session_remove,
+    handle_session_search,
+};
 use crate::logging::setup_logging;
 use crate::recipes::extract_from_cli::extract_recipe_info_from_cli;
 use crate::recipes::recipe::{explain_recipe_with_parameters, load_recipe_content_as_template};
+use crate::recipes::recipe_steps::{run_steps_before_prompt, RecipeSteps};
 use crate::session;
 use crate::session::{build_session, SessionBuilderConfig, SessionSettings};
 use goose_bench::bench_config::BenchRunConfig;
@@ -76,6 +80,10 @@ fn parse_key_val(s: &str) -> Result<(String, String), String> {
     }
 }
 
+fn parse_extension_config(s: &str) -> Result<ExtensionConfig, String> {
+    serde_json::from_str(s).map_err(|e| format!("invalid extension config: {}", e))
+}
+
 #[derive(Subcommand)]
 enum SessionCommand {
     #[command(about = "List all available sessions")]
@@ -105,6 +113,52 @@ enum SessionCommand {
         #[arg(short, long, help = "Regex for removing matched sessions (optional)")]
         regex: Option<String>,
     },
+    #[command(about = "Search the history of all sessions")]
+    Search {
+        #[arg(help = "Words to search for in messages, tool calls and descriptions")]
+        query: String,
+
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of results to show",
+            default_value = "20"
+        )]
+        limit: usize,
+
+        #[arg(
+            short,
+            long,
+            help = "Output format (text, json)",
+            default_value = "text"
+        )]
+        format: String,
+    },
+    #[command(about = "Fork a session from one of its messages into a new branch")]
+    Fork {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(
+            long = "at",
+            value_name = "MESSAGE_INDEX",
+            help = "Number of messages to keep in the new branch",
+            long_help = "Index of the first message to leave out. The new branch keeps every message before it, so you can continue from that point with a different prompt."
+        )]
+        at: usize,
+
+        #[arg(
+            long = "branch",
+            value_name = "NAME",
+            help = "Name for the new branch (default: <session>-branch-<n>)"
+        )]
+        branch: Option<String>,
+    },
+    #[command(about = "List the branches of a session with their usage")]
+    Branches {
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+    },
     #[command(about = "Export a session to Markdown format")]
     Export {
         #[command(flatten)]
@@ -246,6 +300,22 @@ enum RecipeCommand {
         )]
         recipe_name: String,
     },
+
+    /// Run a recipe's tests against a scripted model
+    #[command(about = "Run recipe tests against a scripted model")]
+    Test {
+        /// Test file listing the recipe, parameters, model script and expectations
+        #[arg(help = "path to the recipe test file")]
+        test_file: PathBuf,
+
+        /// Write the results as a JUnit XML report
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Write the results as JUnit XML to this file"
+        )]
+        junit: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -519,6 +589,18 @@ enum Command {
         )]
         builtins: Vec<String>,
 
+        /// Add extensions from their full config
+        #[arg(
+            long = "with-extension-config",
+            value_name = "JSON",
+            help = "Add an extension from its JSON config (internal use)",
+            long_help = "Internal parameter used by sub-recipe tasks to pass an extension with its command, arguments and environment intact. Can be specified multiple times.",
+            action = clap::ArgAction::Append,
+            value_parser = parse_extension_config,
+            hide = true
+        )]
+        extension_configs: Vec<ExtensionConfig>,
+
         /// Quiet mode - suppress non-response output
         #[arg(
             short = 'q',
@@ -548,8 +630,8 @@ enum Command {
         additional_sub_recipes: Vec<String>,
     },
 
-    /// Recipe utilities for validation and deeplinking
-    #[command(about = "Recipe utilities for validation and deeplinking")]
+    /// Recipe utilities for validation, deeplinking and testing
+    #[command(about = "Recipe utilities for validation, deeplinking and testing")]
     Recipe {
         #[command(subcommand)]
         command: RecipeCommand,
@@ -624,6 +706,7 @@ pub struct InputConfig {
     pub contents: Option<String>,
     pub extensions_override: Option<Vec<ExtensionConfig>>,
     pub additional_system_prompt: Option<String>,
+    pub recipe_steps: Option<RecipeSteps>,
 }
 
 pub async fn cli() -> Result<()> {
@@ -671,6 +754,26 @@ pub async fn cli() -> Result<()> {
                     handle_session_remove(id, regex)?;
                     return Ok(());
                 }
+                Some(SessionCommand::Search {
+                    query,
+                    limit,
+                    format,
+                }) => {
+                    handle_session_search(&query, limit, format)?;
+                    Ok(())
+                }
+                Some(SessionCommand::Fork {
+                    identifier,
+                    at,
+                    branch,
+                }) => {
+                    handle_session_fork(identifier.map(extract_identifier), at, branch).await?;
+                    Ok(())
+                }
+                Some(SessionCommand::Branches { identifier }) => {
+                    handle_session_branches(identifier.map(extract_identifier))?;
+                    Ok(())
+                }
                 Some(SessionCommand::Export { identifier, output }) => {
                     let session_identifier = if let Some(id) = identifier {
                         extract_identifier(id)
@@ -697,6 +800,7 @@ pub async fn cli() -> Result<()> {
                         extensions,
                         remote_extensions,
                         builtins,
+                        extension_configs: Vec::new(),
                         extensions_override: None,
                         additional_system_prompt: None,
                         settings: None,
@@ -755,6 +859,7 @@ pub async fn cli() -> Result<()> {
             extensions,
             remote_extensions,
             builtins,
+            extension_configs,
             params,
             explain,
             render_recipe,
@@ -762,7 +867,7 @@ pub async fn cli() -> Result<()> {
             quiet,
             additional_sub_recipes,
         }) => {
-            let (input_config, session_settings, sub_recipes, final_output_response) = match (
+            let (mut input_config, session_settings, sub_recipes, final_output_response) = match (
                 instructions,
                 input_text,
                 recipe,
@@ -778,6 +883,7 @@ pub async fn cli() -> Result<()> {
                             contents: Some(input),
                             extensions_override: None,
                             additional_system_prompt: system,
+                            recipe_steps: None,
                         },
                         None,
                         None,
@@ -797,6 +903,7 @@ pub async fn cli() -> Result<()> {
                             contents: Some(contents),
                             extensions_override: None,
                             additional_system_prompt: None,
+                            recipe_steps: None,
                         },
                         None,
                         None,
@@ -808,6 +915,7 @@ pub async fn cli() -> Result<()> {
                         contents: Some(text),
                         extensions_override: None,
                         additional_system_prompt: system,
+                        recipe_steps: None,
                     },
                     None,
                     None,
@@ -835,6 +943,25 @@ pub async fn cli() -> Result<()> {
                 }
             };
 
+            if let Some(steps) = input_config.recipe_steps.take() {
+                let context = run_steps_before_prompt(steps, quiet)
+                    .await
+                    .unwrap_or_else(|err| {
+                        eprintln!("{}: {}", console::style("Error").red().bold(), err);
+                        std::process::exit(1);
+                    });
+                input_config.contents = input_config
+                    .contents
+                    .map(|prompt| context.substitute(&prompt));
+                input_config.additional_system_prompt = input_config
+                    .additional_system_prompt
+                    .map(|instructions| context.substitute(&instructions));
+                if input_config.contents.is_none() && !interactive {
+                    // A recipe of only steps is done once they have run
+                    return Ok(());
+                }
+            }
+
             let mut session = build_session(SessionBuilderConfig {
                 identifier: identifier.map(extract_identifier),
                 resume,
@@ -842,6 +969,7 @@ pub async fn cli() -> Result<()> {
                 extensions,
                 remote_extensions,
                 builtins,
+                extension_configs,
                 extensions_override: input_config.extensions_override,
                 additional_system_prompt: input_config.additional_system_prompt,
                 settings: session_settings,
@@ -947,6 +1075,9 @@ pub async fn cli() -> Result<()> {
                 RecipeCommand::Deeplink { recipe_name } => {
                     handle_deeplink(&recipe_name)?;
                 }
+                RecipeCommand::Test { test_file, junit } => {
+                    handle_test(&test_file, junit.as_deref()).await?;
+                }
             }
             return Ok(());
         }
@@ -967,6 +1098,7 @@ pub async fn cli() -> Result<()> {
                     extensions: Vec::new(),
                     remote_extensions: Vec::new(),
                     builtins: Vec::new(),
+                    extension_configs: Vec::new(),
                     extensions_override: None,
                     additional_system_prompt: None,
                     settings: None::<SessionSettings>,
@@ -38,6 +38,7 @@ pub async fn agent_generator(
         extensions: requirements.external,
         remote_extensions: requirements.remote,
         builtins: requirements.builtin,
+        extension_configs: Vec::new(),
         extensions_override: None,
         additional_system_prompt: None,
         settings: None,
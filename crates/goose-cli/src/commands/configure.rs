@@ -14,6 +14,8 @@ use goose::config::{
     PermissionManager,
 };
 use goose::message::Message;
+use goose::permission::permission_rules::WORKSPACE_PLACEHOLDER;
+use goose::permission::{ArgumentCondition, ArgumentMatcher, PermissionRule, RulePattern};
 use goose::providers::{create, providers};
 use mcp_core::tool::ToolAnnotations;
 use mcp_core::Tool;
@@ -841,6 +843,11 @@ pub async fn configure_settings_dialog() -> Result<(), Box<dyn Error>> {
             "Tool Permission",
             "Set permission for individual tool of enabled extensions",
         )
+        .item(
+            "permission_rules",
+            "Permission Rules",
+            "Allow, ask or deny tool calls based on their arguments",
+        )
         .item(
             "tool_output",
             "Tool Output",
@@ -878,6 +885,9 @@ pub async fn configure_settings_dialog() -> Result<(), Box<dyn Error>> {
         "tool_permission" => {
             configure_tool_permissions_dialog().await.and(Ok(()))?;
         }
+        "permission_rules" => {
+            configure_permission_rules_dialog()?;
+        }
         "tool_output" => {
             configure_tool_output_dialog()?;
         }
@@ -1221,6 +1231,172 @@ pub async fn configure_tool_permissions_dialog() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn describe_permission_rule(rule: &PermissionRule) -> String {
+    let level = match rule.level {
+        PermissionLevel::AlwaysAllow => "always allow",
+        PermissionLevel::AskBefore => "ask before",
+        PermissionLevel::NeverAllow => "never allow",
+    };
+    let conditions: Vec<String> = rule
+        .when
+        .iter()
+        .map(|condition| {
+            let (kind, patterns) = match &condition.matcher {
+                ArgumentMatcher::CommandPrefix(p) => ("starts with", p.join(", ")),
+                ArgumentMatcher::Glob(p) => ("matches glob", p.clone()),
+                ArgumentMatcher::Regex(p) => ("matches regex", p.as_str().to_string()),
+                ArgumentMatcher::PathUnder(p) => ("is under", p.join(", ")),
+                ArgumentMatcher::UrlHost(p) => ("has host", p.join(", ")),
+            };
+            format!("{} {} {}", condition.argument, kind, patterns)
+        })
+        .collect();
+    let when = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" when {}", conditions.join(" and "))
+    };
+    format!(
+        "{} {}{} (priority {})",
+        level, rule.tool, when, rule.priority
+    )
+}
+
+fn split_patterns(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+pub fn configure_permission_rules_dialog() -> Result<(), Box<dyn Error>> {
+    let mut permission_manager = PermissionManager::default();
+    let mut rules = permission_manager.get_user_rules();
+
+    if rules.is_empty() {
+        cliclack::log::info("No permission rules configured yet.")?;
+    } else {
+        let listing: Vec<String> = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| format!("{}. {}", i + 1, describe_permission_rule(rule)))
+            .collect();
+        cliclack::log::info(listing.join("\n"))?;
+    }
+
+    let mut action = cliclack::select("What would you like to do?").item(
+        "add",
+        "Add a rule",
+        "Match a tool and conditions on its arguments",
+    );
+    if !rules.is_empty() {
+        action = action.item("remove", "Remove a rule", "");
+    }
+    let action = action.interact()?;
+
+    if action == "remove" {
+        let index = cliclack::select("Choose a rule to remove")
+            .items(
+                &rules
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rule)| (i, describe_permission_rule(rule), ""))
+                    .collect::<Vec<_>>(),
+            )
+            .interact()?;
+        rules.remove(index);
+        permission_manager.set_user_rules(rules);
+        cliclack::outro("Removed permission rule")?;
+        return Ok(());
+    }
+
+    let tool: String = cliclack::input("Which tool does the rule apply to?")
+        .placeholder("developer__shell")
+        .interact()?;
+
+    let mut when = Vec::new();
+    loop {
+        let prompt = if when.is_empty() {
+            "Add a condition on the tool's arguments?"
+        } else {
+            "Add another condition?"
+        };
+        if !cliclack::confirm(prompt)
+            .initial_value(when.is_empty())
+            .interact()?
+        {
+            break;
+        }
+        let argument: String = cliclack::input("Which argument should be checked?")
+            .placeholder("command")
+            .interact()?;
+        let kind = cliclack::select("How should it match?")
+            .item(
+                "command_prefix",
+                "Command prefix",
+                "e.g. git status, cargo test",
+            )
+            .item("path_under", "Path under directory", "e.g. $WORKSPACE")
+            .item("url_host", "URL host", "e.g. github.com, *.example.com")
+            .item("glob", "Glob", "e.g. src/**/*.rs")
+            .item("regex", "Regex", "")
+            .interact()?;
+        let mut input = cliclack::input("Pattern (separate several with commas)");
+        if kind == "path_under" {
+            input = input.default_input(WORKSPACE_PLACEHOLDER);
+        }
+        if kind == "regex" {
+            input = input.validate(
+                |input: &String| match RulePattern::new(input.trim()).error() {
+                    Some(_) => Err("Please enter a valid regex"),
+                    None => Ok(()),
+                },
+            );
+        }
+        let patterns: String = input.interact()?;
+        let matcher = match kind {
+            "command_prefix" => ArgumentMatcher::CommandPrefix(split_patterns(&patterns)),
+            "path_under" => ArgumentMatcher::PathUnder(split_patterns(&patterns)),
+            "url_host" => ArgumentMatcher::UrlHost(split_patterns(&patterns)),
+            "glob" => ArgumentMatcher::Glob(patterns.trim().to_string()),
+            "regex" => ArgumentMatcher::Regex(RulePattern::new(patterns.trim())),
+            _ => unreachable!(),
+        };
+        when.push(ArgumentCondition { argument, matcher });
+    }
+
+    let level = match cliclack::select("What should happen when the rule matches?")
+        .item("always_allow", "Always Allow", "Run without asking")
+        .item("ask_before", "Ask Before", "Prompt before running")
+        .item("never_allow", "Never Allow", "Refuse to run")
+        .interact()?
+    {
+        "always_allow" => PermissionLevel::AlwaysAllow,
+        "ask_before" => PermissionLevel::AskBefore,
+        "never_allow" => PermissionLevel::NeverAllow,
+        _ => unreachable!(),
+    };
+
+    let priority: i32 = cliclack::input("Priority (higher wins when several rules match)")
+        .default_input("0")
+        .interact()?;
+
+    let rule = PermissionRule {
+        tool,
+        when,
+        level,
+        priority,
+    };
+    rule.validate()?;
+    let description = describe_permission_rule(&rule);
+    rules.push(rule);
+    permission_manager.set_user_rules(rules);
+
+    cliclack::outro(format!("Added permission rule: {}", description))?;
+    Ok(())
+}
+
 fn configure_recipe_dialog() -> Result<(), Box<dyn Error>> {
     let key_name = GOOSE_RECIPE_GITHUB_REPO_CONFIG_KEY;
     let config = Config::global();
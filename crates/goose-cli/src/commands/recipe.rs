@@ -3,6 +3,8 @@ use base64::Engine;
 use console::style;
 
 use crate::recipes::recipe::load_recipe;
+use crate::recipes::test_recipe::{run_recipe_tests, TestOutcome};
+use std::path::Path;
 
 /// Validates a recipe file
 ///
@@ -16,8 +18,11 @@ use crate::recipes::recipe::load_recipe;
 pub fn handle_validate(recipe_name: &str) -> Result<()> {
     // Load and validate the recipe file
     match load_recipe(recipe_name) {
-        Ok(_) => {
+        Ok(recipe) => {
             println!("{} recipe file is valid", style("✓").green().bold());
+            println!();
+            println!("{}", style("Resolved recipe:").bold());
+            print!("{}", serde_yaml::to_string(&recipe)?);
             Ok(())
         }
         Err(err) => {
@@ -61,6 +66,72 @@ pub fn handle_deeplink(recipe_name: &str) -> Result<String> {
     }
 }
 
+/// Runs the tests in a recipe test file against a scripted model
+///
+/// # Arguments
+///
+/// * `test_file` - Path to the test file
+/// * `junit` - Where to write a JUnit XML report, if anywhere
+///
+/// # Returns
+///
+/// An error if any test failed or couldn't be run
+pub async fn handle_test(test_file: &Path, junit: Option<&Path>) -> Result<()> {
+    let suite = run_recipe_tests(test_file).await?;
+    for result in &suite.results {
+        let seconds = result.duration.as_secs_f64();
+        match &result.outcome {
+            TestOutcome::Passed => {
+                println!(
+                    "{} {} ({:.2}s)",
+                    style("✓").green().bold(),
+                    result.name,
+                    seconds
+                )
+            }
+            TestOutcome::Failed(failures) => {
+                println!(
+                    "{} {} ({:.2}s)",
+                    style("✗").red().bold(),
+                    result.name,
+                    seconds
+                );
+                for failure in failures {
+                    println!("    {}", failure);
+                }
+            }
+            TestOutcome::Error(error) => {
+                println!(
+                    "{} {} ({:.2}s)",
+                    style("!").red().bold(),
+                    result.name,
+                    seconds
+                );
+                println!("    {}", error);
+            }
+        }
+    }
+
+    if let Some(junit) = junit {
+        std::fs::write(junit, suite.to_junit_xml())?;
+    }
+
+    let failed = suite.failures() + suite.errors();
+    println!(
+        "\n{} passed, {} failed",
+        suite.results.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} recipe tests failed",
+            failed,
+            suite.results.len()
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,9 +1,9 @@
-use crate::session::message_to_markdown;
+use crate::session::{message_to_markdown, render_branches};
 use crate::utils::safe_truncate;
 use anyhow::{Context, Result};
 use cliclack::{confirm, multiselect, select};
 use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
-use goose::session::{self, Identifier};
+use goose::session::{self, Identifier, SessionSearchResult};
 use regex::Regex;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -22,8 +22,8 @@ pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
 
     if should_delete {
         for session in sessions {
-            fs::remove_file(session.path.clone())
-                .with_context(|| format!("Failed to remove session file '{}'", session.path))?;
+            session::remove_session(Path::new(&session.path))
+                .with_context(|| format!("Failed to remove session '{}'", session.path))?;
             println!("Session `{}` removed.", session.id);
         }
     } else {
@@ -152,10 +152,16 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
                     } else {
                         &metadata.description
                     };
-                    let output = format!("{} - {} - {}", id, description, modified);
+                    let mut output = format!("{} - {} - {}", id, description, modified);
+                    if let Some(cost) = metadata.accumulated_cost {
+                        output.push_str(&format!(" - ${:.4}", cost));
+                    }
                     if verbose {
                         println!("  {}", output);
                         println!("    Path: {}", path);
+                        if let Some(tokens) = metadata.accumulated_total_tokens {
+                            println!("    Tokens: {}", tokens);
+                        }
                     } else {
                         println!("{}", output);
                     }
@@ -166,6 +172,89 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
     Ok(())
 }
 
+pub fn handle_session_search(query: &str, limit: usize, format: String) -> Result<()> {
+    let results = session::search_sessions(query, limit).map_err(|e| {
+        tracing::error!("Failed to search sessions: {:?}", e);
+        anyhow::anyhow!("Failed to search sessions: {}", e)
+    })?;
+
+    match format.as_str() {
+        "json" => {
+            println!("{}", serde_json::to_string(&results)?);
+        }
+        _ => {
+            if results.is_empty() {
+                println!("No sessions match '{}'", query);
+                return Ok(());
+            }
+            for SessionSearchResult {
+                session_id,
+                description,
+                message_index,
+                role,
+                snippet,
+                ..
+            } in results
+            {
+                let description = if description.is_empty() {
+                    "(none)"
+                } else {
+                    &description
+                };
+                let location = match (message_index, role) {
+                    (Some(index), Some(role)) => format!("message {} ({})", index + 1, role),
+                    _ => "description".to_string(),
+                };
+                println!("{} - {} - {}", session_id, description, location);
+                println!("    {}", snippet.replace('\n', " "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The session file for `identifier`, or the most recent session if none was given
+fn existing_session_path(identifier: Option<Identifier>) -> Result<PathBuf> {
+    let path = match identifier {
+        Some(identifier) => session::get_path(identifier)?,
+        None => session::get_most_recent_session()?,
+    };
+    if !session::session_exists(&path) {
+        return Err(anyhow::anyhow!(
+            "Session file not found (expected path: {})",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+pub async fn handle_session_fork(
+    identifier: Option<Identifier>,
+    message_index: usize,
+    branch: Option<String>,
+) -> Result<()> {
+    let source = existing_session_path(identifier)?;
+    let forked = session::fork_session(&source, message_index, branch.as_deref()).await?;
+    let name = forked
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    println!(
+        "Forked {} at message {} into `{}`.",
+        source.display(),
+        message_index,
+        name
+    );
+    println!("Continue it with: goose session --resume --name {}", name);
+    Ok(())
+}
+
+pub fn handle_session_branches(identifier: Option<Identifier>) -> Result<()> {
+    let path = existing_session_path(identifier)?;
+    render_branches(&session::list_branches(&path)?, Some(&path));
+    Ok(())
+}
+
 /// Export a session to Markdown without creating a full Session object
 ///
 /// This function directly reads messages from the session file and converts them to Markdown
@@ -179,7 +268,7 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
         }
     };
 
-    if !session_file_path.exists() {
+    if !goose::session::session_exists(&session_file_path) {
         return Err(anyhow::anyhow!(
             "Session file not found (expected path: {})",
             session_file_path.display()
@@ -614,6 +614,9 @@ async fn process_message_streaming(
                             }
                         }
                     }
+                    Ok(AgentEvent::MessageDelta(_)) => {
+                        // The web interface renders complete messages only
+                    }
                     Ok(AgentEvent::McpNotification(_notification)) => {
                         // Handle MCP notifications if needed
                         // For now, we'll just log them
@@ -623,6 +626,14 @@ async fn process_message_streaming(
                         // Log model change
                         tracing::info!("Model changed to {} in {} mode", model, mode);
                     }
+                    Ok(AgentEvent::HistoryReplaced(compacted)) => {
+                        let current_messages = {
+                            let mut session_msgs = session_messages.lock().await;
+                            *session_msgs = compacted;
+                            session_msgs.clone()
+                        };
+                        session::persist_messages(&session_file, &current_messages, None).await?;
+                    }
 
                     Err(e) => {
                         error!("Error in message stream: {}", e);
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use anyhow::Result;
+use goose::recipe::resolve_extends;
+
+use crate::recipes::recipe::RECIPE_FILE_EXTENSIONS;
+use crate::recipes::search_recipe::{retrieve_recipe_file, RecipeFile};
+
+/// Retrieve a recipe file with the base recipes it `extends` merged into its content
+///
+/// A recipe that extends nothing is returned as it was. Otherwise the content becomes the
+/// merged recipe as YAML, still unrendered, so parameters are applied to the result as usual.
+/// Each base's `{{ recipe_dir }}` is filled in with the base's own directory.
+pub fn retrieve_extended_recipe_file(recipe_name: &str) -> Result<RecipeFile> {
+    let recipe_file = retrieve_recipe_file(recipe_name)?;
+    let merged = resolve_extends(
+        &recipe_file.content,
+        &recipe_file.file_path,
+        |base_name, recipe_dir| {
+            let base = retrieve_base_recipe_file(base_name, recipe_dir)?;
+            Ok((base.content, base.file_path))
+        },
+    )?;
+    match merged {
+        Some(recipe) => Ok(RecipeFile {
+            content: serde_yaml::to_string(&recipe)?,
+            ..recipe_file
+        }),
+        None => Ok(recipe_file),
+    }
+}
+
+/// Paths to recipe files are relative to the extending recipe, anything else is looked up
+/// by name like `goose run --recipe`, locally or in the configured GitHub repo
+fn retrieve_base_recipe_file(base_name: &str, recipe_dir: &Path) -> Result<RecipeFile> {
+    let is_recipe_file = RECIPE_FILE_EXTENSIONS
+        .iter()
+        .any(|ext| base_name.ends_with(&format!(".{}", ext)));
+    if is_recipe_file && !base_name.starts_with('~') {
+        let path = recipe_dir.join(base_name);
+        return retrieve_recipe_file(&path.to_string_lossy());
+    }
+    retrieve_recipe_file(base_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::recipe::Recipe;
+    use std::fs;
+
+    #[test]
+    fn test_extends_merges_bases_relative_to_recipe() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::write(
+            dir.path().join("shared/base.yaml"),
+            r#"
+version: 1.0.0
+title: Base
+description: Shared setup
+instructions: Read {{ recipe_dir }}/style.md and write {{ language }}.
+parameters:
+  - key: language
+    input_type: string
+    requirement: optional
+    default: rust
+    description: The language
+"#,
+        )
+        .unwrap();
+        let child_path = dir.path().join("child.yaml");
+        fs::write(
+            &child_path,
+            r#"
+version: 1.0.0
+title: Child
+description: Reviews code
+extends: shared/base.yaml
+instructions: Review the pull request in {{ repo }}.
+parameters:
+  - key: repo
+    input_type: string
+    requirement: required
+    description: The repository
+"#,
+        )
+        .unwrap();
+
+        let resolved = retrieve_extended_recipe_file(child_path.to_str().unwrap()).unwrap();
+        let recipe = Recipe::from_content(&resolved.content).unwrap();
+        let base_dir = dir.path().join("shared").canonicalize().unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            format!(
+                "Read {}/style.md and write {{{{ language }}}}.\n\nReview the pull request in {{{{ repo }}}}.",
+                base_dir.display()
+            )
+        );
+        assert_eq!(recipe.parameters.unwrap().len(), 2);
+        assert!(recipe.extends.is_none());
+    }
+
+    #[test]
+    fn test_extends_cycle_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, base) in [("a", "b"), ("b", "a")] {
+            fs::write(
+                dir.path().join(format!("{}.yaml", name)),
+                format!(
+                    "version: 1.0.0\ntitle: {}\ndescription: d\ninstructions: i\nextends: {}.yaml\n",
+                    name, base
+                ),
+            )
+            .unwrap();
+        }
+
+        let path = dir.path().join("a.yaml");
+        let err = retrieve_extended_recipe_file(path.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("extends itself"));
+    }
+}
@@ -1,10 +1,13 @@
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
-use goose::recipe::{Response, SubRecipe};
+use goose::recipe::{Recipe, Response, SubRecipe};
 
+use crate::recipes::recipe_steps::RecipeSteps;
 use crate::recipes::search_recipe::retrieve_recipe_file;
-use crate::{cli::InputConfig, recipes::recipe::load_recipe_as_template, session::SessionSettings};
+use crate::{
+    cli::InputConfig, recipes::recipe::load_recipe_and_values_as_template, session::SessionSettings,
+};
 
 #[allow(clippy::type_complexity)]
 pub fn extract_recipe_info_from_cli(
@@ -17,10 +20,11 @@ pub fn extract_recipe_info_from_cli(
     Option<Vec<SubRecipe>>,
     Option<Response>,
 )> {
-    let recipe = load_recipe_as_template(&recipe_name, params).unwrap_or_else(|err| {
-        eprintln!("{}: {}", console::style("Error").red().bold(), err);
-        std::process::exit(1);
-    });
+    let (recipe, values) =
+        load_recipe_and_values_as_template(&recipe_name, params).unwrap_or_else(|err| {
+            eprintln!("{}: {}", console::style("Error").red().bold(), err);
+            std::process::exit(1);
+        });
     let mut all_sub_recipes = recipe.sub_recipes.clone().unwrap_or_default();
     if !additional_sub_recipes.is_empty() {
         for sub_recipe_name in additional_sub_recipes {
@@ -45,16 +49,27 @@ pub fn extract_recipe_info_from_cli(
             }
         }
     }
+    let recipe_steps = recipe.steps.is_some().then(|| RecipeSteps {
+        recipe: Recipe {
+            sub_recipes: Some(all_sub_recipes.clone()),
+            ..recipe.clone()
+        },
+        params: values,
+    });
     Ok((
         InputConfig {
             contents: recipe.prompt,
             extensions_override: recipe.extensions,
             additional_system_prompt: recipe.instructions,
+            recipe_steps,
         },
         recipe.settings.map(|s| SessionSettings {
             goose_provider: s.goose_provider,
             goose_model: s.goose_model,
             temperature: s.temperature,
+            sandbox: s.sandbox,
+            compaction: s.compaction,
+            budget: s.budget,
         }),
         Some(all_sub_recipes),
         recipe.response,
@@ -79,6 +94,8 @@ fn extract_recipe_name(recipe_identifier: &str) -> String {
 mod tests {
     use std::path::PathBuf;
 
+    use goose::context_mgmt::compaction::CompactionStrategy;
+
     use tempfile::TempDir;
 
     use super::*;
@@ -98,12 +115,23 @@ mod tests {
             Some("test_instructions my_value".to_string())
         );
         assert!(input_config.extensions_override.is_none());
+        assert!(input_config.recipe_steps.is_none());
 
         assert!(settings.is_some());
         let settings = settings.unwrap();
         assert_eq!(settings.goose_provider, Some("test_provider".to_string()));
         assert_eq!(settings.goose_model, Some("test_model".to_string()));
         assert_eq!(settings.temperature, Some(0.7));
+        let sandbox = settings.sandbox.unwrap();
+        assert!(sandbox.enabled);
+        assert!(!sandbox.allow_network);
+        assert_eq!(sandbox.writable_paths, vec!["/opt/cache".to_string()]);
+        let compaction = settings.compaction.unwrap();
+        assert_eq!(compaction.strategy, CompactionStrategy::KeepPinned);
+        assert_eq!(compaction.auto_threshold, Some(0.8));
+        let budget = settings.budget.unwrap();
+        assert_eq!(budget.max_cost, Some(2.5));
+        assert_eq!(budget.max_tokens, None);
 
         assert!(sub_recipes.is_some());
         let sub_recipes = sub_recipes.unwrap();
@@ -200,6 +228,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_recipe_info_from_cli_with_steps() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let recipe_path = temp_dir.path().join("release.yaml");
+        std::fs::write(
+            &recipe_path,
+            r#"
+title: release
+description: Release to each region
+instructions: Release {{ version }}
+parameters:
+- key: version
+  description: version
+  input_type: string
+  requirement: required
+- key: regions
+  description: regions
+  input_type: list
+  requirement: optional
+  default: us, eu
+sub_recipes:
+- name: deploy
+  path: deploy.yaml
+steps:
+- name: deploy
+  sub_recipe: deploy
+  for_each: regions
+  values:
+    region: ${item}
+"#,
+        )
+        .unwrap();
+        let params = vec![("version".to_string(), "1.2".to_string())];
+
+        let (input_config, _, _, _) = extract_recipe_info_from_cli(
+            recipe_path.to_str().unwrap().to_string(),
+            params,
+            Vec::new(),
+        )
+        .unwrap();
+
+        assert!(input_config.contents.is_none());
+        let steps = input_config.recipe_steps.unwrap();
+        assert_eq!(steps.recipe.steps.unwrap().len(), 1);
+        assert_eq!(steps.recipe.sub_recipes.unwrap()[0].name, "deploy");
+        assert_eq!(steps.params["regions"], "us, eu");
+        assert_eq!(steps.params["version"], "1.2");
+    }
+
     fn create_recipe() -> (TempDir, PathBuf) {
         let test_recipe_content = r#"
 title: test_recipe
@@ -215,6 +292,13 @@ settings:
   goose_provider: test_provider
   goose_model: test_model
   temperature: 0.7
+  sandbox:
+    writable_paths: [/opt/cache]
+  compaction:
+    strategy: keep_pinned
+    auto_threshold: 0.8
+  budget:
+    max_cost: 2.5
 sub_recipes:
 - path: existing_sub_recipe.yaml
   name: existing_sub_recipe        
@@ -1,6 +1,9 @@
+pub mod extend_recipe;
 pub mod extract_from_cli;
 pub mod github_recipe;
 pub mod print_recipe;
 pub mod recipe;
+pub mod recipe_steps;
 pub mod search_recipe;
 pub mod template_recipe;
+pub mod test_recipe;
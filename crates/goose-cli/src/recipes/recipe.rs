@@ -1,35 +1,49 @@
+use crate::recipes::extend_recipe::retrieve_extended_recipe_file;
 use crate::recipes::print_recipe::{
     missing_parameters_command_line, print_parameters_with_values, print_recipe_explanation,
     print_required_parameters_for_template,
 };
-use crate::recipes::search_recipe::{retrieve_recipe_file, RecipeFile};
+use crate::recipes::search_recipe::RecipeFile;
 use crate::recipes::template_recipe::{
-    parse_recipe_content, render_recipe_content_with_params, render_recipe_for_preview,
+    deferred_placeholder, parse_recipe_content, render_recipe_content_with_params,
+    render_recipe_for_preview, substitute_deferred_values,
 };
 use anyhow::Result;
 use console::style;
-use goose::recipe::{Recipe, RecipeParameter, RecipeParameterRequirement};
+use goose::recipe::{
+    step_parameter_references, validate_parameter_definitions, validate_parameter_values,
+    validate_steps, Recipe, RecipeParameter, RecipeParameterInputType, RecipeParameterRequirement,
+};
 use std::collections::{HashMap, HashSet};
 
-pub const BUILT_IN_RECIPE_DIR_PARAM: &str = "recipe_dir";
+pub use goose::recipe::BUILT_IN_RECIPE_DIR_PARAM;
 pub const RECIPE_FILE_EXTENSIONS: &[&str] = &["yaml", "json"];
 
 pub fn load_recipe_content_as_template(
     recipe_name: &str,
     params: Vec<(String, String)>,
 ) -> Result<String> {
+    render_recipe_with_parameter_values(recipe_name, params).map(|(content, _)| content)
+}
+
+/// The rendered recipe content, and the parameter values it was rendered with
+fn render_recipe_with_parameter_values(
+    recipe_name: &str,
+    params: Vec<(String, String)>,
+) -> Result<(String, HashMap<String, String>)> {
     let RecipeFile {
         content: recipe_file_content,
         parent_dir: recipe_parent_dir,
         ..
-    } = retrieve_recipe_file(recipe_name)?;
+    } = retrieve_extended_recipe_file(recipe_name)?;
     let recipe_dir_str = recipe_parent_dir
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Error getting recipe directory"))?;
     let recipe_parameters = validate_recipe_parameters(&recipe_file_content, recipe_dir_str)?;
+    let recipe_parameters = recipe_parameters.unwrap_or_default();
 
     let (params_for_template, missing_params) =
-        apply_values_to_parameters(&params, recipe_parameters, recipe_dir_str, true)?;
+        apply_values_to_parameters(&params, &recipe_parameters, recipe_dir_str, true)?;
 
     if !missing_params.is_empty() {
         return Err(anyhow::anyhow!(
@@ -38,23 +52,63 @@ pub fn load_recipe_content_as_template(
         ));
     }
 
-    render_recipe_content_with_params(&recipe_file_content, &params_for_template)
+    let mut template_params = params_for_template.clone();
+    let file_contents = defer_file_contents(&recipe_parameters, &mut template_params);
+    let content = render_recipe_content_with_params(&recipe_file_content, &template_params)?;
+    let content = substitute_deferred_values(&content, &file_contents)?;
+    Ok((content, params_for_template))
+}
+
+/// Swap each file parameter's content for a placeholder, returning the contents by placeholder
+///
+/// The contents are put back once the rendered recipe is parsed, so a file can hold anything
+/// without breaking the recipe's YAML or JSON.
+fn defer_file_contents(
+    recipe_parameters: &[RecipeParameter],
+    params: &mut HashMap<String, String>,
+) -> HashMap<String, String> {
+    recipe_parameters
+        .iter()
+        .filter(|param| matches!(param.input_type, RecipeParameterInputType::File))
+        .filter_map(|param| {
+            let value = params.get_mut(&param.key)?;
+            let placeholder = deferred_placeholder(&param.key);
+            let content = std::mem::replace(value, placeholder.clone());
+            Some((placeholder, content))
+        })
+        .collect()
 }
 
 fn validate_recipe_parameters(
     recipe_file_content: &str,
     recipe_dir_str: &str,
 ) -> Result<Option<Vec<RecipeParameter>>> {
-    let (raw_recipe, template_variables) =
+    let (raw_recipe, mut template_variables) =
         parse_recipe_content(recipe_file_content, recipe_dir_str.to_string())?;
+    validate_steps(&raw_recipe)?;
+    if let Some(steps) = &raw_recipe.steps {
+        // Parameters may be used only by the steps, outside of the template
+        template_variables.extend(step_parameter_references(steps));
+    }
     let recipe_parameters = raw_recipe.parameters;
     validate_optional_parameters(&recipe_parameters)?;
+    validate_parameter_definitions(recipe_parameters.as_deref().unwrap_or_default())?;
     validate_parameters_in_template(&recipe_parameters, &template_variables)?;
     Ok(recipe_parameters)
 }
 
 pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>) -> Result<Recipe> {
-    let rendered_content = load_recipe_content_as_template(recipe_name, params.clone())?;
+    load_recipe_and_values_as_template(recipe_name, params).map(|(recipe, _)| recipe)
+}
+
+/// Like [`load_recipe_as_template`], also returning the parameter values, including defaults,
+/// that the recipe was rendered with
+pub fn load_recipe_and_values_as_template(
+    recipe_name: &str,
+    params: Vec<(String, String)>,
+) -> Result<(Recipe, HashMap<String, String>)> {
+    let (rendered_content, values) =
+        render_recipe_with_parameter_values(recipe_name, params.clone())?;
     let recipe = Recipe::from_content(&rendered_content)?;
 
     // Display information about the loaded recipe
@@ -70,7 +124,7 @@ pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>)
         print_parameters_with_values(params.into_iter().collect());
     }
     println!();
-    Ok(recipe)
+    Ok((recipe, values))
 }
 
 pub fn load_recipe(recipe_name: &str) -> Result<Recipe> {
@@ -78,7 +132,7 @@ pub fn load_recipe(recipe_name: &str) -> Result<Recipe> {
         content: recipe_file_content,
         parent_dir: recipe_parent_dir,
         ..
-    } = retrieve_recipe_file(recipe_name)?;
+    } = retrieve_extended_recipe_file(recipe_name)?;
     let recipe_dir_str = recipe_parent_dir
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Error getting recipe directory"))?;
@@ -106,19 +160,28 @@ pub fn explain_recipe_with_parameters(
         content: recipe_file_content,
         parent_dir: recipe_parent_dir,
         ..
-    } = retrieve_recipe_file(recipe_name)?;
+    } = retrieve_extended_recipe_file(recipe_name)?;
     let recipe_dir_str = recipe_parent_dir
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Error getting recipe directory"))?;
     let recipe_parameters = validate_recipe_parameters(&recipe_file_content, recipe_dir_str)?;
+    let recipe_parameters = recipe_parameters.unwrap_or_default();
 
     let (params_for_template, missing_params) =
-        apply_values_to_parameters(&params, recipe_parameters, recipe_dir_str, false)?;
+        apply_values_to_parameters(&params, &recipe_parameters, recipe_dir_str, false)?;
+    let mut preview_params = params_for_template.clone();
+    let file_contents = defer_file_contents(&recipe_parameters, &mut preview_params);
     let recipe = render_recipe_for_preview(
         &recipe_file_content,
         recipe_dir_str.to_string(),
-        &params_for_template,
+        &preview_params,
     )?;
+    let recipe = if file_contents.is_empty() {
+        recipe
+    } else {
+        let content = serde_yaml::to_string(&recipe)?;
+        Recipe::from_content(&substitute_deferred_values(&content, &file_contents)?)?
+    };
     print_recipe_explanation(&recipe);
     print_required_parameters_for_template(params_for_template, missing_params);
 
@@ -197,7 +260,7 @@ fn validate_optional_parameters(parameters: &Option<Vec<RecipeParameter>>) -> Re
 
 fn apply_values_to_parameters(
     user_params: &[(String, String)],
-    recipe_parameters: Option<Vec<RecipeParameter>>,
+    recipe_parameters: &[RecipeParameter],
     recipe_parent_dir: &str,
     enable_user_prompt: bool,
 ) -> Result<(HashMap<String, String>, Vec<String>)> {
@@ -207,16 +270,12 @@ fn apply_values_to_parameters(
         recipe_parent_dir.to_string(),
     );
     let mut missing_params: Vec<String> = Vec::new();
-    for param in recipe_parameters.unwrap_or_default() {
+    for param in recipe_parameters {
         if !param_map.contains_key(&param.key) {
             match (&param.default, &param.requirement) {
                 (Some(default), _) => param_map.insert(param.key.clone(), default.clone()),
                 (None, RecipeParameterRequirement::UserPrompt) if enable_user_prompt => {
-                    let input_value = cliclack::input(format!(
-                        "Please enter {} ({})",
-                        param.key, param.description
-                    ))
-                    .interact()?;
+                    let input_value = prompt_for_parameter(param)?;
                     param_map.insert(param.key.clone(), input_value)
                 }
                 _ => {
@@ -226,9 +285,24 @@ fn apply_values_to_parameters(
             };
         }
     }
+    let param_map = validate_parameter_values(recipe_parameters, &param_map)?;
     Ok((param_map, missing_params))
 }
 
+fn prompt_for_parameter(param: &RecipeParameter) -> Result<String> {
+    let prompt = format!("Please enter {} ({})", param.key, param.description);
+    match (&param.input_type, &param.options) {
+        (RecipeParameterInputType::Enum, Some(options)) => {
+            let mut select = cliclack::select(prompt);
+            for option in options {
+                select = select.item(option.clone(), option, "");
+            }
+            Ok(select.interact()?)
+        }
+        _ => Ok(cliclack::input(prompt).interact()?),
+    }
+}
+
 fn validate_json_schema(schema: &serde_json::Value) -> Result<()> {
     match jsonschema::validator_for(schema) {
         Ok(_) => Ok(()),
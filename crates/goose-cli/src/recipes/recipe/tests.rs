@@ -311,5 +311,84 @@ mod tests {
                 "is_enabled"
             );
         }
+
+        #[test]
+        fn test_load_recipe_as_template_validates_typed_parameters() {
+            let (_temp_dir, recipe_path) = setup_recipe_file(
+                r#""instructions": "Deploy to {{ env }} with {{ notes }}",
+                "parameters": [
+                    {
+                        "key": "env",
+                        "input_type": "enum",
+                        "requirement": "required",
+                        "description": "Where to deploy",
+                        "options": ["dev", "prod"]
+                    },
+                    {
+                        "key": "notes",
+                        "input_type": "file",
+                        "requirement": "required",
+                        "description": "Release notes"
+                    }
+                ]"#,
+            );
+            let notes_path = recipe_path.with_file_name("notes.md");
+            std::fs::write(&notes_path, "fixed the bug").unwrap();
+            let notes = notes_path.to_str().unwrap().to_string();
+
+            let recipe = load_recipe_as_template(
+                recipe_path.to_str().unwrap(),
+                vec![
+                    ("env".to_string(), "prod".to_string()),
+                    ("notes".to_string(), notes.clone()),
+                ],
+            )
+            .unwrap();
+            assert_eq!(
+                recipe.instructions.unwrap(),
+                "Deploy to prod with fixed the bug"
+            );
+
+            let err = load_recipe_as_template(
+                recipe_path.to_str().unwrap(),
+                vec![
+                    ("env".to_string(), "staging".to_string()),
+                    ("notes".to_string(), notes),
+                ],
+            )
+            .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("'env': 'staging' is not one of: dev, prod"));
+        }
+
+        #[test]
+        fn test_load_recipe_as_template_inserts_multi_line_file_into_yaml() {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let recipe_path = temp_dir.path().join("review.yaml");
+            std::fs::write(
+                &recipe_path,
+                r#"version: 1.0.0
+title: Review
+description: Review the notes
+instructions: Review {{ notes }}
+parameters:
+  - key: notes
+    input_type: file
+    requirement: required
+    description: Release notes
+"#,
+            )
+            .unwrap();
+            let notes = "status: \"done\"\n- fixed: the bug\n";
+            std::fs::write(temp_dir.path().join("notes.md"), notes).unwrap();
+
+            let recipe = load_recipe_as_template(
+                recipe_path.to_str().unwrap(),
+                vec![("notes".to_string(), "notes.md".to_string())],
+            )
+            .unwrap();
+            assert_eq!(recipe.instructions.unwrap(), format!("Review {}", notes));
+        }
     }
 }
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use console::style;
+use goose::agents::sub_recipe_execution_tool::steps::{run_recipe_steps, StepResult};
+use goose::recipe::{Recipe, StepContext, STEP_STATUS_FAILED, STEP_STATUS_SKIPPED};
+
+/// A recipe with `steps`, and the parameter values the steps can refer to
+#[derive(Debug)]
+pub struct RecipeSteps {
+    pub recipe: Recipe,
+    pub params: HashMap<String, String>,
+}
+
+/// Run the recipe's steps before the session starts
+///
+/// Returns what the prompt and instructions can refer to with `${steps.<name>.output}` and
+/// `${steps.<name>.status}`, or an error if a step failed without `continue_on_error`.
+pub async fn run_steps_before_prompt(steps: RecipeSteps, quiet: bool) -> Result<StepContext> {
+    let run = run_recipe_steps(&steps.recipe, &steps.params).await?;
+    if !quiet {
+        for result in &run.results {
+            print_step_result(result);
+        }
+        println!();
+    }
+    match run.stop_error() {
+        Some(e) => Err(e),
+        None => Ok(run.context),
+    }
+}
+
+fn print_step_result(result: &StepResult) {
+    let status = match result.status.as_str() {
+        STEP_STATUS_FAILED => style(&result.status).red(),
+        STEP_STATUS_SKIPPED => style(&result.status).dim(),
+        _ => style(&result.status).green(),
+    };
+    println!("{} {} {}", style("Step").bold(), result.name, status);
+}
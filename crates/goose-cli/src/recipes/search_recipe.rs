@@ -9,6 +9,7 @@ use super::github_recipe::{retrieve_recipe_from_github, GOOSE_RECIPE_GITHUB_REPO
 
 const GOOSE_RECIPE_PATH_ENV_VAR: &str = "GOOSE_RECIPE_PATH";
 
+#[derive(Debug)]
 pub struct RecipeFile {
     pub content: String,
     pub parent_dir: PathBuf,
@@ -110,6 +110,51 @@ pub fn render_recipe_for_preview(
     Recipe::from_content(&rendered_content)
 }
 
+/// The text rendered in place of a parameter whose value is only set once the recipe is parsed
+pub fn deferred_placeholder(key: &str) -> String {
+    format!("__goose_deferred_{}__", key)
+}
+
+/// Put the values back in place of their placeholders, in every string of the parsed recipe
+///
+/// Values set this way can hold quotes, colons or several lines without changing the
+/// structure of the recipe around them.
+pub fn substitute_deferred_values(
+    rendered_content: &str,
+    values: &HashMap<String, String>,
+) -> Result<String> {
+    if values.is_empty() {
+        return Ok(rendered_content.to_string());
+    }
+    let mut recipe: serde_yaml::Value = serde_yaml::from_str(rendered_content)?;
+    substitute_in_value(&mut recipe, values);
+    Ok(serde_yaml::to_string(&recipe)?)
+}
+
+fn substitute_in_value(value: &mut serde_yaml::Value, values: &HashMap<String, String>) {
+    match value {
+        serde_yaml::Value::String(text) => {
+            for (placeholder, replacement) in values {
+                if text.contains(placeholder.as_str()) {
+                    *text = text.replace(placeholder.as_str(), replacement);
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                substitute_in_value(item, values);
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                substitute_in_value(item, values);
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => substitute_in_value(&mut tagged.value, values),
+        _ => {}
+    }
+}
+
 fn preserve_vars(variables: &HashSet<String>) -> HashMap<String, String> {
     let mut context = HashMap::<String, String>::new();
     for template_var in variables {
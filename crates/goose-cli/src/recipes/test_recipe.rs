@@ -0,0 +1,606 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use goose::agents::final_output_tool::FINAL_OUTPUT_CONTINUATION_MESSAGE;
+use goose::agents::{Agent, AgentEvent};
+use goose::message::{Message, MessageContent};
+use goose::permission::permission_confirmation::PrincipalType;
+use goose::permission::{Permission, PermissionConfirmation};
+use goose::providers::scripted::ScriptedProvider;
+use goose::recipe::Recipe;
+use goose::session::storage;
+use mcp_core::role::Role;
+use mcp_core::tool::ToolCall;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::recipes::recipe::load_recipe_content_as_template;
+
+/// A file of tests for one recipe, run with `goose recipe test`
+#[derive(Debug, Deserialize)]
+pub struct RecipeTestFile {
+    /// The recipe under test, relative to the test file
+    pub recipe: String,
+    pub tests: Vec<RecipeTest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecipeTest {
+    pub name: String,
+    /// Parameter values to load the recipe with
+    #[serde(default)]
+    pub params: HashMap<String, Value>,
+    /// The first user message, the recipe's prompt if unset
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// A directory copied into the test's working directory before it runs
+    #[serde(default)]
+    pub fixtures: Option<String>,
+    /// The model's turns, in order
+    #[serde(default)]
+    pub script: Vec<ScriptedTurn>,
+    /// A session file whose assistant messages are replayed as the model's turns, instead
+    /// of a script
+    #[serde(default)]
+    pub transcript: Option<String>,
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+/// One response of the model: some text, tool calls, or both
+#[derive(Debug, Deserialize)]
+pub struct ScriptedTurn {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ScriptedToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScriptedToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Expectations {
+    /// Tools that must be called in this order, other calls may come in between
+    #[serde(default)]
+    pub tool_calls: Vec<String>,
+    /// Text the final output must contain
+    #[serde(default)]
+    pub output_contains: Vec<String>,
+    /// Every file created, changed or removed, relative to the working directory
+    #[serde(default)]
+    pub files_changed: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum TestOutcome {
+    Passed,
+    /// The expectations that weren't met
+    Failed(Vec<String>),
+    /// The test couldn't be run
+    Error(String),
+}
+
+#[derive(Debug)]
+pub struct RecipeTestResult {
+    pub name: String,
+    pub duration: Duration,
+    pub outcome: TestOutcome,
+}
+
+/// The results of a test file, named after its recipe
+pub struct RecipeTestSuite {
+    pub name: String,
+    pub results: Vec<RecipeTestResult>,
+}
+
+impl RecipeTestSuite {
+    pub fn failures(&self) -> usize {
+        self.count(|outcome| matches!(outcome, TestOutcome::Failed(_)))
+    }
+
+    pub fn errors(&self) -> usize {
+        self.count(|outcome| matches!(outcome, TestOutcome::Error(_)))
+    }
+
+    fn count(&self, filter: impl Fn(&TestOutcome) -> bool) -> usize {
+        self.results.iter().filter(|r| filter(&r.outcome)).count()
+    }
+
+    /// The results as a JUnit XML report
+    pub fn to_junit_xml(&self) -> String {
+        let time: Duration = self.results.iter().map(|r| r.duration).sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            self.results.len(),
+            self.failures(),
+            self.errors(),
+            time.as_secs_f64()
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            xml_escape(&self.name),
+            self.results.len(),
+            self.failures(),
+            self.errors(),
+            time.as_secs_f64()
+        ));
+        for result in &self.results {
+            let open = format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                xml_escape(&result.name),
+                xml_escape(&self.name),
+                result.duration.as_secs_f64()
+            );
+            match &result.outcome {
+                TestOutcome::Passed => xml.push_str(&format!("{} />\n", open)),
+                TestOutcome::Failed(failures) => xml.push_str(&format!(
+                    "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                    open,
+                    xml_escape(&failures[0]),
+                    xml_escape(&failures.join("\n"))
+                )),
+                TestOutcome::Error(error) => xml.push_str(&format!(
+                    "{}>\n      <error message=\"{}\" />\n    </testcase>\n",
+                    open,
+                    xml_escape(error)
+                )),
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Run every test in a test file against a scripted model, each in a working directory
+/// of its own
+///
+/// The recipe's extensions run as they would in a session, so tool calls in the script
+/// really happen, but nothing is sent to a model provider.
+pub async fn run_recipe_tests(test_file: &Path) -> Result<RecipeTestSuite> {
+    let content = fs::read_to_string(test_file)
+        .map_err(|e| anyhow!("Failed to read test file {}: {}", test_file.display(), e))?;
+    let spec: RecipeTestFile = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow!("Invalid test file {}: {}", test_file.display(), e))?;
+    let test_dir = test_file
+        .canonicalize()?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let recipe_path = test_dir.join(&spec.recipe).canonicalize().map_err(|e| {
+        anyhow!(
+            "Failed to find recipe {} for {}: {}",
+            spec.recipe,
+            test_file.display(),
+            e
+        )
+    })?;
+
+    let mut results = Vec::new();
+    for test in &spec.tests {
+        let started = Instant::now();
+        let outcome = match run_test(test, &recipe_path, &test_dir).await {
+            Ok(failures) if failures.is_empty() => TestOutcome::Passed,
+            Ok(failures) => TestOutcome::Failed(failures),
+            Err(e) => TestOutcome::Error(e.to_string()),
+        };
+        results.push(RecipeTestResult {
+            name: test.name.clone(),
+            duration: started.elapsed(),
+            outcome,
+        });
+    }
+    Ok(RecipeTestSuite {
+        name: recipe_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| spec.recipe.clone()),
+        results,
+    })
+}
+
+/// Puts the process back in its working directory when dropped
+struct WorkingDirGuard(PathBuf);
+
+impl Drop for WorkingDirGuard {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.0);
+    }
+}
+
+async fn run_test(test: &RecipeTest, recipe_path: &Path, test_dir: &Path) -> Result<Vec<String>> {
+    let responses = match &test.transcript {
+        Some(transcript) => replayed_responses(&test_dir.join(transcript))?,
+        None => scripted_responses(&test.script),
+    };
+
+    let work_dir = tempfile::tempdir()?;
+    if let Some(fixtures) = &test.fixtures {
+        copy_dir(&test_dir.join(fixtures), work_dir.path())?;
+    }
+    let before = snapshot(work_dir.path())?;
+
+    // Extensions run in the working directory of the process, and file parameters are
+    // read relative to it, so both see the fixtures. Tests that run recipe tests must be
+    // `#[serial]` for this.
+    let _guard = WorkingDirGuard(std::env::current_dir()?);
+    std::env::set_current_dir(work_dir.path())?;
+
+    let params = test
+        .params
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key.clone(), s.clone()),
+            other => (key.clone(), other.to_string()),
+        })
+        .collect();
+    let recipe_name = recipe_path.to_string_lossy();
+    let recipe = Recipe::from_content(&load_recipe_content_as_template(&recipe_name, params)?)?;
+    let prompt = test
+        .prompt
+        .clone()
+        .or_else(|| recipe.prompt.clone())
+        .ok_or_else(|| anyhow!("The recipe has no prompt, so the test needs one"))?;
+
+    let agent = Agent::new();
+    agent
+        .update_provider(Arc::new(ScriptedProvider::new(responses)))
+        .await?;
+    for extension in recipe.extensions.clone().unwrap_or_default() {
+        let name = extension.name();
+        agent
+            .add_extension(extension)
+            .await
+            .map_err(|e| anyhow!("Failed to start extension {}: {}", name, e))?;
+    }
+    if let Some(instructions) = &recipe.instructions {
+        agent.extend_system_prompt(instructions.clone()).await;
+    }
+    if let Some(sub_recipes) = recipe.sub_recipes.clone() {
+        agent.add_sub_recipes(sub_recipes).await;
+    }
+    if let Some(response) = recipe.response.clone() {
+        if response.json_schema.is_some() {
+            agent.add_final_output_tool(response).await;
+        }
+    }
+
+    let mut failures = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut output = None;
+    let messages = vec![Message::user().with_text(prompt)];
+    let mut stream = agent.reply(&messages, None).await?;
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(AgentEvent::Message(message)) => {
+                if let Some(MessageContent::ToolConfirmationRequest(request)) =
+                    message.content.first()
+                {
+                    agent
+                        .handle_confirmation(
+                            request.id.clone(),
+                            PermissionConfirmation {
+                                principal_type: PrincipalType::Tool,
+                                permission: Permission::AllowOnce,
+                            },
+                        )
+                        .await;
+                    continue;
+                }
+                if message.role != Role::Assistant {
+                    continue;
+                }
+                for content in &message.content {
+                    if let MessageContent::ToolRequest(request) = content {
+                        if let Ok(call) = &request.tool_call {
+                            tool_calls.push(call.name.clone());
+                        }
+                    }
+                }
+                let text = message.as_concat_text();
+                if !text.is_empty() {
+                    output = Some(text);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                failures.push(format!("The agent stopped with an error: {}", e));
+                break;
+            }
+        }
+    }
+    drop(stream);
+
+    check_tool_calls(&test.expect.tool_calls, &tool_calls, &mut failures);
+    // With a response schema the output is what the model passed to the final output tool
+    let final_output = agent.final_output().await;
+    if let Some(schema) = recipe
+        .response
+        .as_ref()
+        .and_then(|r| r.json_schema.as_ref())
+    {
+        match &final_output {
+            Some(final_output) => check_output_schema(schema, final_output, &mut failures),
+            None => failures.push(
+                "Expected a final output matching the response schema, the final output tool \
+                 was never called with one"
+                    .to_string(),
+            ),
+        }
+    }
+    let output = final_output.or(output).unwrap_or_default();
+    for expected in &test.expect.output_contains {
+        if !output.contains(expected.as_str()) {
+            failures.push(format!(
+                "Expected the output to contain '{}', got '{}'",
+                expected, output
+            ));
+        }
+    }
+    if let Some(expected) = &test.expect.files_changed {
+        let changed = changed_files(&before, &snapshot(work_dir.path())?);
+        let mut expected = expected.clone();
+        expected.sort();
+        if changed != expected {
+            failures.push(format!(
+                "Expected files changed [{}], got [{}]",
+                expected.join(", "),
+                changed.join(", ")
+            ));
+        }
+    }
+    Ok(failures)
+}
+
+fn scripted_responses(script: &[ScriptedTurn]) -> Vec<Message> {
+    script
+        .iter()
+        .enumerate()
+        .map(|(turn, scripted)| {
+            let mut message = Message::assistant();
+            if let Some(text) = &scripted.text {
+                message = message.with_text(text);
+            }
+            for (i, call) in scripted.tool_calls.iter().enumerate() {
+                message = message.with_tool_request(
+                    format!("call_{}_{}", turn, i),
+                    Ok(ToolCall::new(&call.name, call.arguments.clone())),
+                );
+            }
+            message
+        })
+        .collect()
+}
+
+/// The model's turns in a recorded session, leaving out the messages the agent added itself
+fn replayed_responses(transcript: &Path) -> Result<Vec<Message>> {
+    let messages = storage::read_messages(transcript)
+        .map_err(|e| anyhow!("Failed to read transcript {}: {}", transcript.display(), e))?;
+    Ok(messages
+        .into_iter()
+        .filter(|m| m.role == Role::Assistant)
+        .filter(|m| m.as_concat_text() != FINAL_OUTPUT_CONTINUATION_MESSAGE)
+        .collect())
+}
+
+fn check_tool_calls(expected: &[String], actual: &[String], failures: &mut Vec<String>) {
+    let mut remaining = actual.iter();
+    for name in expected {
+        if !remaining.any(|call| call == name) {
+            failures.push(format!(
+                "Expected tool calls [{}] in that order, got [{}]",
+                expected.join(", "),
+                actual.join(", ")
+            ));
+            return;
+        }
+    }
+}
+
+fn check_output_schema(schema: &Value, output: &str, failures: &mut Vec<String>) {
+    let value: Value = match serde_json::from_str(output) {
+        Ok(value) => value,
+        Err(e) => {
+            failures.push(format!("The output is not JSON ({}): '{}'", e, output));
+            return;
+        }
+    };
+    match jsonschema::validator_for(schema) {
+        Ok(validator) => {
+            for error in validator.iter_errors(&value) {
+                failures.push(format!(
+                    "The output does not match the response schema at '{}': {}",
+                    error.instance_path, error
+                ));
+            }
+        }
+        Err(e) => failures.push(format!("The response schema is invalid: {}", e)),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)
+        .map_err(|e| anyhow!("Failed to read fixtures {}: {}", from.display(), e))?
+    {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// The content of every file under `dir`, by path relative to it
+fn snapshot(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    fn visit(root: &Path, dir: &Path, files: &mut BTreeMap<String, Vec<u8>>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(root, &path, files)?;
+            } else {
+                let relative = path
+                    .strip_prefix(root)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                files.insert(relative, fs::read(&path)?);
+            }
+        }
+        Ok(())
+    }
+    let mut files = BTreeMap::new();
+    visit(dir, dir, &mut files)?;
+    Ok(files)
+}
+
+/// Files created, changed or removed, sorted
+fn changed_files(
+    before: &BTreeMap<String, Vec<u8>>,
+    after: &BTreeMap<String, Vec<u8>>,
+) -> Vec<String> {
+    let mut changed: Vec<String> = after
+        .iter()
+        .filter(|(path, content)| before.get(*path) != Some(*content))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    const RECIPE: &str = r#"
+version: 1.0.0
+title: Release notes
+description: Writes release notes
+prompt: Write the notes for {{ version }}
+parameters:
+  - key: version
+    input_type: string
+    requirement: required
+    description: The version
+response:
+  json_schema:
+    type: object
+    properties:
+      summary:
+        type: string
+    required: [summary]
+"#;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_run_recipe_tests_with_script() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.yaml"), RECIPE).unwrap();
+        let test_file = dir.path().join("notes.test.yaml");
+        fs::write(
+            &test_file,
+            r#"
+recipe: notes.yaml
+tests:
+  - name: writes a summary
+    params: { version: 1.2 }
+    script:
+      - text: Here are the notes
+        tool_calls:
+          - name: recipe__final_output
+            arguments: { summary: "Fixed the 1.2 bugs" }
+      - text: Done
+    expect:
+      tool_calls: [recipe__final_output]
+      output_contains: ["1.2 bugs"]
+      files_changed: []
+  - name: expects a tool that isn't called
+    params: { version: "1.3" }
+    script:
+      - tool_calls:
+          - name: recipe__final_output
+            arguments: { wrong: true }
+    expect:
+      tool_calls: [developer__shell]
+"#,
+        )
+        .unwrap();
+
+        let suite = run_recipe_tests(&test_file).await.unwrap();
+        assert_eq!(suite.name, "notes");
+        assert!(
+            matches!(suite.results[0].outcome, TestOutcome::Passed),
+            "{:?}",
+            suite.results[0].outcome
+        );
+        match &suite.results[1].outcome {
+            TestOutcome::Failed(failures) => {
+                assert!(failures
+                    .iter()
+                    .any(|f| f.starts_with("Expected tool calls [developer__shell]")));
+                assert!(failures
+                    .iter()
+                    .any(|f| f.starts_with("The agent stopped with an error")));
+                assert!(failures
+                    .iter()
+                    .any(|f| f.starts_with("Expected a final output")));
+            }
+            other => panic!("expected a failure, got {:?}", other),
+        }
+
+        let xml = suite.to_junit_xml();
+        assert!(xml.contains("<testsuite name=\"notes\" tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(xml.contains("<testcase name=\"writes a summary\" classname=\"notes\""));
+        assert!(xml.contains("<failure message=\"Expected tool calls [developer__shell]"));
+    }
+
+    #[test]
+    fn test_changed_files_and_tool_call_order() {
+        let before = BTreeMap::from([
+            ("a.txt".to_string(), b"a".to_vec()),
+            ("b.txt".to_string(), b"b".to_vec()),
+        ]);
+        let after = BTreeMap::from([
+            ("a.txt".to_string(), b"changed".to_vec()),
+            ("c/d.txt".to_string(), b"new".to_vec()),
+        ]);
+        assert_eq!(
+            changed_files(&before, &after),
+            vec!["a.txt", "b.txt", "c/d.txt"]
+        );
+
+        let actual = vec!["shell".to_string(), "edit".to_string(), "shell".to_string()];
+        let mut failures = Vec::new();
+        check_tool_calls(&["edit".into(), "shell".into()], &actual, &mut failures);
+        assert!(failures.is_empty());
+        check_tool_calls(&["edit".into(), "edit".into()], &actual, &mut failures);
+        assert_eq!(failures.len(), 1);
+    }
+}
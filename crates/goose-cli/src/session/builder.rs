@@ -1,7 +1,8 @@
 use console::style;
 use goose::agents::extension::ExtensionError;
 use goose::agents::Agent;
-use goose::config::{Config, ExtensionConfig, ExtensionConfigManager};
+use goose::config::{Budget, Config, ExtensionConfig, ExtensionConfigManager, ShellSandbox};
+use goose::context_mgmt::compaction::CompactionSettings;
 use goose::providers::create;
 use goose::recipe::{Response, SubRecipe};
 use goose::session;
@@ -31,6 +32,8 @@ pub struct SessionBuilderConfig {
     pub remote_extensions: Vec<String>,
     /// List of builtin extension commands to add
     pub builtins: Vec<String>,
+    /// Extensions to add from their full config
+    pub extension_configs: Vec<ExtensionConfig>,
     /// List of extensions to enable, enable only this set and ignore configured ones
     pub extensions_override: Option<Vec<ExtensionConfig>>,
     /// Any additional system prompt to append to the default
@@ -160,6 +163,9 @@ pub struct SessionSettings {
     pub goose_model: Option<String>,
     pub goose_provider: Option<String>,
     pub temperature: Option<f32>,
+    pub sandbox: Option<ShellSandbox>,
+    pub compaction: Option<CompactionSettings>,
+    pub budget: Option<Budget>,
 }
 
 pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
@@ -187,6 +193,50 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
 
     // Create the agent
     let agent: Agent = Agent::new();
+    let sandbox = session_config
+        .settings
+        .as_ref()
+        .and_then(|s| s.sandbox.clone());
+    agent.set_shell_sandbox(sandbox).await;
+    let compaction = session_config
+        .settings
+        .as_ref()
+        .and_then(|s| s.compaction.clone());
+    agent.set_compaction(compaction).await;
+    let budget = session_config
+        .settings
+        .as_ref()
+        .and_then(|s| s.budget.clone());
+    let max_cost = budget
+        .clone()
+        .or_else(Budget::from_config)
+        .and_then(|budget| budget.max_cost);
+    agent.set_budget(budget).await;
+
+    // Load model prices so session costs can be tracked, in the background unless a cost
+    // budget needs them up front
+    let load_pricing = async {
+        if let Err(e) = goose::providers::pricing::initialize_pricing_cache().await {
+            tracing::debug!("Failed to load pricing data: {}", e);
+        }
+    };
+    if let Some(max_cost) = max_cost {
+        load_pricing.await;
+        let pricing =
+            goose::providers::pricing::find_model_pricing(Some(&provider_name), &model_name).await;
+        if pricing.is_none() {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "Warning: No pricing is known for {} on {}, so the budget of ${:.2} can't limit what this session costs",
+                    model_name, provider_name, max_cost
+                ))
+                .yellow()
+            );
+        }
+    } else {
+        tokio::spawn(load_pricing);
+    }
     if let Some(sub_recipes) = session_config.sub_recipes {
         agent.add_sub_recipes(sub_recipes).await;
     }
@@ -195,6 +245,22 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         agent.add_final_output_tool(final_output_response).await;
     }
 
+    // Routing picks the model for each turn, so an explicitly chosen provider or model goes unused
+    let explicit_model = session_config
+        .settings
+        .as_ref()
+        .is_some_and(|s| s.goose_provider.is_some() || s.goose_model.is_some());
+    if explicit_model && goose::providers::routing::RoutingConfig::from_config().is_some() {
+        tracing::warn!(
+            "Ignoring the provider and model given for this session: model_routing is configured"
+        );
+        eprintln!(
+            "{}",
+            style("Warning: model_routing is configured, so the provider and model given for this session are ignored")
+                .yellow()
+        );
+    }
+
     let new_provider = match create(&provider_name, model_config) {
         Ok(provider) => provider,
         Err(e) => {
@@ -248,7 +314,7 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
                 }
                 Ok(path) => path,
             };
-            if !session_file.exists() {
+            if !session::session_exists(&session_file) {
                 output::render_error(&format!(
                     "Cannot resume session {} - no such session exists",
                     style(session_file.display()).cyan()
@@ -476,6 +542,25 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         }
     }
 
+    // Add extensions passed with their full config
+    for extension in session_config.extension_configs {
+        let name = extension.name();
+        if let Err(e) = session.add_extension_config(extension).await {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "Warning: Failed to start extension '{}': {}",
+                    name, e
+                ))
+                .yellow()
+            );
+            eprintln!(
+                "{}",
+                style(format!("Continuing without extension '{}'", name)).yellow()
+            );
+        }
+    }
+
     // Add CLI-specific system prompt extension
     session
         .agent
@@ -520,6 +605,7 @@ mod tests {
             extensions: vec!["echo test".to_string()],
             remote_extensions: vec!["http://example.com".to_string()],
             builtins: vec!["developer".to_string()],
+            extension_configs: Vec::new(),
             extensions_override: None,
             additional_system_prompt: Some("Test prompt".to_string()),
             settings: None,
@@ -21,6 +21,8 @@ pub enum InputResult {
     Clear,
     Recipe(Option<String>),
     Summarize,
+    Rewind(usize),
+    Branch(Option<String>),
 }
 
 #[derive(Debug)]
@@ -96,6 +98,8 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
     const CMD_CLEAR: &str = "/clear";
     const CMD_RECIPE: &str = "/recipe";
     const CMD_SUMMARIZE: &str = "/summarize";
+    const CMD_REWIND: &str = "/rewind";
+    const CMD_BRANCH: &str = "/branch";
 
     match input {
         "/exit" | "/quit" => Some(InputResult::Exit),
@@ -156,6 +160,13 @@ fn handle_slash_command(input: &str) -> Option<InputResult> {
         s if s == CMD_CLEAR => Some(InputResult::Clear),
         s if s.starts_with(CMD_RECIPE) => parse_recipe_command(s),
         s if s == CMD_SUMMARIZE => Some(InputResult::Summarize),
+        s if s == CMD_REWIND || s.starts_with("/rewind ") => {
+            parse_rewind_command(s[CMD_REWIND.len()..].trim())
+        }
+        s if s == CMD_BRANCH => Some(InputResult::Branch(None)),
+        s if s.starts_with("/branch ") => Some(InputResult::Branch(Some(
+            s[CMD_BRANCH.len()..].trim().to_string(),
+        ))),
         _ => None,
     }
 }
@@ -185,6 +196,22 @@ fn parse_recipe_command(s: &str) -> Option<InputResult> {
     Some(InputResult::Recipe(Some(filepath.to_string())))
 }
 
+fn parse_rewind_command(args: &str) -> Option<InputResult> {
+    if args.is_empty() {
+        return Some(InputResult::Rewind(1));
+    }
+    match args.parse::<usize>() {
+        Ok(turns) if turns > 0 => Some(InputResult::Rewind(turns)),
+        _ => {
+            println!(
+                "{}",
+                console::style("Usage: /rewind [number of prompts to go back]").red()
+            );
+            Some(InputResult::Retry)
+        }
+    }
+}
+
 fn parse_prompts_command(args: &str) -> Option<InputResult> {
     let parts: Vec<String> = shlex::split(args).unwrap_or_default();
 
@@ -266,6 +293,9 @@ fn print_help() {
 /recipe [filepath] - Generate a recipe from the current conversation and save it to the specified filepath (must end with .yaml).
                        If no filepath is provided, it will be saved to ./recipe.yaml.
 /summarize - Summarize the current conversation to reduce context length while preserving key information.
+/rewind [n] - Go back to before your last n prompts (default 1). The conversation so far is kept as a branch.
+/branch - List the branches of this session with their token usage.
+/branch <name> - Switch to another branch of this session.
 /? or /help - Display this help message
 /clear - Clears the current chat history
 
@@ -511,4 +541,31 @@ mod tests {
         let result = handle_slash_command("  /summarize  ");
         assert!(matches!(result, Some(InputResult::Summarize)));
     }
+
+    #[test]
+    fn test_rewind_and_branch_commands() {
+        assert!(matches!(
+            handle_slash_command("/rewind"),
+            Some(InputResult::Rewind(1))
+        ));
+        assert!(matches!(
+            handle_slash_command("/rewind 3"),
+            Some(InputResult::Rewind(3))
+        ));
+        assert!(matches!(
+            handle_slash_command("/rewind 0"),
+            Some(InputResult::Retry)
+        ));
+        assert!(handle_slash_command("/rewinds").is_none());
+
+        assert!(matches!(
+            handle_slash_command("/branch"),
+            Some(InputResult::Branch(None))
+        ));
+        if let Some(InputResult::Branch(Some(name))) = handle_slash_command("/branch  alt ") {
+            assert_eq!(name, "alt");
+        } else {
+            panic!("Expected branch with name");
+        }
+    }
 }
@@ -7,6 +7,7 @@ mod prompt;
 mod thinking;
 
 pub use self::export::message_to_markdown;
+pub use self::output::render_branches;
 pub use builder::{build_session, SessionBuilderConfig, SessionSettings};
 use console::Color;
 use goose::agents::AgentEvent;
@@ -16,6 +17,7 @@ use goose::permission::PermissionConfirmation;
 use goose::providers::base::Provider;
 pub use goose::session::Identifier;
 
+use crate::utils::safe_truncate;
 use anyhow::{Context, Result};
 use completion::GooseCompleter;
 use etcetera::{choose_app_strategy, AppStrategy};
@@ -268,6 +270,22 @@ impl Session {
         Ok(())
     }
 
+    /// Add an extension to the session from its full config
+    ///
+    /// # Arguments
+    /// * `config` - The extension's config, as passed by a sub-recipe task
+    pub async fn add_extension_config(&mut self, config: ExtensionConfig) -> Result<()> {
+        self.agent
+            .add_extension(config)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to start extension: {}", e))?;
+
+        // Invalidate the completion cache when a new extension is added
+        self.invalidate_completion_cache().await;
+
+        Ok(())
+    }
+
     pub async fn list_prompts(
         &mut self,
         extension: Option<String>,
@@ -678,6 +696,24 @@ impl Session {
 
                     continue;
                 }
+                InputResult::Rewind(turns) => {
+                    save_history(&mut editor);
+                    if let Err(e) = self.rewind(turns).await {
+                        output::render_error(&format!("Failed to rewind: {}", e));
+                    }
+                    continue;
+                }
+                InputResult::Branch(name) => {
+                    save_history(&mut editor);
+                    let result = match name {
+                        Some(name) => self.switch_branch(&name),
+                        None => self.list_branches(),
+                    };
+                    if let Err(e) = result {
+                        output::render_error(&e.to_string());
+                    }
+                    continue;
+                }
             }
         }
 
@@ -791,6 +827,8 @@ impl Session {
             .await?;
 
         let mut progress_bars = output::McpSpinners::new();
+        // Whether the text of the next assistant message has already been printed
+        let mut streamed = false;
 
         use futures::StreamExt;
         loop {
@@ -943,11 +981,32 @@ impl Session {
                                     .await?;
                                 }
 
-                                if interactive {output::hide_thinking()};
+                                if interactive {
+
+                                    output::hide_thinking();
+
+                                }
+                                let _ = progress_bars.hide();
+                                if streamed && message.role == mcp_core::role::Role::Assistant {
+                                    output::render_streamed_message(&message, self.debug);
+                                    streamed = false;
+                                } else {
+                                    output::render_message(&message, self.debug);
+                                }
+                                if interactive {
+                                    output::show_thinking();
+                                }
+                            }
+                        }
+                        Some(Ok(AgentEvent::MessageDelta(delta))) => {
+                            if !streamed {
+                                if interactive {
+                                    output::hide_thinking();
+                                }
                                 let _ = progress_bars.hide();
-                                output::render_message(&message, self.debug);
-                                if interactive {output::show_thinking()};
+                                streamed = true;
                             }
+                            output::render_message_delta(&delta);
                         }
                         Some(Ok(AgentEvent::McpNotification((_id, message)))) => {
                                 if let JsonRpcMessage::Notification(JsonRpcNotification{
@@ -1067,6 +1126,27 @@ impl Session {
                                 eprintln!("Model changed to {} in {} mode", model, mode);
                             }
                         }
+                        Some(Ok(AgentEvent::HistoryReplaced(messages))) => {
+                            self.messages = messages;
+                            if let Some(session_file) = &self.session_file {
+                                session::persist_messages_with_schedule_id(
+                                    session_file,
+                                    &self.messages,
+                                    None,
+                                    self.scheduled_job_id.clone(),
+                                )
+                                .await?;
+                            }
+                            if interactive {
+                                output::hide_thinking();
+                            }
+                            let _ = progress_bars.hide();
+                            output::render_text(
+                                "Context was getting large, so the conversation was compacted.",
+                                Some(Color::Yellow),
+                                true,
+                            );
+                        }
 
                         Some(Err(e)) => {
                             eprintln!("Error: {}", e);
@@ -1211,6 +1291,90 @@ impl Session {
         Ok(())
     }
 
+    /// Go back to before the last `turns` prompts
+    ///
+    /// The session is forked at that point and continues in the new branch, so the
+    /// conversation being rewound stays available.
+    async fn rewind(&mut self, turns: usize) -> Result<()> {
+        let Some(index) = rewind_index(&self.messages, turns) else {
+            println!(
+                "{}",
+                console::style(format!("There are fewer than {} prompts to rewind", turns))
+                    .yellow()
+            );
+            return Ok(());
+        };
+        let prompt = self.messages[index].as_concat_text();
+
+        if let Some(session_file) = &self.session_file {
+            let branch = session::fork_session(session_file, index, None).await?;
+            self.session_file = Some(branch);
+        }
+        self.messages.truncate(index);
+
+        println!(
+            "{}",
+            console::style(format!("Rewound to before: {}", safe_truncate(&prompt, 80))).green()
+        );
+        if let Some(branch) = self.branch_name() {
+            println!(
+                "{}",
+                console::style(format!(
+                    "Continuing in branch `{}`. Use /branch to see the others.",
+                    branch
+                ))
+                .dim()
+            );
+        }
+        Ok(())
+    }
+
+    fn list_branches(&self) -> Result<()> {
+        let Some(session_file) = &self.session_file else {
+            return Err(anyhow::anyhow!(
+                "This session isn't saved, so it has no branches"
+            ));
+        };
+        output::render_branches(&session::list_branches(session_file)?, Some(session_file));
+        Ok(())
+    }
+
+    /// Continue in another branch of this session's branch tree
+    fn switch_branch(&mut self, name: &str) -> Result<()> {
+        let Some(session_file) = &self.session_file else {
+            return Err(anyhow::anyhow!(
+                "This session isn't saved, so it has no branches"
+            ));
+        };
+        let branch = session::list_branches(session_file)?
+            .into_iter()
+            .find(|b| b.session_id == name)
+            .ok_or_else(|| {
+                anyhow::anyhow!("No branch named `{}`. Use /branch to list them.", name)
+            })?;
+
+        let path = PathBuf::from(&branch.path);
+        self.messages = session::read_messages(&path)?;
+        self.session_file = Some(path);
+        println!(
+            "{}",
+            console::style(format!(
+                "Switched to branch `{}` ({} messages)",
+                branch.session_id,
+                self.messages.len()
+            ))
+            .green()
+        );
+        Ok(())
+    }
+
+    fn branch_name(&self) -> Option<String> {
+        self.session_file
+            .as_ref()
+            .and_then(|f| f.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+    }
+
     pub fn session_file(&self) -> Option<PathBuf> {
         self.session_file.clone()
     }
@@ -1286,7 +1450,11 @@ impl Session {
     }
 
     pub fn get_metadata(&self) -> Result<session::SessionMetadata> {
-        if !self.session_file.as_ref().is_some_and(|f| f.exists()) {
+        if !self
+            .session_file
+            .as_ref()
+            .is_some_and(|f| session::session_exists(f))
+        {
             return Err(anyhow::anyhow!("Session file does not exist"));
         }
 
@@ -1303,7 +1471,7 @@ impl Session {
     pub async fn display_context_usage(&self) -> Result<()> {
         let provider = self.agent.provider().await?;
         let model_config = provider.get_model_config();
-        let context_limit = model_config.context_limit.unwrap_or(32000);
+        let context_limit = model_config.context_limit();
 
         match self.get_metadata() {
             Ok(metadata) => {
@@ -1424,6 +1592,22 @@ impl Session {
     }
 }
 
+/// Index of the prompt `turns` prompts back from the end, counting only messages the user
+/// typed rather than tool results
+fn rewind_index(messages: &[Message], turns: usize) -> Option<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, m)| {
+            m.role == mcp_core::role::Role::User
+                && !m.is_tool_response()
+                && !m.as_concat_text().trim().is_empty()
+        })
+        .nth(turns.checked_sub(1)?)
+        .map(|(i, _)| i)
+}
+
 fn get_reasoner() -> Result<Arc<dyn Provider>, anyhow::Error> {
     use goose::model::ModelConfig;
     use goose::providers::create;
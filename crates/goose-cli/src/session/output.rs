@@ -2,13 +2,15 @@ use bat::WrappingMode;
 use console::{style, Color};
 use goose::config::Config;
 use goose::message::{Message, MessageContent, ToolRequest, ToolResponse};
+use goose::providers::base::MessageDelta;
+use goose::session::BranchInfo;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use mcp_core::prompt::PromptArgument;
 use mcp_core::tool::ToolCall;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Error;
+use std::io::{Error, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
@@ -167,6 +169,46 @@ pub fn render_message(message: &Message, debug: bool) {
     println!();
 }
 
+/// Print partial model output as it arrives
+/// Text is written as-is since markdown can't be rendered until the message is complete
+pub fn render_message_delta(delta: &MessageDelta) {
+    match delta {
+        MessageDelta::Text { text } => print!("{}", text),
+        MessageDelta::Thinking { thinking } => {
+            if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok() {
+                print!("{}", style(thinking).dim().italic());
+            }
+        }
+        // Tool calls are rendered once their arguments are complete
+        MessageDelta::ToolCall { .. } => return,
+    }
+    let _ = std::io::stdout().flush();
+}
+
+/// Render a message whose text and thinking were already printed through deltas
+pub fn render_streamed_message(message: &Message, debug: bool) {
+    let remaining = Message {
+        content: message
+            .content
+            .iter()
+            .filter(|content| {
+                !matches!(
+                    content,
+                    MessageContent::Text(_) | MessageContent::Thinking(_)
+                )
+            })
+            .cloned()
+            .collect(),
+        ..message.clone()
+    };
+    if remaining.content.is_empty() {
+        println!("\n");
+        return;
+    }
+    println!();
+    render_message(&remaining, debug);
+}
+
 pub fn render_text(text: &str, color: Option<Color>, dim: bool) {
     render_text_no_newlines(format!("\n{}\n\n", text).as_str(), color, dim);
 }
@@ -668,6 +710,30 @@ pub fn display_context_usage(total_tokens: usize, context_limit: usize) {
     );
 }
 
+/// Display a session's branch tree with each branch's usage, marking the current one
+pub fn render_branches(branches: &[BranchInfo], current: Option<&Path>) {
+    for branch in branches {
+        let is_current = current.is_some_and(|c| Path::new(&branch.path) == c);
+        let origin = match (&branch.parent_id, branch.message_index) {
+            (Some(parent), Some(index)) => format!(" (from {} at message {})", parent, index),
+            _ => String::new(),
+        };
+        let line = format!(
+            "{}{}{} - {} messages, {} tokens",
+            "  ".repeat(branch.depth),
+            branch.session_id,
+            origin,
+            branch.metadata.message_count,
+            branch.metadata.accumulated_total_tokens.unwrap_or(0)
+        );
+        if is_current {
+            println!("{} {}", style("*").green().bold(), style(line).green());
+        } else {
+            println!("  {}", line);
+        }
+    }
+}
+
 pub struct McpSpinners {
     bars: HashMap<String, ProgressBar>,
     log_spinner: Option<ProgressBar>,
@@ -707,18 +773,30 @@ impl McpSpinners {
             if let Some(total) = total {
                 self.multi_bar.add(
                     ProgressBar::new((total * 100.0) as u64).with_style(
-                        ProgressStyle::with_template("[{elapsed}] {bar:40} {pos:>3}/{len:3} {msg}")
-                            .unwrap(),
+                        ProgressStyle::with_template(
+                            "[{elapsed}] {bar:40.cyan/blue} {percent:>3}% {msg}",
+                        )
+                        .unwrap(),
                     ),
                 )
             } else {
-                self.multi_bar.add(ProgressBar::new_spinner())
+                let spinner = self.multi_bar.add(ProgressBar::new_spinner());
+                spinner.enable_steady_tick(Duration::from_millis(100));
+                spinner
             }
         });
+        if let Some(total) = total {
+            // The total can grow as a tool discovers more work
+            bar.set_length((total * 100.0) as u64);
+        }
         bar.set_position((value * 100.0) as u64);
         if let Some(msg) = message {
             bar.set_message(msg.to_string());
         }
+        if total.is_some_and(|total| value >= total) {
+            bar.finish_and_clear();
+            self.bars.remove(token);
+        }
     }
 
     pub fn hide(&mut self) -> Result<(), Error> {
@@ -263,12 +263,18 @@ pub unsafe extern "C" fn goose_agent_send_message(
                         full_response.push_str(&json);
                     }
                 }
+                Ok(AgentEvent::MessageDelta(_)) => {
+                    // The complete message follows the deltas
+                }
                 Ok(AgentEvent::McpNotification(_)) => {
                     // TODO: Handle MCP notifications.
                 }
                 Ok(AgentEvent::ModelChange { .. }) => {
                     // Model change events are informational, just continue
                 }
+                Ok(AgentEvent::HistoryReplaced(_)) => {
+                    // Callers keep their own history, only the response is returned
+                }
 
                 Err(e) => {
                     full_response.push_str(&format!("\nError in message stream: {}", e));
@@ -0,0 +1,227 @@
+//! Shell commands the agent started in the background, such as dev servers or watchers
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use mcp_core::handler::ToolError;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+// Older output is dropped once a job has produced more than this
+const MAX_JOB_OUTPUT: usize = 400_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JobStatus {
+    Running,
+    Exited(Option<i32>),
+    Killed,
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Exited(Some(code)) => write!(f, "exited with code {}", code),
+            JobStatus::Exited(None) => write!(f, "exited"),
+            JobStatus::Killed => write!(f, "killed"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct JobOutput {
+    text: String,
+    // How much of `text` the agent has already read
+    read_to: usize,
+}
+
+impl JobOutput {
+    fn push(&mut self, line: &str) {
+        self.text.push_str(line);
+        if self.text.len() > MAX_JOB_OUTPUT {
+            let mut cut = self.text.len() - MAX_JOB_OUTPUT;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+            self.read_to = self.read_to.saturating_sub(cut);
+        }
+    }
+
+    fn take_unread(&mut self) -> &str {
+        let start = self.read_to;
+        self.read_to = self.text.len();
+        &self.text[start..]
+    }
+}
+
+struct Job {
+    command: String,
+    pid: Option<u32>,
+    started: Instant,
+    status: Mutex<JobStatus>,
+    output: Mutex<JobOutput>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        *self.status.lock().unwrap()
+    }
+}
+
+/// The background jobs of one developer extension, keyed by a short id like `job-1`
+#[derive(Default)]
+pub struct BackgroundJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+}
+
+impl BackgroundJobs {
+    /// Spawn `command` and collect its output until it exits, returning the job id
+    ///
+    /// The command must have piped stdout and stderr.
+    pub fn start(&self, mut command: Command, description: &str) -> Result<String, ToolError> {
+        let mut child = command
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let id = format!("job-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let job = Arc::new(Job {
+            command: description.to_string(),
+            pid: child.id(),
+            started: Instant::now(),
+            status: Mutex::new(JobStatus::Running),
+            output: Mutex::new(JobOutput::default()),
+        });
+
+        let stdout = child.stdout.take().map(|s| collect(s, Arc::clone(&job)));
+        let stderr = child.stderr.take().map(|s| collect(s, Arc::clone(&job)));
+        let waiter = Arc::clone(&job);
+        tokio::spawn(async move {
+            let code = child.wait().await.ok().and_then(|status| status.code());
+            // Let the readers drain what the process wrote before it exited
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = reader.await;
+            }
+            let mut status = waiter.status.lock().unwrap();
+            if *status == JobStatus::Running {
+                *status = JobStatus::Exited(code);
+            }
+        });
+
+        self.jobs.lock().unwrap().insert(id.clone(), job);
+        Ok(id)
+    }
+
+    /// A line per job with its status and command
+    pub fn list(&self) -> String {
+        let jobs = self.jobs.lock().unwrap();
+        if jobs.is_empty() {
+            return "No background jobs have been started".to_string();
+        }
+        let mut ids: Vec<&String> = jobs.keys().collect();
+        ids.sort_by_key(|id| job_number(id));
+
+        let mut listing = String::new();
+        for id in ids {
+            let job = &jobs[id];
+            let _ = writeln!(
+                listing,
+                "{} ({}, started {}s ago): {}",
+                id,
+                job.status(),
+                job.started.elapsed().as_secs(),
+                job.command
+            );
+        }
+        listing
+    }
+
+    /// Output the job produced since it was last read, with its current status
+    pub fn output(&self, id: &str) -> Result<String, ToolError> {
+        let job = self.get(id)?;
+        let status = job.status();
+        let mut output = job.output.lock().unwrap();
+        let unread = output.take_unread();
+        if unread.is_empty() {
+            Ok(format!("{} is {} and has no new output", id, status))
+        } else {
+            Ok(format!("{} is {}. New output:\n{}", id, status, unread))
+        }
+    }
+
+    /// Stop the job and everything it started
+    pub async fn kill(&self, id: &str) -> Result<String, ToolError> {
+        let job = self.get(id)?;
+        if job.status() != JobStatus::Running {
+            return Ok(format!("{} already {}", id, job.status()));
+        }
+        if let Some(pid) = job.pid {
+            kill_process_tree(pid).await?;
+        }
+        *job.status.lock().unwrap() = JobStatus::Killed;
+        Ok(format!("Killed {}", id))
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<Job>, ToolError> {
+        self.jobs.lock().unwrap().get(id).cloned().ok_or_else(|| {
+            ToolError::InvalidParameters(format!(
+                "No background job '{}'. Use the `list` action to see running jobs.",
+                id
+            ))
+        })
+    }
+}
+
+fn job_number(id: &str) -> u64 {
+    id.trim_start_matches("job-").parse().unwrap_or(u64::MAX)
+}
+
+fn collect<R>(reader: R, job: Arc<Job>) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        while let Ok(n) = reader.read_until(b'\n', &mut buf).await {
+            if n == 0 {
+                break;
+            }
+            job.output
+                .lock()
+                .unwrap()
+                .push(&String::from_utf8_lossy(&buf));
+            buf.clear();
+        }
+    })
+}
+
+/// Kill a process and all of its descendants
+pub async fn kill_process_tree(pid: u32) -> Result<(), ToolError> {
+    tokio::task::spawn_blocking(move || kill_tree::blocking::kill_tree(pid))
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+        .map(|_| ())
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to kill process {}: {}", pid, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_is_read_once_and_capped() {
+        let mut output = JobOutput::default();
+        output.push("first\n");
+        assert_eq!(output.take_unread(), "first\n");
+        assert_eq!(output.take_unread(), "");
+
+        output.push(&"x".repeat(MAX_JOB_OUTPUT));
+        assert_eq!(output.text.len(), MAX_JOB_OUTPUT);
+        assert_eq!(output.take_unread().len(), MAX_JOB_OUTPUT);
+    }
+}
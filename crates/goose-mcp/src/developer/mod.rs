@@ -1,5 +1,10 @@
 mod editor_models;
+mod jobs;
 mod lang;
+mod patch;
+mod sandbox;
+mod search;
+mod session;
 mod shell;
 
 use anyhow::Result;
@@ -13,6 +18,7 @@ use std::{
     io::Cursor,
     path::{Path, PathBuf},
     pin::Pin,
+    time::Duration,
 };
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
@@ -24,7 +30,7 @@ use url::Url;
 use include_dir::{include_dir, Dir};
 use mcp_core::{
     handler::{PromptError, ResourceError, ToolError},
-    protocol::{JsonRpcMessage, JsonRpcNotification, ServerCapabilities},
+    protocol::{JsonRpcMessage, JsonRpcNotification, Root, ServerCapabilities},
     resource::Resource,
     tool::Tool,
     Content,
@@ -39,6 +45,10 @@ use mcp_server::Router;
 use mcp_core::role::Role;
 
 use self::editor_models::{create_editor_model, EditorModel};
+use self::jobs::{kill_process_tree, BackgroundJobs};
+use self::patch::{AppliedPatch, Edit, PatchedFile};
+use self::sandbox::SandboxPolicy;
+use self::session::{SessionStatus, ShellSession};
 use self::shell::{expand_path, get_shell_config, is_absolute_path, normalize_line_endings};
 use indoc::indoc;
 use std::process::Stdio;
@@ -93,13 +103,82 @@ pub fn load_prompt_files() -> HashMap<String, Prompt> {
     prompts
 }
 
+const APPLY_PATCH_DESCRIPTION: &str = "To use the apply_patch command, set `path` to the directory the patch paths are relative to, and give either \
+`patch`, a unified diff (as produced by `git diff`) that may create, modify or delete several files, or `edits`, a list of \
+`{path, old_str, new_str}` replacements where each `old_str` must appear exactly once (an empty `old_str` creates a new file). \
+Every change is checked before anything is written, so if any hunk or edit doesn't apply no files are changed. \
+Calling `undo_edit` with the same `path` reverts the whole patch.";
+
+/// Stream a line of shell output to the client as it arrives
+fn notify_shell_output(notifier: &mpsc::Sender<JsonRpcMessage>, stream: &str, line: &str) {
+    notifier
+        .try_send(JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/message".to_string(),
+            params: Some(json!({
+                "data": {
+                    "type": "shell",
+                    "stream": stream,
+                    "output": line,
+                }
+            })),
+        }))
+        .ok();
+}
+
+fn get_usize(params: &Value, key: &str, default: usize) -> usize {
+    params
+        .get(key)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .unwrap_or(default)
+}
+
+/// Structured results as pretty printed JSON for the model
+fn json_content(value: &impl serde::Serialize) -> Result<Vec<Content>, ToolError> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+    Ok(vec![Content::text(text)])
+}
+
+/// The shell tool's result, refusing output too large to hand to the model
+fn shell_output_content(command: &str, output_str: String) -> Result<Vec<Content>, ToolError> {
+    // Check the character count of the output
+    const MAX_CHAR_COUNT: usize = 400_000; // 409600 chars = 400KB
+    let char_count = output_str.chars().count();
+    if char_count > MAX_CHAR_COUNT {
+        return Err(ToolError::ExecutionError(format!(
+                "Shell output from command '{}' has too many characters ({}). Maximum character count is {}.",
+                command,
+                char_count,
+                MAX_CHAR_COUNT
+            )));
+    }
+
+    Ok(vec![
+        Content::text(output_str.clone()).with_audience(vec![Role::Assistant]),
+        Content::text(output_str)
+            .with_audience(vec![Role::User])
+            .with_priority(0.0),
+    ])
+}
+
 pub struct DeveloperRouter {
     tools: Vec<Tool>,
     prompts: Arc<HashMap<String, Prompt>>,
     instructions: String,
     file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
+    // Patches applied with apply_patch, newest last
+    patch_history: Arc<Mutex<Vec<AppliedPatch>>>,
     ignore_patterns: Arc<Gitignore>,
     editor_model: Option<EditorModel>,
+    // Directories the client is working in, from its MCP roots
+    roots: Arc<Mutex<Vec<PathBuf>>>,
+    // Restrictions on shell commands, when the sandbox is enabled
+    sandbox: Option<SandboxPolicy>,
+    jobs: Arc<BackgroundJobs>,
+    // Started by the first persistent shell command, then kept for the conversation
+    shell_session: Arc<tokio::sync::Mutex<Option<ShellSession>>>,
 }
 
 impl Default for DeveloperRouter {
@@ -130,10 +209,14 @@ impl DeveloperRouter {
                 of if the command succeeded or failed.
 
                 Avoid commands that produce a large amount of output, and consider piping those outputs to files.
+                Set `timeout_secs` to stop a command that may hang. For long lived commands like dev servers,
+                set `background: true` and use the shell_job tool to read their output or stop them.
 
                 **Important**: For searching files and code:
 
-                Preferred: Use ripgrep (`rg`) when available - it respects .gitignore and is fast:
+                Preferred: Use the `search`, `glob` and `tree` tools, which skip ignored files and page their results.
+
+                Otherwise use ripgrep (`rg`) when available - it respects .gitignore and is fast:
                   - To locate a file by name: `rg --files | rg example.py`
                   - To locate content inside files: `rg 'class Example'`
 
@@ -151,17 +234,16 @@ impl DeveloperRouter {
                 of if the command succeeded or failed.
 
                 Avoid commands that produce a large amount of output, and consider piping those outputs to files.
-                If you need to run a long lived command, background it - e.g. `uvicorn main:app &` so that
-                this tool does not run indefinitely.
+                Set `timeout_secs` to stop a command that may hang. For long lived commands like dev servers or
+                `tail -f`, set `background: true` and use the shell_job tool to read their output or stop them.
 
-                **Important**: Each shell command runs in its own process. Things like directory changes or
-                sourcing files do not persist between tool calls. So you may need to repeat them each time by
-                stringing together commands, e.g. `cd example && ls` or `source env/bin/activate && pip install numpy`
+                **Important**: By default each shell command runs in its own process. Things like directory changes or
+                sourcing files do not persist between tool calls, so you may need to string commands together,
+                e.g. `cd example && ls`. Set `persistent: true` to run commands in a shell that stays open for the
+                conversation instead, so `cd`, `export` and `source env/bin/activate` carry over to later persistent calls.
 
-                **Important**: Use ripgrep - `rg` - when you need to locate a file or a code reference, other solutions
-                may show ignored or hidden files. For example *do not* use `find` or `ls -r`
-                  - List files by name: `rg --files | rg <filename>`
-                  - List files that contain a regex: `rg '<regex>' -l`
+                **Important**: Use the `search`, `glob` and `tree` tools to locate files or code references rather than
+                `find`, `ls -r` or `grep -r`, which may show ignored or hidden files and produce huge outputs.
             "#},
         };
 
@@ -172,7 +254,47 @@ impl DeveloperRouter {
                 "type": "object",
                 "required": ["command"],
                 "properties": {
-                    "command": {"type": "string"}
+                    "command": {"type": "string"},
+                    "timeout_secs": {
+                        "type": "integer",
+                        "description": "Stop the command, and anything it started, after this many seconds"
+                    },
+                    "background": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Start the command and return right away with a job id for the shell_job tool"
+                    },
+                    "persistent": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Run in the conversation's long lived shell, keeping its directory and environment"
+                    }
+                }
+            }),
+            None,
+        );
+
+        let shell_job_tool = Tool::new(
+            "shell_job",
+            indoc! {r#"
+                Manage commands started by the shell tool with `background: true`.
+
+                The `action` parameter specifies the operation to perform. Allowed options are:
+                - `list`: List background jobs with their status.
+                - `output`: Read the output a job produced since you last read it.
+                - `kill`: Stop a job and any processes it started.
+
+                `output` and `kill` require the `job_id` returned when the job was started.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["action"],
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "output", "kill"]
+                    },
+                    "job_id": {"type": "string"}
                 }
             }),
             None,
@@ -189,20 +311,24 @@ impl DeveloperRouter {
                 - `write`: Create or overwrite a file with the given content
                 - `edit_file`: Edit the file with the new content.
                 - `insert`: Insert text at a specific line location in the file.
+                - `apply_patch`: Apply changes to many files at once, all or nothing.
                 - `undo_edit`: Undo the last edit made to a file.
 
                 To use the write command, you must specify `file_text` which will become the new content of the file. Be careful with
                 existing files! This is a full overwrite, so you must include everything - not just sections you are modifying.
 
+                {}
+
                 To use the edit_file command, you must specify both `old_str` and `new_str` - {}.
 
                 To use the insert command, you must specify both `insert_line` (the line number after which to insert, 0 for beginning) 
                 and `new_str` (the text to insert).
-            "#, editor.get_str_replace_description()},
+            "#, APPLY_PATCH_DESCRIPTION, editor.get_str_replace_description()},
                 "edit_file",
             )
         } else {
-            (indoc! {r#"
+            (
+                formatdoc! {r#"
                 Perform text editing operations on files.
 
                 The `command` parameter specifies the operation to perform. Allowed options are:
@@ -210,18 +336,23 @@ impl DeveloperRouter {
                 - `write`: Create or overwrite a file with the given content
                 - `str_replace`: Replace a string in a file with a new string.
                 - `insert`: Insert text at a specific line location in the file.
+                - `apply_patch`: Apply changes to many files at once, all or nothing.
                 - `undo_edit`: Undo the last edit made to a file.
 
                 To use the write command, you must specify `file_text` which will become the new content of the file. Be careful with
                 existing files! This is a full overwrite, so you must include everything - not just sections you are modifying.
 
+                {}
+
                 To use the str_replace command, you must specify both `old_str` and `new_str` - the `old_str` needs to exactly match one
                 unique section of the original file, including any whitespace. Make sure to include enough context that the match is not
                 ambiguous. The entire original string will be replaced with `new_str`.
 
                 To use the insert command, you must specify both `insert_line` (the line number after which to insert, 0 for beginning) 
                 and `new_str` (the text to insert).
-            "#}.to_string(), "str_replace")
+            "#, APPLY_PATCH_DESCRIPTION},
+                "str_replace",
+            )
         };
 
         let text_editor_tool = Tool::new(
@@ -237,8 +368,8 @@ impl DeveloperRouter {
                     },
                     "command": {
                         "type": "string",
-                        "enum": ["view", "write", str_replace_command, "insert", "undo_edit"],
-                        "description": format!("Allowed options are: `view`, `write`, `{}`, `insert`, `undo_edit`.", str_replace_command)
+                        "enum": ["view", "write", str_replace_command, "insert", "apply_patch", "undo_edit"],
+                        "description": format!("Allowed options are: `view`, `write`, `{}`, `insert`, `apply_patch`, `undo_edit`.", str_replace_command)
                     },
                     "view_range": {
                         "type": "array",
@@ -253,12 +384,115 @@ impl DeveloperRouter {
                     },
                     "old_str": {"type": "string"},
                     "new_str": {"type": "string"},
-                    "file_text": {"type": "string"}
+                    "file_text": {"type": "string"},
+                    "patch": {
+                        "type": "string",
+                        "description": "A unified diff for the apply_patch command, with paths relative to `path`."
+                    },
+                    "edits": {
+                        "type": "array",
+                        "description": "Replacements for the apply_patch command, as an alternative to `patch`.",
+                        "items": {
+                            "type": "object",
+                            "required": ["path", "new_str"],
+                            "properties": {
+                                "path": {"type": "string"},
+                                "old_str": {"type": "string"},
+                                "new_str": {"type": "string"}
+                            }
+                        }
+                    }
                 }
             }),
             None,
         );
 
+        let search_tool = Tool::new(
+            "search",
+            indoc! {r#"
+                Search file contents for a regex, like `rg`, without needing it installed.
+
+                Searches every text file under `path` (default: the working directory), skipping files
+                excluded by .gitignore or .gooseignore. Returns JSON with each match's path, line number
+                and text, plus `context` lines before and after. Results are paged: when `next_offset` is
+                present, call again with that `offset` to see more.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["pattern"],
+                "properties": {
+                    "pattern": {"type": "string", "description": "Regex to search for"},
+                    "path": {"type": "string", "description": "Absolute path of the directory or file to search"},
+                    "glob": {"type": "string", "description": "Only search files matching this glob, e.g. `*.rs` or `src/**/*.ts`"},
+                    "case_insensitive": {"type": "boolean", "default": false},
+                    "context": {"type": "integer", "default": 2, "description": "Lines of context around each match, up to 10"},
+                    "offset": {"type": "integer", "default": 0},
+                    "limit": {"type": "integer", "default": 50, "description": "Matches per page, up to 200"}
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Search file contents".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let glob_tool = Tool::new(
+            "glob",
+            indoc! {r#"
+                List files whose path matches a glob, e.g. `**/*.py` or `src/**/test_*.rs`.
+
+                Paths are matched relative to `path` (default: the working directory) and files excluded by
+                .gitignore or .gooseignore are skipped. Returns JSON with the matching paths. Results are
+                paged: when `next_offset` is present, call again with that `offset` to see more.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["pattern"],
+                "properties": {
+                    "pattern": {"type": "string"},
+                    "path": {"type": "string", "description": "Absolute path of the directory to list from"},
+                    "offset": {"type": "integer", "default": 0},
+                    "limit": {"type": "integer", "default": 200, "description": "Paths per page, up to 1000"}
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Find files by pattern".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let tree_tool = Tool::new(
+            "tree",
+            indoc! {r#"
+                Show the directories and files under `path` (default: the working directory) as an indented tree.
+
+                Files excluded by .gitignore or .gooseignore are skipped. Use `max_depth` to control how deep to
+                go; large trees are cut off after `limit` entries.
+            "#},
+            json!({
+                "type": "object",
+                "required": [],
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path of the directory to show"},
+                    "max_depth": {"type": "integer", "default": 3},
+                    "limit": {"type": "integer", "default": 300, "description": "Entries to show, up to 2000"}
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Show directory tree".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
         let list_windows_tool = Tool::new(
             "list_windows",
             indoc! {r#"
@@ -430,6 +664,12 @@ impl DeveloperRouter {
             format!("{base_instructions}\n{hints}")
         };
 
+        let sandbox = SandboxPolicy::from_env();
+        let instructions = match &sandbox {
+            Some(policy) => format!("{instructions}\n\n{}", policy.describe()),
+            None => instructions,
+        };
+
         let mut builder = GitignoreBuilder::new(cwd.clone());
         let mut has_ignore_file = false;
         // Initialize ignore patterns
@@ -483,7 +723,11 @@ impl DeveloperRouter {
         Self {
             tools: vec![
                 bash_tool,
+                shell_job_tool,
                 text_editor_tool,
+                search_tool,
+                glob_tool,
+                tree_tool,
                 list_windows_tool,
                 screen_capture_tool,
                 image_processor_tool,
@@ -491,11 +735,27 @@ impl DeveloperRouter {
             prompts: Arc::new(load_prompt_files()),
             instructions,
             file_history: Arc::new(Mutex::new(HashMap::new())),
+            patch_history: Arc::new(Mutex::new(Vec::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model,
+            roots: Arc::new(Mutex::new(Vec::new())),
+            sandbox,
+            jobs: Arc::new(BackgroundJobs::default()),
+            shell_session: Arc::new(tokio::sync::Mutex::new(None)),
         }
     }
 
+    // The directory relative paths and commands run in: the client's first root if it
+    // provided any, otherwise the process working directory
+    fn working_dir(&self) -> PathBuf {
+        self.roots
+            .lock()
+            .unwrap()
+            .first()
+            .cloned()
+            .unwrap_or_else(|| std::env::current_dir().expect("should have a current working dir"))
+    }
+
     // Helper method to check if a path should be ignored
     fn is_ignored(&self, path: &Path) -> bool {
         self.ignore_patterns.matched(path, false).is_ignore()
@@ -503,7 +763,7 @@ impl DeveloperRouter {
 
     // Helper method to resolve a path relative to cwd with platform-specific handling
     fn resolve_path(&self, path_str: &str) -> Result<PathBuf, ToolError> {
-        let cwd = std::env::current_dir().expect("should have a current working dir");
+        let cwd = self.working_dir();
         let expanded = expand_path(path_str);
         let path = Path::new(&expanded);
 
@@ -532,10 +792,28 @@ impl DeveloperRouter {
                 .ok_or(ToolError::InvalidParameters(
                     "The command string is required".to_string(),
                 ))?;
+        let timeout = params
+            .get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .map(Duration::from_secs);
+        let background = params
+            .get("background")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let persistent = params
+            .get("persistent")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if background && persistent {
+            return Err(ToolError::InvalidParameters(
+                "A command can't run both in the background and in the persistent session"
+                    .to_string(),
+            ));
+        }
 
         // Check if command might access ignored files and return early if it does
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
-        for arg in &cmd_parts[1..] {
+        for arg in cmd_parts.iter().skip(1) {
             // Skip command flags
             if arg.starts_with('-') {
                 continue;
@@ -554,17 +832,35 @@ impl DeveloperRouter {
             }
         }
 
+        if persistent {
+            return self.bash_in_session(command, timeout, notifier).await;
+        }
+
         // Get platform-specific shell configuration
         let shell_config = get_shell_config();
 
         // Execute the command using platform-specific shell
-        let mut child = Command::new(&shell_config.executable)
+        let working_dir = self.working_dir();
+        let mut shell = Command::new(&shell_config.executable);
+        shell
+            .current_dir(&working_dir)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
             .kill_on_drop(true)
             .args(&shell_config.args)
-            .arg(command)
+            .arg(command);
+        self.sandbox_command(&mut shell, &working_dir)?;
+
+        if background {
+            let id = self.jobs.start(shell, command)?;
+            return Ok(vec![Content::text(format!(
+                "Started {} in the background. Use the shell_job tool with job_id `{}` to read its output or kill it.",
+                id, id
+            ))]);
+        }
+
+        let mut child = shell
             .spawn()
             .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
 
@@ -574,9 +870,11 @@ impl DeveloperRouter {
         let mut stdout_reader = BufReader::new(stdout);
         let mut stderr_reader = BufReader::new(stderr);
 
-        let output_task = tokio::spawn(async move {
-            let mut combined_output = String::new();
+        // Shared with the reader so output so far survives a timeout
+        let combined_output = Arc::new(Mutex::new(String::new()));
+        let output_sink = Arc::clone(&combined_output);
 
+        let output_task = tokio::spawn(async move {
             let mut stdout_buf = Vec::new();
             let mut stderr_buf = Vec::new();
 
@@ -590,20 +888,8 @@ impl DeveloperRouter {
                             stdout_done = true;
                         } else {
                             let line = String::from_utf8_lossy(&stdout_buf);
-
-                            notifier.try_send(JsonRpcMessage::Notification(JsonRpcNotification {
-                                jsonrpc: "2.0".to_string(),
-                                method: "notifications/message".to_string(),
-                                params: Some(json!({
-                                    "data": {
-                                        "type": "shell",
-                                        "stream": "stdout",
-                                        "output": line.to_string(),
-                                    }
-                                })),
-                            })).ok();
-
-                            combined_output.push_str(&line);
+                            notify_shell_output(&notifier, "stdout", &line);
+                            output_sink.lock().unwrap().push_str(&line);
                             stdout_buf.clear();
                         }
                     }
@@ -613,20 +899,8 @@ impl DeveloperRouter {
                             stderr_done = true;
                         } else {
                             let line = String::from_utf8_lossy(&stderr_buf);
-
-                            notifier.try_send(JsonRpcMessage::Notification(JsonRpcNotification {
-                                jsonrpc: "2.0".to_string(),
-                                method: "notifications/message".to_string(),
-                                params: Some(json!({
-                                    "data": {
-                                        "type": "shell",
-                                        "stream": "stderr",
-                                        "output": line.to_string(),
-                                    }
-                                })),
-                            })).ok();
-
-                            combined_output.push_str(&line);
+                            notify_shell_output(&notifier, "stderr", &line);
+                            output_sink.lock().unwrap().push_str(&line);
                             stderr_buf.clear();
                         }
                     }
@@ -638,38 +912,242 @@ impl DeveloperRouter {
                     break;
                 }
             }
-            Ok::<_, std::io::Error>(combined_output)
+            Ok::<_, std::io::Error>(())
         });
 
-        // Wait for the command to complete and get output
-        child
-            .wait()
-            .await
-            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        // Wait for the command to complete, stopping it if it runs past the timeout
+        let wait = child.wait();
+        let timed_out = match timeout {
+            Some(limit) => match tokio::time::timeout(limit, wait).await {
+                Ok(status) => {
+                    status.map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                    false
+                }
+                Err(_) => {
+                    if let Some(pid) = child.id() {
+                        kill_process_tree(pid).await?;
+                    }
+                    let _ = child.kill().await;
+                    true
+                }
+            },
+            None => {
+                wait.await
+                    .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+                false
+            }
+        };
 
-        let output_str = match output_task.await {
+        // A process that escaped the kill can keep the pipes open, so don't wait on it forever
+        let output_result = if timed_out {
+            tokio::time::timeout(Duration::from_secs(5), output_task)
+                .await
+                .unwrap_or(Ok(Ok(())))
+        } else {
+            output_task.await
+        };
+        match output_result {
             Ok(result) => result.map_err(|e| ToolError::ExecutionError(e.to_string()))?,
             Err(e) => return Err(ToolError::ExecutionError(e.to_string())),
         };
 
-        // Check the character count of the output
-        const MAX_CHAR_COUNT: usize = 400_000; // 409600 chars = 400KB
-        let char_count = output_str.chars().count();
-        if char_count > MAX_CHAR_COUNT {
+        let mut output_str = std::mem::take(&mut *combined_output.lock().unwrap());
+        if let Some(limit) = timeout.filter(|_| timed_out) {
+            output_str.push_str(&format!(
+                "\n\nThe command timed out after {} seconds and was stopped. Run long lived commands with `background: true` instead.",
+                limit.as_secs()
+            ));
+        }
+
+        shell_output_content(command, output_str)
+    }
+
+    /// Run a command in the persistent session, starting the session if needed
+    async fn bash_in_session(
+        &self,
+        command: &str,
+        timeout: Option<Duration>,
+        notifier: mpsc::Sender<JsonRpcMessage>,
+    ) -> Result<Vec<Content>, ToolError> {
+        if cfg!(windows) {
+            return Err(ToolError::InvalidParameters(
+                "Persistent shell sessions are only available with bash".to_string(),
+            ));
+        }
+
+        let mut session = self.shell_session.lock().await;
+        if session.is_none() {
+            let working_dir = self.working_dir();
+            let mut shell = ShellSession::command();
+            shell.current_dir(&working_dir);
+            self.sandbox_command(&mut shell, &working_dir)?;
+            let started = ShellSession::start(shell).await.map_err(|e| {
+                ToolError::ExecutionError(format!("Failed to start the shell session: {}", e))
+            })?;
+            *session = Some(started);
+        }
+
+        let (mut output, status) = session
+            .as_mut()
+            .expect("session was just started")
+            .run(command, timeout, |line| {
+                notify_shell_output(&notifier, "stdout", line)
+            })
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        match status {
+            SessionStatus::Exited(_) => {}
+            SessionStatus::TimedOut => {
+                if let Some(stale) = session.take() {
+                    stale.kill().await;
+                }
+                output.push_str(&format!(
+                    "\n\nThe command timed out after {} seconds. The persistent session was stopped, so its directory and environment are reset.",
+                    timeout.map(|t| t.as_secs()).unwrap_or_default()
+                ));
+            }
+            SessionStatus::Closed => {
+                session.take();
+                output.push_str("\n\nThe shell exited, so the persistent session was reset.");
+            }
+        }
+
+        shell_output_content(command, output)
+    }
+
+    /// Apply the sandbox, if enabled, to a shell about to run in `working_dir`
+    fn sandbox_command(&self, shell: &mut Command, working_dir: &Path) -> Result<(), ToolError> {
+        if let Some(policy) = &self.sandbox {
+            sandbox::apply(shell, policy, working_dir).map_err(|e| {
+                ToolError::ExecutionError(format!("Failed to set up the shell sandbox: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn shell_job(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'action' parameter".into()))?;
+        let job_id = || {
+            params
+                .get("job_id")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::InvalidParameters("Missing 'job_id' parameter".into()))
+        };
+
+        let text = match action {
+            "list" => self.jobs.list(),
+            "output" => self.jobs.output(job_id()?)?,
+            "kill" => self.jobs.kill(job_id()?).await?,
+            _ => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Unknown action '{}'. Allowed options are: `list`, `output`, `kill`.",
+                    action
+                )))
+            }
+        };
+        Ok(vec![Content::text(text)])
+    }
+
+    // The directory or file a search tool starts from, defaulting to the working directory
+    fn search_root(&self, params: &Value) -> Result<PathBuf, ToolError> {
+        let root = match params.get("path").and_then(|v| v.as_str()) {
+            Some(path) => self.resolve_path(path)?,
+            None => self.working_dir(),
+        };
+        if !root.exists() {
+            return Err(ToolError::InvalidParameters(format!(
+                "The path '{}' does not exist",
+                root.display()
+            )));
+        }
+        if self.is_ignored(&root) {
             return Err(ToolError::ExecutionError(format!(
-                    "Shell output from command '{}' has too many characters ({}). Maximum character count is {}.",
-                    command,
-                    char_count,
-                    MAX_CHAR_COUNT
-                )));
+                "Access to '{}' is restricted by .gooseignore",
+                root.display()
+            )));
         }
+        Ok(root)
+    }
 
-        Ok(vec![
-            Content::text(output_str.clone()).with_audience(vec![Role::Assistant]),
-            Content::text(output_str)
-                .with_audience(vec![Role::User])
-                .with_priority(0.0),
-        ])
+    async fn search(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let pattern = params
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'pattern' parameter".into()))?;
+        let case_insensitive = params
+            .get("case_insensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let regex = regex::RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid regex: {}", e)))?;
+        let file_glob = params
+            .get("glob")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let context = get_usize(&params, "context", 2).min(10);
+        let offset = get_usize(&params, "offset", 0);
+        let limit = get_usize(&params, "limit", 50).clamp(1, 200);
+
+        let root = self.search_root(&params)?;
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+        let page = tokio::task::spawn_blocking(move || {
+            search::search(
+                &root,
+                &ignore_patterns,
+                &regex,
+                file_glob.as_deref(),
+                context,
+                offset,
+                limit,
+            )
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+        .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+
+        json_content(&page)
+    }
+
+    async fn glob(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let pattern = params
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'pattern' parameter".into()))?
+            .to_string();
+        let offset = get_usize(&params, "offset", 0);
+        let limit = get_usize(&params, "limit", 200).clamp(1, 1000);
+
+        let root = self.search_root(&params)?;
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+        let page = tokio::task::spawn_blocking(move || {
+            search::glob(&root, &ignore_patterns, &pattern, offset, limit)
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?
+        .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+
+        json_content(&page)
+    }
+
+    async fn tree(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let max_depth = get_usize(&params, "max_depth", 3).max(1);
+        let limit = get_usize(&params, "limit", 300).clamp(1, 2000);
+
+        let root = self.search_root(&params)?;
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+        let listing = tokio::task::spawn_blocking(move || {
+            search::tree(&root, &ignore_patterns, max_depth, limit)
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        Ok(vec![Content::text(listing)])
     }
 
     async fn text_editor(&self, params: Value) -> Result<Vec<Content>, ToolError> {
@@ -753,6 +1231,18 @@ impl DeveloperRouter {
 
                 self.text_editor_insert(&path, insert_line, new_str).await
             }
+            "apply_patch" => {
+                let patch = params.get("patch").and_then(|v| v.as_str());
+                let edits = params
+                    .get("edits")
+                    .map(|v| serde_json::from_value::<Vec<Edit>>(v.clone()))
+                    .transpose()
+                    .map_err(|e| {
+                        ToolError::InvalidParameters(format!("Invalid 'edits' parameter: {}", e))
+                    })?;
+
+                self.text_editor_apply_patch(&path, patch, edits).await
+            }
             "undo_edit" => self.text_editor_undo(&path).await,
             _ => Err(ToolError::InvalidParameters(format!(
                 "Unknown command '{}'",
@@ -1166,7 +1656,118 @@ impl DeveloperRouter {
         ])
     }
 
+    async fn text_editor_apply_patch(
+        &self,
+        path: &Path,
+        patch: Option<&str>,
+        edits: Option<Vec<Edit>>,
+    ) -> Result<Vec<Content>, ToolError> {
+        // Relative paths in the patch are resolved against `path`, or its directory
+        let base = if path.is_file() {
+            path.parent().unwrap_or(path).to_path_buf()
+        } else {
+            path.to_path_buf()
+        };
+        let read = |file: &Path| std::fs::read_to_string(file).ok();
+        let changes = match (patch, edits) {
+            (Some(patch), None) => patch::plan_diff(&base, patch, read),
+            (None, Some(edits)) => patch::plan_edits(&base, &edits, read),
+            _ => Err("The apply_patch command needs exactly one of 'patch' or 'edits'".to_string()),
+        }
+        .map_err(ToolError::InvalidParameters)?;
+
+        for change in &changes {
+            if self.is_ignored(&change.path) {
+                return Err(ToolError::ExecutionError(format!(
+                    "Access to '{}' is restricted by .gooseignore",
+                    change.path.display()
+                )));
+            }
+        }
+
+        // Every hunk has been checked, so only an I/O error can fail now. If one does, put
+        // back the files already written so the patch still applies all or nothing.
+        for (i, change) in changes.iter().enumerate() {
+            if let Err(e) = patch::write_contents(&change.path, change.after.as_deref()) {
+                for done in changes[..i].iter().rev() {
+                    let _ = patch::write_contents(&done.path, done.before.as_deref());
+                }
+                return Err(ToolError::ExecutionError(format!(
+                    "Failed to write {}: {}. No files were changed.",
+                    change.path.display(),
+                    e
+                )));
+            }
+        }
+
+        let mut patches = self.patch_history.lock().unwrap();
+        let mut history = self.file_history.lock().unwrap();
+        let files = changes
+            .iter()
+            .map(|change| {
+                let entries = history.entry(change.path.clone()).or_default();
+                entries.push(change.before.clone().unwrap_or_default());
+                PatchedFile {
+                    path: change.path.clone(),
+                    before: change.before.clone(),
+                    history_len: entries.len(),
+                }
+            })
+            .collect();
+        patches.push(AppliedPatch {
+            base: base.clone(),
+            files,
+        });
+
+        Ok(vec![Content::text(format!(
+            "Applied the patch:\n{}",
+            patch::summarize(&base, &changes)
+        ))])
+    }
+
+    // Revert the latest patch that touched `path`, or was applied at it, if none of its
+    // files have been edited since. Returns None when there is no such patch.
+    fn undo_patch(&self, path: &Path) -> Result<Option<Vec<Content>>, ToolError> {
+        let mut patches = self.patch_history.lock().unwrap();
+        let Some(index) = patches
+            .iter()
+            .rposition(|p| p.base == path || p.files.iter().any(|f| f.path == path))
+        else {
+            return Ok(None);
+        };
+
+        let mut history = self.file_history.lock().unwrap();
+        let unchanged_since = patches[index]
+            .files
+            .iter()
+            .all(|f| history.get(&f.path).map(Vec::len) == Some(f.history_len));
+        if !unchanged_since {
+            return Ok(None);
+        }
+
+        let applied = patches.remove(index);
+        for file in applied.files.iter().rev() {
+            if let Some(entries) = history.get_mut(&file.path) {
+                entries.pop();
+            }
+            patch::write_contents(&file.path, file.before.as_deref()).map_err(|e| {
+                ToolError::ExecutionError(format!(
+                    "Failed to restore {}: {}",
+                    file.path.display(),
+                    e
+                ))
+            })?;
+        }
+        Ok(Some(vec![Content::text(format!(
+            "Undid the patch, restoring {} files",
+            applied.files.len()
+        ))]))
+    }
+
     async fn text_editor_undo(&self, path: &PathBuf) -> Result<Vec<Content>, ToolError> {
+        if let Some(result) = self.undo_patch(path)? {
+            return Ok(result);
+        }
         let mut history = self.file_history.lock().unwrap();
         if let Some(contents) = history.get_mut(path) {
             if let Some(previous_content) = contents.pop() {
@@ -1438,7 +2039,11 @@ impl Router for DeveloperRouter {
         Box::pin(async move {
             match tool_name.as_str() {
                 "shell" => this.bash(arguments, notifier).await,
+                "shell_job" => this.shell_job(arguments).await,
                 "text_editor" => this.text_editor(arguments).await,
+                "search" => this.search(arguments).await,
+                "glob" => this.glob(arguments).await,
+                "tree" => this.tree(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
                 "image_processor" => this.image_processor(arguments).await,
@@ -1463,6 +2068,14 @@ impl Router for DeveloperRouter {
         self.prompts.values().cloned().collect()
     }
 
+    fn roots_changed(&self, roots: Vec<Root>) {
+        let dirs: Vec<PathBuf> = roots
+            .iter()
+            .filter_map(|root| Url::parse(&root.uri).ok()?.to_file_path().ok())
+            .collect();
+        *self.roots.lock().unwrap() = dirs;
+    }
+
     fn get_prompt(
         &self,
         prompt_name: &str,
@@ -1498,8 +2111,13 @@ impl Clone for DeveloperRouter {
             prompts: Arc::clone(&self.prompts),
             instructions: self.instructions.clone(),
             file_history: Arc::clone(&self.file_history),
+            patch_history: Arc::clone(&self.patch_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
             editor_model: create_editor_model(), // Recreate the editor model since it's not Clone
+            roots: Arc::clone(&self.roots),
+            sandbox: self.sandbox.clone(),
+            jobs: Arc::clone(&self.jobs),
+            shell_session: Arc::clone(&self.shell_session),
         }
     }
 }
@@ -1600,6 +2218,188 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[tokio::test]
+    #[serial]
+    #[cfg(not(windows))]
+    async fn test_shell_runs_in_client_root() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let router = DeveloperRouter::new();
+        router.roots_changed(vec![Root {
+            uri: Url::from_file_path(root_dir.path()).unwrap().to_string(),
+            name: None,
+        }]);
+
+        let result = router
+            .call_tool("shell", json!({"command": "pwd"}), dummy_sender())
+            .await
+            .unwrap();
+        let output = result[0].as_text().unwrap();
+        let dir_name = root_dir.path().file_name().unwrap().to_string_lossy();
+        assert!(output.contains(dir_name.as_ref()));
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[cfg(not(windows))]
+    async fn test_shell_timeout_returns_partial_output() {
+        let router = DeveloperRouter::new();
+        let result = router
+            .call_tool(
+                "shell",
+                json!({"command": "echo started && sleep 30", "timeout_secs": 1}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let output = result[0].as_text().unwrap();
+        assert!(output.starts_with("started\n"));
+        assert!(output.contains("timed out after 1 seconds"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[cfg(not(windows))]
+    async fn test_shell_background_job() {
+        let router = DeveloperRouter::new();
+        let result = router
+            .call_tool(
+                "shell",
+                json!({"command": "echo ready && sleep 30", "background": true}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        assert!(result[0].as_text().unwrap().contains("job-1"));
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let output = router
+            .call_tool(
+                "shell_job",
+                json!({"action": "output", "job_id": "job-1"}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        assert!(output[0].as_text().unwrap().contains("running"));
+        assert!(output[0].as_text().unwrap().contains("ready"));
+
+        router
+            .call_tool(
+                "shell_job",
+                json!({"action": "kill", "job_id": "job-1"}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let listing = router
+            .call_tool("shell_job", json!({"action": "list"}), dummy_sender())
+            .await
+            .unwrap();
+        assert!(listing[0].as_text().unwrap().contains("job-1 (killed"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[cfg(not(windows))]
+    async fn test_shell_persistent_session_keeps_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = DeveloperRouter::new();
+        router
+            .call_tool(
+                "shell",
+                json!({"command": format!("cd {}", dir.path().display()), "persistent": true}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let result = router
+            .call_tool(
+                "shell",
+                json!({"command": "pwd", "persistent": true}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let dir_name = dir.path().file_name().unwrap().to_string_lossy();
+        assert!(result[0].as_text().unwrap().contains(dir_name.as_ref()));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_search_respects_gooseignore() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+        fs::write(temp_dir.path().join(".gooseignore"), "secret.txt").unwrap();
+        fs::write(temp_dir.path().join("secret.txt"), "token = 123").unwrap();
+        fs::write(temp_dir.path().join("config.txt"), "token = none").unwrap();
+
+        let router = DeveloperRouter::new();
+        let result = router
+            .call_tool("search", json!({"pattern": "token"}), dummy_sender())
+            .await
+            .unwrap();
+        let page: Value = serde_json::from_str(result[0].as_text().unwrap()).unwrap();
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["results"][0]["path"], "config.txt");
+
+        temp_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_text_editor_apply_patch_all_or_nothing_and_undo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base = temp_dir.path();
+        fs::write(base.join("a.txt"), "alpha\n").unwrap();
+        fs::write(base.join("b.txt"), "beta\n").unwrap();
+        let router = DeveloperRouter::new();
+
+        // The second edit doesn't match, so neither file changes
+        let result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "apply_patch",
+                    "path": base.to_str().unwrap(),
+                    "edits": [
+                        {"path": "a.txt", "old_str": "alpha", "new_str": "ALPHA"},
+                        {"path": "b.txt", "old_str": "missing", "new_str": "BETA"}
+                    ]
+                }),
+                dummy_sender(),
+            )
+            .await;
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+        assert_eq!(fs::read_to_string(base.join("a.txt")).unwrap(), "alpha\n");
+
+        let patch = "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-alpha\n+ALPHA\n\
+                     --- a/b.txt\n+++ b/b.txt\n@@ -1 +1 @@\n-beta\n+BETA\n\
+                     --- /dev/null\n+++ b/c.txt\n@@ -0,0 +1 @@\n+gamma\n";
+        router
+            .call_tool(
+                "text_editor",
+                json!({"command": "apply_patch", "path": base.to_str().unwrap(), "patch": patch}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(base.join("a.txt")).unwrap(), "ALPHA\n");
+        assert_eq!(fs::read_to_string(base.join("b.txt")).unwrap(), "BETA\n");
+        assert_eq!(fs::read_to_string(base.join("c.txt")).unwrap(), "gamma\n");
+
+        router
+            .call_tool(
+                "text_editor",
+                json!({"command": "undo_edit", "path": base.to_str().unwrap()}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(base.join("a.txt")).unwrap(), "alpha\n");
+        assert_eq!(fs::read_to_string(base.join("b.txt")).unwrap(), "beta\n");
+        assert!(!base.join("c.txt").exists());
+    }
+
     #[tokio::test]
     #[serial]
     #[cfg(windows)]
@@ -1928,8 +2728,13 @@ mod tests {
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
+            patch_history: Arc::new(Mutex::new(Vec::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            roots: Arc::new(Mutex::new(Vec::new())),
+            sandbox: None,
+            jobs: Arc::new(BackgroundJobs::default()),
+            shell_session: Arc::new(tokio::sync::Mutex::new(None)),
         };
 
         // Test basic file matching
@@ -1979,8 +2784,13 @@ mod tests {
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
+            patch_history: Arc::new(Mutex::new(Vec::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            roots: Arc::new(Mutex::new(Vec::new())),
+            sandbox: None,
+            jobs: Arc::new(BackgroundJobs::default()),
+            shell_session: Arc::new(tokio::sync::Mutex::new(None)),
         };
 
         // Try to write to an ignored file
@@ -2039,8 +2849,13 @@ mod tests {
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
             file_history: Arc::new(Mutex::new(HashMap::new())),
+            patch_history: Arc::new(Mutex::new(Vec::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            roots: Arc::new(Mutex::new(Vec::new())),
+            sandbox: None,
+            jobs: Arc::new(BackgroundJobs::default()),
+            shell_session: Arc::new(tokio::sync::Mutex::new(None)),
         };
 
         // Create an ignored file
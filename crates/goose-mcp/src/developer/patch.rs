@@ -0,0 +1,574 @@
+//! Multi-file patches for the text_editor `apply_patch` command
+//!
+//! A patch is either a unified diff or a list of string replacements. Every change is
+//! worked out in memory first, so a hunk that doesn't apply leaves every file untouched.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+
+/// A replacement in the `edits` form of a patch
+#[derive(Debug, Clone, Deserialize)]
+pub struct Edit {
+    pub path: String,
+    /// Text to replace, which must appear exactly once. Empty to create a new file.
+    #[serde(default)]
+    pub old_str: String,
+    pub new_str: String,
+}
+
+/// The contents of one file before and after the patch, `None` meaning it doesn't exist
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    header: String,
+    // 0-based index of the original lines the hunk replaces
+    old_index: usize,
+    lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Add(text) => Some(text.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FileDiff {
+    // None for /dev/null, i.e. the file is created or deleted
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+    no_newline_at_end: bool,
+}
+
+fn diff_path(spec: &str) -> Option<String> {
+    // Drop a trailing timestamp, as written by `diff -u`
+    let spec = spec.split('\t').next().unwrap_or(spec).trim();
+    if spec == "/dev/null" {
+        return None;
+    }
+    let spec = spec
+        .strip_prefix("a/")
+        .or_else(|| spec.strip_prefix("b/"))
+        .unwrap_or(spec);
+    Some(spec.to_string())
+}
+
+/// Where the hunk applies in the original file, as a 0-based line index
+fn parse_hunk_header(line: &str) -> Result<usize, String> {
+    // @@ -start[,count] +start[,count] @@ optional section
+    let invalid = || format!("Invalid hunk header '{}'", line);
+    let old = line
+        .trim_start_matches("@@")
+        .split_whitespace()
+        .next()
+        .and_then(|range| range.strip_prefix('-'))
+        .ok_or_else(invalid)?;
+    let (start, count) = match old.split_once(',') {
+        Some((start, count)) => (start, count.parse::<usize>().map_err(|_| invalid())?),
+        None => (old, 1),
+    };
+    let start: usize = start.parse().map_err(|_| invalid())?;
+    // A hunk that only adds lines names the line it inserts after
+    Ok(if count == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    })
+}
+
+/// Split a unified diff into the changes for each file
+fn parse_unified_diff(patch: &str) -> Result<Vec<FileDiff>, String> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut lines = patch.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| format!("Expected a '+++' line after '{}'", line))?;
+            files.push(FileDiff {
+                old_path: diff_path(old),
+                new_path: diff_path(new),
+                hunks: Vec::new(),
+                no_newline_at_end: false,
+            });
+        } else if line.starts_with("@@") {
+            let file = files
+                .last_mut()
+                .ok_or_else(|| format!("Hunk '{}' comes before any file header", line))?;
+            let mut hunk = Hunk {
+                header: line.to_string(),
+                old_index: parse_hunk_header(line)?,
+                lines: Vec::new(),
+            };
+            while let Some(&next) = lines.peek() {
+                let parsed = if let Some(text) = next.strip_prefix('+') {
+                    HunkLine::Add(text.to_string())
+                } else if let Some(text) = next.strip_prefix('-') {
+                    if next.starts_with("--- ") && is_file_header(&mut lines.clone()) {
+                        break;
+                    }
+                    HunkLine::Remove(text.to_string())
+                } else if let Some(text) = next.strip_prefix(' ') {
+                    HunkLine::Context(text.to_string())
+                } else if next.is_empty() {
+                    // Some tools drop the space on empty context lines
+                    HunkLine::Context(String::new())
+                } else if next.starts_with('\\') {
+                    // "\ No newline at end of file" refers to the line before it
+                    if matches!(
+                        hunk.lines.last(),
+                        Some(HunkLine::Add(_) | HunkLine::Context(_))
+                    ) {
+                        file.no_newline_at_end = true;
+                    }
+                    lines.next();
+                    continue;
+                } else {
+                    break;
+                };
+                hunk.lines.push(parsed);
+                lines.next();
+            }
+            file.hunks.push(hunk);
+        }
+        // Anything else (diff --git, index, mode lines) carries nothing we need
+    }
+
+    if files.is_empty() {
+        return Err("The patch doesn't contain any file changes".to_string());
+    }
+    Ok(files)
+}
+
+fn is_file_header<'a>(lines: &mut impl Iterator<Item = &'a str>) -> bool {
+    lines.next();
+    lines.next().is_some_and(|l| l.starts_with("+++ "))
+}
+
+/// Find where `needle` occurs in `haystack`, preferring the spot closest to `expected`
+fn find_lines(haystack: &[String], needle: &[&str], expected: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(expected.min(haystack.len()));
+    }
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    let last = haystack.len() - needle.len();
+    let matches_at = |start: usize| {
+        haystack[start..start + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+    let expected = expected.min(last);
+    (0..=last.max(expected)).find_map(|distance| {
+        [expected.checked_sub(distance), Some(expected + distance)]
+            .into_iter()
+            .flatten()
+            .filter(|&start| start <= last)
+            .find(|&start| matches_at(start))
+    })
+}
+
+fn split_lines(content: &str) -> (Vec<String>, &'static str, bool) {
+    let ending = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines = content.lines().map(str::to_string).collect();
+    (lines, ending, content.ends_with('\n') || content.is_empty())
+}
+
+fn apply_hunks(path: &Path, content: &str, diff: &FileDiff) -> Result<String, String> {
+    let (mut lines, ending, trailing_newline) = split_lines(content);
+    // Lines added or removed by earlier hunks shift where later ones start
+    let mut shift: isize = 0;
+    for hunk in &diff.hunks {
+        let old = hunk.old_lines();
+        let expected = (hunk.old_index as isize + shift).max(0) as usize;
+        let start = find_lines(&lines, &old, expected).ok_or_else(|| {
+            format!(
+                "Hunk '{}' does not match the current contents of {}",
+                hunk.header,
+                path.display()
+            )
+        })?;
+        let new: Vec<String> = hunk.new_lines().into_iter().map(str::to_string).collect();
+        shift += new.len() as isize - old.len() as isize;
+        lines.splice(start..start + old.len(), new);
+    }
+    let mut result = lines.join(ending);
+    if !lines.is_empty() && trailing_newline && !diff.no_newline_at_end {
+        result.push_str(ending);
+    }
+    Ok(result)
+}
+
+/// `relative` joined to `base`, without `.` or `..` components
+///
+/// Patches may only touch files beneath `base`, so absolute paths and paths that climb out
+/// of it are rejected.
+fn resolve(base: &Path, relative: &str) -> Result<PathBuf, String> {
+    let mut parts: Vec<&std::ffi::OsStr> = Vec::new();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err(format!("{} is outside {}", relative, base.display()));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!(
+                    "{} must be relative to {}",
+                    relative,
+                    base.display()
+                ))
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err(format!("{} is not a file path", relative));
+    }
+    Ok(parts
+        .iter()
+        .fold(base.to_path_buf(), |path, part| path.join(part)))
+}
+
+/// Work out the changes a unified diff makes, with paths relative to `base`
+///
+/// `read` returns a file's current contents, or None if it doesn't exist.
+pub fn plan_diff(
+    base: &Path,
+    patch: &str,
+    read: impl Fn(&Path) -> Option<String>,
+) -> Result<Vec<FileChange>, String> {
+    let mut changes: Vec<FileChange> = Vec::new();
+    for diff in parse_unified_diff(patch)? {
+        let target = diff
+            .new_path
+            .as_ref()
+            .or(diff.old_path.as_ref())
+            .ok_or("A file diff has /dev/null as both paths")?;
+        let path = resolve(base, target)?;
+        let before = match changes.iter().find(|c| c.path == path) {
+            Some(earlier) => earlier.after.clone(),
+            None => read(&path),
+        };
+
+        let after = match (&diff.old_path, &diff.new_path) {
+            (None, Some(_)) => {
+                if before.is_some() {
+                    return Err(format!("{} already exists", path.display()));
+                }
+                Some(apply_hunks(&path, "", &diff)?)
+            }
+            (Some(_), None) => {
+                if before.is_none() {
+                    return Err(format!("{} does not exist", path.display()));
+                }
+                None
+            }
+            _ => {
+                let content = before
+                    .as_deref()
+                    .ok_or_else(|| format!("{} does not exist", path.display()))?;
+                Some(apply_hunks(&path, content, &diff)?)
+            }
+        };
+        record(&mut changes, path, before, after);
+    }
+    Ok(changes)
+}
+
+/// Work out the changes a list of replacements makes, with relative paths under `base`
+pub fn plan_edits(
+    base: &Path,
+    edits: &[Edit],
+    read: impl Fn(&Path) -> Option<String>,
+) -> Result<Vec<FileChange>, String> {
+    let mut changes: Vec<FileChange> = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        let path = resolve(base, &edit.path)?;
+        let before = match changes.iter().find(|c| c.path == path) {
+            Some(earlier) => earlier.after.clone(),
+            None => read(&path),
+        };
+
+        let after = match (&before, edit.old_str.is_empty()) {
+            (None, true) => edit.new_str.clone(),
+            (Some(_), true) => {
+                return Err(format!(
+                    "Edit {} has an empty old_str but {} already exists",
+                    i + 1,
+                    path.display()
+                ))
+            }
+            (None, false) => return Err(format!("{} does not exist", path.display())),
+            (Some(content), false) => match content.matches(&edit.old_str).count() {
+                1 => content.replacen(&edit.old_str, &edit.new_str, 1),
+                0 => {
+                    return Err(format!(
+                        "Edit {}: old_str was not found in {}",
+                        i + 1,
+                        path.display()
+                    ))
+                }
+                _ => {
+                    return Err(format!(
+                        "Edit {}: old_str appears more than once in {}, include more context",
+                        i + 1,
+                        path.display()
+                    ))
+                }
+            },
+        };
+        record(&mut changes, path, before, Some(after));
+    }
+    Ok(changes)
+}
+
+// Fold a change into the plan, keeping one entry per file with its original contents
+fn record(
+    changes: &mut Vec<FileChange>,
+    path: PathBuf,
+    before: Option<String>,
+    after: Option<String>,
+) {
+    match changes.iter_mut().find(|c| c.path == path) {
+        Some(existing) => existing.after = after,
+        None => changes.push(FileChange {
+            path,
+            before,
+            after,
+        }),
+    }
+}
+
+/// A file changed by an applied patch
+#[derive(Debug, Clone)]
+pub struct PatchedFile {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    /// Length of the file's edit history right after the patch was recorded
+    pub history_len: usize,
+}
+
+/// The files changed by one `apply_patch`, so `undo_edit` can revert them together
+#[derive(Debug, Clone)]
+pub struct AppliedPatch {
+    pub base: PathBuf,
+    pub files: Vec<PatchedFile>,
+}
+
+/// Write a file's new contents, removing it when `contents` is None
+pub fn write_contents(path: &Path, contents: Option<&str>) -> std::io::Result<()> {
+    match contents {
+        Some(contents) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, contents)
+        }
+        None => match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// A short summary of a planned patch, one line per file
+pub fn summarize(base: &Path, changes: &[FileChange]) -> String {
+    let mut lines = Vec::new();
+    for change in changes {
+        let action = match (&change.before, &change.after) {
+            (None, Some(_)) => "created",
+            (Some(_), None) => "deleted",
+            _ => "modified",
+        };
+        let shown = change.path.strip_prefix(base).unwrap_or(&change.path);
+        lines.push(format!("{} {}", action, shown.display()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn reader(files: &[(&str, &str)]) -> impl Fn(&Path) -> Option<String> {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(p, c)| (Path::new("/repo").join(p), c.to_string()))
+            .collect();
+        move |path| files.get(path).cloned()
+    }
+
+    #[test]
+    fn test_plan_diff_modifies_creates_and_deletes() {
+        let patch = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn one() {}
+-fn two() {}
++fn two() -> u8 { 2 }
+ fn three() {}
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1,2 @@
++// new file
++fn four() {}
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+        let read = reader(&[
+            ("src/lib.rs", "fn one() {}\nfn two() {}\nfn three() {}\n"),
+            ("old.txt", "gone\n"),
+        ]);
+        let changes = plan_diff(Path::new("/repo"), patch, read).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            changes[0].after.as_deref(),
+            Some("fn one() {}\nfn two() -> u8 { 2 }\nfn three() {}\n")
+        );
+        assert_eq!(changes[1].before, None);
+        assert_eq!(
+            changes[1].after.as_deref(),
+            Some("// new file\nfn four() {}\n")
+        );
+        assert_eq!(changes[2].after, None);
+        assert_eq!(
+            summarize(Path::new("/repo"), &changes),
+            "modified src/lib.rs\ncreated src/new.rs\ndeleted old.txt"
+        );
+    }
+
+    #[test]
+    fn test_plan_diff_tolerates_shifted_hunks() {
+        let patch = "\
+--- a/notes.txt
++++ b/notes.txt
+@@ -2,2 +2,2 @@
+ beta
+-gamma
++GAMMA
+";
+        let read = reader(&[("notes.txt", "intro\nalpha\nbeta\ngamma\n")]);
+        let changes = plan_diff(Path::new("/repo"), patch, read).unwrap();
+        assert_eq!(
+            changes[0].after.as_deref(),
+            Some("intro\nalpha\nbeta\nGAMMA\n")
+        );
+    }
+
+    #[test]
+    fn test_plan_diff_rejects_mismatched_hunk() {
+        let patch = "\
+--- a/notes.txt
++++ b/notes.txt
+@@ -1,1 +1,1 @@
+-missing
++present
+";
+        let read = reader(&[("notes.txt", "alpha\n")]);
+        let err = plan_diff(Path::new("/repo"), patch, read).unwrap_err();
+        assert!(err.contains("does not match"));
+    }
+
+    #[test]
+    fn test_paths_must_stay_beneath_base() {
+        let base = Path::new("/repo");
+        assert_eq!(
+            resolve(base, "./src/../lib.rs").unwrap(),
+            PathBuf::from("/repo/lib.rs")
+        );
+        assert!(resolve(base, "../etc/passwd").is_err());
+        assert!(resolve(base, "src/../../etc/passwd").is_err());
+        assert!(resolve(base, "/etc/passwd").is_err());
+
+        let edits = vec![Edit {
+            path: "../outside.txt".to_string(),
+            old_str: String::new(),
+            new_str: "x".to_string(),
+        }];
+        assert!(plan_edits(base, &edits, reader(&[])).is_err());
+        let patch = "--- /dev/null\n+++ b/../outside.txt\n@@ -0,0 +1 @@\n+x\n";
+        assert!(plan_diff(base, patch, reader(&[])).is_err());
+    }
+
+    #[test]
+    fn test_plan_edits_chains_edits_to_one_file() {
+        let edits = vec![
+            Edit {
+                path: "a.txt".to_string(),
+                old_str: "one".to_string(),
+                new_str: "uno".to_string(),
+            },
+            Edit {
+                path: "a.txt".to_string(),
+                old_str: "two".to_string(),
+                new_str: "dos".to_string(),
+            },
+            Edit {
+                path: "b.txt".to_string(),
+                old_str: String::new(),
+                new_str: "fresh".to_string(),
+            },
+        ];
+        let read = reader(&[("a.txt", "one two")]);
+        let changes = plan_edits(Path::new("/repo"), &edits, &read).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].before.as_deref(), Some("one two"));
+        assert_eq!(changes[0].after.as_deref(), Some("uno dos"));
+        assert_eq!(changes[1].after.as_deref(), Some("fresh"));
+
+        let duplicate = vec![Edit {
+            path: "a.txt".to_string(),
+            old_str: "o".to_string(),
+            new_str: "0".to_string(),
+        }];
+        assert!(plan_edits(Path::new("/repo"), &duplicate, &read)
+            .unwrap_err()
+            .contains("more than once"));
+    }
+}
@@ -0,0 +1,354 @@
+//! Optional sandbox for commands run by the shell tool
+//!
+//! On Linux the command gets its own session, user and network namespace, landlock limits
+//! writes to the working directory, temp and any extra paths, and a seccomp filter denies
+//! syscalls that a build or test run never needs, including creating unix sockets that could
+//! reach daemons such as docker or ssh-agent. Other platforms refuse to run commands while the
+//! sandbox is enabled rather than quietly running them unrestricted.
+
+use std::path::{Path, PathBuf};
+
+use mcp_core::sandbox::{SANDBOX_ENV, SANDBOX_NETWORK_ENV, SANDBOX_WRITABLE_ENV};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxPolicy {
+    pub allow_network: bool,
+    pub writable_paths: Vec<PathBuf>,
+}
+
+fn is_enabled(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+impl SandboxPolicy {
+    /// Read the policy from the environment, returning None when the sandbox is off
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if !var(SANDBOX_ENV).is_some_and(|v| is_enabled(&v)) {
+            return None;
+        }
+        let writable_paths = var(SANDBOX_WRITABLE_ENV)
+            .map(|paths| {
+                std::env::split_paths(&paths)
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .map(|p| PathBuf::from(shellexpand::tilde(&p.to_string_lossy()).as_ref()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            allow_network: var(SANDBOX_NETWORK_ENV).is_some_and(|v| is_enabled(&v)),
+            writable_paths,
+        })
+    }
+
+    /// The directories a command running in `working_dir` may write to
+    pub fn writable_dirs(&self, working_dir: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![working_dir.to_path_buf(), std::env::temp_dir()];
+        dirs.extend(self.writable_paths.iter().cloned());
+        dirs
+    }
+
+    /// A note for the model so it knows why writes or network access fail
+    pub fn describe(&self) -> String {
+        let network = if self.allow_network {
+            "Network access is allowed."
+        } else {
+            "Network access is blocked."
+        };
+        let mut extra = String::new();
+        for path in &self.writable_paths {
+            extra.push_str(&format!(", {}", path.display()));
+        }
+        format!(
+            "Shell commands run in a sandbox. They can read the whole filesystem but only write \
+            to the working directory, the temp directory{}. {}",
+            extra, network
+        )
+    }
+}
+
+/// Restrict `command` to the policy when it is spawned
+#[cfg(target_os = "linux")]
+pub fn apply(
+    command: &mut Command,
+    policy: &SandboxPolicy,
+    working_dir: &Path,
+) -> std::io::Result<()> {
+    linux::apply(command, policy, working_dir)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(
+    _command: &mut Command,
+    _policy: &SandboxPolicy,
+    _working_dir: &Path,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "The shell sandbox is only available on Linux",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::BTreeMap;
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreated,
+        RulesetCreatedAttr, RulesetError, RulesetStatus, Scope, ABI,
+    };
+    use seccompiler::{
+        BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+        SeccompRule,
+    };
+    use tokio::process::Command;
+
+    use super::SandboxPolicy;
+
+    // Rights newer than the running kernel supports are dropped (best effort), so older
+    // kernels still get everything they can enforce
+    const LANDLOCK_ABI: ABI = ABI::V6;
+
+    // Devices that ordinary commands expect to write to
+    const WRITABLE_DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/full", "/dev/tty"];
+
+    // Syscalls that could undo the sandbox or reach into the rest of the system
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        // io_uring performs operations without syscalls that seccomp could see
+        libc::SYS_io_uring_setup,
+        libc::SYS_io_uring_enter,
+        libc::SYS_io_uring_register,
+    ];
+
+    pub fn apply(
+        command: &mut Command,
+        policy: &SandboxPolicy,
+        working_dir: &Path,
+    ) -> io::Result<()> {
+        // Everything that allocates happens here, before the fork
+        let mut ruleset =
+            Some(landlock_ruleset(&policy.writable_dirs(working_dir)).map_err(io::Error::other)?);
+        let filter = seccomp_filter(policy.allow_network).map_err(io::Error::other)?;
+        let namespace = if policy.allow_network {
+            None
+        } else {
+            Some(NetworkNamespace::new()?)
+        };
+
+        // SAFETY: the closure runs in the child between fork and exec. It only makes raw
+        // syscalls using values prepared above, and allocates only to report an error.
+        unsafe {
+            command.pre_exec(move || {
+                // A new session has no controlling terminal, so /dev/tty can't reach the user's
+                if libc::setsid() < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(namespace) = &namespace {
+                    namespace.enter()?;
+                }
+                if let Some(ruleset) = ruleset.take() {
+                    let status = ruleset.restrict_self().map_err(io::Error::other)?;
+                    if status.ruleset == RulesetStatus::NotEnforced {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            "The shell sandbox needs a kernel with landlock enabled",
+                        ));
+                    }
+                }
+                seccompiler::apply_filter(&filter).map_err(io::Error::other)
+            });
+        }
+        Ok(())
+    }
+
+    /// Read access everywhere, write access only beneath `writable`
+    fn landlock_ruleset(writable: &[PathBuf]) -> Result<RulesetCreated, RulesetError> {
+        let writable: Vec<&Path> = WRITABLE_DEVICES
+            .iter()
+            .map(Path::new)
+            .chain(writable.iter().map(PathBuf::as_path))
+            .filter(|path| path.exists())
+            .collect();
+
+        Ruleset::default()
+            .handle_access(AccessFs::from_all(LANDLOCK_ABI))?
+            // Keeps abstract unix sockets outside the sandbox out of reach
+            .scope(Scope::AbstractUnixSocket)?
+            .create()?
+            .add_rules(path_beneath_rules(["/"], AccessFs::from_read(LANDLOCK_ABI)))?
+            .add_rules(path_beneath_rules(
+                writable,
+                AccessFs::from_all(LANDLOCK_ABI),
+            ))
+    }
+
+    fn seccomp_filter(
+        allow_network: bool,
+    ) -> Result<BpfProgram, Box<dyn std::error::Error + Send + Sync>> {
+        // An empty rule list matches the syscall whatever its arguments
+        let mut rules: BTreeMap<i64, Vec<SeccompRule>> = DENIED_SYSCALLS
+            .iter()
+            .map(|syscall| (*syscall, Vec::new()))
+            .collect();
+
+        // Seccomp can't see the address passed to connect, so unix sockets are refused when
+        // created instead. Otherwise a command could reach the docker socket or ssh-agent and
+        // act outside the sandbox. socketpair stays allowed since it connects nothing outside.
+        let mut families = vec![libc::AF_UNIX];
+        if !allow_network {
+            families.extend([libc::AF_INET, libc::AF_INET6]);
+        }
+        let mut socket_rules = Vec::new();
+        for family in families {
+            socket_rules.push(SeccompRule::new(vec![SeccompCondition::new(
+                0,
+                SeccompCmpArgLen::Dword,
+                SeccompCmpOp::Eq,
+                family as u64,
+            )?])?);
+        }
+        rules.insert(libc::SYS_socket, socket_rules);
+
+        // Pushing input into a terminal would run commands outside the sandbox
+        rules.insert(
+            libc::SYS_ioctl,
+            vec![SeccompRule::new(vec![SeccompCondition::new(
+                1,
+                SeccompCmpArgLen::Dword,
+                SeccompCmpOp::Eq,
+                u64::from(libc::TIOCSTI),
+            )?])?],
+        );
+
+        let filter = SeccompFilter::new(
+            rules,
+            SeccompAction::Allow,
+            SeccompAction::Errno(libc::EPERM as u32),
+            std::env::consts::ARCH.try_into()?,
+        )?;
+        Ok(filter.try_into()?)
+    }
+
+    /// A new user and network namespace, so the command only sees a loopback interface
+    struct NetworkNamespace {
+        uid_map: CString,
+        gid_map: CString,
+    }
+
+    impl NetworkNamespace {
+        fn new() -> io::Result<Self> {
+            // SAFETY: getuid and getgid always succeed
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Ok(Self {
+                uid_map: CString::new(format!("{uid} {uid} 1"))?,
+                gid_map: CString::new(format!("{gid} {gid} 1"))?,
+            })
+        }
+
+        /// Enter the namespace, keeping the caller's uid and gid inside it
+        fn enter(&self) -> io::Result<()> {
+            // SAFETY: unshare only affects the calling process
+            if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            write_proc_file(c"/proc/self/setgroups", c"deny")?;
+            write_proc_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_proc_file(c"/proc/self/gid_map", &self.gid_map)
+        }
+    }
+
+    fn write_proc_file(path: &CStr, contents: &CStr) -> io::Result<()> {
+        let bytes = contents.to_bytes();
+        // SAFETY: both pointers come from live CStrs and the fd is closed before returning
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+            let error = io::Error::last_os_error();
+            libc::close(fd);
+            if written < 0 {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn policy_from(vars: &[(&str, &str)]) -> Option<SandboxPolicy> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        SandboxPolicy::from_vars(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_policy_from_vars() {
+        assert_eq!(policy_from(&[]), None);
+        assert_eq!(policy_from(&[(SANDBOX_ENV, "false")]), None);
+
+        let policy = policy_from(&[
+            (SANDBOX_ENV, "true"),
+            (SANDBOX_WRITABLE_ENV, "/opt/cache:/var/tmp/build"),
+        ])
+        .unwrap();
+        assert!(!policy.allow_network);
+        assert_eq!(
+            policy.writable_paths,
+            vec![PathBuf::from("/opt/cache"), PathBuf::from("/var/tmp/build")]
+        );
+
+        let policy = policy_from(&[(SANDBOX_ENV, "1"), (SANDBOX_NETWORK_ENV, "yes")]).unwrap();
+        assert!(policy.allow_network);
+        assert!(policy.writable_paths.is_empty());
+    }
+
+    #[test]
+    fn test_writable_dirs_start_with_working_dir() {
+        let policy = SandboxPolicy {
+            allow_network: false,
+            writable_paths: vec![PathBuf::from("/opt/cache")],
+        };
+        let dirs = policy.writable_dirs(Path::new("/work/project"));
+        assert_eq!(dirs[0], PathBuf::from("/work/project"));
+        assert!(dirs.contains(&std::env::temp_dir()));
+        assert_eq!(dirs.last(), Some(&PathBuf::from("/opt/cache")));
+    }
+}
@@ -0,0 +1,332 @@
+//! Native file search behind the developer extension's search, glob and tree tools
+//!
+//! Walks respect `.gitignore` files and the router's own ignore patterns, and results are
+//! paged so a broad query can't flood the context.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ignore::gitignore::Gitignore;
+use ignore::overrides::OverrideBuilder;
+use ignore::{DirEntry, WalkBuilder};
+use regex::Regex;
+use serde::Serialize;
+
+// Files larger than this are skipped by content search
+const MAX_SEARCH_FILE_SIZE: u64 = 10 * 1024 * 1024;
+// Matched lines are cut to this many characters
+const MAX_LINE_CHARS: usize = 500;
+
+/// One page of results, with where the next page starts
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub results: Vec<T>,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+}
+
+impl<T> Page<T> {
+    fn collect(items: impl Iterator<Item = T>, offset: usize, limit: usize) -> Self {
+        let mut results = Vec::new();
+        let mut total = 0;
+        for item in items {
+            if total >= offset && results.len() < limit {
+                results.push(item);
+            }
+            total += 1;
+        }
+        let end = offset + results.len();
+        Self {
+            results,
+            total,
+            next_offset: (end < total).then_some(end),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+/// Walk everything under `root` that isn't ignored, in a stable order
+///
+/// Ignored directories are skipped entirely rather than just left out of the results.
+fn walk(
+    root: &Path,
+    ignore_patterns: &Arc<Gitignore>,
+    max_depth: Option<usize>,
+) -> impl Iterator<Item = DirEntry> {
+    let ignore_patterns = Arc::clone(ignore_patterns);
+    WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .max_depth(max_depth)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            entry.file_name() != ".git"
+                && !ignore_patterns
+                    .matched(entry.path(), is_dir(entry))
+                    .is_ignore()
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() > 0)
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn is_file(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(|t| t.is_file())
+}
+
+fn is_dir(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(|t| t.is_dir())
+}
+
+/// Match paths relative to `root` against a gitignore style glob, e.g. `src/**/*.rs`
+fn glob_matcher(root: &Path, pattern: &str) -> Result<ignore::overrides::Override, ignore::Error> {
+    let mut builder = OverrideBuilder::new(root);
+    builder.add(pattern)?;
+    builder.build()
+}
+
+/// Files under `root` whose path matches `pattern`
+pub fn glob(
+    root: &Path,
+    ignore_patterns: &Arc<Gitignore>,
+    pattern: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<String>, ignore::Error> {
+    let matcher = glob_matcher(root, pattern)?;
+    let files = walk(root, ignore_patterns, None)
+        .filter(is_file)
+        .filter(|entry| matcher.matched(entry.path(), false).is_whitelist())
+        .map(|entry| relative(root, entry.path()));
+    Ok(Page::collect(files, offset, limit))
+}
+
+fn is_binary(path: &Path) -> bool {
+    let mut head = [0u8; 8192];
+    match File::open(path).and_then(|mut f| f.read(&mut head)) {
+        Ok(n) => head[..n].contains(&0),
+        Err(_) => true,
+    }
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}...", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// Every line in `path` matching `pattern`, with `context` lines either side
+fn search_file(root: &Path, path: &Path, pattern: &Regex, context: usize) -> Vec<SearchMatch> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| truncate_line(&line))
+        .collect();
+
+    let display_path = relative(root, path);
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| pattern.is_match(line))
+        .map(|(i, line)| SearchMatch {
+            path: display_path.clone(),
+            line: i + 1,
+            text: line.clone(),
+            before: lines[i.saturating_sub(context)..i].to_vec(),
+            after: lines[(i + 1).min(lines.len())..(i + 1 + context).min(lines.len())].to_vec(),
+        })
+        .collect()
+}
+
+/// Lines matching `pattern` in text files under `root`, optionally only in files matching
+/// `file_glob`
+pub fn search(
+    root: &Path,
+    ignore_patterns: &Arc<Gitignore>,
+    pattern: &Regex,
+    file_glob: Option<&str>,
+    context: usize,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<SearchMatch>, ignore::Error> {
+    let matcher = file_glob.map(|glob| glob_matcher(root, glob)).transpose()?;
+    // A single file searches just that file
+    let files: Box<dyn Iterator<Item = PathBuf> + '_> = if root.is_file() {
+        Box::new(std::iter::once(root.to_path_buf()))
+    } else {
+        Box::new(
+            walk(root, ignore_patterns, None)
+                .filter(is_file)
+                .filter(|entry| {
+                    matcher
+                        .as_ref()
+                        .is_none_or(|m| m.matched(entry.path(), false).is_whitelist())
+                })
+                .map(DirEntry::into_path),
+        )
+    };
+    let base = if root.is_file() {
+        root.parent().unwrap_or(root)
+    } else {
+        root
+    };
+
+    let matches = files
+        .filter(|path| {
+            path.metadata()
+                .is_ok_and(|m| m.len() <= MAX_SEARCH_FILE_SIZE)
+                && !is_binary(path)
+        })
+        .flat_map(|path| search_file(base, &path, pattern, context));
+    Ok(Page::collect(matches, offset, limit))
+}
+
+/// An indented listing of the directories and files under `root`
+///
+/// Stops after `limit` entries, saying how many more there were.
+pub fn tree(
+    root: &Path,
+    ignore_patterns: &Arc<Gitignore>,
+    max_depth: usize,
+    limit: usize,
+) -> String {
+    let mut listing = format!("{}/\n", root.display());
+    let mut shown = 0;
+    let mut hidden = 0;
+    for entry in walk(root, ignore_patterns, Some(max_depth)) {
+        if shown >= limit {
+            hidden += 1;
+            continue;
+        }
+        let indent = "  ".repeat(entry.depth());
+        let name = entry.file_name().to_string_lossy();
+        let suffix = if is_dir(&entry) { "/" } else { "" };
+        listing.push_str(&format!("{}{}{}\n", indent, name, suffix));
+        shown += 1;
+    }
+    if hidden > 0 {
+        listing.push_str(&format!(
+            "... {} more entries not shown. List a subdirectory or lower max_depth to see them.\n",
+            hidden
+        ));
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "mod nested;\nfn alpha() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/nested/mod.rs"),
+            "// one\n// two\nfn beta() {}\n// three\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("README.md"), "alpha and beta\n").unwrap();
+        fs::write(dir.path().join("secret.env"), "fn gamma() {}\n").unwrap();
+        dir
+    }
+
+    fn ignore_env(root: &Path) -> Arc<Gitignore> {
+        let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+        builder.add_line(None, "*.env").unwrap();
+        Arc::new(builder.build().unwrap())
+    }
+
+    #[test]
+    fn test_glob_matches_relative_paths() {
+        let dir = project();
+        let page = glob(dir.path(), &ignore_env(dir.path()), "src/**/*.rs", 0, 10).unwrap();
+        assert_eq!(page.results, vec!["src/lib.rs", "src/nested/mod.rs"]);
+        assert_eq!(page.total, 2);
+        assert_eq!(page.next_offset, None);
+
+        let page = glob(dir.path(), &ignore_env(dir.path()), "*", 0, 1).unwrap();
+        assert_eq!(page.results, vec!["README.md"]);
+        assert_eq!(page.next_offset, Some(1));
+    }
+
+    #[test]
+    fn test_search_returns_context_and_skips_ignored() {
+        let dir = project();
+        let pattern = Regex::new(r"fn \w+").unwrap();
+        let page = search(
+            dir.path(),
+            &ignore_env(dir.path()),
+            &pattern,
+            None,
+            1,
+            0,
+            10,
+        )
+        .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(
+            page.results[1],
+            SearchMatch {
+                path: "src/nested/mod.rs".to_string(),
+                line: 3,
+                text: "fn beta() {}".to_string(),
+                before: vec!["// two".to_string()],
+                after: vec!["// three".to_string()],
+            }
+        );
+
+        let page = search(
+            dir.path(),
+            &ignore_env(dir.path()),
+            &pattern,
+            Some("*.md"),
+            0,
+            0,
+            10,
+        )
+        .unwrap();
+        assert_eq!(page.total, 0);
+    }
+
+    #[test]
+    fn test_tree_indents_and_limits() {
+        let dir = project();
+        let listing = tree(dir.path(), &ignore_env(dir.path()), 2, 10);
+        assert!(listing.contains("\n  src/\n    lib.rs\n    nested/\n"));
+        assert!(!listing.contains("secret.env"));
+        assert!(!listing.contains("mod.rs"));
+
+        let listing = tree(dir.path(), &ignore_env(dir.path()), 3, 2);
+        assert!(listing.contains("3 more entries not shown"));
+    }
+}
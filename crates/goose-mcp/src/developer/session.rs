@@ -0,0 +1,244 @@
+//! A long lived bash process, so `cd` and `export` carry over between shell tool calls
+
+use std::io;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::Instant;
+
+/// How a command run in the session finished
+#[derive(Debug, PartialEq)]
+pub enum SessionStatus {
+    Exited(i32),
+    TimedOut,
+    /// The shell itself exited, e.g. because the command ran `exit`
+    Closed,
+}
+
+pub struct ShellSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    marker: String,
+    runs: u64,
+    /// The marker of a run whose caller stopped waiting before it finished
+    unfinished: Option<String>,
+    /// Processes the commands left running in the background
+    background: Vec<u32>,
+}
+
+impl ShellSession {
+    /// The command that starts a session shell, ready for [`ShellSession::start`]
+    pub fn command() -> Command {
+        let mut command = Command::new("bash");
+        command
+            .args(["--noprofile", "--norc"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        command
+    }
+
+    pub async fn start(mut command: Command) -> io::Result<Self> {
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take().expect("session stdin is piped");
+        let stdout = child.stdout.take().expect("session stdout is piped");
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        let mut session = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            marker: format!("__GOOSE_COMMAND_DONE_{}_{}", std::process::id(), nanos),
+            runs: 0,
+            unfinished: None,
+            background: Vec::new(),
+        };
+        // Everything the shell writes, including its own errors, goes to the one pipe
+        session.stdin.write_all(b"exec 2>&1\n").await?;
+        Ok(session)
+    }
+
+    /// Run `command` in the session, calling `on_line` with each line of output as it arrives
+    ///
+    /// Returns everything the command wrote. After a timeout or `Closed` the session can't
+    /// be reused, since the shell may still be busy or gone. If an earlier run was cancelled,
+    /// whatever it still writes is skipped before this command's output is read.
+    pub async fn run(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+        mut on_line: impl FnMut(&str),
+    ) -> io::Result<(String, SessionStatus)> {
+        self.runs += 1;
+        let marker = format!("{}_{}__", self.marker, self.runs);
+        // Commands read from /dev/null so they can't consume the script we send next. The
+        // marker line also lists the jobs left in the background, to stop with the session.
+        let script = format!(
+            "{{ {}\n}} < /dev/null 2>&1; __goose_status=$?; __goose_jobs=$(jobs -p); printf '\\n%s %s %s\\n' '{}' \"$__goose_status\" \"${{__goose_jobs//$'\\n'/ }}\"\n",
+            command, marker
+        );
+        self.stdin.write_all(script.as_bytes()).await?;
+        self.stdin.flush().await?;
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut output = String::new();
+        let mut buf = Vec::new();
+        // Until this run finishes, a later one has to skip its output
+        let mut draining = self.unfinished.replace(marker.clone());
+        loop {
+            let read = self.stdout.read_until(b'\n', &mut buf);
+            let n = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, read).await {
+                    Ok(n) => n?,
+                    Err(_) => return Ok((output, SessionStatus::TimedOut)),
+                },
+                None => read.await?,
+            };
+            if n == 0 {
+                return Ok((output, SessionStatus::Closed));
+            }
+
+            let line = String::from_utf8_lossy(&buf).into_owned();
+            buf.clear();
+            if let Some(stale) = &draining {
+                if let Some(rest) = line.split_once(stale.as_str()).map(|(_, rest)| rest) {
+                    self.track_background(rest);
+                    draining = None;
+                }
+                continue;
+            }
+            if let Some(rest) = line.trim_end().strip_prefix(&marker) {
+                let mut fields = rest.split_whitespace();
+                let Some(code) = fields.next().and_then(|code| code.parse().ok()) else {
+                    continue;
+                };
+                self.track_background(rest);
+                self.unfinished = None;
+                // Drop the newline printed ahead of the marker
+                if output.ends_with('\n') {
+                    output.pop();
+                }
+                return Ok((output, SessionStatus::Exited(code)));
+            }
+            on_line(&line);
+            output.push_str(&line);
+        }
+    }
+
+    /// Remember the background jobs listed after the exit code on a marker line
+    fn track_background(&mut self, marker_rest: &str) {
+        self.background = marker_rest
+            .split_whitespace()
+            .skip(1)
+            .filter_map(|pid| pid.parse().ok())
+            .collect();
+    }
+
+    /// Stop the shell and anything still running in it
+    pub async fn kill(self) {
+        let _ = tokio::task::spawn_blocking(move || drop(self)).await;
+    }
+}
+
+impl Drop for ShellSession {
+    /// Background jobs would outlive the shell, so they're stopped along with it
+    fn drop(&mut self) {
+        let pids = self.child.id().into_iter().chain(self.background.drain(..));
+        for pid in pids {
+            let _ = kill_tree::blocking::kill_tree(pid);
+        }
+        let _ = self.child.start_kill();
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(windows))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_keeps_directory_and_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = ShellSession::start(ShellSession::command()).await.unwrap();
+
+        let (_, status) = session
+            .run(
+                &format!("cd {} && export GREETING=hi", dir.path().display()),
+                None,
+                |_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, SessionStatus::Exited(0));
+
+        let (output, status) = session
+            .run("echo $GREETING; pwd; ls /missing", None, |_| {})
+            .await
+            .unwrap();
+        assert_ne!(status, SessionStatus::Exited(0));
+        let dir_name = dir.path().file_name().unwrap().to_string_lossy();
+        assert!(output.starts_with("hi\n"));
+        assert!(output.contains(dir_name.as_ref()));
+        assert!(output.contains("/missing"));
+    }
+
+    #[tokio::test]
+    async fn test_session_times_out() {
+        let mut session = ShellSession::start(ShellSession::command()).await.unwrap();
+        let (output, status) = session
+            .run(
+                "echo started; sleep 5",
+                Some(Duration::from_millis(500)),
+                |_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, SessionStatus::TimedOut);
+        assert_eq!(output, "started\n");
+        session.kill().await;
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_output_is_skipped() {
+        let mut session = ShellSession::start(ShellSession::command()).await.unwrap();
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(200),
+            session.run("sleep 1; echo stale", None, |_| {}),
+        )
+        .await;
+        assert!(cancelled.is_err());
+
+        let (output, status) = session.run("echo fresh", None, |_| {}).await.unwrap();
+        assert_eq!(status, SessionStatus::Exited(0));
+        assert_eq!(output, "fresh\n");
+    }
+
+    #[tokio::test]
+    async fn test_background_jobs_stop_with_the_session() {
+        let mut session = ShellSession::start(ShellSession::command()).await.unwrap();
+        let (_, status) = session.run("sleep 30 &", None, |_| {}).await.unwrap();
+        assert_eq!(status, SessionStatus::Exited(0));
+        let pid = session.background[0];
+
+        drop(session);
+        // A killed job may linger as a zombie until it's reaped
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        for _ in 0..50 {
+            if !alive() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!alive());
+    }
+}
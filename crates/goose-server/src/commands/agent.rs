@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::configuration;
 use crate::state;
@@ -45,6 +46,10 @@ pub async fn run() -> Result<()> {
     // NEW: Provide scheduler access to the agent
     agent_ref.set_scheduler(scheduler_instance).await;
 
+    if settings.agent_idle_timeout > 0 {
+        app_state.spawn_idle_eviction(Duration::from_secs(settings.agent_idle_timeout));
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
@@ -8,9 +8,11 @@ use goose::message::{
     SummarizationRequested, ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
 };
 use goose::permission::permission_confirmation::PrincipalType;
+use goose::permission::{ArgumentCondition, ArgumentMatcher, PermissionRule};
 use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
+use goose::providers::routing::RoutingDecision;
 use goose::session::info::SessionInfo;
-use goose::session::SessionMetadata;
+use goose::session::{BranchInfo, ForkOrigin, SessionBranch, SessionMetadata, SessionSearchResult};
 use mcp_core::content::{Annotations, Content, EmbeddedResource, ImageContent, TextContent};
 use mcp_core::handler::ToolResultSchema;
 use mcp_core::resource::ResourceContents;
@@ -35,11 +37,19 @@ use utoipa::OpenApi;
         super::routes::config_management::read_all_config,
         super::routes::config_management::providers,
         super::routes::config_management::upsert_permissions,
+        super::routes::config_management::get_permission_rules,
         super::routes::agent::get_tools,
+        super::routes::agent::create_session_agent,
+        super::routes::agent::list_session_agents,
+        super::routes::agent::get_session_agent,
+        super::routes::agent::evict_session_agent,
         super::routes::reply::confirm_permission,
         super::routes::context::manage_context,
         super::routes::session::list_sessions,
         super::routes::session::get_session_history,
+        super::routes::session::search_sessions,
+        super::routes::session::fork_session,
+        super::routes::session::list_session_branches,
         super::routes::schedule::create_schedule,
         super::routes::schedule::list_schedules,
         super::routes::schedule::delete_schedule,
@@ -49,7 +59,8 @@ use utoipa::OpenApi;
         super::routes::schedule::unpause_schedule,
         super::routes::schedule::kill_running_job,
         super::routes::schedule::inspect_running_job,
-        super::routes::schedule::sessions_handler
+        super::routes::schedule::sessions_handler,
+        super::routes::recipe::validate_parameters
     ),
     components(schemas(
         super::routes::config_management::UpsertConfigQuery,
@@ -61,11 +72,25 @@ use utoipa::OpenApi;
         super::routes::config_management::ExtensionQuery,
         super::routes::config_management::ToolPermission,
         super::routes::config_management::UpsertPermissionsQuery,
+        super::routes::config_management::PermissionRulesResponse,
         super::routes::reply::PermissionConfirmationRequest,
+        super::routes::agent::CreateSessionAgentRequest,
+        super::routes::agent::SessionAgentInfo,
         super::routes::context::ContextManageRequest,
         super::routes::context::ContextManageResponse,
+        super::routes::recipe::ValidateParametersRequest,
+        super::routes::recipe::ValidateParametersResponse,
         super::routes::session::SessionListResponse,
         super::routes::session::SessionHistoryResponse,
+        super::routes::session::SessionSearchResponse,
+        super::routes::session::ForkSessionRequest,
+        super::routes::session::ForkSessionResponse,
+        super::routes::session::SessionBranchesResponse,
+        SessionSearchResult,
+        BranchInfo,
+        ForkOrigin,
+        SessionBranch,
+        RoutingDecision,
         Message,
         MessageContent,
         Content,
@@ -93,6 +118,9 @@ use utoipa::OpenApi;
         ToolAnnotations,
         ToolInfo,
         PermissionLevel,
+        PermissionRule,
+        ArgumentCondition,
+        ArgumentMatcher,
         PrincipalType,
         ModelInfo,
         SessionInfo,
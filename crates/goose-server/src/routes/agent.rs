@@ -1,7 +1,7 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     routing::{get, post},
     Json, Router,
@@ -18,6 +18,7 @@ use goose::{
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use utoipa::ToSchema;
 
 #[derive(Serialize)]
 struct VersionsResponse {
@@ -73,6 +74,38 @@ pub struct GetToolsQuery {
     extension_name: Option<String>,
 }
 
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSessionAgentRequest {
+    /// Session to create the agent for; a new id is generated when omitted
+    session_id: Option<String>,
+    /// Provider for the new agent; it can also be set later through /agent/update_provider
+    provider: Option<String>,
+    /// Model for the provider, defaulting to GOOSE_MODEL
+    model: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionAgentInfo {
+    /// Session the agent serves
+    session_id: String,
+    /// Seconds since the agent last handled a request
+    idle_seconds: u64,
+    /// Seconds since the agent was created
+    age_seconds: u64,
+}
+
+impl From<crate::state::PooledAgentInfo> for SessionAgentInfo {
+    fn from(info: crate::state::PooledAgentInfo) -> Self {
+        Self {
+            session_id: info.session_id,
+            idle_seconds: info.idle.as_secs(),
+            age_seconds: info.age.as_secs(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
@@ -96,7 +129,7 @@ async fn extend_prompt(
     verify_secret_key(&headers, &state)?;
 
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     agent.extend_system_prompt(payload.extension.clone()).await;
@@ -149,7 +182,7 @@ async fn get_tools(
     let config = Config::global();
     let goose_mode = config.get_param("GOOSE_MODE").unwrap_or("auto".to_string());
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     let permission_manager = PermissionManager::default();
@@ -208,7 +241,7 @@ async fn update_agent_provider(
     }
 
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
@@ -246,12 +279,15 @@ async fn update_router_tool_selector(
         })
     })?;
 
-    let agent = state.get_agent().await.map_err(|e| {
-        tracing::error!("Failed to get agent: {}", e);
-        Json(ErrorResponse {
-            error: format!("Failed to get agent: {}", e),
-        })
-    })?;
+    let agent = state
+        .get_agent(session_id_from_headers(&headers))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            Json(ErrorResponse {
+                error: format!("Failed to get agent: {}", e),
+            })
+        })?;
 
     agent
         .update_router_tool_selector(None, Some(true))
@@ -287,12 +323,15 @@ async fn update_session_config(
         })
     })?;
 
-    let agent = state.get_agent().await.map_err(|e| {
-        tracing::error!("Failed to get agent: {}", e);
-        Json(ErrorResponse {
-            error: format!("Failed to get agent: {}", e),
-        })
-    })?;
+    let agent = state
+        .get_agent(session_id_from_headers(&headers))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            Json(ErrorResponse {
+                error: format!("Failed to get agent: {}", e),
+            })
+        })?;
 
     if let Some(response) = payload.response {
         agent.add_final_output_tool(response).await;
@@ -306,6 +345,135 @@ async fn update_session_config(
     }
 }
 
+async fn set_session_provider(
+    agent: &goose::agents::Agent,
+    provider: &str,
+    model: Option<String>,
+) -> Result<(), StatusCode> {
+    let model = model
+        .or_else(|| Config::global().get_param("GOOSE_MODEL").ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let new_provider =
+        create(provider, ModelConfig::new(model)).map_err(|_| StatusCode::BAD_REQUEST)?;
+    agent
+        .update_provider(new_provider)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/sessions",
+    request_body = CreateSessionAgentRequest,
+    responses(
+        (status = 200, description = "Agent created for the session", body = SessionAgentInfo),
+        (status = 400, description = "Invalid provider or model"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 409, description = "An agent already exists for the session")
+    )
+)]
+async fn create_session_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateSessionAgentRequest>,
+) -> Result<Json<SessionAgentInfo>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let session_id = payload
+        .session_id
+        .unwrap_or_else(goose::session::generate_session_id);
+    let agent = state
+        .create_session_agent(session_id.clone())
+        .await
+        .map_err(|_| StatusCode::CONFLICT)?;
+
+    if let Some(provider) = payload.provider {
+        if let Err(status) = set_session_provider(&agent, &provider, payload.model).await {
+            state.agents.evict(&session_id).await;
+            return Err(status);
+        }
+    }
+
+    let info = state
+        .agents
+        .info(&session_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(info.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/sessions",
+    responses(
+        (status = 200, description = "Agents in the session pool", body = Vec<SessionAgentInfo>),
+        (status = 401, description = "Unauthorized - invalid secret key")
+    )
+)]
+async fn list_session_agents(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SessionAgentInfo>>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let agents = state.agents.list().await;
+    Ok(Json(agents.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/sessions/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session the agent serves")
+    ),
+    responses(
+        (status = 200, description = "Agent found", body = SessionAgentInfo),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No agent exists for the session")
+    )
+)]
+async fn get_session_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionAgentInfo>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let info = state
+        .agents
+        .info(&session_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(info.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/agent/sessions/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session the agent serves")
+    ),
+    responses(
+        (status = 204, description = "Agent evicted"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No agent exists for the session")
+    )
+)]
+async fn evict_session_agent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    state
+        .agents
+        .evict(&session_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/agent/versions", get(get_versions))
@@ -318,5 +486,13 @@ pub fn routes(state: Arc<AppState>) -> Router {
             post(update_router_tool_selector),
         )
         .route("/agent/session_config", post(update_session_config))
+        .route(
+            "/agent/sessions",
+            get(list_session_agents).post(create_session_agent),
+        )
+        .route(
+            "/agent/sessions/{session_id}",
+            get(get_session_agent).delete(evict_session_agent),
+        )
         .with_state(state)
 }
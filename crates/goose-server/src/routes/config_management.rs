@@ -12,6 +12,7 @@ use goose::config::APP_STRATEGY;
 use goose::config::{extensions::name_to_key, PermissionManager};
 use goose::config::{ExtensionConfigManager, ExtensionEntry};
 use goose::model::ModelConfig;
+use goose::permission::PermissionRule;
 use goose::providers::base::ProviderMetadata;
 use goose::providers::pricing::{
     get_all_pricing, get_model_pricing, parse_model_id, refresh_pricing,
@@ -78,6 +79,14 @@ pub struct ToolPermission {
 #[derive(Deserialize, ToSchema)]
 pub struct UpsertPermissionsQuery {
     pub tool_permissions: Vec<ToolPermission>,
+    /// Replaces the argument-aware rules when present
+    #[serde(default)]
+    pub rules: Option<Vec<PermissionRule>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PermissionRulesResponse {
+    pub rules: Vec<PermissionRule>,
 }
 
 #[utoipa::path(
@@ -323,6 +332,10 @@ pub struct PricingData {
     pub model: String,
     pub input_token_cost: f64,
     pub output_token_cost: f64,
+    /// Cost per token read from the prompt cache, if the model has cache pricing
+    pub cache_read_token_cost: Option<f64>,
+    /// Cost per token written to the prompt cache, if the model has cache pricing
+    pub cache_write_token_cost: Option<f64>,
     pub currency: String,
     pub context_length: Option<u32>,
 }
@@ -376,6 +389,8 @@ pub async fn get_pricing(
                     model: model.clone(),
                     input_token_cost: pricing.input_cost,
                     output_token_cost: pricing.output_cost,
+                    cache_read_token_cost: pricing.cache_read_cost,
+                    cache_write_token_cost: pricing.cache_write_cost,
                     currency: "$".to_string(),
                     context_length: pricing.context_length,
                 });
@@ -413,6 +428,8 @@ pub async fn get_pricing(
                         model: model_info.name.clone(),
                         input_token_cost: pricing.input_cost,
                         output_token_cost: pricing.output_cost,
+                        cache_read_token_cost: pricing.cache_read_cost,
+                        cache_write_token_cost: pricing.cache_write_cost,
                         currency: "$".to_string(),
                         context_length: pricing.context_length,
                     });
@@ -486,6 +503,14 @@ pub async fn upsert_permissions(
 ) -> Result<Json<String>, StatusCode> {
     verify_secret_key(&headers, &state)?;
 
+    // Reject the whole update rather than save a rule that can't be checked
+    for rule in query.rules.iter().flatten() {
+        if let Err(e) = rule.validate() {
+            tracing::warn!("Rejecting permission rules: {}", e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
     let mut permission_manager = PermissionManager::default();
 
     for tool_permission in &query.tool_permissions {
@@ -495,9 +520,33 @@ pub async fn upsert_permissions(
         );
     }
 
+    if let Some(rules) = query.rules {
+        permission_manager.set_user_rules(rules);
+    }
+
     Ok(Json("Permissions updated successfully".to_string()))
 }
 
+#[utoipa::path(
+    get,
+    path = "/config/permissions/rules",
+    responses(
+        (status = 200, description = "Argument-aware permission rules", body = PermissionRulesResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+    )
+)]
+pub async fn get_permission_rules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<PermissionRulesResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let permission_manager = PermissionManager::default();
+    Ok(Json(PermissionRulesResponse {
+        rules: permission_manager.get_user_rules(),
+    }))
+}
+
 #[utoipa::path(
     post,
     path = "/config/backup",
@@ -648,6 +697,7 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route("/config/recover", post(recover_config))
         .route("/config/validate", get(validate_config))
         .route("/config/permissions", post(upsert_permissions))
+        .route("/config/permissions/rules", get(get_permission_rules))
         .route("/config/current-model", get(get_current_model))
         .with_state(state)
 }
@@ -1,4 +1,4 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
     extract::State,
@@ -54,7 +54,7 @@ async fn manage_context(
     verify_secret_key(&headers, &state)?;
 
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
@@ -3,7 +3,7 @@ use std::path::Path;
 use std::sync::Arc;
 use std::sync::OnceLock;
 
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{extract::State, routing::post, Json, Router};
 use goose::agents::{extension::Envs, ExtensionConfig};
@@ -266,7 +266,7 @@ async fn add_extension(
 
     // Get a reference to the agent
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     let response = agent.add_extension(extension_config).await;
@@ -300,7 +300,7 @@ async fn remove_extension(
 
     // Get a reference to the agent
     let agent = state
-        .get_agent()
+        .get_agent(session_id_from_headers(&headers))
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     match agent.remove_extension(&name).await {
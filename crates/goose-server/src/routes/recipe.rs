@@ -1,10 +1,21 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
 use goose::message::Message;
-use goose::recipe::Recipe;
+use goose::recipe::{
+    validate_parameter_definitions, validate_parameter_values_without_files, Recipe,
+    RecipeParameterRequirement,
+};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use super::utils::verify_secret_key;
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -44,7 +55,7 @@ async fn create_recipe(
         error: Some("Missing agent".to_string()),
     };
     let agent = state
-        .get_agent()
+        .get_agent(None)
         .await
         .map_err(|_| (StatusCode::PRECONDITION_FAILED, Json(error_response)))?;
 
@@ -84,8 +95,111 @@ async fn create_recipe(
     }
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateParametersRequest {
+    #[schema(value_type = Object)]
+    recipe: Recipe,
+    #[serde(default)]
+    values: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidateParametersResponse {
+    values: Option<HashMap<String, String>>,
+    error: Option<String>,
+}
+
+/// Check parameter values against a recipe's parameters, returning the values to render
+/// the recipe with, with defaults filled in
+///
+/// A `file` parameter is only checked to name an existing file, and keeps its path as the
+/// value, so file contents never leave the server. Recipes with `steps` are refused, as only
+/// `goose run` and scheduled jobs run them.
+#[utoipa::path(
+    post,
+    path = "/recipe/parameters/validate",
+    request_body = ValidateParametersRequest,
+    responses(
+        (status = 200, description = "Parameter values are valid", body = ValidateParametersResponse),
+        (status = 400, description = "Invalid parameter definitions or values, or a recipe with steps", body = ValidateParametersResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Recipe Management"
+)]
+async fn validate_parameters(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ValidateParametersRequest>,
+) -> Result<Json<ValidateParametersResponse>, (StatusCode, Json<ValidateParametersResponse>)> {
+    verify_secret_key(&headers, &state).map_err(|status| {
+        (
+            status,
+            Json(ValidateParametersResponse {
+                values: None,
+                error: Some("Unauthorized".to_string()),
+            }),
+        )
+    })?;
+
+    if request.recipe.steps.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ValidateParametersResponse {
+                values: None,
+                error: Some(
+                    "Recipe steps only run with `goose run` or as a scheduled job".to_string(),
+                ),
+            }),
+        ));
+    }
+
+    let parameters = request.recipe.parameters.unwrap_or_default();
+    let mut values = request.values;
+    let mut missing = Vec::new();
+    for param in &parameters {
+        if values.contains_key(&param.key) {
+            continue;
+        }
+        match (&param.default, &param.requirement) {
+            (Some(default), _) => {
+                values.insert(param.key.clone(), default.clone());
+            }
+            (None, RecipeParameterRequirement::Optional) => {}
+            (None, _) => missing.push(param.key.clone()),
+        }
+    }
+
+    let result = validate_parameter_definitions(&parameters).and_then(|_| {
+        if missing.is_empty() {
+            validate_parameter_values_without_files(&parameters, &values)
+        } else {
+            Err(anyhow::anyhow!(
+                "Missing values for required parameters: {}",
+                missing.join(", ")
+            ))
+        }
+    });
+    match result {
+        Ok(values) => Ok(Json(ValidateParametersResponse {
+            values: Some(values),
+            error: None,
+        })),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ValidateParametersResponse {
+                values: None,
+                error: Some(e.to_string()),
+            }),
+        )),
+    }
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/recipe/create", post(create_recipe))
+        .route("/recipe/parameters/validate", post(validate_parameters))
         .with_state(state)
 }
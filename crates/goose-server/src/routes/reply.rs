@@ -1,4 +1,4 @@
-use super::utils::verify_secret_key;
+use super::utils::{session_id_from_headers, verify_secret_key};
 use crate::state::AppState;
 use axum::{
     extract::State,
@@ -13,6 +13,7 @@ use goose::{
     agents::{AgentEvent, SessionConfig},
     message::{Message, MessageContent},
     permission::permission_confirmation::PrincipalType,
+    providers::base::MessageDelta,
 };
 use goose::{
     permission::{Permission, PermissionConfirmation},
@@ -83,6 +84,9 @@ enum MessageEvent {
     Message {
         message: Message,
     },
+    MessageDelta {
+        delta: MessageDelta,
+    },
     Error {
         error: String,
     },
@@ -97,6 +101,10 @@ enum MessageEvent {
         request_id: String,
         message: JsonRpcMessage,
     },
+    /// The conversation was compacted, clients should replace their history with `messages`
+    HistoryReplaced {
+        messages: Vec<Message>,
+    },
 }
 
 async fn stream_event(
@@ -112,6 +120,25 @@ async fn stream_event(
     tx.send(format!("data: {}\n\n", json)).await
 }
 
+/// Pick the pooled agent a conversation runs on
+/// An explicit session header wins, then a pooled agent for the conversation's session id;
+/// otherwise the conversation runs on the default agent
+async fn agent_session_id(
+    state: &AppState,
+    headers: &HeaderMap,
+    session_id: Option<&str>,
+) -> Option<String> {
+    if let Some(header) = session_id_from_headers(headers) {
+        return Some(header.to_string());
+    }
+    let session_id = session_id?;
+    state
+        .agents
+        .contains(session_id)
+        .await
+        .then(|| session_id.to_string())
+}
+
 async fn handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -128,9 +155,10 @@ async fn handler(
     let session_id = request
         .session_id
         .unwrap_or_else(session::generate_session_id);
+    let agent_session_id = agent_session_id(&state, &headers, Some(&session_id)).await;
 
     tokio::spawn(async move {
-        let agent = state.get_agent().await;
+        let agent = state.get_agent(agent_session_id.as_deref()).await;
         let agent = match agent {
             Ok(agent) => {
                 let provider = agent.provider().await;
@@ -253,6 +281,18 @@ async fn handler(
                                 }
                             });
                         }
+                        Ok(Some(Ok(AgentEvent::MessageDelta(delta)))) => {
+                            if let Err(e) = stream_event(MessageEvent::MessageDelta { delta }, &tx).await {
+                                tracing::error!("Error sending message delta through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                                break;
+                            }
+                        }
                         Ok(Some(Ok(AgentEvent::ModelChange { model, mode }))) => {
                             if let Err(e) = stream_event(MessageEvent::ModelChange { model, mode }, &tx).await {
                                 tracing::error!("Error sending model change through channel: {}", e);
@@ -264,6 +304,28 @@ async fn handler(
                                 ).await;
                             }
                         }
+                        Ok(Some(Ok(AgentEvent::HistoryReplaced(messages)))) => {
+                            all_messages = messages.clone();
+                            if let Err(e) = stream_event(MessageEvent::HistoryReplaced { messages }, &tx).await {
+                                tracing::error!("Error sending compacted history through channel: {}", e);
+                                let _ = stream_event(
+                                    MessageEvent::Error {
+                                        error: e.to_string(),
+                                    },
+                                    &tx,
+                                ).await;
+                                break;
+                            }
+
+                            let session_path = session_path.clone();
+                            let messages = all_messages.clone();
+                            let provider = Arc::clone(provider.as_ref().unwrap());
+                            tokio::spawn(async move {
+                                if let Err(e) = session::persist_messages(&session_path, &messages, Some(provider)).await {
+                                    tracing::error!("Failed to store session history: {:?}", e);
+                                }
+                            });
+                        }
                         Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
                             if let Err(e) = stream_event(MessageEvent::Notification{
                                 request_id: request_id.clone(),
@@ -340,9 +402,10 @@ async fn ask_handler(
     let session_id = request
         .session_id
         .unwrap_or_else(session::generate_session_id);
+    let agent_session_id = agent_session_id(&state, &headers, Some(&session_id)).await;
 
     let agent = state
-        .get_agent()
+        .get_agent(agent_session_id.as_deref())
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
@@ -387,10 +450,18 @@ async fn ask_handler(
                     }
                 }
             }
+            Ok(AgentEvent::MessageDelta(_)) => {
+                // Only the complete message is needed for non-streaming
+            }
             Ok(AgentEvent::ModelChange { model, mode }) => {
                 // Log model change for non-streaming
                 tracing::info!("Model changed to {} in {} mode", model, mode);
             }
+            Ok(AgentEvent::HistoryReplaced(messages)) => {
+                // Assistant output so far is part of the compacted history
+                all_messages = messages;
+                response_message = Message::assistant();
+            }
             Ok(AgentEvent::McpNotification(n)) => {
                 // Handle notifications if needed
                 tracing::info!("Received notification: {:?}", n);
@@ -437,6 +508,9 @@ pub struct PermissionConfirmationRequest {
     #[serde(default = "default_principal_type")]
     principal_type: PrincipalType,
     action: String,
+    /// The conversation the tool call belongs to, as sent to `/reply`
+    #[serde(default)]
+    session_id: Option<String>,
 }
 
 fn default_principal_type() -> PrincipalType {
@@ -460,8 +534,9 @@ pub async fn confirm_permission(
 ) -> Result<Json<Value>, StatusCode> {
     verify_secret_key(&headers, &state)?;
 
+    let agent_session_id = agent_session_id(&state, &headers, request.session_id.as_deref()).await;
     let agent = state
-        .get_agent()
+        .get_agent(agent_session_id.as_deref())
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
@@ -488,6 +563,8 @@ pub async fn confirm_permission(
 struct ToolResultRequest {
     id: String,
     result: ToolResult<Vec<Content>>,
+    #[serde(default)]
+    session_id: Option<String>,
 }
 
 async fn submit_tool_result(
@@ -514,8 +591,9 @@ async fn submit_tool_result(
         }
     };
 
+    let agent_session_id = agent_session_id(&state, &headers, payload.session_id.as_deref()).await;
     let agent = state
-        .get_agent()
+        .get_agent(agent_session_id.as_deref())
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     agent.handle_tool_result(payload.id, payload.result).await;
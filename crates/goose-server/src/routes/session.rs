@@ -3,17 +3,17 @@ use std::sync::Arc;
 
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
 use goose::message::Message;
 use goose::session;
 use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
-use goose::session::SessionMetadata;
-use serde::Serialize;
-use utoipa::ToSchema;
+use goose::session::{BranchInfo, SessionMetadata, SessionSearchResult};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -59,6 +59,58 @@ async fn list_sessions(
     Ok(Json(SessionListResponse { sessions }))
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct SessionSearchQuery {
+    /// Words to search for in messages, tool calls and session descriptions
+    q: String,
+    /// Maximum number of results to return
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSearchResponse {
+    /// Matching messages and descriptions, best match first
+    results: Vec<SessionSearchResult>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/search",
+    params(SessionSearchQuery),
+    responses(
+        (status = 200, description = "Search results retrieved successfully", body = SessionSearchResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// Search the history of all sessions
+async fn search_sessions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<SessionSearchQuery>,
+) -> Result<Json<SessionSearchResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let limit = query.limit.unwrap_or(20).min(200);
+    let results = tokio::task::spawn_blocking(move || session::search_sessions(&query.q, limit))
+        .await
+        .map_err(|e| {
+            tracing::error!("Session search task failed: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|e| {
+            tracing::error!("Failed to search sessions: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SessionSearchResponse { results }))
+}
+
 #[utoipa::path(
     get,
     path = "/sessions/{session_id}",
@@ -106,10 +158,133 @@ async fn get_session_history(
     }))
 }
 
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionRequest {
+    /// Number of messages to keep; the new branch continues from this point
+    message_index: usize,
+    /// Name for the new branch, defaults to one based on the root session
+    name: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionResponse {
+    /// Identifier of the new branch session
+    session_id: String,
+    /// Every session in the branch tree, root first
+    branches: Vec<BranchInfo>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBranchesResponse {
+    /// Every session in the branch tree, root first, with its own usage totals
+    branches: Vec<BranchInfo>,
+}
+
+fn existing_session_path(session_id: String) -> Result<std::path::PathBuf, StatusCode> {
+    let path = session::get_path(session::Identifier::Name(session_id))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !session::session_exists(&path) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(path)
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/fork",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session to fork")
+    ),
+    request_body = ForkSessionRequest,
+    responses(
+        (status = 200, description = "Session forked successfully", body = ForkSessionResponse),
+        (status = 400, description = "Invalid message index or branch name"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// Fork a session from one of its messages into a new branch
+async fn fork_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<ForkSessionRequest>,
+) -> Result<Json<ForkSessionResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let source = existing_session_path(session_id)?;
+    let forked = session::fork_session(&source, request.message_index, request.name.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to fork session: {:?}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+    let branches = session::list_branches(&forked).map_err(|e| {
+        tracing::error!("Failed to list session branches: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let session_id = forked
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    Ok(Json(ForkSessionResponse {
+        session_id,
+        branches,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/branches",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for any session in the branch tree")
+    ),
+    responses(
+        (status = 200, description = "Session branches retrieved successfully", body = SessionBranchesResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Session Management"
+)]
+// List every branch in a session's branch tree
+async fn list_session_branches(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionBranchesResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let path = existing_session_path(session_id)?;
+    let branches = session::list_branches(&path).map_err(|e| {
+        tracing::error!("Failed to list session branches: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(SessionBranchesResponse { branches }))
+}
+
 // Configure routes for this module
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/sessions", get(list_sessions))
+        .route("/sessions/search", get(search_sessions))
         .route("/sessions/{session_id}", get(get_session_history))
+        .route("/sessions/{session_id}/fork", post(fork_session))
+        .route(
+            "/sessions/{session_id}/branches",
+            get(list_session_branches),
+        )
         .with_state(state)
 }
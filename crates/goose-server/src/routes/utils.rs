@@ -1,6 +1,7 @@
-use crate::state::AppState;
+use crate::state::{AppState, SESSION_ID_HEADER};
 use goose::config::Config;
 use goose::providers::base::{ConfigKey, ProviderMetadata};
+use goose::providers::custom::custom_provider;
 use http::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -37,6 +38,14 @@ pub fn verify_secret_key(headers: &HeaderMap, state: &AppState) -> Result<Status
     }
 }
 
+/// The session id a request is routed to, if the client sent one
+pub fn session_id_from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+}
+
 /// Inspects a configuration key to determine if it's set, its location, and value (for non-secret keys)
 #[allow(dead_code)]
 pub fn inspect_key(key_name: &str, is_secret: bool) -> Result<KeyInfo, Box<dyn Error>> {
@@ -116,6 +125,11 @@ pub fn check_provider_configured(metadata: &ProviderMetadata) -> bool {
         .filter(|key| key.required)
         .collect();
 
+    // Providers declared under custom_providers need nothing else once declared
+    if required_keys.is_empty() && custom_provider(&metadata.name).is_some() {
+        return true;
+    }
+
     // Special case: If a provider has exactly one required key and that key
     // has a default value, check if it's explicitly set
     if required_keys.len() == 1 && required_keys[0].default.is_some() {
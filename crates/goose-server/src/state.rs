@@ -1,13 +1,127 @@
 use goose::agents::Agent;
 use goose::scheduler_trait::SchedulerTrait;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
 
 pub type AgentRef = Arc<Agent>;
 
+/// Header that routes a request to the agent of a pooled session
+pub const SESSION_ID_HEADER: &str = "X-Session-Id";
+
+struct PooledAgent {
+    agent: AgentRef,
+    created_at: Instant,
+    last_used: Instant,
+}
+
+/// Agents keyed by session id, so concurrent conversations each get their own
+/// extensions, provider and system prompt
+#[derive(Default)]
+pub struct AgentPool {
+    agents: RwLock<HashMap<String, PooledAgent>>,
+}
+
+/// A snapshot of one pooled agent
+#[derive(Debug, Clone)]
+pub struct PooledAgentInfo {
+    pub session_id: String,
+    pub idle: Duration,
+    pub age: Duration,
+}
+
+impl AgentPool {
+    /// Register an agent for the session, failing if one already exists
+    pub async fn insert(&self, session_id: String, agent: AgentRef) -> anyhow::Result<()> {
+        let mut agents = self.agents.write().await;
+        if agents.contains_key(&session_id) {
+            return Err(anyhow::anyhow!(
+                "An agent already exists for session {}",
+                session_id
+            ));
+        }
+        let now = Instant::now();
+        agents.insert(
+            session_id,
+            PooledAgent {
+                agent,
+                created_at: now,
+                last_used: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Get the agent for a session and mark it as used
+    pub async fn get(&self, session_id: &str) -> Option<AgentRef> {
+        let mut agents = self.agents.write().await;
+        let entry = agents.get_mut(session_id)?;
+        entry.last_used = Instant::now();
+        Some(entry.agent.clone())
+    }
+
+    pub async fn contains(&self, session_id: &str) -> bool {
+        self.agents.read().await.contains_key(session_id)
+    }
+
+    pub async fn info(&self, session_id: &str) -> Option<PooledAgentInfo> {
+        let agents = self.agents.read().await;
+        agents
+            .get(session_id)
+            .map(|entry| Self::entry_info(session_id, entry))
+    }
+
+    pub async fn list(&self) -> Vec<PooledAgentInfo> {
+        let agents = self.agents.read().await;
+        let mut infos: Vec<PooledAgentInfo> = agents
+            .iter()
+            .map(|(session_id, entry)| Self::entry_info(session_id, entry))
+            .collect();
+        infos.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        infos
+    }
+
+    /// Remove the agent for a session, returning it if it existed
+    pub async fn evict(&self, session_id: &str) -> Option<AgentRef> {
+        self.agents
+            .write()
+            .await
+            .remove(session_id)
+            .map(|entry| entry.agent)
+    }
+
+    /// Remove agents unused for longer than `idle_timeout`, returning their session ids
+    ///
+    /// Agents still referenced elsewhere (e.g. by an in-flight reply) are kept even when idle.
+    pub async fn evict_idle(&self, idle_timeout: Duration) -> Vec<String> {
+        let mut agents = self.agents.write().await;
+        let expired: Vec<String> = agents
+            .iter()
+            .filter(|(_, entry)| {
+                entry.last_used.elapsed() >= idle_timeout && Arc::strong_count(&entry.agent) == 1
+            })
+            .map(|(session_id, _)| session_id.clone())
+            .collect();
+        for session_id in &expired {
+            agents.remove(session_id);
+        }
+        expired
+    }
+
+    fn entry_info(session_id: &str, entry: &PooledAgent) -> PooledAgentInfo {
+        PooledAgentInfo {
+            session_id: session_id.to_string(),
+            idle: entry.last_used.elapsed(),
+            age: entry.created_at.elapsed(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     agent: Option<AgentRef>,
+    pub agents: Arc<AgentPool>,
     pub secret_key: String,
     pub scheduler: Arc<Mutex<Option<Arc<dyn SchedulerTrait>>>>,
 }
@@ -16,15 +130,50 @@ impl AppState {
     pub async fn new(agent: AgentRef, secret_key: String) -> Arc<AppState> {
         Arc::new(Self {
             agent: Some(agent.clone()),
+            agents: Arc::new(AgentPool::default()),
             secret_key,
             scheduler: Arc::new(Mutex::new(None)),
         })
     }
 
-    pub async fn get_agent(&self) -> Result<Arc<Agent>, anyhow::Error> {
-        self.agent
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("Agent needs to be created first."))
+    /// Get the agent for a session, or the default agent when no session is given
+    pub async fn get_agent(&self, session_id: Option<&str>) -> Result<Arc<Agent>, anyhow::Error> {
+        match session_id {
+            Some(session_id) => self
+                .agents
+                .get(session_id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("No agent exists for session {}", session_id)),
+            None => self
+                .agent
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("Agent needs to be created first.")),
+        }
+    }
+
+    /// Create a new agent for a session, sharing the server's scheduler
+    pub async fn create_session_agent(&self, session_id: String) -> anyhow::Result<AgentRef> {
+        let agent = Arc::new(Agent::new());
+        if let Some(scheduler) = self.scheduler.lock().await.clone() {
+            agent.set_scheduler(scheduler).await;
+        }
+        self.agents.insert(session_id, agent.clone()).await?;
+        Ok(agent)
+    }
+
+    /// Periodically evict pooled agents that have been idle for longer than `idle_timeout`
+    pub fn spawn_idle_eviction(self: &Arc<Self>, idle_timeout: Duration) {
+        let agents = self.agents.clone();
+        let interval = (idle_timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(60));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                for session_id in agents.evict_idle(idle_timeout).await {
+                    tracing::info!("Evicted idle agent for session {}", session_id);
+                }
+            }
+        });
     }
 
     pub async fn set_scheduler(&self, sched: Arc<dyn SchedulerTrait>) {
@@ -40,3 +189,57 @@ impl AppState {
             .ok_or_else(|| anyhow::anyhow!("Scheduler not initialized"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_agent_pool_insert_get_evict() {
+        let pool = AgentPool::default();
+        pool.insert("a".to_string(), Arc::new(Agent::new()))
+            .await
+            .unwrap();
+        assert!(pool
+            .insert("a".to_string(), Arc::new(Agent::new()))
+            .await
+            .is_err());
+
+        assert!(pool.get("a").await.is_some());
+        assert!(pool.get("b").await.is_none());
+        assert_eq!(pool.list().await.len(), 1);
+
+        assert!(pool.evict("a").await.is_some());
+        assert!(!pool.contains("a").await);
+    }
+
+    #[tokio::test]
+    async fn test_agent_pool_evict_idle_keeps_agents_in_use() {
+        let pool = AgentPool::default();
+        pool.insert("idle".to_string(), Arc::new(Agent::new()))
+            .await
+            .unwrap();
+        pool.insert("busy".to_string(), Arc::new(Agent::new()))
+            .await
+            .unwrap();
+        let _in_use = pool.get("busy").await.unwrap();
+
+        let evicted = pool.evict_idle(Duration::ZERO).await;
+        assert_eq!(evicted, vec!["idle".to_string()]);
+        assert!(pool.contains("busy").await);
+    }
+
+    #[tokio::test]
+    async fn test_get_agent_routes_by_session() {
+        let state = AppState::new(Arc::new(Agent::new()), "test".to_string()).await;
+        assert!(state.get_agent(None).await.is_ok());
+        assert!(state.get_agent(Some("missing")).await.is_err());
+
+        let agent = state
+            .create_session_agent("session".to_string())
+            .await
+            .unwrap();
+        let routed = state.get_agent(Some("session")).await.unwrap();
+        assert!(Arc::ptr_eq(&agent, &routed));
+    }
+}
@@ -36,8 +36,10 @@ async fn main() {
         .with_text("can you summarize the readme.md in this dir using just a haiku?")];
 
     let mut stream = agent.reply(&messages, None).await.unwrap();
-    while let Some(Ok(AgentEvent::Message(message))) = stream.next().await {
-        println!("{}", serde_json::to_string_pretty(&message).unwrap());
-        println!("\n");
+    while let Some(Ok(event)) = stream.next().await {
+        if let AgentEvent::Message(message) = event {
+            println!("{}", serde_json::to_string_pretty(&message).unwrap());
+            println!("\n");
+        }
     }
 }
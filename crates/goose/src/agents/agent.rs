@@ -13,12 +13,14 @@ use crate::agents::sub_recipe_execution_tool::sub_recipe_execute_task_tool::{
     self, SUB_RECIPE_EXECUTE_TASK_TOOL_NAME,
 };
 use crate::agents::sub_recipe_manager::SubRecipeManager;
-use crate::config::{Config, ExtensionConfigManager, PermissionManager};
+use crate::config::{Budget, Config, ExtensionConfigManager, PermissionManager, ShellSandbox};
+use crate::context_mgmt::compaction::CompactionSettings;
 use crate::message::Message;
 use crate::permission::permission_judge::check_tool_permissions;
 use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::providers::base::{MessageDelta, Provider, StreamChunk};
 use crate::providers::errors::ProviderError;
+use crate::providers::pricing::usage_cost;
 use crate::recipe::{Author, Recipe, Response, Settings, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::tool_monitor::{ToolCall, ToolMonitor};
@@ -39,6 +41,7 @@ use crate::agents::router_tool_selector::{
     create_tool_selector, RouterToolSelectionStrategy, RouterToolSelector,
 };
 use crate::agents::router_tools::{ROUTER_LLM_SEARCH_TOOL_NAME, ROUTER_VECTOR_SEARCH_TOOL_NAME};
+use crate::agents::sampling::SamplingRequest;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
 use crate::agents::tool_vectordb::generate_table_id;
 use crate::agents::types::SessionConfig;
@@ -76,13 +79,24 @@ pub struct Agent {
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) subagent_manager: Mutex<Option<SubAgentManager>>,
     pub(super) mcp_notification_rx: Arc<Mutex<mpsc::Receiver<JsonRpcMessage>>>,
+    pub(super) sampling_rx: Mutex<mpsc::Receiver<SamplingRequest>>,
+    pub(super) compaction: Mutex<Option<CompactionSettings>>,
+    pub(super) budget: Mutex<Option<Budget>>,
 }
 
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     Message(Message),
+    /// Partial output from the model, followed by the complete `Message` once it finishes
+    MessageDelta(MessageDelta),
     McpNotification((String, JsonRpcMessage)),
-    ModelChange { model: String, mode: String },
+    ModelChange {
+        model: String,
+        mode: String,
+    },
+    /// The conversation was compacted before calling the model, replace the history with these
+    /// messages
+    HistoryReplaced(Vec<Message>),
 }
 
 impl Default for Agent {
@@ -91,6 +105,12 @@ impl Default for Agent {
     }
 }
 
+/// What the tool loop waits on next: a tool's output, or an extension's sampling request
+enum ToolLoopEvent<T> {
+    Tool(Option<T>),
+    Sampling(Box<SamplingRequest>),
+}
+
 pub enum ToolStreamItem<T> {
     Message(JsonRpcMessage),
     Result(T),
@@ -132,10 +152,14 @@ impl Agent {
         let (tool_tx, tool_rx) = mpsc::channel(32);
         // Add MCP notification channel
         let (mcp_tx, mcp_rx) = mpsc::channel(100);
+        // Sampling requests from extensions, served while their tool calls are running
+        let (sampling_tx, sampling_rx) = mpsc::channel(32);
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.set_sampling_sender(sampling_tx);
 
         Self {
             provider: Mutex::new(None),
-            extension_manager: RwLock::new(ExtensionManager::new()),
+            extension_manager: RwLock::new(extension_manager),
             sub_recipe_manager: Mutex::new(SubRecipeManager::new()),
             final_output_tool: Mutex::new(None),
             frontend_tools: Mutex::new(HashMap::new()),
@@ -151,6 +175,9 @@ impl Agent {
             // Initialize with MCP notification support
             subagent_manager: Mutex::new(Some(SubAgentManager::new(mcp_tx))),
             mcp_notification_rx: Arc::new(Mutex::new(mcp_rx)),
+            sampling_rx: Mutex::new(sampling_rx),
+            compaction: Mutex::new(None),
+            budget: Mutex::new(None),
         }
     }
 
@@ -159,6 +186,31 @@ impl Agent {
         *tool_monitor = Some(ToolMonitor::new(max_repetitions));
     }
 
+    /// Sandbox the shell of builtin extensions added after this call
+    ///
+    /// `None` falls back to the `shell_sandbox` config key.
+    pub async fn set_shell_sandbox(&self, sandbox: Option<ShellSandbox>) {
+        self.extension_manager
+            .write()
+            .await
+            .set_shell_sandbox(sandbox);
+    }
+
+    /// How to compact the conversation, and when to do it automatically
+    ///
+    /// `None` falls back to the `compaction` config key.
+    pub async fn set_compaction(&self, settings: Option<CompactionSettings>) {
+        *self.compaction.lock().await = settings;
+    }
+
+    /// Stop replying once a session has used up this budget
+    ///
+    /// `None` falls back to the `session_budget` config key. Only sessions that are saved to a
+    /// session file are tracked.
+    pub async fn set_budget(&self, budget: Option<Budget>) {
+        *self.budget.lock().await = budget;
+    }
+
     pub async fn get_tool_stats(&self) -> Option<HashMap<String, u32>> {
         let tool_monitor = self.tool_monitor.lock().await;
         tool_monitor.as_ref().map(|monitor| monitor.get_stats())
@@ -220,12 +272,24 @@ impl Agent {
         self.extend_system_prompt(final_output_system_prompt).await;
     }
 
+    /// The JSON the model passed to the final output tool, once it has passed valid output
+    pub async fn final_output(&self) -> Option<String> {
+        self.final_output_tool
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|tool| tool.final_output.clone())
+    }
+
     pub async fn add_sub_recipes(&self, sub_recipes: Vec<SubRecipe>) {
         let mut sub_recipe_manager = self.sub_recipe_manager.lock().await;
         sub_recipe_manager.add_sub_recipe_tools(sub_recipes);
     }
 
     /// Dispatch a single tool call to the appropriate client
+    ///
+    /// Dropping the returned result future before it completes cancels the call, including
+    /// on the extension's MCP server.
     #[instrument(skip(self, tool_call, request_id), fields(input, output))]
     pub async fn dispatch_tool_call(
         &self,
@@ -676,6 +740,15 @@ impl Agent {
         // Load settings from config
         let config = Config::global();
 
+        // Let extensions know if the session moved to another directory
+        if let Some(session_config) = &session {
+            self.extension_manager
+                .read()
+                .await
+                .set_working_dirs(vec![session_config.working_dir.clone()])
+                .await;
+        }
+
         // Setup tools and prompt
         let (mut tools, mut toolshim_tools, mut system_prompt) =
             self.prepare_tools_and_prompt().await?;
@@ -720,6 +793,10 @@ impl Agent {
                 .unwrap_or_else(|| {
                     config.get_param("GOOSE_MAX_TURNS").unwrap_or(DEFAULT_MAX_TURNS)
                 });
+            let compaction = self.compaction_settings().await;
+            let budget = self.budget.lock().await.clone().or_else(Budget::from_config);
+            let mut routed_model: Option<String> = None;
+            let mut warned_unpriced = false;
 
             loop {
                 turns_taken += 1;
@@ -752,13 +829,62 @@ impl Agent {
                     }
                 }
 
-                match Self::generate_response_from_provider(
-                    self.provider().await?,
+                if let (Some(budget), Some(session_config)) = (&budget, &session) {
+                    if let Some(reason) = Self::budget_exceeded(budget, session_config) {
+                        yield AgentEvent::Message(Message::assistant().with_text(format!(
+                            "{} I've stopped here, raise the budget to let me continue.",
+                            reason
+                        )));
+                        break;
+                    }
+                }
+
+                // Compact ahead of the provider rejecting the conversation, if configured to
+                if let Some(compacted) = self
+                    .auto_compact(&messages, &compaction, &system_prompt, &tools)
+                    .await
+                {
+                    messages = compacted;
+                    yield AgentEvent::HistoryReplaced(messages.clone());
+                }
+
+                let provider = self.provider().await?;
+                provider.set_session_budget(
+                    budget.clone(),
+                    session.as_ref().and_then(Self::session_cost),
+                );
+
+                let mut response_result = None;
+                match Self::stream_response_from_provider(
+                    provider,
                     &system_prompt,
                     &messages,
                     &tools,
                     &toolshim_tools,
                 ).await {
+                    Ok(mut response_stream) => {
+                        while let Some(chunk) = response_stream.next().await {
+                            match chunk {
+                                Ok(StreamChunk::Delta(delta)) => yield AgentEvent::MessageDelta(delta),
+                                Ok(StreamChunk::Complete(response, usage)) => {
+                                    response_result = Some(Ok((response, usage)));
+                                }
+                                Err(e) => {
+                                    response_result = Some(Err(e));
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => response_result = Some(Err(e)),
+                }
+                let response_result = response_result.unwrap_or_else(|| {
+                    Err(ProviderError::ExecutionError(
+                        "Provider stream ended without a complete message".to_string(),
+                    ))
+                });
+
+                match response_result {
                     Ok((response, usage)) => {
                         // Emit model change event if provider is lead-worker
                         let provider = self.provider().await?;
@@ -780,9 +906,34 @@ impl Agent {
                             };
                         }
 
+                        // Emit model change event when a routing provider switches models
+                        let routing = usage.routing.clone();
+                        if routing.is_some() && routed_model.as_deref() != Some(usage.model.as_str()) {
+                            routed_model = Some(usage.model.clone());
+                            yield AgentEvent::ModelChange {
+                                model: usage.model.clone(),
+                                mode: "routed".to_string(),
+                            };
+                        }
+
+                        // A cost budget can't be kept to without the model's pricing
+                        let usage_provider = provider.usage_provider(&usage);
+                        let max_cost = budget.as_ref().and_then(|budget| budget.max_cost);
+                        if let (Some(max_cost), false) = (max_cost, warned_unpriced) {
+                            if usage_cost(usage_provider.as_deref(), &usage).await.is_none() {
+                                warned_unpriced = true;
+                                tracing::warn!(
+                                    "No pricing is known for {} on {}, so its cost doesn't count toward the session budget of ${:.2}",
+                                    usage.model,
+                                    usage_provider.as_deref().unwrap_or("an unknown provider"),
+                                    max_cost
+                                );
+                            }
+                        }
+
                         // record usage for the session in the session file
                         if let Some(session_config) = session.clone() {
-                            Self::update_session_metrics(session_config, &usage, messages.len()).await?;
+                            Self::update_session_metrics(session_config, &usage, usage_provider.as_deref(), messages.len(), routing).await?;
                         }
 
                         // categorize the type of requests we need to handle
@@ -872,7 +1023,8 @@ impl Agent {
                                 tools_with_readonly_annotation.clone(),
                                 tools_without_annotation.clone(),
                                 &mut permission_manager,
-                                self.provider().await?).await;
+                                self.provider().await?,
+                                session.as_ref().map(|s| s.working_dir.as_path())).await;
 
                             // Handle pre-approved and read-only tools in parallel
                             let mut tool_futures: Vec<(String, ToolStream)> = Vec::new();
@@ -940,20 +1092,39 @@ impl Agent {
 
                             let mut all_install_successful = true;
 
-                            while let Some((request_id, item)) = combined.next().await {
-                                match item {
-                                    ToolStreamItem::Result(output) => {
-                                        if enable_extension_request_ids.contains(&request_id) && output.is_err(){
-                                            all_install_successful = false;
+                            // Extensions may ask to sample from the model while their tools run
+                            let mut sampling_rx = self.sampling_rx.lock().await;
+                            loop {
+                                // Only pick the next event here; handle it outside the select so
+                                // its errors propagate out of the stream
+                                let event = tokio::select! {
+                                    next = combined.next() => ToolLoopEvent::Tool(next),
+                                    Some(request) = sampling_rx.recv() => ToolLoopEvent::Sampling(Box::new(request)),
+                                };
+                                match event {
+                                    ToolLoopEvent::Tool(None) => break,
+                                    ToolLoopEvent::Tool(Some((request_id, item))) => match item {
+                                        ToolStreamItem::Result(output) => {
+                                            if enable_extension_request_ids.contains(&request_id) && output.is_err(){
+                                                all_install_successful = false;
+                                            }
+                                            let mut response = message_tool_response.lock().await;
+                                            *response = response.clone().with_tool_response(request_id, output);
+                                        },
+                                        ToolStreamItem::Message(msg) => {
+                                            yield AgentEvent::McpNotification((request_id, msg))
                                         }
-                                        let mut response = message_tool_response.lock().await;
-                                        *response = response.clone().with_tool_response(request_id, output);
                                     },
-                                    ToolStreamItem::Message(msg) => {
-                                        yield AgentEvent::McpNotification((request_id, msg))
+                                    ToolLoopEvent::Sampling(request) => {
+                                        let mut sampling_stream =
+                                            self.handle_sampling_request(*request, &goose_mode, session.clone());
+                                        while let Some(msg) = sampling_stream.try_next().await? {
+                                            yield AgentEvent::Message(msg);
+                                        }
                                     }
                                 }
                             }
+                            drop(sampling_rx);
 
                             // Update system prompt and tools if installations were successful
                             if all_install_successful {
@@ -1309,6 +1480,9 @@ impl Agent {
             goose_provider: Some(provider_name.clone()),
             goose_model: Some(model_name.clone()),
             temperature: Some(model_config.temperature.unwrap_or(0.0)),
+            sandbox: None,
+            compaction: None,
+            budget: None,
         };
 
         let recipe = Recipe::builder()
@@ -1,14 +1,22 @@
-use anyhow::Ok;
+use mcp_core::Tool;
 
 use crate::message::Message;
 use crate::token_counter::create_async_token_counter;
 
-use crate::context_mgmt::summarize::summarize_messages_async;
-use crate::context_mgmt::truncate::{truncate_messages, OldestFirstTruncation};
+use crate::context_mgmt::compaction::{
+    current_prompt_index, drop_tool_responses, CompactionSettings, CompactionStrategy,
+};
+use crate::context_mgmt::summarize::{summarize_messages_async, summarize_tool_results};
+use crate::context_mgmt::truncate::{
+    truncate_messages, OldestFirstTruncation, PinnedTruncation, TruncationStrategy,
+};
 use crate::context_mgmt::{estimate_target_context_limit, get_messages_token_counts_async};
 
 use super::super::agents::Agent;
 
+/// How much of the automatic threshold a compacted conversation may use
+const AUTO_COMPACT_TARGET_PERCENT: usize = 60;
+
 impl Agent {
     /// Public API to truncate oldest messages so that the conversation's token count is within the allowed context limit.
     pub async fn truncate_context(
@@ -84,4 +92,167 @@ impl Agent {
 
         Ok((new_messages, new_token_counts))
     }
+
+    /// The compaction settings set on this agent, or else the ones from the config
+    pub async fn compaction_settings(&self) -> CompactionSettings {
+        self.compaction
+            .lock()
+            .await
+            .clone()
+            .unwrap_or_else(CompactionSettings::from_config)
+    }
+
+    /// Public API to shrink the conversation to about `target` tokens with the given strategy.
+    ///
+    /// The prompt being answered and everything after it are kept, so this can run in the
+    /// middle of a turn.
+    pub async fn compact_context(
+        &self,
+        messages: &[Message],
+        settings: &CompactionSettings,
+        target: usize,
+    ) -> Result<Vec<Message>, anyhow::Error> {
+        let Some(prompt) = current_prompt_index(messages) else {
+            return Ok(messages.to_vec());
+        };
+        let provider = self.provider().await?;
+        let token_counter = create_async_token_counter()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        let count_tokens =
+            |msg: &Message| token_counter.count_chat_tokens("", std::slice::from_ref(msg), &[]);
+        let token_counts = get_messages_token_counts_async(&token_counter, messages);
+
+        match settings.strategy {
+            CompactionStrategy::Truncate => truncate_before_prompt(
+                messages,
+                &token_counts,
+                prompt,
+                target,
+                &OldestFirstTruncation,
+            ),
+            CompactionStrategy::KeepPinned => truncate_before_prompt(
+                messages,
+                &token_counts,
+                prompt,
+                target,
+                &PinnedTruncation {
+                    markers: settings.pin_markers.clone(),
+                },
+            ),
+            CompactionStrategy::DropToolResponses => {
+                let (new_messages, new_token_counts) = drop_tool_responses(
+                    messages,
+                    &token_counts,
+                    target,
+                    settings.keep_recent,
+                    &count_tokens,
+                );
+                truncate_before_prompt(
+                    &new_messages,
+                    &new_token_counts,
+                    prompt,
+                    target,
+                    &OldestFirstTruncation,
+                )
+            }
+            CompactionStrategy::SummarizeToolResults => {
+                let (new_messages, new_token_counts) = summarize_tool_results(
+                    provider,
+                    messages,
+                    &token_counts,
+                    target,
+                    settings.keep_recent,
+                    &count_tokens,
+                )
+                .await?;
+                truncate_before_prompt(
+                    &new_messages,
+                    &new_token_counts,
+                    prompt,
+                    target,
+                    &OldestFirstTruncation,
+                )
+            }
+            CompactionStrategy::Summarize => {
+                if prompt == 0 {
+                    return Ok(messages.to_vec());
+                }
+                let tail_tokens: usize = token_counts[prompt..].iter().sum();
+                let (mut new_messages, _) = summarize_messages_async(
+                    provider,
+                    &messages[..prompt],
+                    &token_counter,
+                    target.saturating_sub(tail_tokens),
+                )
+                .await?;
+                new_messages
+                    .push(Message::assistant().with_text("I summarized our conversation so far."));
+                new_messages.extend_from_slice(&messages[prompt..]);
+                Ok(new_messages)
+            }
+        }
+    }
+
+    /// Compact the conversation if it has grown past the automatic threshold of `settings`,
+    /// returning the compacted messages
+    ///
+    /// Failures are logged rather than returned, the provider call goes ahead either way.
+    pub(super) async fn auto_compact(
+        &self,
+        messages: &[Message],
+        settings: &CompactionSettings,
+        system_prompt: &str,
+        tools: &[Tool],
+    ) -> Option<Vec<Message>> {
+        let provider = self.provider().await.ok()?;
+        let limit = settings.auto_limit(provider.get_model_config().context_limit())?;
+        let token_counter = create_async_token_counter().await.ok()?;
+        let used = token_counter.count_chat_tokens(system_prompt, messages, tools);
+        if used <= limit {
+            return None;
+        }
+
+        // Leave headroom so the next few turns don't trigger compaction again
+        let overhead = token_counter.count_chat_tokens(system_prompt, &[], tools);
+        let target = (limit * AUTO_COMPACT_TARGET_PERCENT / 100).saturating_sub(overhead);
+        tracing::info!(
+            "Conversation uses {} tokens, over the compaction threshold of {}; compacting with {:?}",
+            used,
+            limit,
+            settings.strategy
+        );
+        match self.compact_context(messages, settings, target).await {
+            Ok(compacted) if compacted != messages => Some(compacted),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Automatic compaction failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Truncate the messages up to and including the current prompt, keeping the rest of the
+/// turn as it is
+fn truncate_before_prompt(
+    messages: &[Message],
+    token_counts: &[usize],
+    prompt: usize,
+    target: usize,
+    strategy: &dyn TruncationStrategy,
+) -> Result<Vec<Message>, anyhow::Error> {
+    let total: usize = token_counts.iter().sum();
+    if total <= target {
+        return Ok(messages.to_vec());
+    }
+    let tail_tokens: usize = token_counts[prompt + 1..].iter().sum();
+    let (mut new_messages, _) = truncate_messages(
+        &messages[..=prompt],
+        &token_counts[..=prompt],
+        target.saturating_sub(tail_tokens),
+        strategy,
+    )?;
+    new_messages.extend_from_slice(&messages[prompt + 1..]);
+    Ok(new_messages)
 }
@@ -4,20 +4,26 @@ use futures::stream::{FuturesUnordered, StreamExt};
 use futures::{future, FutureExt};
 use mcp_core::protocol::GetPromptResult;
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::LazyLock;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tokio::task;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, warn};
 
 use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
+use super::extension_requests::ExtensionRequestHandler;
+use super::roots::WorkspaceRoots;
+use super::sampling::SamplingRequest;
 use super::tool_execution::ToolCallResult;
 use crate::agents::extension::Envs;
-use crate::config::{Config, ExtensionConfigManager};
+use crate::config::{Config, ExtensionConfigManager, ShellSandbox};
 use crate::prompt_template;
-use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+use mcp_client::client::{
+    ClientCapabilities, ClientInfo, McpClient, McpClientTrait, RootsCapability, SamplingCapability,
+};
 use mcp_client::transport::{SseTransport, StdioTransport, StreamableHttpTransport, Transport};
 use mcp_core::{prompt::Prompt, Content, Tool, ToolCall, ToolError};
 use serde_json::Value;
@@ -34,6 +40,9 @@ pub struct ExtensionManager {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    sampling_tx: Option<mpsc::Sender<SamplingRequest>>,
+    roots: WorkspaceRoots,
+    shell_sandbox: Option<ShellSandbox>,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -104,6 +113,31 @@ impl ExtensionManager {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            sampling_tx: None,
+            roots: WorkspaceRoots::default(),
+            shell_sandbox: None,
+        }
+    }
+
+    /// Route sampling requests from extensions added after this call to `sender`
+    pub(crate) fn set_sampling_sender(&mut self, sender: mpsc::Sender<SamplingRequest>) {
+        self.sampling_tx = Some(sender);
+    }
+
+    /// Sandbox the shell of builtin extensions added after this call, overriding the config
+    pub fn set_shell_sandbox(&mut self, sandbox: Option<ShellSandbox>) {
+        self.shell_sandbox = sandbox;
+    }
+
+    /// Set the directories extensions see as roots, notifying them if they changed
+    pub async fn set_working_dirs(&self, dirs: Vec<PathBuf>) {
+        if !self.roots.set(dirs).await {
+            return;
+        }
+        for (name, client) in &self.clients {
+            if let Err(e) = client.lock().await.notify_roots_list_changed().await {
+                warn!(extension = %name, error = %e, "Failed to notify extension of new roots");
+            }
         }
     }
 
@@ -249,11 +283,14 @@ impl ExtensionManager {
                     .to_str()
                     .expect("should resolve executable to string path")
                     .to_string();
-                let transport = StdioTransport::new(
-                    &cmd,
-                    vec!["mcp".to_string(), name.clone()],
-                    HashMap::new(),
-                );
+                let envs = self
+                    .shell_sandbox
+                    .clone()
+                    .or_else(ShellSandbox::from_config)
+                    .map(|sandbox| sandbox.envs())
+                    .unwrap_or_default();
+                let transport =
+                    StdioTransport::new(&cmd, vec!["mcp".to_string(), name.clone()], envs);
                 let handle = transport.start().await?;
                 Box::new(
                     McpClient::connect(
@@ -268,12 +305,24 @@ impl ExtensionManager {
             _ => unreachable!(),
         };
 
-        // Initialize the client with default capabilities
+        // Initialize the client, offering roots and, when the agent can serve it, sampling
         let info = ClientInfo {
             name: "goose".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         };
-        let capabilities = ClientCapabilities::default();
+        client
+            .set_request_handler(Arc::new(ExtensionRequestHandler::new(
+                sanitized_name.clone(),
+                self.sampling_tx.clone(),
+                self.roots.clone(),
+            )))
+            .await;
+        let capabilities = ClientCapabilities {
+            sampling: self.sampling_tx.as_ref().map(|_| SamplingCapability {}),
+            roots: Some(RootsCapability {
+                list_changed: Some(true),
+            }),
+        };
 
         let init_result = client
             .initialize(info, capabilities)
@@ -828,7 +877,7 @@ impl ExtensionManager {
 mod tests {
     use super::*;
     use mcp_client::client::Error;
-    use mcp_client::client::McpClientTrait;
+    use mcp_client::client::{ClientRequestHandler, McpClientTrait};
     use mcp_core::protocol::{
         CallToolResult, GetPromptResult, InitializeResult, JsonRpcMessage, ListPromptsResult,
         ListResourcesResult, ListToolsResult, ReadResourceResult,
@@ -891,6 +940,12 @@ mod tests {
         async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage> {
             mpsc::channel(1).1
         }
+
+        async fn set_request_handler(&self, _handler: Arc<dyn ClientRequestHandler>) {}
+
+        async fn notify_roots_list_changed(&self) -> Result<(), Error> {
+            Ok(())
+        }
     }
 
     #[test]
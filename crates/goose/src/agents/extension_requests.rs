@@ -0,0 +1,117 @@
+use mcp_client::client::ClientRequestHandler;
+use mcp_core::protocol::{CreateMessageParams, CreateMessageResult, ErrorData, ListRootsResult};
+use tokio::sync::{mpsc, oneshot};
+
+use super::roots::WorkspaceRoots;
+use super::sampling::{internal_error, SamplingRequest};
+
+/// Answers the requests an extension sends back to goose
+///
+/// Sampling requests are forwarded to the agent that owns the extension, and roots come
+/// from the agent's working directories.
+pub struct ExtensionRequestHandler {
+    extension: String,
+    sampling_tx: Option<mpsc::Sender<SamplingRequest>>,
+    roots: WorkspaceRoots,
+}
+
+impl ExtensionRequestHandler {
+    pub fn new(
+        extension: String,
+        sampling_tx: Option<mpsc::Sender<SamplingRequest>>,
+        roots: WorkspaceRoots,
+    ) -> Self {
+        Self {
+            extension,
+            sampling_tx,
+            roots,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientRequestHandler for ExtensionRequestHandler {
+    async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        let sender = self
+            .sampling_tx
+            .as_ref()
+            .ok_or_else(|| internal_error("Sampling is not available"))?;
+        let (tx, rx) = oneshot::channel();
+        sender
+            .send(SamplingRequest {
+                extension: self.extension.clone(),
+                params,
+                respond_to: tx,
+            })
+            .await
+            .map_err(|_| internal_error("The agent is no longer running"))?;
+        rx.await
+            .map_err(|_| internal_error("The agent dropped the sampling request"))?
+    }
+
+    async fn list_roots(&self) -> Result<ListRootsResult, ErrorData> {
+        Ok(self.roots.list().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{Content, Role};
+    use std::path::PathBuf;
+
+    fn handler(sampling_tx: Option<mpsc::Sender<SamplingRequest>>) -> ExtensionRequestHandler {
+        ExtensionRequestHandler::new(
+            "summarizer".to_string(),
+            sampling_tx,
+            WorkspaceRoots::new(vec![PathBuf::from("/tmp/project")]),
+        )
+    }
+
+    fn params() -> CreateMessageParams {
+        CreateMessageParams {
+            messages: vec![],
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 10,
+            stop_sequences: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampling_forwarded_to_agent() {
+        let (tx, mut rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let request: SamplingRequest = rx.recv().await.unwrap();
+            assert_eq!(request.extension, "summarizer");
+            let _ = request.respond_to.send(Ok(CreateMessageResult {
+                role: Role::Assistant,
+                content: Content::text("ok"),
+                model: "test".to_string(),
+                stop_reason: None,
+            }));
+        });
+
+        let result = handler(Some(tx)).create_message(params()).await.unwrap();
+        assert_eq!(result.content, Content::text("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_sampling_without_agent_is_rejected() {
+        assert!(handler(None).create_message(params()).await.is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(not(windows))]
+    async fn test_roots_listed_from_workspace() {
+        let result = handler(None).list_roots().await.unwrap();
+        assert_eq!(result.roots.len(), 1);
+        assert_eq!(result.roots[0].uri, "file:///tmp/project/");
+    }
+}
@@ -2,14 +2,17 @@ mod agent;
 mod context;
 pub mod extension;
 pub mod extension_manager;
+mod extension_requests;
 pub mod final_output_tool;
 mod large_response_handler;
 pub mod platform_tools;
 pub mod prompt_manager;
 mod recipe_tools;
 mod reply_parts;
+mod roots;
 mod router_tool_selector;
 mod router_tools;
+mod sampling;
 mod schedule_tool;
 pub mod sub_recipe_execution_tool;
 pub mod sub_recipe_manager;
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use crate::agents::extension::ExtensionInfo;
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
 use crate::agents::router_tools::{llm_search_tool_prompt, vector_search_tool_prompt};
+use crate::model_registry;
 use crate::providers::base::get_current_model;
 use crate::{config::Config, prompt_template};
 
@@ -40,23 +41,11 @@ impl PromptManager {
         self.system_prompt_override = Some(template);
     }
 
-    /// Normalize a model name (replace - and / with _, lower case)
-    fn normalize_model_name(name: &str) -> String {
-        name.replace(['-', '/', '.'], "_").to_lowercase()
-    }
-
-    /// Map model (normalized) to prompt filenames; returns filename if a key is contained in the normalized model
-    fn model_prompt_map(model: &str) -> &'static str {
-        let mut map = HashMap::new();
-        map.insert("gpt_4_1", "system_gpt_4_1.md");
-        // Add more mappings as needed
-        let norm_model = Self::normalize_model_name(model);
-        for (key, val) in &map {
-            if norm_model.contains(key) {
-                return val;
-            }
-        }
-        "system.md"
+    /// The prompt file the model registry has for the model, or the default system.md
+    fn model_prompt_map(model: &str) -> String {
+        model_registry::capabilities(None, model)
+            .system_prompt
+            .unwrap_or_else(|| "system.md".to_string())
     }
 
     /// Build the final system prompt
@@ -172,10 +161,10 @@ mod tests {
 
     #[test]
     fn test_normalize_model_name() {
-        assert_eq!(PromptManager::normalize_model_name("gpt-4.1"), "gpt_4_1");
-        assert_eq!(PromptManager::normalize_model_name("gpt/3.5"), "gpt_3_5");
+        assert_eq!(model_registry::normalize_model_name("gpt-4.1"), "gpt_4_1");
+        assert_eq!(model_registry::normalize_model_name("gpt/3.5"), "gpt_3_5");
         assert_eq!(
-            PromptManager::normalize_model_name("GPT-3.5/PLUS"),
+            model_registry::normalize_model_name("GPT-3.5/PLUS"),
             "gpt_3_5_plus"
         );
     }
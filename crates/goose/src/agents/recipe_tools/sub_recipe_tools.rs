@@ -5,7 +5,9 @@ use mcp_core::tool::{Tool, ToolAnnotations};
 use serde_json::{json, Map, Value};
 
 use crate::agents::sub_recipe_execution_tool::lib::Task;
-use crate::recipe::{Recipe, RecipeParameter, RecipeParameterRequirement, SubRecipe};
+use crate::recipe::{
+    Recipe, RecipeParameter, RecipeParameterInputType, RecipeParameterRequirement, SubRecipe,
+};
 
 pub const SUB_RECIPE_TASK_TOOL_NAME_PREFIX: &str = "subrecipe__create_task";
 
@@ -49,13 +51,7 @@ fn get_input_schema(sub_recipe: &SubRecipe) -> Result<Value> {
             if sub_recipe_params_map.contains_key(&param.key) {
                 continue;
             }
-            properties.insert(
-                param.key.clone(),
-                json!({
-                    "type": param.input_type.to_string(),
-                    "description": param.description.clone(),
-                }),
-            );
+            properties.insert(param.key.clone(), parameter_schema(&param));
             if !matches!(param.requirement, RecipeParameterRequirement::Optional) {
                 required.push(param.key);
             }
@@ -73,6 +69,31 @@ fn get_input_schema(sub_recipe: &SubRecipe) -> Result<Value> {
     }
 }
 
+/// The JSON schema for a parameter's value
+///
+/// Dates and files are passed as strings, and a list as an array of strings that is joined
+/// back into one value when the task is created.
+fn parameter_schema(param: &RecipeParameter) -> Value {
+    let mut property = match param.input_type {
+        RecipeParameterInputType::Number => json!({ "type": "number" }),
+        RecipeParameterInputType::Boolean => json!({ "type": "boolean" }),
+        RecipeParameterInputType::List => json!({ "type": "array", "items": { "type": "string" } }),
+        RecipeParameterInputType::String
+        | RecipeParameterInputType::Date
+        | RecipeParameterInputType::File
+        | RecipeParameterInputType::Enum => json!({ "type": "string" }),
+    };
+    if let Some(options) = &param.options {
+        match param.input_type {
+            RecipeParameterInputType::Enum => property["enum"] = json!(options),
+            RecipeParameterInputType::List => property["items"]["enum"] = json!(options),
+            _ => {}
+        }
+    }
+    property["description"] = json!(param.description);
+    property
+}
+
 fn prepare_command_params(
     sub_recipe: &SubRecipe,
     params_from_tool_call: Value,
@@ -85,10 +106,19 @@ fn prepare_command_params(
     }
     if let Some(params_map) = params_from_tool_call.as_object() {
         for (key, value) in params_map {
-            sub_recipe_params.insert(
-                key.to_string(),
-                value.as_str().unwrap_or(&value.to_string()).to_string(),
-            );
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| {
+                        item.as_str()
+                            .map_or_else(|| item.to_string(), str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value => value.to_string(),
+            };
+            sub_recipe_params.insert(key.to_string(), value);
         }
     }
     Ok(sub_recipe_params)
@@ -48,6 +48,15 @@ mod tests {
             let result = prepare_command_params(&sub_recipe, params_value).unwrap();
             assert_eq!(result.len(), 0);
         }
+
+        #[test]
+        fn test_prepare_command_params_joins_lists() {
+            let sub_recipe = setup_sub_recipe();
+            let params_value = serde_json::json!({ "regions": ["us", "eu"], "count": 2 });
+            let result = prepare_command_params(&sub_recipe, params_value).unwrap();
+            assert_eq!(result.get("regions"), Some(&"us, eu".to_string()));
+            assert_eq!(result.get("count"), Some(&"2".to_string()));
+        }
     }
 
     mod get_input_schema_tests {
@@ -151,5 +160,47 @@ mod tests {
             assert_eq!(result["required"].as_array().unwrap().len(), 1);
             assert_eq!(result["required"][0], "key1");
         }
+
+        #[test]
+        fn test_get_input_schema_uses_json_schema_types() {
+            let sub_recipe_file_content = r#"{
+                "version": "1.0.0",
+                "title": "Test Recipe",
+                "description": "A test recipe",
+                "prompt": "Test prompt",
+                "parameters": [
+                    {
+                        "key": "regions",
+                        "input_type": "list",
+                        "requirement": "required",
+                        "description": "Where to deploy",
+                        "options": ["us", "eu"]
+                    },
+                    {
+                        "key": "notes",
+                        "input_type": "file",
+                        "requirement": "optional",
+                        "description": "Release notes"
+                    }
+                ]
+            }"#;
+
+            let temp_dir = tempfile::tempdir().unwrap();
+            let temp_file = temp_dir.path().join("test_sub_recipe.yaml");
+            std::fs::write(&temp_file, sub_recipe_file_content).unwrap();
+            let sub_recipe = SubRecipe {
+                name: "test_sub_recipe".to_string(),
+                path: temp_file.to_string_lossy().to_string(),
+                values: None,
+            };
+
+            let result = get_input_schema(&sub_recipe).unwrap();
+
+            let regions = &result["properties"]["regions"];
+            assert_eq!(regions["type"], "array");
+            assert_eq!(regions["items"]["type"], "string");
+            assert_eq!(regions["items"]["enum"], serde_json::json!(["us", "eu"]));
+            assert_eq!(result["properties"]["notes"]["type"], "string");
+        }
     }
 }
@@ -1,12 +1,15 @@
 use anyhow::Result;
+use futures::{stream, StreamExt};
 use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
-use crate::config::Config;
+use crate::config::{Budget, Config};
 use crate::message::{Message, MessageContent, ToolRequest};
-use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::base::{MessageStream, Provider, ProviderUsage, StreamChunk};
 use crate::providers::errors::ProviderError;
+use crate::providers::pricing::usage_cost;
+use crate::providers::routing::RoutingDecision;
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
     modify_system_prompt_for_tool_json, OllamaInterpreter,
@@ -147,6 +150,43 @@ impl Agent {
         Ok((response, usage))
     }
 
+    /// Stream a response from the LLM provider
+    /// Falls back to a single complete message when the provider or the model doesn't stream,
+    /// or when toolshim is enabled since it needs the whole response to interpret tool calls
+    pub(crate) async fn stream_response_from_provider(
+        provider: Arc<dyn Provider>,
+        system_prompt: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        toolshim_tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let model_config = provider.get_model_config();
+        if model_config.toolshim
+            || !provider.supports_streaming()
+            || !model_config.capabilities().supports_streaming()
+        {
+            let (response, usage) = Self::generate_response_from_provider(
+                provider,
+                system_prompt,
+                messages,
+                tools,
+                toolshim_tools,
+            )
+            .await?;
+            return Ok(Box::pin(stream::once(async move {
+                Ok(StreamChunk::Complete(response, usage))
+            })));
+        }
+
+        let response_stream = provider.stream(system_prompt, messages, tools).await?;
+        Ok(Box::pin(response_stream.inspect(|chunk| {
+            // Store the model information in the global store
+            if let Ok(StreamChunk::Complete(_, usage)) = chunk {
+                crate::providers::base::set_current_model(&usage.model);
+            }
+        })))
+    }
+
     /// Categorize tool requests from the response into different types
     /// Returns:
     /// - frontend_requests: Tool requests that should be handled by the frontend
@@ -220,7 +260,9 @@ impl Agent {
     pub(crate) async fn update_session_metrics(
         session_config: crate::agents::types::SessionConfig,
         usage: &crate::providers::base::ProviderUsage,
+        usage_provider: Option<&str>,
         messages_length: usize,
+        routing: Option<RoutingDecision>,
     ) -> Result<()> {
         let session_file_path = match session::storage::get_path(session_config.id.clone()) {
             Ok(path) => path,
@@ -235,6 +277,9 @@ impl Agent {
         metadata.total_tokens = usage.usage.total_tokens;
         metadata.input_tokens = usage.usage.input_tokens;
         metadata.output_tokens = usage.usage.output_tokens;
+        metadata.cache_read_input_tokens = usage.usage.cache_read_input_tokens;
+        metadata.cache_write_input_tokens = usage.usage.cache_write_input_tokens;
+        metadata.cost = usage_cost(usage_provider, usage).await;
 
         metadata.message_count = messages_length + 1;
 
@@ -252,9 +297,45 @@ impl Agent {
             metadata.accumulated_output_tokens,
             usage.usage.output_tokens,
         );
+        metadata.accumulated_cache_read_input_tokens = accumulate(
+            metadata.accumulated_cache_read_input_tokens,
+            usage.usage.cache_read_input_tokens,
+        );
+        metadata.accumulated_cache_write_input_tokens = accumulate(
+            metadata.accumulated_cache_write_input_tokens,
+            usage.usage.cache_write_input_tokens,
+        );
+        if let Some(cost) = metadata.cost {
+            metadata.accumulated_cost = Some(metadata.accumulated_cost.unwrap_or(0.0) + cost);
+        }
+        metadata.routing.extend(routing);
+        let excess = metadata
+            .routing
+            .len()
+            .saturating_sub(session::storage::MAX_ROUTING_DECISIONS);
+        metadata.routing.drain(..excess);
 
         session::storage::update_metadata(&session_file_path, &metadata).await?;
 
         Ok(())
     }
+
+    /// Why the session has no budget left, if it hasn't
+    pub(crate) fn budget_exceeded(
+        budget: &Budget,
+        session_config: &crate::agents::types::SessionConfig,
+    ) -> Option<String> {
+        let session_file_path = session::storage::get_path(session_config.id.clone()).ok()?;
+        let metadata = session::storage::read_metadata(&session_file_path).ok()?;
+        budget.exceeded(&metadata)
+    }
+
+    /// What the session has cost so far, including earlier runs of it
+    pub(crate) fn session_cost(
+        session_config: &crate::agents::types::SessionConfig,
+    ) -> Option<f64> {
+        let session_file_path = session::storage::get_path(session_config.id.clone()).ok()?;
+        let metadata = session::storage::read_metadata(&session_file_path).ok()?;
+        metadata.accumulated_cost
+    }
 }
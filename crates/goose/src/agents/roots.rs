@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use mcp_core::protocol::{ListRootsResult, Root};
+use tokio::sync::RwLock;
+use url::Url;
+
+/// The directories the agent is working in, shared with extensions as MCP roots
+#[derive(Clone)]
+pub struct WorkspaceRoots {
+    dirs: Arc<RwLock<Vec<PathBuf>>>,
+}
+
+impl Default for WorkspaceRoots {
+    fn default() -> Self {
+        Self::new(std::env::current_dir().into_iter().collect())
+    }
+}
+
+impl WorkspaceRoots {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs: Arc::new(RwLock::new(dirs)),
+        }
+    }
+
+    /// Replace the directories, returning true if they changed
+    pub async fn set(&self, dirs: Vec<PathBuf>) -> bool {
+        let mut current = self.dirs.write().await;
+        if *current == dirs {
+            return false;
+        }
+        *current = dirs;
+        true
+    }
+
+    /// The directories as a `roots/list` result, skipping any that can't be made a file URI
+    pub async fn list(&self) -> ListRootsResult {
+        let roots = self
+            .dirs
+            .read()
+            .await
+            .iter()
+            .filter_map(|dir| {
+                let uri = Url::from_directory_path(dir).ok()?;
+                Some(Root {
+                    uri: uri.to_string(),
+                    name: dir
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned()),
+                })
+            })
+            .collect();
+        ListRootsResult { roots }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_reports_changes() {
+        let roots = WorkspaceRoots::new(vec![PathBuf::from("/tmp/a")]);
+        assert!(!roots.set(vec![PathBuf::from("/tmp/a")]).await);
+        assert!(roots.set(vec![PathBuf::from("/tmp/b")]).await);
+        assert_eq!(*roots.dirs.read().await, vec![PathBuf::from("/tmp/b")]);
+    }
+
+    #[tokio::test]
+    #[cfg(not(windows))]
+    async fn test_list_uses_file_uris() {
+        let roots = WorkspaceRoots::new(vec![
+            PathBuf::from("/home/user/project"),
+            PathBuf::from("relative"),
+        ]);
+        let result = roots.list().await;
+        assert_eq!(
+            result.roots,
+            vec![Root {
+                uri: "file:///home/user/project/".to_string(),
+                name: Some("project".to_string()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use async_stream::try_stream;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, SamplingMessage, INTERNAL_ERROR,
+};
+use mcp_core::{Content, Role};
+use serde_json::json;
+use tokio::sync::oneshot;
+
+use crate::agents::types::SessionConfig;
+use crate::agents::Agent;
+use crate::config::permission::PermissionLevel;
+use crate::config::PermissionManager;
+use crate::message::{Message, MessageContent};
+use crate::permission::Permission;
+use crate::providers::base::ProviderUsage;
+use crate::providers::pricing::usage_cost;
+use crate::session;
+
+/// Error code returned to the server when the user declines a sampling request
+pub const SAMPLING_DECLINED: i32 = -1;
+
+/// A `sampling/createMessage` request from an extension, waiting for the agent to run it
+pub struct SamplingRequest {
+    pub extension: String,
+    pub params: CreateMessageParams,
+    pub respond_to: oneshot::Sender<Result<CreateMessageResult, ErrorData>>,
+}
+
+pub(crate) fn internal_error(message: impl Into<String>) -> ErrorData {
+    ErrorData {
+        code: INTERNAL_ERROR,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// The principal name used to remember a user's sampling decision for an extension
+pub fn sampling_principal(extension: &str) -> String {
+    format!("{}__sampling", extension)
+}
+
+fn to_message(sampling_message: &SamplingMessage) -> Message {
+    let message = match sampling_message.role {
+        Role::User => Message::user(),
+        Role::Assistant => Message::assistant(),
+    };
+    message.with_content(MessageContent::from(sampling_message.content.clone()))
+}
+
+fn to_result(message: &Message, usage: &ProviderUsage) -> CreateMessageResult {
+    CreateMessageResult {
+        role: Role::Assistant,
+        content: Content::text(message.as_concat_text()),
+        model: usage.model.clone(),
+        stop_reason: Some("endTurn".to_string()),
+    }
+}
+
+impl Agent {
+    /// Run an extension's sampling request through the active provider
+    ///
+    /// Depending on goose_mode the user is asked first, using the same confirmation flow as
+    /// tool calls. Tokens used are added to the session's accumulated usage.
+    pub(crate) fn handle_sampling_request<'a>(
+        &'a self,
+        request: SamplingRequest,
+        goose_mode: &'a str,
+        session: Option<SessionConfig>,
+    ) -> BoxStream<'a, anyhow::Result<Message>> {
+        try_stream! {
+            let SamplingRequest { extension, params, respond_to } = request;
+            // The extension gave up waiting (e.g. it timed out), so there is nothing to do
+            if respond_to.is_closed() {
+                return;
+            }
+
+            let principal = sampling_principal(&extension);
+            let mut permission_manager = PermissionManager::default();
+            let allowed = match (goose_mode, permission_manager.get_user_permission(&principal)) {
+                ("chat", _) | (_, Some(PermissionLevel::NeverAllow)) => false,
+                ("auto", _) | (_, Some(PermissionLevel::AlwaysAllow)) => true,
+                _ => {
+                    let request_id = format!("sampling_{}", uuid::Uuid::new_v4());
+                    let preview: Vec<String> = params
+                        .messages
+                        .iter()
+                        .filter_map(|m| m.content.as_text().map(str::to_string))
+                        .collect();
+                    yield Message::user().with_tool_confirmation_request(
+                        request_id.clone(),
+                        principal.clone(),
+                        json!({
+                            "systemPrompt": params.system_prompt,
+                            "messages": preview,
+                            "maxTokens": params.max_tokens,
+                        }),
+                        Some(format!(
+                            "The {} extension would like to use the model. Allow? (y/n):",
+                            extension
+                        )),
+                    );
+
+                    let mut allowed = false;
+                    let mut rx = self.confirmation_rx.lock().await;
+                    while let Some((req_id, confirmation)) = rx.recv().await {
+                        if req_id == request_id {
+                            allowed = matches!(
+                                confirmation.permission,
+                                Permission::AllowOnce | Permission::AlwaysAllow
+                            );
+                            if confirmation.permission == Permission::AlwaysAllow {
+                                permission_manager
+                                    .update_user_permission(&principal, PermissionLevel::AlwaysAllow);
+                            }
+                            break;
+                        }
+                    }
+                    allowed
+                }
+            };
+
+            if !allowed {
+                let _ = respond_to.send(Err(ErrorData {
+                    code: SAMPLING_DECLINED,
+                    message: "The user declined the sampling request".to_string(),
+                    data: None,
+                }));
+                return;
+            }
+
+            let result = self.run_sampling(&params, session).await;
+            let _ = respond_to.send(result.map_err(|e| internal_error(e.to_string())));
+        }
+        .boxed()
+    }
+
+    async fn run_sampling(
+        &self,
+        params: &CreateMessageParams,
+        session: Option<SessionConfig>,
+    ) -> anyhow::Result<CreateMessageResult> {
+        let provider = self.provider().await?;
+        let messages: Vec<Message> = params.messages.iter().map(to_message).collect();
+        let system_prompt = params.system_prompt.clone().unwrap_or_default();
+        let max_tokens = i32::try_from(params.max_tokens).unwrap_or(i32::MAX);
+
+        let (message, usage) = provider
+            .complete_with_max_tokens(&system_prompt, &messages, &[], max_tokens)
+            .await?;
+
+        if let Some(session_config) = session {
+            let usage_provider = provider.usage_provider(&usage);
+            if let Err(e) =
+                record_sampling_usage(&session_config, &usage, usage_provider.as_deref()).await
+            {
+                tracing::warn!("Failed to record sampling usage: {}", e);
+            }
+        }
+
+        Ok(to_result(&message, &usage))
+    }
+}
+
+/// Add tokens used by sampling to the session's accumulated totals
+///
+/// The per-turn token fields describe the conversation's context, which sampling doesn't
+/// change, so only the accumulated totals are updated.
+async fn record_sampling_usage(
+    session_config: &SessionConfig,
+    usage: &ProviderUsage,
+    usage_provider: Option<&str>,
+) -> anyhow::Result<()> {
+    let session_file_path = session::storage::get_path(session_config.id.clone())?;
+    let mut metadata = session::storage::read_metadata(&session_file_path)?;
+
+    let accumulate = |a: Option<i32>, b: Option<i32>| -> Option<i32> {
+        match (a, b) {
+            (Some(x), Some(y)) => Some(x + y),
+            _ => a.or(b),
+        }
+    };
+    metadata.accumulated_total_tokens =
+        accumulate(metadata.accumulated_total_tokens, usage.usage.total_tokens);
+    metadata.accumulated_input_tokens =
+        accumulate(metadata.accumulated_input_tokens, usage.usage.input_tokens);
+    metadata.accumulated_output_tokens = accumulate(
+        metadata.accumulated_output_tokens,
+        usage.usage.output_tokens,
+    );
+    metadata.accumulated_cache_read_input_tokens = accumulate(
+        metadata.accumulated_cache_read_input_tokens,
+        usage.usage.cache_read_input_tokens,
+    );
+    metadata.accumulated_cache_write_input_tokens = accumulate(
+        metadata.accumulated_cache_write_input_tokens,
+        usage.usage.cache_write_input_tokens,
+    );
+    if let Some(cost) = usage_cost(usage_provider, usage).await {
+        metadata.accumulated_cost = Some(metadata.accumulated_cost.unwrap_or(0.0) + cost);
+    }
+
+    session::storage::update_metadata(&session_file_path, &metadata).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    #[test]
+    fn test_sampling_messages_convert_to_agent_messages() {
+        let message = to_message(&SamplingMessage {
+            role: Role::Assistant,
+            content: Content::text("earlier answer"),
+        });
+        assert_eq!(message.role, Role::Assistant);
+        assert_eq!(message.as_concat_text(), "earlier answer");
+    }
+
+    #[test]
+    fn test_result_uses_response_text_and_model() {
+        let message = Message::assistant().with_text("a short summary");
+        let usage = ProviderUsage::new("gpt-4o".to_string(), Usage::default());
+        let result = to_result(&message, &usage);
+        assert_eq!(result.content, Content::text("a short summary"));
+        assert_eq!(result.model, "gpt-4o");
+        assert_eq!(result.role, Role::Assistant);
+    }
+}
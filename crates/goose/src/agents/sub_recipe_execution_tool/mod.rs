@@ -1,5 +1,6 @@
 mod executor;
 pub mod lib;
+pub mod steps;
 pub mod sub_recipe_execute_task_tool;
 mod tasks;
 mod types;
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::agents::sub_recipe_execution_tool::executor::{execute_single_task, parallel_execute};
+use crate::agents::sub_recipe_execution_tool::types::{Config, Task, TaskResult};
+use crate::recipe::{
+    Recipe, RecipeStep, StepContext, SubRecipe, STEP_STATUS_FAILED, STEP_STATUS_SKIPPED,
+    STEP_STATUS_SUCCESS,
+};
+
+/// The outcome of one of a recipe's steps, across all of its `for_each` items
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub name: String,
+    pub status: String,
+    pub output: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The steps that ran, and what later parts of the recipe can refer to
+#[derive(Debug, Clone)]
+pub struct StepsRun {
+    pub results: Vec<StepResult>,
+    pub context: StepContext,
+    /// Whether a step failed without `continue_on_error`, so the rest were not run
+    pub stopped: bool,
+}
+
+impl StepsRun {
+    /// Why the run stopped, if a step failed without `continue_on_error`
+    pub fn stop_error(&self) -> Option<anyhow::Error> {
+        let failed = self.results.last().filter(|_| self.stopped)?;
+        Some(anyhow::anyhow!(
+            "Step '{}' failed: {}",
+            failed.name,
+            failed.error.as_deref().unwrap_or_default()
+        ))
+    }
+}
+
+/// Run the recipe's `steps` in order, as sub-recipe or instruction tasks
+///
+/// `params` are the recipe's parameter values. Each step's `when` is checked for every item
+/// of its `for_each`, and a step with no item left is skipped. A step whose `when` or
+/// `for_each` can't be evaluated fails like one whose task failed.
+pub async fn run_recipe_steps(
+    recipe: &Recipe,
+    params: &HashMap<String, String>,
+) -> Result<StepsRun> {
+    let mut context = StepContext::for_recipe(recipe, params);
+    let mut results = Vec::new();
+    let sub_recipes = recipe.sub_recipes.as_deref().unwrap_or_default();
+
+    for step in recipe.steps.iter().flatten() {
+        let result = match create_step_tasks(step, sub_recipes, &context) {
+            Ok(tasks) if tasks.is_empty() => StepResult {
+                name: step.name.clone(),
+                status: STEP_STATUS_SKIPPED.to_string(),
+                output: String::new(),
+                error: None,
+            },
+            Ok(tasks) => summarize(step, run_step_tasks(step, tasks).await),
+            Err(e) => StepResult {
+                name: step.name.clone(),
+                status: STEP_STATUS_FAILED.to_string(),
+                output: String::new(),
+                error: Some(e.to_string()),
+            },
+        };
+        context.record(&step.name, &result.status, &result.output);
+        let stop = result.status == STEP_STATUS_FAILED && !step.continue_on_error;
+        results.push(result);
+        if stop {
+            return Ok(StepsRun {
+                results,
+                context,
+                stopped: true,
+            });
+        }
+    }
+
+    Ok(StepsRun {
+        results,
+        context,
+        stopped: false,
+    })
+}
+
+/// A task for each of the step's `for_each` items that its `when` holds for
+fn create_step_tasks(
+    step: &RecipeStep,
+    sub_recipes: &[SubRecipe],
+    context: &StepContext,
+) -> Result<Vec<Task>> {
+    let items: Vec<Option<String>> = match &step.for_each {
+        Some(key) => context.items(key)?.into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let mut tasks = Vec::new();
+    for item in items {
+        let item_context = match &item {
+            Some(item) => context.with_item(item),
+            None => context.clone(),
+        };
+        if let Some(when) = &step.when {
+            let holds = item_context
+                .evaluate(when)
+                .map_err(|e| anyhow::anyhow!("Failed to evaluate when '{}': {}", when, e))?;
+            if !holds {
+                continue;
+            }
+        }
+        let id = format!("{}-{}", step.name, tasks.len());
+        tasks.push(create_step_task(step, sub_recipes, &item_context, id)?);
+    }
+    Ok(tasks)
+}
+
+fn create_step_task(
+    step: &RecipeStep,
+    sub_recipes: &[SubRecipe],
+    context: &StepContext,
+    id: String,
+) -> Result<Task> {
+    let extensions = json!(step.extensions.clone().unwrap_or_default());
+    let (task_type, payload) = match (&step.sub_recipe, &step.instructions) {
+        (Some(name), _) => {
+            let sub_recipe = sub_recipes
+                .iter()
+                .find(|s| &s.name == name)
+                .ok_or_else(|| anyhow::anyhow!("There is no sub-recipe named '{}'", name))?;
+            let mut command_parameters = sub_recipe.values.clone().unwrap_or_default();
+            for (key, value) in step.values.iter().flatten() {
+                command_parameters.insert(key.clone(), context.substitute(value));
+            }
+            (
+                "sub_recipe",
+                json!({
+                    "sub_recipe": {
+                        "name": sub_recipe.name.clone(),
+                        "command_parameters": command_parameters,
+                        "recipe_path": sub_recipe.path.clone(),
+                    },
+                    "extensions": extensions,
+                }),
+            )
+        }
+        (None, Some(instructions)) => (
+            "text_instruction",
+            json!({
+                "text_instruction": context.substitute(instructions),
+                "extensions": extensions,
+            }),
+        ),
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "Step '{}' needs either a sub_recipe or instructions",
+                step.name
+            ))
+        }
+    };
+    Ok(Task {
+        id,
+        task_type: task_type.to_string(),
+        payload,
+    })
+}
+
+async fn run_step_tasks(step: &RecipeStep, tasks: Vec<Task>) -> Vec<TaskResult> {
+    let config = Config::default();
+    if step.parallel {
+        let order: Vec<String> = tasks.iter().map(|task| task.id.clone()).collect();
+        let mut results = parallel_execute(tasks, config).await.results;
+        results.sort_by_key(|result| order.iter().position(|id| id == &result.task_id));
+        results
+    } else {
+        let mut results = Vec::new();
+        for task in &tasks {
+            results.extend(execute_single_task(task, config.clone()).await.results);
+        }
+        results
+    }
+}
+
+fn summarize(step: &RecipeStep, results: Vec<TaskResult>) -> StepResult {
+    let mut outputs = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match (result.data, result.error) {
+            (_, Some(error)) => errors.push(error),
+            (Some(Value::String(output)), None) => outputs.push(output.trim_end().to_string()),
+            (Some(data), None) => outputs.push(data.to_string()),
+            (None, None) => {}
+        }
+    }
+    StepResult {
+        name: step.name.clone(),
+        status: if errors.is_empty() {
+            STEP_STATUS_SUCCESS
+        } else {
+            STEP_STATUS_FAILED
+        }
+        .to_string(),
+        output: outputs.join("\n"),
+        error: (!errors.is_empty()).then(|| errors.join("\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_step_task_substitutes_values() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Release
+description: Release to each region
+instructions: Release
+sub_recipes:
+  - name: deploy
+    path: deploy.yaml
+    values:
+      env: prod
+steps:
+  - name: deploy
+    sub_recipe: deploy
+    for_each: regions
+    values:
+      region: ${item}
+      notes: ${steps.build.output}
+    extensions:
+      - type: builtin
+        name: developer
+  - name: announce
+    instructions: Announce ${steps.deploy.status}
+"#,
+        )
+        .unwrap();
+        let steps = recipe.steps.as_ref().unwrap();
+        let sub_recipes = recipe.sub_recipes.as_ref().unwrap();
+        let mut context = StepContext::new(&HashMap::new());
+        context.record("build", STEP_STATUS_SUCCESS, "v1.2");
+
+        let task = create_step_task(
+            &steps[0],
+            sub_recipes,
+            &context.with_item("eu"),
+            "deploy-0".to_string(),
+        )
+        .unwrap();
+        assert_eq!(task.task_type, "sub_recipe");
+        assert_eq!(
+            task.payload["sub_recipe"]["command_parameters"],
+            json!({"env": "prod", "region": "eu", "notes": "v1.2"})
+        );
+        assert_eq!(task.payload["extensions"][0]["name"], "developer");
+
+        context.record("deploy", STEP_STATUS_FAILED, "");
+        let task =
+            create_step_task(&steps[1], sub_recipes, &context, "announce-0".to_string()).unwrap();
+        assert_eq!(task.task_type, "text_instruction");
+        assert_eq!(task.payload["text_instruction"], "Announce failed");
+    }
+
+    #[tokio::test]
+    async fn test_steps_skipped_by_when() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Release
+description: Release
+instructions: Release
+steps:
+  - name: deploy
+    instructions: Deploy to ${env}
+    when: env == 'prod'
+  - name: cleanup
+    instructions: Clean up
+    when: steps.deploy.status == 'skipped' and not dry_run
+"#,
+        )
+        .unwrap();
+        let params = HashMap::from([
+            ("env".to_string(), "dev".to_string()),
+            ("dry_run".to_string(), "true".to_string()),
+        ]);
+
+        let run = run_recipe_steps(&recipe, &params).await.unwrap();
+        assert!(!run.stopped);
+        let statuses: Vec<&str> = run.results.iter().map(|r| r.status.as_str()).collect();
+        assert_eq!(statuses, vec![STEP_STATUS_SKIPPED, STEP_STATUS_SKIPPED]);
+    }
+
+    #[tokio::test]
+    async fn test_when_errors_fail_the_step() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Release
+description: Release
+instructions: Release
+steps:
+  - name: deploy
+    instructions: Deploy
+    when: region == 'eu'
+    continue_on_error: true
+  - name: cleanup
+    instructions: Clean up
+    when: steps.deploy.status == 'success'
+  - name: announce
+    instructions: Announce
+    when: missing == 'yes'
+  - name: never
+    instructions: Never
+    when: steps.deploy.status == 'success'
+"#,
+        )
+        .unwrap();
+
+        let run = run_recipe_steps(&recipe, &HashMap::new()).await.unwrap();
+        assert!(run.stopped);
+        let statuses: Vec<&str> = run.results.iter().map(|r| r.status.as_str()).collect();
+        assert_eq!(
+            statuses,
+            vec![STEP_STATUS_FAILED, STEP_STATUS_SKIPPED, STEP_STATUS_FAILED]
+        );
+        assert!(run.results[0].error.as_ref().unwrap().contains("region"));
+        let error = run.stop_error().unwrap().to_string();
+        assert!(error.starts_with("Step 'announce' failed"));
+    }
+}
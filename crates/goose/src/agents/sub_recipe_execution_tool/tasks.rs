@@ -5,6 +5,7 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::time::timeout;
 
+use crate::agents::extension::ExtensionConfig;
 use crate::agents::sub_recipe_execution_tool::types::{Task, TaskResult};
 
 // Process a single task based on its type
@@ -65,6 +66,9 @@ async fn execute_task(task: Task) -> Result<Value, String> {
         cmd.arg("run").arg("--text").arg(text);
         cmd
     };
+    if let Some(extensions) = task.payload.get("extensions") {
+        add_extension_args(&mut command, extensions)?;
+    }
 
     // Configure to capture stdout
     command.stdout(Stdio::piped());
@@ -117,3 +121,66 @@ async fn execute_task(task: Task) -> Result<Value, String> {
         Err(format!("Command failed:\n{}", stderr_output))
     }
 }
+
+// Pass the task's own extensions to goose run as --with-* flags
+fn add_extension_args(command: &mut Command, extensions: &Value) -> Result<(), String> {
+    let extensions: Vec<ExtensionConfig> = serde_json::from_value(extensions.clone())
+        .map_err(|e| format!("Failed to parse task extensions: {}", e))?;
+    for extension in extensions {
+        match extension {
+            ExtensionConfig::Builtin { name, .. } => {
+                command.arg("--with-builtin").arg(name);
+            }
+            ExtensionConfig::Sse { uri, .. } => {
+                command.arg("--with-remote-extension").arg(uri);
+            }
+            // Passed whole, so arguments and environment values with spaces survive
+            stdio @ ExtensionConfig::Stdio { .. } => {
+                let config = serde_json::to_string(&stdio)
+                    .map_err(|e| format!("Failed to serialize task extension: {}", e))?;
+                command.arg("--with-extension-config").arg(config);
+            }
+            other => {
+                return Err(format!(
+                    "Extension '{}' can't be added to a task, use builtin, stdio or sse",
+                    other.name()
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stdio_extensions_are_passed_whole() {
+        let mut command = Command::new("goose");
+        let extensions = json!([{
+            "type": "stdio",
+            "name": "files",
+            "cmd": "npx",
+            "args": ["server", "--root", "/tmp/my files"],
+            "envs": {"TOKEN": "a b"},
+        }]);
+
+        add_extension_args(&mut command, &extensions).unwrap();
+
+        let args: Vec<_> = command.as_std().get_args().collect();
+        assert_eq!(args[0], "--with-extension-config");
+        let config: ExtensionConfig = serde_json::from_str(args[1].to_str().unwrap()).unwrap();
+        match config {
+            ExtensionConfig::Stdio {
+                cmd, args, envs, ..
+            } => {
+                assert_eq!(cmd, "npx");
+                assert_eq!(args, vec!["server", "--root", "/tmp/my files"]);
+                assert_eq!(envs.get_env()["TOKEN"], "a b");
+            }
+            other => panic!("expected a stdio extension, got {:?}", other),
+        }
+    }
+}
@@ -487,7 +487,7 @@ impl SubAgent {
                         "The context length of the model has been exceeded. Please start a new session and try again.",
                     ));
                 }
-                Err(ProviderError::RateLimitExceeded(_)) => {
+                Err(ProviderError::RateLimitExceeded { .. }) => {
                     self.set_status(SubAgentStatus::Completed("Rate limit exceeded".to_string()))
                         .await;
                     break Ok(Message::assistant()
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::base::Config;
+use crate::session::storage::SessionMetadata;
+
+/// Limits on how much a session may use before the agent stops
+///
+/// Checked against the session's accumulated usage before each call to the model, so the
+/// call that crosses a limit still completes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub struct Budget {
+    /// Most tokens the session may use, input and output together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i64>,
+    /// Most the session may cost, in the currency of the pricing data (USD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+impl Budget {
+    /// The budget from the `session_budget` key in the global config, if set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get_param("session_budget").ok()
+    }
+
+    /// Why the session described by `metadata` has no budget left, if it hasn't
+    pub fn exceeded(&self, metadata: &SessionMetadata) -> Option<String> {
+        if let (Some(max), Some(used)) = (self.max_tokens, metadata.accumulated_total_tokens) {
+            if used as i64 >= max {
+                return Some(format!(
+                    "This session has used {} tokens, reaching its budget of {} tokens.",
+                    used, max
+                ));
+            }
+        }
+        if let (Some(max), Some(cost)) = (self.max_cost, metadata.accumulated_cost) {
+            if cost >= max {
+                return Some(format!(
+                    "This session has cost ${:.4}, reaching its budget of ${:.2}.",
+                    cost, max
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_exceeded_by_tokens_or_cost() {
+        let budget: Budget = serde_yaml::from_str("max_tokens: 1000\nmax_cost: 0.5").unwrap();
+        let mut metadata = SessionMetadata::default();
+        assert_eq!(budget.exceeded(&metadata), None);
+
+        metadata.accumulated_total_tokens = Some(999);
+        metadata.accumulated_cost = Some(0.49);
+        assert_eq!(budget.exceeded(&metadata), None);
+
+        metadata.accumulated_total_tokens = Some(1000);
+        assert!(budget.exceeded(&metadata).unwrap().contains("1000 tokens"));
+
+        metadata.accumulated_total_tokens = Some(10);
+        metadata.accumulated_cost = Some(0.75);
+        assert!(budget.exceeded(&metadata).unwrap().contains("$0.7500"));
+
+        assert_eq!(Budget::default().exceeded(&metadata), None);
+    }
+}
@@ -1,13 +1,17 @@
 pub mod base;
+pub mod budget;
 mod experiments;
 pub mod extensions;
 pub mod permission;
+pub mod sandbox;
 
 pub use crate::agents::ExtensionConfig;
 pub use base::{Config, ConfigError, APP_STRATEGY};
+pub use budget::Budget;
 pub use experiments::ExperimentManager;
 pub use extensions::{ExtensionConfigManager, ExtensionEntry};
 pub use permission::PermissionManager;
+pub use sandbox::ShellSandbox;
 
 pub use extensions::DEFAULT_DISPLAY_NAME;
 pub use extensions::DEFAULT_EXTENSION;
@@ -1,6 +1,8 @@
 use super::APP_STRATEGY;
+use crate::permission::permission_rules::{evaluate_rules, PermissionRule};
 use etcetera::{choose_app_strategy, AppStrategy};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -17,10 +19,13 @@ pub enum PermissionLevel {
 
 /// Struct representing the configuration of permissions, categorized by level.
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(default)]
 pub struct PermissionConfig {
     pub always_allow: Vec<String>, // List of tools that are always allowed
     pub ask_before: Vec<String>,   // List of tools that require user consent
     pub never_allow: Vec<String>,  // List of tools that are never allowed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PermissionRule>, // Rules matching tool arguments, checked before the lists
 }
 
 /// PermissionManager manages permission configurations for various tools.
@@ -138,6 +143,34 @@ impl PermissionManager {
         self.update_permission(SMART_APPROVE_PERMISSION, principal_name, level)
     }
 
+    /// Retrieves the user's argument-aware permission rules.
+    pub fn get_user_rules(&self) -> Vec<PermissionRule> {
+        self.permission_map
+            .get(USER_PERMISSION)
+            .map(|config| config.rules.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the user's argument-aware permission rules.
+    pub fn set_user_rules(&mut self, rules: Vec<PermissionRule>) {
+        self.permission_map
+            .entry(USER_PERMISSION.to_string())
+            .or_default()
+            .rules = rules;
+        self.save();
+    }
+
+    /// Retrieves the level of the winning user rule for a tool call, if any rule applies.
+    pub fn evaluate_user_rules(
+        &self,
+        tool_name: &str,
+        arguments: &Value,
+        workspace: Option<&Path>,
+    ) -> Option<PermissionLevel> {
+        let config = self.permission_map.get(USER_PERMISSION)?;
+        evaluate_rules(&config.rules, tool_name, arguments, workspace)
+    }
+
     /// Helper function to update a permission level for a specific tool in a given permission category.
     fn update_permission(&mut self, name: &str, principal_name: &str, level: PermissionLevel) {
         // Get or create a new PermissionConfig for the specified category
@@ -165,7 +198,11 @@ impl PermissionManager {
                 .push(principal_name.to_string()),
         }
 
-        // Serialize the updated permission map and write it back to the config file
+        self.save();
+    }
+
+    /// Serializes the permission map and writes it back to the config file.
+    fn save(&self) {
         let yaml_content = serde_yaml::to_string(&self.permission_map)
             .expect("Failed to serialize permission config");
         fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
@@ -183,17 +220,19 @@ impl PermissionManager {
             permission_config
                 .never_allow
                 .retain(|p| !p.starts_with(extension_name));
+            permission_config
+                .rules
+                .retain(|rule| !rule.tool.starts_with(extension_name));
         }
 
-        let yaml_content = serde_yaml::to_string(&self.permission_map)
-            .expect("Failed to serialize permission config");
-        fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
+        self.save();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::permission::permission_rules::{ArgumentCondition, ArgumentMatcher};
     use tempfile::NamedTempFile;
 
     // Helper function to create a test instance of PermissionManager with a temp dir
@@ -305,4 +344,44 @@ mod tests {
             .always_allow
             .contains(&"nonprefix__tool2".to_string()));
     }
+
+    #[test]
+    fn test_user_rules_round_trip_and_evaluate() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut manager = PermissionManager::new(temp_file.path());
+        manager.update_user_permission("developer__shell", PermissionLevel::AskBefore);
+        manager.set_user_rules(vec![PermissionRule {
+            tool: "developer__shell".to_string(),
+            when: vec![ArgumentCondition {
+                argument: "command".to_string(),
+                matcher: ArgumentMatcher::CommandPrefix(vec!["git status".to_string()]),
+            }],
+            level: PermissionLevel::AlwaysAllow,
+            priority: 0,
+        }]);
+
+        // Rules survive reloading the file next to the existing lists
+        let manager = PermissionManager::new(temp_file.path());
+        assert_eq!(manager.get_user_rules().len(), 1);
+        assert_eq!(
+            manager.get_user_permission("developer__shell"),
+            Some(PermissionLevel::AskBefore)
+        );
+        assert_eq!(
+            manager.evaluate_user_rules(
+                "developer__shell",
+                &serde_json::json!({"command": "git status"}),
+                None
+            ),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            manager.evaluate_user_rules(
+                "developer__shell",
+                &serde_json::json!({"command": "rm -rf /"}),
+                None
+            ),
+            None
+        );
+    }
 }
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::base::Config;
+
+pub use mcp_core::sandbox::{SANDBOX_ENV, SANDBOX_NETWORK_ENV, SANDBOX_WRITABLE_ENV};
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Restrictions on commands run by the developer extension's shell tool
+///
+/// When enabled, commands can only write to the session working directory, temp and
+/// `writable_paths`, and can't reach the network unless `allow_network` is set. Only
+/// supported on Linux; elsewhere sandboxed commands fail instead of running unrestricted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ShellSandbox {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub allow_network: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable_paths: Vec<String>,
+}
+
+impl ShellSandbox {
+    /// The sandbox from the `shell_sandbox` key in the global config, if set
+    pub fn from_config() -> Option<Self> {
+        Config::global().get_param("shell_sandbox").ok()
+    }
+
+    /// The environment that passes this sandbox to a builtin extension
+    pub fn envs(&self) -> HashMap<String, String> {
+        let mut envs = HashMap::from([
+            (SANDBOX_ENV.to_string(), self.enabled.to_string()),
+            (
+                SANDBOX_NETWORK_ENV.to_string(),
+                self.allow_network.to_string(),
+            ),
+        ]);
+        if let Ok(paths) = std::env::join_paths(&self.writable_paths) {
+            envs.insert(
+                SANDBOX_WRITABLE_ENV.to_string(),
+                paths.to_string_lossy().into_owned(),
+            );
+        }
+        envs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_defaults_and_envs() {
+        let sandbox: ShellSandbox =
+            serde_yaml::from_str("writable_paths: [/opt/cache, ~/.cargo]").unwrap();
+        assert!(sandbox.enabled);
+        assert!(!sandbox.allow_network);
+
+        let envs = sandbox.envs();
+        assert_eq!(envs[SANDBOX_ENV], "true");
+        assert_eq!(envs[SANDBOX_NETWORK_ENV], "false");
+        #[cfg(unix)]
+        assert_eq!(envs[SANDBOX_WRITABLE_ENV], "/opt/cache:~/.cargo");
+    }
+}
@@ -0,0 +1,249 @@
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use mcp_core::{Content, ResourceContents, Role};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Replaces the body of a tool result dropped by [`CompactionStrategy::DropToolResponses`]
+pub const DROPPED_TOOL_RESULT: &str = "[Tool output removed to save context]";
+
+/// How the conversation is made smaller when it approaches the model's context limit
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionStrategy {
+    /// Remove the oldest messages first
+    #[default]
+    Truncate,
+    /// Summarize the conversation so far into one message
+    Summarize,
+    /// Replace the bodies of older tool results with a placeholder, then truncate if that
+    /// wasn't enough
+    DropToolResponses,
+    /// Summarize older tool results, keeping user and assistant text as it was, then
+    /// truncate if that wasn't enough
+    SummarizeToolResults,
+    /// Remove the oldest messages first, but keep the first prompt and pinned messages
+    KeepPinned,
+}
+
+fn default_keep_recent() -> usize {
+    4
+}
+
+fn default_pin_markers() -> Vec<String> {
+    vec!["#pin".to_string()]
+}
+
+/// Which compaction strategy to use, and when to compact without waiting for the provider to
+/// reject the conversation
+///
+/// Read from the `compaction` config key, and can be overridden by a recipe's settings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct CompactionSettings {
+    #[serde(default)]
+    pub strategy: CompactionStrategy,
+    /// Compact before calling the model once the conversation uses this fraction of the
+    /// model's context limit, e.g. 0.8. Unset means only compact on request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_threshold: Option<f32>,
+    /// How many of the most recent tool results the tool result strategies leave alone
+    #[serde(default = "default_keep_recent")]
+    pub keep_recent: usize,
+    /// Text messages containing any of these are pinned for `keep_pinned`
+    #[serde(default = "default_pin_markers")]
+    pub pin_markers: Vec<String>,
+}
+
+impl Default for CompactionSettings {
+    fn default() -> Self {
+        Self {
+            strategy: CompactionStrategy::default(),
+            auto_threshold: None,
+            keep_recent: default_keep_recent(),
+            pin_markers: default_pin_markers(),
+        }
+    }
+}
+
+impl CompactionSettings {
+    /// The settings from the `compaction` key in the global config, or the defaults
+    pub fn from_config() -> Self {
+        Config::global().get_param("compaction").unwrap_or_default()
+    }
+
+    /// The token count at which to compact automatically, if enabled
+    pub fn auto_limit(&self, context_limit: usize) -> Option<usize> {
+        self.auto_threshold
+            .filter(|t| *t > 0.0 && *t <= 1.0)
+            .map(|t| (context_limit as f32 * t) as usize)
+    }
+}
+
+/// Index of the prompt the current turn is answering: the last user message that is plain
+/// text rather than tool results
+pub fn current_prompt_index(messages: &[Message]) -> Option<usize> {
+    messages
+        .iter()
+        .rposition(|m| m.role == Role::User && m.has_only_text_content())
+}
+
+/// Indices of messages with tool results, oldest first, leaving out the last `keep_recent`
+pub fn older_tool_results(messages: &[Message], keep_recent: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.is_tool_response())
+        .map(|(i, _)| i)
+        .collect();
+    indices.truncate(indices.len().saturating_sub(keep_recent));
+    indices
+}
+
+/// The text bodies of the tool results in `message`
+pub fn tool_result_texts(message: &Message) -> Vec<&str> {
+    let mut texts = Vec::new();
+    for content in &message.content {
+        if let MessageContent::ToolResponse(response) = content {
+            if let Ok(result) = &response.tool_result {
+                for item in result {
+                    match item {
+                        Content::Text(text) => texts.push(text.text.as_str()),
+                        Content::Resource(resource) => {
+                            if let ResourceContents::TextResourceContents { text, .. } =
+                                &resource.resource
+                            {
+                                texts.push(text.as_str());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    texts
+}
+
+/// Replace every tool result body in `message` with the next of `replacements`, in the
+/// order [`tool_result_texts`] returns them
+///
+/// Images and other binary results are replaced with text too, since they are usually
+/// the largest part of a result.
+pub fn replace_tool_results(
+    message: &Message,
+    mut replacements: impl Iterator<Item = String>,
+    fallback: &str,
+) -> Message {
+    let mut message = message.clone();
+    for content in &mut message.content {
+        if let MessageContent::ToolResponse(response) = content {
+            if let Ok(result) = &mut response.tool_result {
+                for item in result.iter_mut() {
+                    let replacement = match item {
+                        Content::Text(_) => replacements.next(),
+                        Content::Resource(resource) => match resource.resource {
+                            ResourceContents::TextResourceContents { .. } => replacements.next(),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    *item = Content::text(replacement.unwrap_or_else(|| fallback.to_string()));
+                }
+            }
+        }
+    }
+    message
+}
+
+/// Replace older tool result bodies with a placeholder, oldest first, until the messages
+/// fit in `target` tokens
+///
+/// Returns the new messages and their token counts, which may still be over `target` if
+/// dropping every older result wasn't enough.
+pub fn drop_tool_responses(
+    messages: &[Message],
+    token_counts: &[usize],
+    target: usize,
+    keep_recent: usize,
+    count_tokens: &dyn Fn(&Message) -> usize,
+) -> (Vec<Message>, Vec<usize>) {
+    let mut messages = messages.to_vec();
+    let mut token_counts = token_counts.to_vec();
+    let mut total: usize = token_counts.iter().sum();
+
+    for i in older_tool_results(&messages, keep_recent) {
+        if total <= target {
+            break;
+        }
+        let replacements = std::iter::repeat(DROPPED_TOOL_RESULT.to_string());
+        messages[i] = replace_tool_results(&messages[i], replacements, DROPPED_TOOL_RESULT);
+        let tokens = count_tokens(&messages[i]);
+        total = total - token_counts[i] + tokens;
+        token_counts[i] = tokens;
+    }
+    (messages, token_counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    fn tool_turn(id: &str, output: &str) -> Vec<Message> {
+        vec![
+            Message::assistant()
+                .with_text("running it")
+                .with_tool_request(id, Ok(ToolCall::new("shell", json!({"command": "ls"})))),
+            Message::user().with_tool_response(id, Ok(vec![Content::text(output)])),
+        ]
+    }
+
+    fn count(message: &Message) -> usize {
+        message.as_concat_text().len()
+            + tool_result_texts(message)
+                .iter()
+                .map(|t| t.len())
+                .sum::<usize>()
+    }
+
+    #[test]
+    fn test_settings_defaults_and_auto_limit() {
+        let settings: CompactionSettings =
+            serde_yaml::from_str("strategy: drop_tool_responses\nauto_threshold: 0.8").unwrap();
+        assert_eq!(settings.strategy, CompactionStrategy::DropToolResponses);
+        assert_eq!(settings.keep_recent, 4);
+        assert_eq!(settings.auto_limit(1000), Some(800));
+        assert_eq!(CompactionSettings::default().auto_limit(1000), None);
+    }
+
+    #[test]
+    fn test_drop_tool_responses_oldest_first_and_keeps_recent() {
+        let mut messages = vec![Message::user().with_text("list files")];
+        messages.extend(tool_turn("1", &"a".repeat(500)));
+        messages.extend(tool_turn("2", &"b".repeat(500)));
+        messages.extend(tool_turn("3", &"c".repeat(500)));
+        let counts: Vec<usize> = messages.iter().map(count).collect();
+        let total: usize = counts.iter().sum();
+
+        // Dropping the first result is enough
+        let (dropped, new_counts) = drop_tool_responses(&messages, &counts, total - 400, 1, &count);
+        assert_eq!(tool_result_texts(&dropped[2]), vec![DROPPED_TOOL_RESULT]);
+        assert_eq!(tool_result_texts(&dropped[4]), vec!["b".repeat(500)]);
+        assert!(new_counts.iter().sum::<usize>() <= total - 400);
+
+        // The most recent result is never dropped, even if the target isn't reached
+        let (dropped, _) = drop_tool_responses(&messages, &counts, 0, 1, &count);
+        assert_eq!(tool_result_texts(&dropped[4]), vec![DROPPED_TOOL_RESULT]);
+        assert_eq!(tool_result_texts(&dropped[6]), vec!["c".repeat(500)]);
+        assert_eq!(dropped[1].as_concat_text(), "running it");
+    }
+
+    #[test]
+    fn test_current_prompt_index_skips_tool_results() {
+        let mut messages = vec![Message::user().with_text("first")];
+        messages.extend(tool_turn("1", "out"));
+        assert_eq!(current_prompt_index(&messages), Some(0));
+        assert_eq!(current_prompt_index(&messages[1..]), None);
+    }
+}
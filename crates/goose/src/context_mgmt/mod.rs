@@ -1,4 +1,5 @@
 mod common;
+pub mod compaction;
 pub mod summarize;
 pub mod truncate;
 
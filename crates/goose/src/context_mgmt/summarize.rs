@@ -1,4 +1,5 @@
 use super::common::{get_messages_token_counts, get_messages_token_counts_async};
+use super::compaction::{older_tool_results, replace_tool_results, tool_result_texts};
 use crate::message::{Message, MessageContent};
 use crate::providers::base::Provider;
 use crate::token_counter::{AsyncTokenCounter, TokenCounter};
@@ -8,6 +9,11 @@ use std::sync::Arc;
 
 // Constants for the summarization prompt and a follow-up user message.
 const SUMMARY_PROMPT: &str = "You are good at summarizing conversations";
+const TOOL_RESULT_SUMMARY_PROMPT: &str = "You summarize the output of tools an agent called. Keep file paths, names, numbers and errors, and drop everything else.";
+// Tool results shorter than this aren't worth a call to summarize
+const MIN_TOOL_RESULT_SUMMARY_CHARS: usize = 1000;
+// Only this much of a tool result is sent to be summarized
+const MAX_TOOL_RESULT_SUMMARY_INPUT_CHARS: usize = 50_000;
 
 /// Summarize the combined messages from the accumulated summary and the current chunk.
 ///
@@ -212,6 +218,70 @@ pub async fn summarize_messages_async(
     ))
 }
 
+async fn summarize_tool_result(provider: &Arc<dyn Provider>, text: &str) -> Result<String> {
+    let input = match text.char_indices().nth(MAX_TOOL_RESULT_SUMMARY_INPUT_CHARS) {
+        Some((cut, _)) => &text[..cut],
+        None => text,
+    };
+    let request = vec![Message::user().with_text(format!(
+        "Summarize this tool output in a few sentences:\n\n```\n{}\n```",
+        input
+    ))];
+    let (response, _) = provider
+        .complete(TOOL_RESULT_SUMMARY_PROMPT, &request, &[])
+        .await?;
+    Ok(format!(
+        "[Summary of tool output] {}",
+        response.as_concat_text()
+    ))
+}
+
+/// Summarize the bodies of older tool results, oldest first, until the messages fit in
+/// `target` tokens
+///
+/// User and assistant messages are left as they were, as are the last `keep_recent` tool
+/// results. Returns the new messages and their token counts, which may still be over
+/// `target` if summarizing every older result wasn't enough.
+pub async fn summarize_tool_results(
+    provider: Arc<dyn Provider>,
+    messages: &[Message],
+    token_counts: &[usize],
+    target: usize,
+    keep_recent: usize,
+    count_tokens: &(dyn Fn(&Message) -> usize + Sync),
+) -> Result<(Vec<Message>, Vec<usize>)> {
+    let mut messages = messages.to_vec();
+    let mut token_counts = token_counts.to_vec();
+    let mut total: usize = token_counts.iter().sum();
+
+    for i in older_tool_results(&messages, keep_recent) {
+        if total <= target {
+            break;
+        }
+        let texts: Vec<String> = tool_result_texts(&messages[i])
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let mut replacements = Vec::with_capacity(texts.len());
+        for text in texts {
+            if text.len() < MIN_TOOL_RESULT_SUMMARY_CHARS {
+                replacements.push(text);
+            } else {
+                replacements.push(summarize_tool_result(&provider, &text).await?);
+            }
+        }
+        messages[i] = replace_tool_results(
+            &messages[i],
+            replacements.into_iter(),
+            "[Non-text tool output removed to save context]",
+        );
+        let tokens = count_tokens(&messages[i]);
+        total = total - token_counts[i] + tokens;
+        token_counts[i] = tokens;
+    }
+    Ok((messages, token_counts))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,4 +542,40 @@ mod tests {
             "The final message list should include the summary and removed messages."
         );
     }
+
+    #[tokio::test]
+    async fn test_summarize_tool_results_keeps_text_and_recent_results() -> Result<()> {
+        let provider = create_mock_provider();
+        let long_output = "x".repeat(MIN_TOOL_RESULT_SUMMARY_CHARS * 2);
+        let messages = vec![
+            set_up_text_message("Check the logs", Role::User),
+            set_up_tool_request_message("1", ToolCall::new("read", json!({}))),
+            set_up_tool_response_message("1", vec![Content::text(long_output.clone())]),
+            set_up_text_message("They look fine", Role::Assistant),
+            set_up_tool_request_message("2", ToolCall::new("read", json!({}))),
+            set_up_tool_response_message("2", vec![Content::text(long_output.clone())]),
+        ];
+        let count = |m: &Message| {
+            10 + tool_result_texts(m)
+                .iter()
+                .map(|t| t.len() / 4)
+                .sum::<usize>()
+        };
+        let token_counts: Vec<usize> = messages.iter().map(count).collect();
+
+        let (summarized, new_counts) =
+            summarize_tool_results(provider, &messages, &token_counts, 0, 1, &count).await?;
+
+        assert_eq!(
+            tool_result_texts(&summarized[2]),
+            vec!["[Summary of tool output] Summarized content"]
+        );
+        assert_eq!(
+            tool_result_texts(&summarized[5]),
+            vec![long_output.as_str()]
+        );
+        assert_eq!(summarized[3].as_concat_text(), "They look fine");
+        assert!(new_counts[2] < token_counts[2]);
+        Ok(())
+    }
 }
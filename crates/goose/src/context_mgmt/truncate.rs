@@ -369,6 +369,66 @@ impl TruncationStrategy for OldestFirstTruncation {
     }
 }
 
+/// Strategy to truncate messages by removing the oldest first, except pinned ones
+///
+/// The first user prompt is always pinned, since it usually states the task, as is any text
+/// message containing one of `markers`.
+pub struct PinnedTruncation {
+    pub markers: Vec<String>,
+}
+
+impl PinnedTruncation {
+    fn pinned_indices(&self, messages: &[Message]) -> HashSet<usize> {
+        let first_prompt = messages
+            .iter()
+            .position(|m| m.role == Role::User && m.has_only_text_content());
+        messages
+            .iter()
+            .enumerate()
+            .filter(|(i, message)| {
+                Some(*i) == first_prompt
+                    || (message.has_only_text_content() && {
+                        let text = message.as_concat_text();
+                        self.markers.iter().any(|marker| text.contains(marker))
+                    })
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl TruncationStrategy for PinnedTruncation {
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let pinned = self.pinned_indices(messages);
+        let unpinned: Vec<(usize, &Message)> = messages
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !pinned.contains(i))
+            .collect();
+        let unpinned_messages: Vec<Message> = unpinned.iter().map(|(_, m)| (*m).clone()).collect();
+        let unpinned_counts: Vec<usize> = unpinned.iter().map(|(i, _)| token_counts[*i]).collect();
+        let pinned_tokens: usize = pinned.iter().map(|&i| token_counts[i]).sum();
+
+        // Truncate what isn't pinned as if the pinned messages weren't there
+        let removed = OldestFirstTruncation.determine_indices_to_remove(
+            &unpinned_messages,
+            &unpinned_counts,
+            context_limit.saturating_sub(pinned_tokens),
+        )?;
+        debug!(
+            "Pinned: keeping {} pinned messages, removing {}",
+            pinned.len(),
+            removed.len()
+        );
+        Ok(removed.into_iter().map(|i| unpinned[i].0).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -711,4 +771,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_pinned_truncation_keeps_first_prompt_and_marked_messages() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("Refactor the parser"),
+            Message::assistant().with_text("Looking at it"),
+            Message::user().with_text("#pin never touch the public API"),
+            Message::assistant().with_text("Understood"),
+            Message::user().with_text("Now the lexer"),
+            Message::assistant().with_text("Done with the lexer"),
+            Message::user().with_text("And the tests?"),
+        ];
+        let token_counts = vec![10; messages.len()];
+        let strategy = PinnedTruncation {
+            markers: vec!["#pin".to_string()],
+        };
+
+        let (kept, kept_counts) = truncate_messages(&messages, &token_counts, 45, &strategy)?;
+        assert!(kept_counts.iter().sum::<usize>() <= 45);
+        let texts: Vec<String> = kept.iter().map(|m| m.as_concat_text()).collect();
+        assert_eq!(texts[0], "Refactor the parser");
+        assert!(texts.contains(&"#pin never touch the public API".to_string()));
+        assert_eq!(texts.last().unwrap(), "And the tests?");
+        assert!(!texts.contains(&"Looking at it".to_string()));
+
+        Ok(())
+    }
 }
@@ -3,6 +3,7 @@ pub mod config;
 pub mod context_mgmt;
 pub mod message;
 pub mod model;
+pub mod model_registry;
 pub mod permission;
 pub mod prompt_template;
 pub mod providers;
@@ -1,36 +1,8 @@
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
+use crate::model_registry::{self, ModelCapabilities};
 
-// Define the model limits as a static HashMap for reuse
-static MODEL_SPECIFIC_LIMITS: Lazy<HashMap<&'static str, usize>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-    // OpenAI models, https://platform.openai.com/docs/models#models-overview
-    map.insert("gpt-4o", 128_000);
-    map.insert("gpt-4-turbo", 128_000);
-    map.insert("o3", 200_000);
-    map.insert("o3-mini", 200_000);
-    map.insert("o4-mini", 200_000);
-    map.insert("gpt-4.1", 1_000_000);
-    map.insert("gpt-4-1", 1_000_000);
-
-    // Anthropic models, https://docs.anthropic.com/en/docs/about-claude/models
-    map.insert("claude", 200_000);
-
-    // Google models, https://ai.google/get-started/our-models/
-    map.insert("gemini-2.5", 1_000_000);
-    map.insert("gemini-2-5", 1_000_000);
-
-    // Meta Llama models, https://github.com/meta-llama/llama-models/tree/main?tab=readme-ov-file#llama-models-1
-    map.insert("llama3.2", 128_000);
-    map.insert("llama3.3", 128_000);
-
-    // x.ai Grok models, https://docs.x.ai/docs/overview
-    map.insert("grok", 131_072);
-    map
-});
+const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
 /// Configuration for model-specific settings and limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +19,11 @@ pub struct ModelConfig {
     pub toolshim: bool,
     /// Model to use for toolshim (optional as a default exists)
     pub toolshim_model: Option<String>,
+    /// Optional reasoning effort for reasoning models ("low", "medium" or "high")
+    pub reasoning_effort: Option<String>,
+    /// The provider serving the model, whose knowledge of it the model registry adds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
 }
 
 /// Struct to represent model pattern matches and their limits
@@ -59,13 +36,11 @@ pub struct ModelLimitConfig {
 impl ModelConfig {
     /// Create a new ModelConfig with the specified model name
     ///
-    /// The context limit is set with the following precedence:
+    /// The context limit is set with the following precedence (in context_limit):
     /// 1. Explicit context_limit if provided in config
-    /// 2. Model-specific default based on model name
-    /// 3. Global default (128_000) (in get_context_limit)
+    /// 2. Model-specific default from the model registry, for the provider when it's known
+    /// 3. Global default (128_000)
     pub fn new(model_name: String) -> Self {
-        let context_limit = Self::get_model_specific_limit(&model_name);
-
         let toolshim = std::env::var("GOOSE_TOOLSHIM")
             .map(|val| val == "1" || val.to_lowercase() == "true")
             .unwrap_or(false);
@@ -76,33 +51,30 @@ impl ModelConfig {
             .ok()
             .and_then(|val| val.parse::<f32>().ok());
 
+        let reasoning_effort = std::env::var("GOOSE_REASONING_EFFORT").ok();
+
         Self {
             model_name,
-            context_limit,
+            context_limit: None,
             temperature,
             max_tokens: None,
             toolshim,
             toolshim_model,
+            reasoning_effort,
+            provider: None,
         }
     }
 
-    /// Get model-specific context limit based on model name
-    fn get_model_specific_limit(model_name: &str) -> Option<usize> {
-        for (pattern, &limit) in MODEL_SPECIFIC_LIMITS.iter() {
-            if model_name.contains(pattern) {
-                return Some(limit);
-            }
-        }
-        None
-    }
-
     /// Get all model pattern matches and their limits
     pub fn get_all_model_limits() -> Vec<ModelLimitConfig> {
-        MODEL_SPECIFIC_LIMITS
-            .iter()
-            .map(|(&pattern, &context_limit)| ModelLimitConfig {
-                pattern: pattern.to_string(),
-                context_limit,
+        model_registry::all_entries()
+            .into_iter()
+            .filter(|entry| entry.provider.is_none())
+            .filter_map(|entry| {
+                Some(ModelLimitConfig {
+                    context_limit: entry.capabilities.context_limit?,
+                    pattern: entry.pattern,
+                })
             })
             .collect()
     }
@@ -130,6 +102,14 @@ impl ModelConfig {
         self
     }
 
+    /// A copy whose output is limited to `max_tokens`, or its own lower limit
+    pub fn capped_at(&self, max_tokens: i32) -> Self {
+        let limit = self
+            .max_tokens
+            .map_or(max_tokens, |limit| limit.min(max_tokens));
+        self.clone().with_max_tokens(Some(limit))
+    }
+
     /// Set whether to interpret tool calls
     pub fn with_toolshim(mut self, toolshim: bool) -> Self {
         self.toolshim = toolshim;
@@ -142,10 +122,32 @@ impl ModelConfig {
         self
     }
 
+    /// Set the reasoning effort
+    pub fn with_reasoning_effort(mut self, effort: Option<String>) -> Self {
+        self.reasoning_effort = effort;
+        self
+    }
+
+    /// Set the provider serving the model
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    /// What the model registry knows about the model as its provider serves it
+    pub fn capabilities(&self) -> ModelCapabilities {
+        model_registry::capabilities(self.provider.as_deref(), &self.model_name)
+    }
+
     /// Get the context_limit for the current model
-    /// If none are defined, use the DEFAULT_CONTEXT_LIMIT
+    ///
+    /// Without an explicit limit the registry is asked each time, so what the provider
+    /// reports about its models later still applies. If none are defined, use the
+    /// DEFAULT_CONTEXT_LIMIT.
     pub fn context_limit(&self) -> usize {
-        self.context_limit.unwrap_or(DEFAULT_CONTEXT_LIMIT)
+        self.context_limit
+            .or_else(|| self.capabilities().context_limit)
+            .unwrap_or(DEFAULT_CONTEXT_LIMIT)
     }
 }
 
@@ -182,6 +184,11 @@ mod tests {
         assert_eq!(config.temperature, Some(0.7));
         assert_eq!(config.max_tokens, Some(1000));
         assert_eq!(config.context_limit, Some(50_000));
+
+        assert_eq!(config.capped_at(200).max_tokens, Some(200));
+        assert_eq!(config.capped_at(5000).max_tokens, Some(1000));
+        let unset = ModelConfig::new("test-model".to_string());
+        assert_eq!(unset.capped_at(200).max_tokens, Some(200));
     }
 
     #[test]
@@ -190,6 +197,10 @@ mod tests {
         let config = ModelConfig::new("test-model".to_string());
         assert!(!config.toolshim);
 
+        // Test models known not to call tools still need toolshim asked for
+        let config = ModelConfig::new("o1-mini".to_string());
+        assert!(!config.toolshim);
+
         // Test with tool interpretation setting
         let config = ModelConfig::new("test-model".to_string()).with_toolshim(true);
         assert!(config.toolshim);
@@ -223,6 +234,29 @@ mod tests {
         assert_eq!(config.temperature, None);
     }
 
+    #[test]
+    fn test_provider_context_limit_overrides_builtin_model() {
+        model_registry::refine(
+            "test-provider-limits",
+            "claude-3-opus",
+            ModelCapabilities {
+                context_limit: Some(50_000),
+                ..Default::default()
+            },
+        );
+
+        let config = ModelConfig::new("claude-3-opus".to_string());
+        assert_eq!(config.context_limit(), 200_000);
+        let config = config.with_provider("test-provider-limits");
+        assert_eq!(config.context_limit(), 50_000);
+
+        // An explicit limit still wins
+        let config = ModelConfig::new("claude-3-opus".to_string())
+            .with_context_limit(Some(150_000))
+            .with_provider("test-provider-limits");
+        assert_eq!(config.context_limit(), 150_000);
+    }
+
     #[test]
     fn test_get_all_model_limits() {
         let limits = ModelConfig::get_all_model_limits();
@@ -0,0 +1,366 @@
+//! What goose knows about each model: its context window, how much it can write, and which
+//! features it supports
+//!
+//! Capabilities come from three layers, each overriding the one before:
+//! 1. the built-in entries in `model_registry.json`
+//! 2. what providers learn about their models from their APIs, see [`refine`]
+//! 3. the user's entries under `model_capabilities` in the config
+//!
+//! An entry applies to every model whose name contains its pattern at a word boundary,
+//! ignoring case and treating `-`, `/`, `.` and `:` alike, so `gpt-4.1` matches
+//! `openai/gpt-4-1-mini`. When several entries match, longer patterns win. An entry with a
+//! `provider` only applies to models served by that provider, and what a provider learned
+//! only applies to its own models.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// The capabilities known for a model. Anything left as None is unknown.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ModelCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_limit: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calling: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    /// Whether the model thinks before answering, and takes a reasoning effort or budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_caching: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<bool>,
+    /// Who makes the model, e.g. `openai`, `anthropic` or `google`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    /// The system prompt template to use instead of `system.md`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+impl ModelCapabilities {
+    /// These capabilities with the ones `other` knows replacing them
+    pub fn overlay(mut self, other: &ModelCapabilities) -> Self {
+        fn set<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *field = other.clone();
+            }
+        }
+        set(&mut self.context_limit, &other.context_limit);
+        set(&mut self.max_output_tokens, &other.max_output_tokens);
+        set(&mut self.tool_calling, &other.tool_calling);
+        set(&mut self.vision, &other.vision);
+        set(&mut self.reasoning, &other.reasoning);
+        set(&mut self.prompt_caching, &other.prompt_caching);
+        set(&mut self.streaming, &other.streaming);
+        set(&mut self.family, &other.family);
+        set(&mut self.system_prompt, &other.system_prompt);
+        self
+    }
+
+    /// Whether the model takes tool definitions, assumed unless known otherwise
+    pub fn supports_tools(&self) -> bool {
+        self.tool_calling.unwrap_or(true)
+    }
+
+    /// Whether the model takes images, assumed unless known otherwise
+    pub fn supports_vision(&self) -> bool {
+        self.vision.unwrap_or(true)
+    }
+
+    pub fn is_reasoning(&self) -> bool {
+        self.reasoning.unwrap_or(false)
+    }
+
+    pub fn supports_prompt_caching(&self) -> bool {
+        self.prompt_caching.unwrap_or(false)
+    }
+
+    pub fn supports_streaming(&self) -> bool {
+        self.streaming.unwrap_or(true)
+    }
+
+    pub fn is_family(&self, family: &str) -> bool {
+        self.family.as_deref() == Some(family)
+    }
+}
+
+/// Capabilities for the models matching a pattern
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CapabilityEntry {
+    pub pattern: String,
+    /// The provider whose models this applies to, or every provider if none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(flatten)]
+    pub capabilities: ModelCapabilities,
+}
+
+/// Lower case with separators unified, so `GPT-4.1` and `gpt_4_1` compare equal
+pub fn normalize_model_name(name: &str) -> String {
+    name.replace(['-', '/', '.', ':'], "_").to_lowercase()
+}
+
+/// Whether `pattern` occurs in `name` at the start or right after a separator, both normalized
+fn matches(name: &str, pattern: &str) -> bool {
+    name.match_indices(pattern)
+        .any(|(index, _)| index == 0 || name.as_bytes()[index - 1] == b'_')
+}
+
+/// The matching entries' capabilities layered from the shortest pattern to the longest
+fn layer(
+    mut capabilities: ModelCapabilities,
+    entries: &[CapabilityEntry],
+    provider: Option<&str>,
+    name: &str,
+) -> ModelCapabilities {
+    let mut matching: Vec<_> = entries
+        .iter()
+        .filter(|entry| {
+            entry
+                .provider
+                .as_deref()
+                .is_none_or(|only| Some(only) == provider)
+        })
+        .map(|entry| (normalize_model_name(&entry.pattern), &entry.capabilities))
+        .filter(|(pattern, _)| !pattern.is_empty() && matches(name, pattern))
+        .collect();
+    matching.sort_by_key(|(pattern, _)| pattern.len());
+    for (_, entry) in matching {
+        capabilities = capabilities.overlay(entry);
+    }
+    capabilities
+}
+
+/// The built-in, provider and user layers of capabilities
+#[derive(Debug, Default)]
+pub struct ModelRegistry {
+    builtin: Vec<CapabilityEntry>,
+    refined: HashMap<(String, String), ModelCapabilities>,
+    overrides: Vec<CapabilityEntry>,
+}
+
+impl ModelRegistry {
+    pub fn new(builtin: Vec<CapabilityEntry>, overrides: Vec<CapabilityEntry>) -> Self {
+        Self {
+            builtin,
+            refined: HashMap::new(),
+            overrides,
+        }
+    }
+
+    /// The entries shipped with goose
+    pub fn builtin_entries() -> Vec<CapabilityEntry> {
+        serde_json::from_str(include_str!("model_registry.json"))
+            .expect("Failed to parse model_registry.json")
+    }
+
+    /// The entries under `model_capabilities` in the global config
+    pub fn user_entries() -> Vec<CapabilityEntry> {
+        Config::global()
+            .get_param("model_capabilities")
+            .unwrap_or_default()
+    }
+
+    /// What is known about `model` when `provider` serves it, or about the model in general
+    /// when the provider isn't known
+    pub fn capabilities(&self, provider: Option<&str>, model: &str) -> ModelCapabilities {
+        let name = normalize_model_name(model);
+        let mut capabilities = layer(ModelCapabilities::default(), &self.builtin, provider, &name);
+        if let Some(provider) = provider {
+            if let Some(refined) = self.refined.get(&(provider.to_string(), name.clone())) {
+                capabilities = capabilities.overlay(refined);
+            }
+        }
+        layer(capabilities, &self.overrides, provider, &name)
+    }
+
+    /// Record what `provider` learned about `model`, on top of what it reported before
+    pub fn refine(&mut self, provider: &str, model: &str, capabilities: ModelCapabilities) {
+        let refined = self
+            .refined
+            .entry((provider.to_string(), normalize_model_name(model)))
+            .or_default();
+        *refined = std::mem::take(refined).overlay(&capabilities);
+    }
+
+    /// The built-in entries followed by the user's
+    pub fn entries(&self) -> Vec<CapabilityEntry> {
+        self.builtin
+            .iter()
+            .chain(&self.overrides)
+            .cloned()
+            .collect()
+    }
+}
+
+static REGISTRY: Lazy<RwLock<ModelRegistry>> = Lazy::new(|| {
+    RwLock::new(ModelRegistry::new(
+        ModelRegistry::builtin_entries(),
+        ModelRegistry::user_entries(),
+    ))
+});
+
+/// What the global registry knows about `model` when `provider` serves it
+pub fn capabilities(provider: Option<&str>, model: &str) -> ModelCapabilities {
+    REGISTRY
+        .read()
+        .expect("model registry lock poisoned")
+        .capabilities(provider, model)
+}
+
+/// Record in the global registry what `provider` learned about `model`
+pub fn refine(provider: &str, model: &str, capabilities: ModelCapabilities) {
+    REGISTRY
+        .write()
+        .expect("model registry lock poisoned")
+        .refine(provider, model, capabilities);
+}
+
+/// The built-in and user entries of the global registry
+pub fn all_entries() -> Vec<CapabilityEntry> {
+    REGISTRY
+        .read()
+        .expect("model registry lock poisoned")
+        .entries()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(overrides: Vec<CapabilityEntry>) -> ModelRegistry {
+        ModelRegistry::new(ModelRegistry::builtin_entries(), overrides)
+    }
+
+    #[test]
+    fn test_builtin_matching() {
+        let registry = registry(vec![]);
+
+        let o1_mini = registry.capabilities(None, "o1-mini");
+        assert!(o1_mini.is_reasoning());
+        assert!(!o1_mini.supports_tools());
+        assert_eq!(o1_mini.context_limit, Some(128_000));
+        assert!(registry.capabilities(None, "o1").supports_tools());
+
+        assert!(registry
+            .capabilities(None, "goose-o3-mini-high")
+            .is_reasoning());
+        assert!(!registry.capabilities(None, "gpt-4o").is_reasoning());
+
+        let gpt_4_1 = registry.capabilities(None, "openai/gpt-4-1-mini");
+        assert_eq!(gpt_4_1.context_limit, Some(1_000_000));
+        assert_eq!(gpt_4_1.system_prompt.as_deref(), Some("system_gpt_4_1.md"));
+
+        let sonnet = registry.capabilities(None, "databricks-claude-3-7-sonnet");
+        assert!(sonnet.is_reasoning());
+        assert!(sonnet.is_family("anthropic"));
+        assert_eq!(sonnet.max_output_tokens, Some(8192));
+        assert!(!registry
+            .capabilities(None, "claude-3-5-sonnet")
+            .is_reasoning());
+
+        assert!(registry
+            .capabilities(None, "llama3.2:latest")
+            .context_limit
+            .is_some());
+        assert!(registry
+            .capabilities(None, "Google_XYZ")
+            .is_family("google"));
+        assert!(!registry
+            .capabilities(None, "mistral-nemo1")
+            .is_family("openai"));
+
+        let unknown = registry.capabilities(None, "unknown-model");
+        assert_eq!(unknown, ModelCapabilities::default());
+        assert!(unknown.supports_tools() && unknown.supports_vision());
+    }
+
+    #[test]
+    fn test_refinements_and_overrides() {
+        let mut registry = registry(vec![CapabilityEntry {
+            pattern: "claude-3-5".to_string(),
+            provider: None,
+            capabilities: ModelCapabilities {
+                context_limit: Some(100_000),
+                ..Default::default()
+            },
+        }]);
+        registry.refine(
+            "anthropic",
+            "claude-3-5-sonnet",
+            ModelCapabilities {
+                context_limit: Some(150_000),
+                vision: Some(false),
+                ..Default::default()
+            },
+        );
+        registry.refine(
+            "anthropic",
+            "claude-3-5-sonnet",
+            ModelCapabilities {
+                streaming: Some(false),
+                ..Default::default()
+            },
+        );
+
+        let capabilities = registry.capabilities(Some("anthropic"), "claude-3-5-sonnet");
+        assert_eq!(capabilities.context_limit, Some(100_000));
+        assert!(!capabilities.supports_vision());
+        assert!(!capabilities.supports_streaming());
+        assert!(capabilities.is_family("anthropic"));
+
+        // Refinements are for the exact model of the provider that learned them only
+        assert!(registry
+            .capabilities(Some("anthropic"), "claude-3-5-haiku")
+            .supports_vision());
+        assert!(registry
+            .capabilities(Some("openrouter"), "claude-3-5-sonnet")
+            .supports_vision());
+        assert!(registry
+            .capabilities(None, "claude-3-5-sonnet")
+            .supports_streaming());
+    }
+
+    #[test]
+    fn test_provider_entries() {
+        let registry = registry(vec![CapabilityEntry {
+            pattern: "gpt-4o".to_string(),
+            provider: Some("my_proxy".to_string()),
+            capabilities: ModelCapabilities {
+                vision: Some(false),
+                ..Default::default()
+            },
+        }]);
+        assert!(!registry
+            .capabilities(Some("my_proxy"), "gpt-4o")
+            .supports_vision());
+        assert!(registry
+            .capabilities(Some("openai"), "gpt-4o")
+            .supports_vision());
+        assert!(registry.capabilities(None, "gpt-4o").supports_vision());
+    }
+
+    #[test]
+    fn test_user_entries_parse() {
+        let entries: Vec<CapabilityEntry> = serde_yaml::from_str(
+            r#"
+- pattern: my-finetune
+  context_limit: 32000
+  tool_calling: false
+"#,
+        )
+        .unwrap();
+        let capabilities = registry(entries).capabilities(None, "org/my-finetune-v2");
+        assert_eq!(capabilities.context_limit, Some(32_000));
+        assert!(!capabilities.supports_tools());
+    }
+}
@@ -1,7 +1,9 @@
 pub mod permission_confirmation;
 pub mod permission_judge;
+pub mod permission_rules;
 pub mod permission_store;
 
 pub use permission_confirmation::{Permission, PermissionConfirmation};
 pub use permission_judge::detect_read_only_tools;
+pub use permission_rules::{ArgumentCondition, ArgumentMatcher, PermissionRule, RulePattern};
 pub use permission_store::ToolPermissionStore;
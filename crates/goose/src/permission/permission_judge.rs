@@ -10,6 +10,7 @@ use mcp_core::{tool::Tool, TextContent};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashSet;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Creates the tool definition for checking read-only permissions.
@@ -167,6 +168,7 @@ pub async fn check_tool_permissions(
     tools_without_annotation: HashSet<String>,
     permission_manager: &mut PermissionManager,
     provider: Arc<dyn Provider>,
+    workspace: Option<&Path>,
 ) -> (PermissionCheckResult, Vec<String>) {
     let mut approved = vec![];
     let mut needs_approval = vec![];
@@ -185,8 +187,12 @@ pub async fn check_tool_permissions(
                     extension_request_ids.push(request.id.clone());
                 }
 
-                // 1. Check user-defined permission
-                if let Some(level) = permission_manager.get_user_permission(&tool_call.name) {
+                // 1. Check user-defined permission, with rules on the arguments taking
+                // precedence over the level set for the tool as a whole
+                if let Some(level) = permission_manager
+                    .evaluate_user_rules(&tool_call.name, &tool_call.arguments, workspace)
+                    .or_else(|| permission_manager.get_user_permission(&tool_call.name))
+                {
                     match level {
                         PermissionLevel::AlwaysAllow => approved.push(request.clone()),
                         PermissionLevel::AskBefore => needs_approval.push(request.clone()),
@@ -266,6 +272,7 @@ mod tests {
     use super::*;
     use crate::message::{Message, MessageContent, ToolRequest};
     use crate::model::ModelConfig;
+    use crate::permission::permission_rules::{ArgumentCondition, ArgumentMatcher, PermissionRule};
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
     use crate::providers::errors::ProviderError;
     use chrono::Utc;
@@ -448,6 +455,7 @@ mod tests {
             tools_without_annotation,
             &mut permission_manager,
             provider,
+            None,
         )
         .await;
 
@@ -507,6 +515,7 @@ mod tests {
             tools_without_annotation,
             &mut permission_manager,
             provider,
+            None,
         )
         .await;
 
@@ -515,4 +524,48 @@ mod tests {
         assert_eq!(result.needs_approval.len(), 0); // data_fetcher should need approval
         assert_eq!(result.denied.len(), 0); // No tool should be denied in this test
     }
+
+    #[tokio::test]
+    async fn test_check_tool_permissions_argument_rules() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut permission_manager = PermissionManager::new(temp_file.path());
+        permission_manager.update_user_permission("developer__shell", PermissionLevel::AskBefore);
+        permission_manager.set_user_rules(vec![PermissionRule {
+            tool: "developer__shell".to_string(),
+            when: vec![ArgumentCondition {
+                argument: "command".to_string(),
+                matcher: ArgumentMatcher::CommandPrefix(vec!["cargo test".to_string()]),
+            }],
+            level: PermissionLevel::AlwaysAllow,
+            priority: 0,
+        }]);
+
+        let shell_request = |id: &str, command: &str| ToolRequest {
+            id: id.to_string(),
+            tool_call: ToolResult::Ok(ToolCall {
+                name: "developer__shell".to_string(),
+                arguments: json!({ "command": command }),
+            }),
+        };
+        let candidate_requests = vec![
+            shell_request("tool_1", "cargo test -p goose"),
+            shell_request("tool_2", "rm -rf target"),
+        ];
+
+        let (result, _) = check_tool_permissions(
+            &candidate_requests,
+            "approve",
+            HashSet::new(),
+            HashSet::new(),
+            &mut permission_manager,
+            create_mock_provider(),
+            Some(Path::new("/tmp/project")),
+        )
+        .await;
+
+        assert_eq!(result.approved.len(), 1);
+        assert_eq!(result.approved[0].id, "tool_1");
+        assert_eq!(result.needs_approval.len(), 1);
+        assert_eq!(result.needs_approval[0].id, "tool_2");
+    }
 }
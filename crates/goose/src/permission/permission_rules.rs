@@ -0,0 +1,494 @@
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::config::permission::PermissionLevel;
+
+/// Placeholder in `path_under` patterns for the session's working directory
+pub const WORKSPACE_PLACEHOLDER: &str = "$WORKSPACE";
+
+/// A permission for tool calls matching a tool name and conditions on the arguments
+///
+/// ```yaml
+/// - tool: developer__shell
+///   when:
+///     - argument: command
+///       command_prefix: [git status, cargo test]
+///   level: always_allow
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PermissionRule {
+    /// Tool name, where `*` matches any run of characters, e.g. `developer__*`
+    pub tool: String,
+    /// Conditions on the arguments, all of which must hold for the rule to apply
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<ArgumentCondition>,
+    pub level: PermissionLevel,
+    /// Rules with a higher priority win; between equal priorities the most restrictive wins
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ArgumentCondition {
+    /// The argument to inspect, with nested fields separated by dots
+    pub argument: String,
+    #[serde(flatten)]
+    pub matcher: ArgumentMatcher,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentMatcher {
+    /// A shell command line where each command starts with one of these, e.g. `git status`
+    CommandPrefix(Vec<String>),
+    /// A glob over the whole value, where `*` stops at `/` and `**` doesn't
+    Glob(String),
+    /// A regex found anywhere in the value
+    #[schema(value_type = String)]
+    Regex(RulePattern),
+    /// A path inside one of these directories, which may use `$WORKSPACE` and `~`
+    PathUnder(Vec<String>),
+    /// A URL whose host is one of these, where `*.example.com` covers subdomains
+    UrlHost(Vec<String>),
+}
+
+/// A regex in a permission rule, compiled once when the rule is loaded
+///
+/// An invalid pattern still loads so the other rules do. It counts as a match for rules
+/// that restrict and as no match for rules that allow, so a broken rule never grants more.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct RulePattern {
+    pattern: String,
+    regex: Result<Regex, regex::Error>,
+}
+
+impl RulePattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        let regex = Regex::new(&pattern);
+        Self { pattern, regex }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Why the pattern doesn't compile, if it doesn't
+    pub fn error(&self) -> Option<&regex::Error> {
+        self.regex.as_ref().err()
+    }
+}
+
+impl PartialEq for RulePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl From<String> for RulePattern {
+    fn from(pattern: String) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl From<RulePattern> for String {
+    fn from(pattern: RulePattern) -> Self {
+        pattern.pattern
+    }
+}
+
+fn restrictiveness(level: &PermissionLevel) -> u8 {
+    match level {
+        PermissionLevel::AlwaysAllow => 0,
+        PermissionLevel::AskBefore => 1,
+        PermissionLevel::NeverAllow => 2,
+    }
+}
+
+/// Find the level of the winning rule that applies to a tool call, if any applies
+pub fn evaluate_rules(
+    rules: &[PermissionRule],
+    tool_name: &str,
+    arguments: &Value,
+    workspace: Option<&Path>,
+) -> Option<PermissionLevel> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(tool_name, arguments, workspace))
+        .max_by_key(|rule| (rule.priority, restrictiveness(&rule.level)))
+        .map(|rule| rule.level.clone())
+}
+
+impl PermissionRule {
+    /// Check that the rule's patterns compile, so it can be saved
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for condition in &self.when {
+            if let ArgumentMatcher::Regex(pattern) = &condition.matcher {
+                if let Some(e) = pattern.error() {
+                    anyhow::bail!(
+                        "Invalid regex '{}' for argument '{}' of {}: {}",
+                        pattern.as_str(),
+                        condition.argument,
+                        self.tool,
+                        e
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, tool_name: &str, arguments: &Value, workspace: Option<&Path>) -> bool {
+        if !glob_matches(&self.tool, tool_name, false) {
+            return false;
+        }
+        // Allowing is only safe when every value passes, while restricting should catch any
+        let permissive = self.level == PermissionLevel::AlwaysAllow;
+        self.when
+            .iter()
+            .all(|condition| condition.matches(arguments, workspace, permissive))
+    }
+}
+
+impl ArgumentCondition {
+    fn matches(&self, arguments: &Value, workspace: Option<&Path>, permissive: bool) -> bool {
+        let values = argument_values(arguments, &self.argument);
+        if values.is_empty() {
+            return false;
+        }
+        let check = |value: &str| self.matcher.matches(value, workspace, permissive);
+        if permissive {
+            values.into_iter().all(check)
+        } else {
+            values.into_iter().any(check)
+        }
+    }
+}
+
+impl ArgumentMatcher {
+    fn matches(&self, value: &str, workspace: Option<&Path>, permissive: bool) -> bool {
+        match self {
+            ArgumentMatcher::CommandPrefix(prefixes) => {
+                command_matches(value, prefixes, permissive)
+            }
+            ArgumentMatcher::Glob(pattern) => glob_matches(pattern, value, true),
+            ArgumentMatcher::Regex(pattern) => match &pattern.regex {
+                Ok(regex) => regex.is_match(value),
+                Err(e) => {
+                    tracing::warn!(
+                        "Invalid regex in permission rule '{}': {}",
+                        pattern.pattern,
+                        e
+                    );
+                    !permissive
+                }
+            },
+            ArgumentMatcher::PathUnder(dirs) => path_under(value, dirs, workspace),
+            ArgumentMatcher::UrlHost(hosts) => url_host_matches(value, hosts),
+        }
+    }
+}
+
+/// Collect the string values at a dotted path, flattening arrays of strings
+fn argument_values<'a>(arguments: &'a Value, path: &str) -> Vec<&'a str> {
+    let value = path
+        .split('.')
+        .try_fold(arguments, |value, key| value.get(key));
+    match value {
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    }
+}
+
+/// Match `value` against a glob, where `*` stops at `/` only when `path_aware` is set
+fn glob_matches(pattern: &str, value: &str, path_aware: bool) -> bool {
+    let single = if path_aware { "[^/]*" } else { ".*" };
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str(single),
+            '?' => regex.push_str(if path_aware { "[^/]" } else { "." }),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(value))
+}
+
+/// Check that the commands in a shell line start with one of the prefixes
+///
+/// For permissive rules every command must match and command substitution or redirection
+/// disqualifies the line, since either could run or write something the prefix doesn't
+/// show. For restrictive rules any matching command is enough.
+fn command_matches(command: &str, prefixes: &[String], permissive: bool) -> bool {
+    let has_hidden_effects = ["$(", "`", ">", "<"]
+        .iter()
+        .any(|token| command.contains(token));
+    if permissive && has_hidden_effects {
+        return false;
+    }
+
+    let commands: Vec<&str> = command
+        .split(['\n', ';', '&', '|'])
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect();
+    if commands.is_empty() {
+        return false;
+    }
+
+    let starts_with_prefix = |command: &&str| {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        prefixes.iter().any(|prefix| {
+            let prefix_words: Vec<&str> = prefix.split_whitespace().collect();
+            !prefix_words.is_empty() && words.starts_with(&prefix_words)
+        })
+    };
+    if permissive {
+        commands.iter().all(starts_with_prefix)
+    } else {
+        commands.iter().any(starts_with_prefix)
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn expand_dir(dir: &str, workspace: Option<&Path>) -> Option<PathBuf> {
+    if let Some(rest) = dir.strip_prefix(WORKSPACE_PLACEHOLDER) {
+        let workspace = workspace?;
+        return Some(workspace.join(rest.trim_start_matches('/')));
+    }
+    if let Some(rest) = dir.strip_prefix('~') {
+        let home = etcetera::home_dir().ok()?;
+        return Some(home.join(rest.trim_start_matches('/')));
+    }
+    Some(PathBuf::from(dir))
+}
+
+fn path_under(value: &str, dirs: &[String], workspace: Option<&Path>) -> bool {
+    let path = Path::new(value);
+    let path = match (path.is_absolute(), workspace) {
+        (true, _) => path.to_path_buf(),
+        (false, Some(workspace)) => workspace.join(path),
+        (false, None) => return false,
+    };
+    let path = normalize_path(&path);
+    dirs.iter()
+        .filter_map(|dir| expand_dir(dir, workspace))
+        .any(|dir| path.starts_with(normalize_path(&dir)))
+}
+
+fn url_host_matches(value: &str, hosts: &[String]) -> bool {
+    let Some(host) = Url::parse(value)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+    hosts.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => host == pattern,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shell_rule(prefixes: &[&str], level: PermissionLevel, priority: i32) -> PermissionRule {
+        PermissionRule {
+            tool: "developer__shell".to_string(),
+            when: vec![ArgumentCondition {
+                argument: "command".to_string(),
+                matcher: ArgumentMatcher::CommandPrefix(
+                    prefixes.iter().map(|p| p.to_string()).collect(),
+                ),
+            }],
+            level,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_command_prefix_rules() {
+        let rules = vec![
+            shell_rule(
+                &["git status", "cargo test"],
+                PermissionLevel::AlwaysAllow,
+                0,
+            ),
+            shell_rule(&["rm"], PermissionLevel::AskBefore, 0),
+        ];
+        let level = |command: &str| {
+            evaluate_rules(
+                &rules,
+                "developer__shell",
+                &json!({ "command": command }),
+                None,
+            )
+        };
+
+        assert_eq!(level("git status -s"), Some(PermissionLevel::AlwaysAllow));
+        assert_eq!(
+            level("cargo test && git status"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(level("git statusx"), None);
+        assert_eq!(
+            level("git status; rm -rf /"),
+            Some(PermissionLevel::AskBefore)
+        );
+        assert_eq!(level("git status > out.txt"), None);
+        assert_eq!(level("cargo test $(rm -rf /)"), None);
+        assert_eq!(level("rm foo"), Some(PermissionLevel::AskBefore));
+    }
+
+    #[test]
+    fn test_priority_then_restrictiveness() {
+        let allow_high = shell_rule(&["rm"], PermissionLevel::AlwaysAllow, 10);
+        let deny_low = shell_rule(&["rm"], PermissionLevel::NeverAllow, 0);
+        let ask_low = shell_rule(&["rm"], PermissionLevel::AskBefore, 0);
+        let args = json!({ "command": "rm tmp.txt" });
+
+        assert_eq!(
+            evaluate_rules(
+                &[deny_low.clone(), allow_high],
+                "developer__shell",
+                &args,
+                None
+            ),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            evaluate_rules(&[ask_low, deny_low], "developer__shell", &args, None),
+            Some(PermissionLevel::NeverAllow)
+        );
+    }
+
+    #[test]
+    fn test_path_under_workspace() {
+        let rule = PermissionRule {
+            tool: "developer__*".to_string(),
+            when: vec![ArgumentCondition {
+                argument: "path".to_string(),
+                matcher: ArgumentMatcher::PathUnder(vec![WORKSPACE_PLACEHOLDER.to_string()]),
+            }],
+            level: PermissionLevel::AlwaysAllow,
+            priority: 0,
+        };
+        let workspace = Path::new("/home/user/project");
+        let matches = |path: &str| {
+            rule.matches(
+                "developer__text_editor",
+                &json!({ "path": path }),
+                Some(workspace),
+            )
+        };
+
+        assert!(matches("/home/user/project/src/main.rs"));
+        assert!(matches("src/main.rs"));
+        assert!(!matches("/home/user/project/../secrets"));
+        assert!(!matches("/etc/passwd"));
+        assert!(!rule.matches(
+            "other__tool",
+            &json!({ "path": "src/main.rs" }),
+            Some(workspace)
+        ));
+    }
+
+    #[test]
+    fn test_url_host_and_glob() {
+        let host =
+            ArgumentMatcher::UrlHost(vec!["*.github.com".to_string(), "docs.rs".to_string()]);
+        assert!(host.matches("https://api.github.com/repos", None, true));
+        assert!(host.matches("https://docs.rs/serde", None, true));
+        assert!(!host.matches("https://github.com.evil.io/", None, true));
+
+        let glob = ArgumentMatcher::Glob("src/**/*.rs".to_string());
+        assert!(glob.matches("src/agents/mod.rs", None, true));
+        assert!(!glob.matches("tests/mod.rs", None, true));
+        assert!(!ArgumentMatcher::Glob("src/*.rs".to_string()).matches("src/a/b.rs", None, true));
+    }
+
+    #[test]
+    fn test_rules_deserialize_from_yaml() {
+        let rules: Vec<PermissionRule> = serde_yaml::from_str(
+            r#"
+- tool: developer__shell
+  when:
+    - argument: command
+      command_prefix: [git status]
+  level: always_allow
+  priority: 5
+- tool: computercontroller__web_fetch
+  when:
+    - argument: url
+      url_host: ["*.internal.example.com"]
+  level: never_allow
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].priority, 5);
+        assert_eq!(
+            rules[1].when[0].matcher,
+            ArgumentMatcher::UrlHost(vec!["*.internal.example.com".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected_and_fails_closed() {
+        let rule = |level: PermissionLevel| PermissionRule {
+            tool: "developer__shell".to_string(),
+            when: vec![ArgumentCondition {
+                argument: "command".to_string(),
+                matcher: ArgumentMatcher::Regex(RulePattern::new("rm (-rf")),
+            }],
+            level,
+            priority: 0,
+        };
+        let args = json!({ "command": "rm -rf /" });
+
+        let deny = rule(PermissionLevel::NeverAllow);
+        assert!(deny.validate().is_err());
+        assert!(deny.matches("developer__shell", &args, None));
+        let allow = rule(PermissionLevel::AlwaysAllow);
+        assert!(!allow.matches("developer__shell", &args, None));
+
+        let valid: PermissionRule = serde_yaml::from_str(
+            "tool: developer__shell\nwhen:\n  - argument: command\n    regex: '^rm '\nlevel: never_allow\n",
+        )
+        .unwrap();
+        assert!(valid.validate().is_ok());
+        assert!(valid.matches("developer__shell", &args, None));
+    }
+}
@@ -5,10 +5,13 @@ use reqwest::{Client, StatusCode};
 use serde_json::Value;
 use std::time::Duration;
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
-use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::formats::anthropic::{
+    create_request, get_usage, response_to_message, AnthropicStreamAccumulator,
+};
+use super::streaming::{check_stream_response, stream_response};
+use super::utils::{finish_response, retry_after};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -65,59 +68,119 @@ impl AnthropicProvider {
         })
     }
 
-    async fn post(&self, headers: HeaderMap, payload: Value) -> Result<Value, ProviderError> {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-api-key", self.api_key.parse().unwrap());
+        headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
+
+        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
+        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
+            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
+            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
+        }
+
+        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
+            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
+            headers.insert(
+                "anthropic-beta",
+                "token-efficient-tools-2025-02-19".parse().unwrap(),
+            );
+        }
+
+        headers
+    }
+
+    async fn send(
+        &self,
+        headers: HeaderMap,
+        payload: &Value,
+    ) -> Result<reqwest::Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
         let url = base_url.join("v1/messages").map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
+        Ok(self
             .client
             .post(url)
             .headers(headers)
-            .json(&payload)
+            .json(payload)
             .send()
-            .await?;
+            .await?)
+    }
 
-        let status = response.status();
-        let payload: Option<Value> = response.json().await.ok();
-
-        // https://docs.anthropic.com/en/api/errors
-        match status {
-            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                    Status: {}. Response: {:?}", status, payload)))
-            }
-            StatusCode::BAD_REQUEST => {
-                let mut error_msg = "Unknown error".to_string();
-                if let Some(payload) = &payload {
-                    if let Some(error) = payload.get("error") {
-                    tracing::debug!("Bad Request Error: {error:?}");
-                    error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
-                    if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
-                        return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
-                    }
-                }}
-                tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
-                );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
-            }
-            StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
-            }
-            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-                Err(ProviderError::ServerError(format!("{:?}", payload)))
-            }
-            _ => {
-                tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
-                );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
-            }
+    async fn post(&self, headers: HeaderMap, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(headers, &payload).await?;
+        handle_response(response).await
+    }
+}
+
+async fn handle_response(response: reqwest::Response) -> Result<Value, ProviderError> {
+    let status = response.status();
+    let retry_after = retry_after(&response);
+    let payload: Option<Value> = response.json().await.ok();
+
+    // https://docs.anthropic.com/en/api/errors
+    match status {
+        StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                Status: {}. Response: {:?}", status, payload)))
+        }
+        StatusCode::BAD_REQUEST => {
+            let mut error_msg = "Unknown error".to_string();
+            if let Some(payload) = &payload {
+                if let Some(error) = payload.get("error") {
+                tracing::debug!("Bad Request Error: {error:?}");
+                error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
+                if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
+                    return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
+                }
+            }}
+            tracing::debug!(
+                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+            );
+            Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
         }
+        StatusCode::TOO_MANY_REQUESTS => {
+            Err(ProviderError::RateLimitExceeded {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
+        }
+        StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+            Err(ProviderError::ServerError {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
+        }
+        _ => {
+            tracing::debug!(
+                "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+            );
+            Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+        }
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(model, system, messages, tools)?;
+
+        // Make request
+        let response = self.post(self.headers(), payload.clone()).await?;
+
+        // Parse response
+        finish_response(model, &payload, response, response_to_message, get_usage)
     }
 }
 
@@ -159,46 +222,61 @@ impl Provider for AnthropicProvider {
         self.model.clone()
     }
 
-    #[tracing::instrument(
-        skip(self, system, messages, tools),
-        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
-    )]
     async fn complete(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools)?;
-
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("x-api-key", self.api_key.parse().unwrap());
-        headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
 
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
-            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
-        }
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.model.capped_at(max_tokens);
+        self.complete_with_model(&model, system, messages, tools)
+            .await
+    }
 
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
-            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
-            headers.insert(
-                "anthropic-beta",
-                "token-efficient-tools-2025-02-19".parse().unwrap(),
-            );
-        }
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 
-        // Make request
-        let response = self.post(headers, payload.clone()).await?;
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, tools)?;
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stream".to_string(), Value::Bool(true));
 
-        // Parse response
-        let message = response_to_message(response.clone())?;
-        let usage = get_usage(&response)?;
+        let response = self.send(self.headers(), &payload).await?;
+        let response = check_stream_response(response, handle_response).await?;
 
-        let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        Ok((message, ProviderUsage::new(model, usage)))
+        let model_config = self.model.clone();
+        Ok(stream_response(
+            response,
+            AnthropicStreamAccumulator::default(),
+            move |response| {
+                finish_response(
+                    &model_config,
+                    &payload,
+                    response,
+                    response_to_message,
+                    get_usage,
+                )
+            },
+        ))
     }
 
     /// Fetch supported models from Anthropic; returns Err on failure, Ok(None) if not present
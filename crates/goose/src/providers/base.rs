@@ -1,13 +1,17 @@
 use anyhow::Result;
+use futures::Stream;
 use serde::{Deserialize, Serialize};
 
 use super::errors::ProviderError;
+use super::routing::RoutingDecision;
+use crate::config::Budget;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
+use std::pin::Pin;
 use std::sync::Mutex;
 
 /// A global store for the current model being used, we use this as when a provider returns, it tells us the real model, not an alias
@@ -176,19 +180,33 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// Why the model was chosen, for providers that choose a model for each turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingDecision>,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            routing: None,
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Usage {
+    /// Every input token, including the ones read from or written to the prompt cache
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// How many of the input tokens were read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+    /// How many of the input tokens were written to the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_input_tokens: Option<i32>,
 }
 
 impl Usage {
@@ -201,10 +219,59 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            cache_read_input_tokens: None,
+            cache_write_input_tokens: None,
         }
     }
+
+    pub fn with_cache_tokens(mut self, read: Option<i32>, write: Option<i32>) -> Self {
+        self.cache_read_input_tokens = read;
+        self.cache_write_input_tokens = write;
+        self
+    }
+
+    /// Input tokens that were neither read from nor written to the prompt cache
+    pub fn uncached_input_tokens(&self) -> Option<i32> {
+        self.input_tokens.map(|input| {
+            (input
+                - self.cache_read_input_tokens.unwrap_or(0)
+                - self.cache_write_input_tokens.unwrap_or(0))
+            .max(0)
+        })
+    }
+}
+
+/// An incremental piece of a response streamed back from a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MessageDelta {
+    /// A fragment of assistant text
+    Text { text: String },
+    /// A fragment of the model's reasoning
+    Thinking { thinking: String },
+    /// A fragment of a tool call's JSON arguments
+    ///
+    /// `index` identifies the call within the response. `id` and `name` are only
+    /// present once the provider has sent them, usually on the first fragment.
+    ToolCall {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
 }
 
+/// An item yielded by `Provider::stream`
+#[derive(Debug, Clone)]
+pub enum StreamChunk {
+    /// Partial output to surface while the response is still being generated
+    Delta(MessageDelta),
+    /// The fully assembled message and its usage, always the last item of the stream
+    Complete(Message, ProviderUsage),
+}
+
+pub type MessageStream = Pin<Box<dyn Stream<Item = Result<StreamChunk, ProviderError>> + Send>>;
+
 use async_trait::async_trait;
 
 /// Trait for LeadWorkerProvider-specific functionality
@@ -244,6 +311,42 @@ pub trait Provider: Send + Sync {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError>;
 
+    /// Generate the next message with the output limited to `max_tokens`
+    ///
+    /// Providers that can't set the limit for a single request ignore it and call
+    /// `complete`, so their configured limit applies.
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        _max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete(system, messages, tools).await
+    }
+
+    /// Check if this provider streams partial output from `stream`
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    /// Generate the next message, yielding deltas as the model produces them
+    ///
+    /// The stream ends with a `StreamChunk::Complete` carrying the same message and usage
+    /// that `complete` would have returned. The default implementation calls `complete`
+    /// and yields only that final item.
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let (message, usage) = self.complete(system, messages, tools).await?;
+        Ok(Box::pin(futures::stream::once(async move {
+            Ok(StreamChunk::Complete(message, usage))
+        })))
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 
@@ -270,6 +373,17 @@ pub trait Provider: Send + Sync {
         None
     }
 
+    /// Tell a provider that chooses a model for each turn the session's budget and what the
+    /// session has cost so far, for providers that weigh them
+    fn set_session_budget(&self, _budget: Option<Budget>, _spent: Option<f64>) {}
+
+    /// The name of the provider that served the completion `usage` came from, to price it by
+    ///
+    /// Providers that pass requests on to others name the one that served it.
+    fn usage_provider(&self, _usage: &ProviderUsage) -> Option<String> {
+        self.get_model_config().provider
+    }
+
     /// Get the currently active model name
     /// For regular providers, this returns the configured model
     /// For LeadWorkerProvider, this returns the currently active model (lead or worker)
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -8,7 +7,6 @@ use aws_sdk_bedrockruntime::operation::converse::ConverseError;
 use aws_sdk_bedrockruntime::{types as bedrock, Client};
 use mcp_core::Tool;
 use serde_json::Value;
-use tokio::time::sleep;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
@@ -18,7 +16,8 @@ use crate::providers::utils::emit_debug_trace;
 
 // Import the migrated helper functions from providers/formats/bedrock.rs
 use super::formats::bedrock::{
-    from_bedrock_message, from_bedrock_usage, to_bedrock_message, to_bedrock_tool_config,
+    from_bedrock_message, from_bedrock_usage, to_bedrock_messages, to_bedrock_system,
+    to_bedrock_tool_config_with_cache,
 };
 
 pub const BEDROCK_DOC_LINK: &str =
@@ -106,133 +105,78 @@ impl Provider for BedrockProvider {
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
         let model_name = &self.model.model_name;
+        let cache = self.model.capabilities().supports_prompt_caching();
 
         let mut request = self
             .client
             .converse()
-            .system(bedrock::SystemContentBlock::Text(system.to_string()))
+            .set_system(Some(to_bedrock_system(system, cache)?))
             .model_id(model_name.to_string())
-            .set_messages(Some(
-                messages
-                    .iter()
-                    .map(to_bedrock_message)
-                    .collect::<Result<_>>()?,
-            ));
+            .set_messages(Some(to_bedrock_messages(messages, cache)?));
 
         if !tools.is_empty() {
-            request = request.tool_config(to_bedrock_tool_config(tools)?);
+            request = request.tool_config(to_bedrock_tool_config_with_cache(tools, cache)?);
         }
 
-        // Retry configuration
-        const MAX_RETRIES: u32 = 10;
-        const INITIAL_BACKOFF_MS: u64 = 20_000; // 20 seconds
-        const MAX_BACKOFF_MS: u64 = 120_000; // 120 seconds (2 minutes)
-
-        let mut attempts = 0;
-        let mut backoff_ms = INITIAL_BACKOFF_MS;
-
-        loop {
-            attempts += 1;
-
-            match request.clone().send().await {
-                Ok(response) => {
-                    // Successful response, process it and return
-                    return match response.output {
-                        Some(bedrock::ConverseOutput::Message(message)) => {
-                            let usage = response
-                                .usage
-                                .as_ref()
-                                .map(from_bedrock_usage)
-                                .unwrap_or_default();
-
-                            let message = from_bedrock_message(&message)?;
-
-                            // Add debug trace with input context
-                            let debug_payload = serde_json::json!({
-                                "system": system,
-                                "messages": messages,
-                                "tools": tools
-                            });
-                            emit_debug_trace(
-                                &self.model,
-                                &debug_payload,
-                                &serde_json::to_value(&message).unwrap_or_default(),
-                                &usage,
-                            );
-
-                            let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
-                            Ok((message, provider_usage))
-                        }
-                        _ => Err(ProviderError::RequestFailed(
-                            "No output from Bedrock".to_string(),
-                        )),
-                    };
+        // Throttling is retried by the RetryingProvider that wraps every provider
+        match request.send().await {
+            Ok(response) => match response.output {
+                Some(bedrock::ConverseOutput::Message(message)) => {
+                    let usage = response
+                        .usage
+                        .as_ref()
+                        .map(from_bedrock_usage)
+                        .unwrap_or_default();
+
+                    let message = from_bedrock_message(&message)?;
+
+                    // Add debug trace with input context
+                    let debug_payload = serde_json::json!({
+                        "system": system,
+                        "messages": messages,
+                        "tools": tools
+                    });
+                    emit_debug_trace(
+                        &self.model,
+                        &debug_payload,
+                        &serde_json::to_value(&message).unwrap_or_default(),
+                        &usage,
+                    );
+
+                    let provider_usage = ProviderUsage::new(model_name.to_string(), usage);
+                    Ok((message, provider_usage))
                 }
-                Err(err) => {
-                    match err.into_service_error() {
-                        ConverseError::ThrottlingException(throttle_err) => {
-                            if attempts > MAX_RETRIES {
-                                // We've exhausted our retries
-                                tracing::error!(
-                                    "Failed after {MAX_RETRIES} retries: {:?}",
-                                    throttle_err
-                                );
-                                return Err(ProviderError::RateLimitExceeded(format!(
-                                    "Failed to call Bedrock after {MAX_RETRIES} retries: {:?}",
-                                    throttle_err
-                                )));
-                            }
-
-                            // Log retry attempt
-                            tracing::warn!(
-                                "Bedrock throttling error (attempt {}/{}), retrying in {} ms: {:?}",
-                                attempts,
-                                MAX_RETRIES,
-                                backoff_ms,
-                                throttle_err
-                            );
-
-                            // Wait before retry with exponential backoff
-                            sleep(Duration::from_millis(backoff_ms)).await;
-
-                            // Calculate next backoff with exponential growth, capped at max
-                            backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
-
-                            // Continue to the next retry attempt
-                            continue;
-                        }
-                        ConverseError::AccessDeniedException(err) => {
-                            return Err(ProviderError::Authentication(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        ConverseError::ValidationException(err)
-                            if err
-                                .message()
-                                .unwrap_or_default()
-                                .contains("Input is too long for requested model.") =>
-                        {
-                            return Err(ProviderError::ContextLengthExceeded(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        ConverseError::ModelErrorException(err) => {
-                            return Err(ProviderError::ExecutionError(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                        err => {
-                            return Err(ProviderError::ServerError(format!(
-                                "Failed to call Bedrock: {:?}",
-                                err
-                            )));
-                        }
-                    }
+                _ => Err(ProviderError::RequestFailed(
+                    "No output from Bedrock".to_string(),
+                )),
+            },
+            Err(err) => match err.into_service_error() {
+                ConverseError::ThrottlingException(err) => Err(ProviderError::RateLimitExceeded {
+                    details: format!("Failed to call Bedrock: {:?}", err),
+                    retry_after: None,
+                }),
+                ConverseError::AccessDeniedException(err) => Err(ProviderError::Authentication(
+                    format!("Failed to call Bedrock: {:?}", err),
+                )),
+                ConverseError::ValidationException(err)
+                    if err
+                        .message()
+                        .unwrap_or_default()
+                        .contains("Input is too long for requested model.") =>
+                {
+                    Err(ProviderError::ContextLengthExceeded(format!(
+                        "Failed to call Bedrock: {:?}",
+                        err
+                    )))
                 }
-            }
+                ConverseError::ModelErrorException(err) => Err(ProviderError::ExecutionError(
+                    format!("Failed to call Bedrock: {:?}", err),
+                )),
+                err => Err(ProviderError::ServerError {
+                    details: format!("Failed to call Bedrock: {:?}", err),
+                    retry_after: None,
+                }),
+            },
         }
     }
 }
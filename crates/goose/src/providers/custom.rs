@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use super::factory::is_builtin_provider;
+use super::formats::openai::{
+    create_request, enable_streaming, get_usage, response_to_message, OpenAiStreamAccumulator,
+};
+use super::streaming::{check_stream_response, stream_response};
+use super::utils::{finish_response, handle_response_openai_compat, ImageFormat};
+use crate::config::{Config, ConfigError};
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::model_registry::{self, ModelCapabilities};
+
+fn default_true() -> bool {
+    true
+}
+fn default_api_key_header() -> String {
+    "api-key".to_string()
+}
+fn default_timeout_secs() -> u64 {
+    600
+}
+
+/// How a custom provider sends its API key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <key>`, as OpenAI does
+    #[default]
+    Bearer,
+    /// The key as is, in the header named by `api_key_header`
+    ApiKey,
+    /// No authentication, for local servers
+    None,
+}
+
+/// A model a custom provider serves
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CustomModel {
+    pub name: String,
+    /// Overrides the context limit goose would guess from the model name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_limit: Option<usize>,
+}
+
+/// What a custom provider's endpoint supports
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CustomCapabilities {
+    /// Whether tools are sent with requests. Without them, tool calls need the toolshim.
+    #[serde(default = "default_true")]
+    pub tools: bool,
+    /// Whether images are sent, otherwise they are left out of the conversation
+    #[serde(default = "default_true")]
+    pub images: bool,
+    #[serde(default = "default_true")]
+    pub streaming: bool,
+}
+
+impl Default for CustomCapabilities {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            images: true,
+            streaming: true,
+        }
+    }
+}
+
+/// An OpenAI compatible endpoint declared under `custom_providers` in the config, such as
+/// vLLM, LM Studio, LiteLLM or an internal gateway
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CustomProviderConfig {
+    /// The name to select the provider by, as with `GOOSE_PROVIDER`
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The URL chat completions are posted under, e.g. `http://localhost:8000/v1`
+    pub base_url: String,
+    /// The config key or environment variable holding the API key, stored as a secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub auth: AuthStyle,
+    #[serde(default = "default_api_key_header")]
+    pub api_key_header: String,
+    /// Extra headers sent with every request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// The models to offer, the first being the default
+    #[serde(default)]
+    pub models: Vec<CustomModel>,
+    #[serde(default)]
+    pub capabilities: CustomCapabilities,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl CustomProviderConfig {
+    /// The providers declared under `custom_providers` in the global config
+    ///
+    /// An entry that can't be read is logged and left out, as is a provider named like a
+    /// built-in one, which the built-in would shadow.
+    pub fn from_config() -> Vec<Self> {
+        let entries = match Config::global().get_param::<Vec<Value>>("custom_providers") {
+            Ok(entries) => entries,
+            Err(ConfigError::NotFound(_)) => return Vec::new(),
+            Err(e) => {
+                tracing::warn!("Ignoring custom_providers, which isn't a list: {}", e);
+                return Vec::new();
+            }
+        };
+        without_builtin_names(parse_entries(entries))
+    }
+
+    pub fn metadata(&self) -> ProviderMetadata {
+        let default_model = self
+            .models
+            .first()
+            .map(|model| model.name.clone())
+            .unwrap_or_default();
+        let known_models = self
+            .models
+            .iter()
+            .map(|model| {
+                let context_limit = model
+                    .context_limit
+                    .unwrap_or_else(|| ModelConfig::new(model.name.clone()).context_limit());
+                ModelInfo::new(&model.name, context_limit)
+            })
+            .collect();
+        let config_keys = self
+            .api_key
+            .iter()
+            .map(|key| ConfigKey::new(key, true, true, None))
+            .collect();
+        ProviderMetadata::with_models(
+            &self.name,
+            self.display_name.as_deref().unwrap_or(&self.name),
+            self.description
+                .as_deref()
+                .unwrap_or("An OpenAI compatible endpoint declared in the config"),
+            &default_model,
+            known_models,
+            "",
+            config_keys,
+        )
+    }
+
+    /// Record the declared capabilities for `model` and the listed models in the model
+    /// registry, so the shared request formatting leaves out what the endpoint can't take
+    fn refine_registry(&self, model: &str) {
+        let capabilities = ModelCapabilities {
+            tool_calling: Some(self.capabilities.tools),
+            vision: Some(self.capabilities.images),
+            streaming: Some(self.capabilities.streaming),
+            ..Default::default()
+        };
+        model_registry::refine(&self.name, model, capabilities.clone());
+        for listed in &self.models {
+            model_registry::refine(
+                &self.name,
+                &listed.name,
+                ModelCapabilities {
+                    context_limit: listed.context_limit,
+                    ..capabilities.clone()
+                },
+            );
+        }
+    }
+}
+
+fn parse_entries(entries: Vec<Value>) -> Vec<CustomProviderConfig> {
+    let mut providers = Vec::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let name = match entry["name"].as_str() {
+            Some(name) => format!("'{}'", name),
+            None => format!("#{}", index + 1),
+        };
+        match serde_json::from_value(entry) {
+            Ok(provider) => providers.push(provider),
+            Err(e) => tracing::warn!(
+                "Ignoring custom provider {}, which can't be read: {}",
+                name,
+                e
+            ),
+        }
+    }
+    providers
+}
+
+fn without_builtin_names(providers: Vec<CustomProviderConfig>) -> Vec<CustomProviderConfig> {
+    providers
+        .into_iter()
+        .filter(|provider| {
+            let builtin = is_builtin_provider(&provider.name);
+            if builtin {
+                tracing::warn!(
+                    "Ignoring custom provider '{}', a built-in provider has that name",
+                    provider.name
+                );
+            }
+            !builtin
+        })
+        .collect()
+}
+
+/// The custom provider declared with `name`, if there is one
+pub fn custom_provider(name: &str) -> Option<CustomProviderConfig> {
+    CustomProviderConfig::from_config()
+        .into_iter()
+        .find(|provider| provider.name == name)
+}
+
+/// A provider for an endpoint declared under `custom_providers`
+#[derive(Debug)]
+pub struct CustomProvider {
+    client: Client,
+    config: CustomProviderConfig,
+    api_key: Option<String>,
+    model: ModelConfig,
+}
+
+impl CustomProvider {
+    pub fn new(config: CustomProviderConfig, model: ModelConfig) -> Result<Self> {
+        let api_key = match &config.api_key {
+            Some(key) => Some(Config::global().get_secret(key)?),
+            None => None,
+        };
+        let context_limit = config
+            .models
+            .iter()
+            .find(|m| m.name == model.model_name)
+            .and_then(|m| m.context_limit);
+        config.refine_registry(&model.model_name);
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+
+        Ok(Self {
+            client,
+            api_key,
+            model: model.with_context_limit(context_limit),
+            config,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.config.base_url.trim_end_matches('/'), path);
+        let mut request = self.client.request(method, url);
+        if let Some(api_key) = &self.api_key {
+            request = match self.config.auth {
+                AuthStyle::Bearer => request.bearer_auth(api_key),
+                AuthStyle::ApiKey => request.header(&self.config.api_key_header, api_key),
+                AuthStyle::None => request,
+            };
+        }
+        for (key, value) in &self.config.headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    fn create_request(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<Value, ProviderError> {
+        let tools = if self.config.capabilities.tools {
+            tools
+        } else {
+            &[]
+        };
+        Ok(create_request(
+            model,
+            system,
+            messages,
+            tools,
+            &ImageFormat::OpenAi,
+        )?)
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.create_request(model, system, messages, tools)?;
+        let response = self
+            .request(reqwest::Method::POST, "chat/completions")
+            .json(&payload)
+            .send()
+            .await?;
+        let response = handle_response_openai_compat(response).await?;
+        finish_response(model, &payload, response, response_to_message, get_usage)
+    }
+}
+
+#[async_trait]
+impl Provider for CustomProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.model.capped_at(max_tokens);
+        self.complete_with_model(&model, system, messages, tools)
+            .await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.config.capabilities.streaming
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        if !self.config.capabilities.streaming {
+            let (message, usage) = self.complete(system, messages, tools).await?;
+            return Ok(Box::pin(futures::stream::once(async move {
+                Ok(super::base::StreamChunk::Complete(message, usage))
+            })));
+        }
+
+        let mut payload = self.create_request(&self.model, system, messages, tools)?;
+        enable_streaming(&mut payload);
+
+        let response = self
+            .request(reqwest::Method::POST, "chat/completions")
+            .json(&payload)
+            .send()
+            .await?;
+        let response = check_stream_response(response, handle_response_openai_compat).await?;
+
+        let model_config = self.model.clone();
+        Ok(stream_response(
+            response,
+            OpenAiStreamAccumulator::default(),
+            move |response| {
+                finish_response(
+                    &model_config,
+                    &payload,
+                    response,
+                    response_to_message,
+                    get_usage,
+                )
+            },
+        ))
+    }
+
+    /// The configured models, or the ones the endpoint lists if none are configured
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        if !self.config.models.is_empty() {
+            return Ok(Some(
+                self.config.models.iter().map(|m| m.name.clone()).collect(),
+            ));
+        }
+
+        let response = self.request(reqwest::Method::GET, "models").send().await?;
+        if !response.status().is_success() {
+            // Not every compatible server lists its models
+            return Ok(None);
+        }
+        let json: Value = response.json().await?;
+        let mut models: Vec<String> = json["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["id"].as_str().map(str::to_string))
+            .collect();
+        models.sort();
+        Ok((!models.is_empty()).then_some(models))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults_and_metadata() {
+        let config: CustomProviderConfig = serde_yaml::from_str(
+            r#"
+name: vllm
+display_name: vLLM
+base_url: http://localhost:8000/v1
+models:
+  - name: llama-3.1-70b
+    context_limit: 32000
+  - name: qwen
+capabilities:
+  images: false
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.auth, AuthStyle::Bearer);
+        assert!(config.capabilities.tools);
+        assert!(!config.capabilities.images);
+        assert!(config.capabilities.streaming);
+
+        let metadata = config.metadata();
+        assert_eq!(metadata.name, "vllm");
+        assert_eq!(metadata.display_name, "vLLM");
+        assert_eq!(metadata.default_model, "llama-3.1-70b");
+        assert_eq!(metadata.known_models[0].context_limit, 32000);
+        assert_eq!(metadata.known_models.len(), 2);
+        assert!(metadata.config_keys.is_empty());
+
+        let config = CustomProviderConfig {
+            api_key: Some("GATEWAY_API_KEY".to_string()),
+            ..config
+        };
+        let keys = config.metadata().config_keys;
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].required && keys[0].secret);
+    }
+
+    #[test]
+    fn test_builtin_names_are_left_out() {
+        let providers: Vec<CustomProviderConfig> = serde_yaml::from_str(
+            r#"
+- name: openai
+  base_url: http://localhost:8000/v1
+- name: vllm
+  base_url: http://localhost:8000/v1
+- name: replay
+  base_url: http://localhost:8000/v1
+"#,
+        )
+        .unwrap();
+
+        let names: Vec<String> = without_builtin_names(providers)
+            .into_iter()
+            .map(|provider| provider.name)
+            .collect();
+        assert_eq!(names, vec!["vllm"]);
+    }
+
+    #[test]
+    fn test_unreadable_entries_are_left_out() {
+        let entries: Vec<Value> = serde_yaml::from_str(
+            r#"
+- name: vllm
+  base_url: http://localhost:8000/v1
+- name: lmstudio
+- name: litellm
+  base_url: http://localhost:4000
+  auth: oauth
+- base_url: http://localhost:9000/v1
+- name: gateway
+  base_url: https://gateway.internal/v1
+"#,
+        )
+        .unwrap();
+
+        let names: Vec<String> = parse_entries(entries)
+            .into_iter()
+            .map(|provider| provider.name)
+            .collect();
+        assert_eq!(names, vec!["vllm", "gateway"]);
+    }
+}
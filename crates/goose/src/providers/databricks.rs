@@ -295,7 +295,10 @@ impl DatabricksProvider {
                     self.retry_config.max_retries
                 );
                 tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
+                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded {
+                    details: error_msg,
+                    retry_after: None,
+                }));
             }
 
             let auth_header = self.ensure_auth_header().await?;
@@ -383,7 +386,10 @@ impl DatabricksProvider {
                     tracing::warn!("{}. Retrying after backoff...", error_msg);
 
                     // Store the error in case we need to return it after max retries
-                    last_error = Some(ProviderError::RateLimitExceeded(error_msg));
+                    last_error = Some(ProviderError::RateLimitExceeded {
+                        details: error_msg,
+                        retry_after: None,
+                    });
 
                     // Calculate and apply the backoff delay
                     let delay = self.retry_config.delay_for_attempt(attempts);
@@ -402,7 +408,10 @@ impl DatabricksProvider {
                     tracing::warn!("{}. Retrying after backoff...", error_msg);
 
                     // Store the error in case we need to return it after max retries
-                    last_error = Some(ProviderError::ServerError(error_msg));
+                    last_error = Some(ProviderError::ServerError {
+                        details: error_msg,
+                        retry_after: None,
+                    });
 
                     // Calculate and apply the backoff delay
                     let delay = self.retry_config.delay_for_attempt(attempts);
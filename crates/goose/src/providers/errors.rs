@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use reqwest::StatusCode;
 use thiserror::Error;
 
@@ -9,11 +11,19 @@ pub enum ProviderError {
     #[error("Context length exceeded: {0}")]
     ContextLengthExceeded(String),
 
-    #[error("Rate limit exceeded: {0}")]
-    RateLimitExceeded(String),
+    #[error("Rate limit exceeded: {details}")]
+    RateLimitExceeded {
+        details: String,
+        /// How long the provider asked to wait before retrying
+        retry_after: Option<Duration>,
+    },
 
-    #[error("Server error: {0}")]
-    ServerError(String),
+    #[error("Server error: {details}")]
+    ServerError {
+        details: String,
+        /// How long the provider asked to wait before retrying
+        retry_after: Option<Duration>,
+    },
 
     #[error("Request failed: {0}")]
     RequestFailed(String),
@@ -25,6 +35,25 @@ pub enum ProviderError {
     UsageError(String),
 }
 
+impl ProviderError {
+    /// Whether the same request may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ProviderError::RateLimitExceeded { .. } | ProviderError::ServerError { .. }
+        )
+    }
+
+    /// How long the provider asked to wait before retrying, if it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ProviderError::RateLimitExceeded { retry_after, .. }
+            | ProviderError::ServerError { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
 impl From<anyhow::Error> for ProviderError {
     fn from(error: anyhow::Error) -> Self {
         ProviderError::ExecutionError(error.to_string())
@@ -177,3 +206,44 @@ impl std::fmt::Display for OpenAIError {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let rate_limited = ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_after: Some(Duration::from_secs(20)),
+        };
+        assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(20)));
+        assert_eq!(rate_limited.to_string(), "Rate limit exceeded: slow down");
+
+        let overloaded = ProviderError::ServerError {
+            details: "overloaded".to_string(),
+            retry_after: None,
+        };
+        assert_eq!(overloaded.retry_after(), None);
+        assert_eq!(
+            ProviderError::RequestFailed("retry after 20s".to_string()).retry_after(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(ProviderError::RateLimitExceeded {
+            details: String::new(),
+            retry_after: None,
+        }
+        .is_retryable());
+        assert!(ProviderError::ServerError {
+            details: String::new(),
+            retry_after: None,
+        }
+        .is_retryable());
+        assert!(!ProviderError::ContextLengthExceeded(String::new()).is_retryable());
+        assert!(!ProviderError::Authentication(String::new()).is_retryable());
+    }
+}
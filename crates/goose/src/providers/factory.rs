@@ -6,7 +6,9 @@ use super::{
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
     claude_code::ClaudeCodeProvider,
+    custom::{custom_provider, CustomProvider, CustomProviderConfig},
     databricks::DatabricksProvider,
+    fallback::{FallbackProvider, FallbackTarget},
     gcpvertexai::GcpVertexAIProvider,
     gemini_cli::GeminiCliProvider,
     google::GoogleProvider,
@@ -15,11 +17,15 @@ use super::{
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
+    replay::{ReplayProvider, REPLAY_PROVIDER_NAME},
+    retry::{RetryConfig, RetryingProvider},
+    routing::{RoutingConfig, RoutingProvider},
     sagemaker_tgi::SageMakerTgiProvider,
     snowflake::SnowflakeProvider,
     venice::VeniceProvider,
     xai::XaiProvider,
 };
+use crate::config::Budget;
 use crate::model::ModelConfig;
 use anyhow::Result;
 
@@ -38,7 +44,25 @@ fn default_fallback_turns() -> usize {
     2
 }
 
+/// The built-in providers, followed by those declared under `custom_providers` in the config
 pub fn providers() -> Vec<ProviderMetadata> {
+    let mut providers = builtin_providers();
+    providers.extend(
+        CustomProviderConfig::from_config()
+            .iter()
+            .map(CustomProviderConfig::metadata),
+    );
+    providers
+}
+
+/// Whether `name` is taken by a provider built into goose
+pub(crate) fn is_builtin_provider(name: &str) -> bool {
+    builtin_providers()
+        .iter()
+        .any(|provider| provider.name == name)
+}
+
+fn builtin_providers() -> Vec<ProviderMetadata> {
     vec![
         AnthropicProvider::metadata(),
         AzureProvider::metadata(),
@@ -53,6 +77,7 @@ pub fn providers() -> Vec<ProviderMetadata> {
         OllamaProvider::metadata(),
         OpenAiProvider::metadata(),
         OpenRouterProvider::metadata(),
+        ReplayProvider::metadata(),
         SageMakerTgiProvider::metadata(),
         VeniceProvider::metadata(),
         SnowflakeProvider::metadata(),
@@ -60,18 +85,82 @@ pub fn providers() -> Vec<ProviderMetadata> {
     ]
 }
 
+/// Create the named provider, retrying failed calls as [`RetryConfig::for_provider`] says
+/// and failing over to the `provider_fallbacks` providers
+///
+/// When `model_routing` lists models, each turn is routed among them instead and the named
+/// provider and model are not used.
 pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     let config = crate::config::Config::global();
 
-    // Check for lead model environment variables
-    if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
+    let primary = if let Some(routing) = RoutingConfig::from_config() {
+        tracing::info!("Creating routing provider from model_routing");
+
+        create_routing_provider(routing)?
+    } else if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
+        // Check for lead model environment variables
         tracing::info!("Creating lead/worker provider from environment variables");
 
-        return create_lead_worker_from_env(name, &model, &lead_model_name);
+        create_lead_worker_from_env(name, &model, &lead_model_name)?
+    } else {
+        // Default: create regular provider
+        create_retrying_provider(name, model)?
+    };
+
+    with_fallbacks(primary, FallbackTarget::from_config())
+}
+
+/// Create a provider whose failed calls are retried, unless it retries them itself
+fn create_retrying_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let provider = create_provider(name, model)?;
+    Ok(match RetryConfig::for_provider(name) {
+        Some(retry) => Arc::new(RetryingProvider::new(provider, retry)),
+        None => provider,
+    })
+}
+
+/// Put `primary` in front of the providers in `targets`, if there are any
+///
+/// A fallback that can't be created, for example for lack of credentials, is left out
+/// rather than keeping the primary from being used.
+fn with_fallbacks(
+    primary: Arc<dyn Provider>,
+    targets: Vec<FallbackTarget>,
+) -> Result<Arc<dyn Provider>> {
+    if targets.is_empty() {
+        return Ok(primary);
+    }
+    let mut providers = vec![primary];
+    for target in targets {
+        match create_retrying_provider(&target.provider, ModelConfig::new(target.model.clone())) {
+            Ok(provider) => providers.push(provider),
+            Err(e) => tracing::warn!(
+                "Skipping fallback provider {} with model {}: {}",
+                target.provider,
+                target.model,
+                e
+            ),
+        }
     }
+    Ok(Arc::new(FallbackProvider::new(providers)))
+}
 
-    // Default: create regular provider
-    create_provider(name, model)
+/// Create a provider routing each turn among the models `routing` lists
+fn create_routing_provider(routing: RoutingConfig) -> Result<Arc<dyn Provider>> {
+    let routes = routing
+        .models
+        .iter()
+        .map(|target| {
+            let provider =
+                create_retrying_provider(&target.provider, ModelConfig::new(target.model.clone()))?;
+            Ok((target.clone(), provider))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Arc::new(RoutingProvider::new(
+        routes,
+        routing,
+        Budget::from_config(),
+    )))
 }
 
 /// Create a lead/worker provider from environment variables
@@ -103,8 +192,8 @@ fn create_lead_worker_from_env(
     let worker_model_config = default_model.clone();
 
     // Create the providers
-    let lead_provider = create_provider(&lead_provider_name, lead_model_config)?;
-    let worker_provider = create_provider(default_provider_name, worker_model_config)?;
+    let lead_provider = create_retrying_provider(&lead_provider_name, lead_model_config)?;
+    let worker_provider = create_retrying_provider(default_provider_name, worker_model_config)?;
 
     // Create the lead/worker provider with configured settings
     Ok(Arc::new(LeadWorkerProvider::new_with_settings(
@@ -117,6 +206,7 @@ fn create_lead_worker_from_env(
 }
 
 fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let model = model.with_provider(name);
     // We use Arc instead of Box to be able to clone for multiple async tasks
     match name {
         "openai" => Ok(Arc::new(OpenAiProvider::from_env(model)?)),
@@ -136,7 +226,11 @@ fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>>
         "snowflake" => Ok(Arc::new(SnowflakeProvider::from_env(model)?)),
         // "github_copilot" => Ok(Arc::new(GithubCopilotProvider::from_env(model)?)),
         "xai" => Ok(Arc::new(XaiProvider::from_env(model)?)),
-        _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        REPLAY_PROVIDER_NAME => ReplayProvider::from_env(model, create_provider),
+        _ => match custom_provider(name) {
+            Some(config) => Ok(Arc::new(CustomProvider::new(config, model)?)),
+            None => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        },
     }
 }
 
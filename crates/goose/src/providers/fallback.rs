@@ -0,0 +1,288 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+use serde::{Deserialize, Serialize};
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use super::errors::ProviderError;
+use crate::config::{Budget, Config};
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+/// A provider and model to fail over to, from the `provider_fallbacks` config key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FallbackTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+impl FallbackTarget {
+    /// The fallbacks listed under `provider_fallbacks` in the global config, in order
+    pub fn from_config() -> Vec<Self> {
+        Config::global()
+            .get_param("provider_fallbacks")
+            .unwrap_or_default()
+    }
+}
+
+/// A provider that tries each of an ordered list of providers until one answers
+///
+/// Every call starts from the first provider. Only rate limit and server errors fail over;
+/// anything else, like a bad key, a rejected request or a context length error the agent
+/// handles by compacting, would most likely fail the same way elsewhere and is returned.
+pub struct FallbackProvider {
+    providers: Vec<Arc<dyn Provider>>,
+    active: AtomicUsize,
+}
+
+impl FallbackProvider {
+    pub fn new(providers: Vec<Arc<dyn Provider>>) -> Self {
+        assert!(!providers.is_empty(), "FallbackProvider needs a provider");
+        Self {
+            providers,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    fn primary(&self) -> &Arc<dyn Provider> {
+        &self.providers[0]
+    }
+
+    fn active(&self) -> &Arc<dyn Provider> {
+        &self.providers[self.active.load(Ordering::Relaxed)]
+    }
+
+    /// Whether to give up on `error` from provider `index` rather than try the next one
+    fn should_return(&self, index: usize, error: &ProviderError) -> bool {
+        if index + 1 == self.providers.len() || !error.is_retryable() {
+            return true;
+        }
+        tracing::warn!(
+            "Provider {} failed ({}), falling back to {}",
+            self.providers[index].get_model_config().model_name,
+            error,
+            self.providers[index + 1].get_model_config().model_name
+        );
+        false
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.complete(system, messages, tools).await {
+                Ok(result) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    return Ok(result);
+                }
+                Err(e) if self.should_return(index, &e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        unreachable!("the last provider's error is returned")
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider
+                .complete_with_max_tokens(system, messages, tools, max_tokens)
+                .await
+            {
+                Ok(result) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    return Ok(result);
+                }
+                Err(e) if self.should_return(index, &e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        unreachable!("the last provider's error is returned")
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.primary().supports_streaming()
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.stream(system, messages, tools).await {
+                Ok(stream) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    return Ok(stream);
+                }
+                Err(e) if self.should_return(index, &e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        unreachable!("the last provider's error is returned")
+    }
+
+    /// The model config of the provider that answered last, the first one until then
+    fn get_model_config(&self) -> ModelConfig {
+        self.active().get_model_config()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.primary().fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.primary().supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.primary().create_embeddings(texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.primary().as_lead_worker()
+    }
+
+    fn set_session_budget(&self, budget: Option<Budget>, spent: Option<f64>) {
+        for provider in &self.providers {
+            provider.set_session_budget(budget.clone(), spent);
+        }
+    }
+
+    fn usage_provider(&self, usage: &ProviderUsage) -> Option<String> {
+        self.active().usage_provider(usage)
+    }
+
+    fn get_active_model_name(&self) -> String {
+        self.active().get_active_model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    struct FixedProvider {
+        model: &'static str,
+        error: Option<fn() -> ProviderError>,
+    }
+
+    #[async_trait]
+    impl Provider for FixedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok((
+                    Message::assistant().with_text(self.model),
+                    ProviderUsage::new(self.model.to_string(), Usage::default()),
+                )),
+            }
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new(self.model.to_string())
+        }
+    }
+
+    fn provider(model: &'static str, error: Option<fn() -> ProviderError>) -> Arc<dyn Provider> {
+        Arc::new(FixedProvider { model, error })
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_in_order() {
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::ServerError {
+                    details: "down".into(),
+                    retry_after: None,
+                }),
+            ),
+            provider(
+                "second",
+                Some(|| ProviderError::RateLimitExceeded {
+                    details: "busy".into(),
+                    retry_after: None,
+                }),
+            ),
+            provider("third", None),
+        ]);
+        assert_eq!(fallback.get_model_config().model_name, "primary");
+
+        let (message, _) = fallback.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "third");
+        assert_eq!(fallback.get_model_config().model_name, "third");
+        assert_eq!(fallback.get_active_model_name(), "third");
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_all_fail() {
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::ServerError {
+                    details: "down".into(),
+                    retry_after: None,
+                }),
+            ),
+            provider(
+                "second",
+                Some(|| ProviderError::RateLimitExceeded {
+                    details: "busy".into(),
+                    retry_after: None,
+                }),
+            ),
+        ]);
+
+        let err = fallback.complete("", &[], &[]).await.unwrap_err();
+        assert!(matches!(err, ProviderError::RateLimitExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_failed_over() {
+        let errors: [fn() -> ProviderError; 3] = [
+            || ProviderError::ContextLengthExceeded("too long".into()),
+            || ProviderError::Authentication("bad key".into()),
+            || ProviderError::RequestFailed("400 Bad Request".into()),
+        ];
+        for error in errors {
+            let fallback = FallbackProvider::new(vec![
+                provider("primary", Some(error)),
+                provider("second", None),
+            ]);
+
+            let err = fallback.complete("", &[], &[]).await.unwrap_err();
+            assert_eq!(err.to_string(), error().to_string());
+        }
+    }
+}
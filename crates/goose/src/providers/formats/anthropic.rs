@@ -1,13 +1,15 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai_responses::is_responses_item;
+use crate::providers::streaming::{SseEvent, StreamAccumulator};
 use anyhow::{anyhow, Result};
 use mcp_core::content::Content;
 use mcp_core::role::Role;
 use mcp_core::tool::{Tool, ToolCall};
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Convert internal Message format to Anthropic's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -66,6 +68,10 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                // Reasoning from the OpenAI Responses API can't be verified by Anthropic
+                MessageContent::Thinking(thinking) if is_responses_item(&thinking.signature) => {
+                    continue
+                }
                 MessageContent::Thinking(thinking) => {
                     content.push(json!({
                         "type": "thinking",
@@ -73,6 +79,9 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                         "signature": thinking.signature
                     }));
                 }
+                MessageContent::RedactedThinking(redacted) if is_responses_item(&redacted.data) => {
+                    continue
+                }
                 MessageContent::RedactedThinking(redacted) => {
                     content.push(json!({
                         "type": "redacted_thinking",
@@ -258,39 +267,21 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .and_then(|v| v.as_u64())
             .unwrap_or(0);
 
-        // IMPORTANT: Based on the API responses, when caching is used:
-        // - input_tokens is ONLY the new/fresh tokens (can be very small, like 7)
-        // - cache_creation_input_tokens and cache_read_input_tokens are the cached content
-        // - These cached tokens are charged at different rates:
-        //   * Fresh input tokens: 100% of regular price
-        //   * Cache creation tokens: 125% of regular price
-        //   * Cache read tokens: 10% of regular price
-        //
-        // Calculate effective input tokens for cost calculation based on Anthropic's pricing:
-        // - Fresh input tokens: 100% of regular price (1.0x)
-        // - Cache creation tokens: 125% of regular price (1.25x)
-        // - Cache read tokens: 10% of regular price (0.10x)
-        //
-        // The effective input tokens represent the cost-equivalent tokens when multiplied
-        // by the regular input price, ensuring accurate cost calculations in the frontend.
-        let effective_input_tokens = input_tokens as f64 * 1.0
-            + cache_creation_tokens as f64 * 1.25
-            + cache_read_tokens as f64 * 0.10;
-
-        // For token counting purposes, we still want to show the actual total count
-        let _total_actual_tokens = input_tokens + cache_creation_tokens + cache_read_tokens;
-
-        // Return the effective input tokens for cost calculation
-        // This ensures the frontend cost calculation is accurate when multiplying by regular prices
-        let effective_input_i32 = effective_input_tokens.round().clamp(0.0, i32::MAX as f64) as i32;
-        let output_tokens_i32 = output_tokens.min(i32::MAX as u64) as i32;
-        let total_tokens_i32 =
-            (effective_input_i32 as i64 + output_tokens_i32 as i64).min(i32::MAX as i64) as i32;
+        // When caching is used, input_tokens only counts the tokens after the last cache
+        // breakpoint. The cached prefix is reported separately, as tokens written to the cache
+        // (charged at a premium) and tokens read from it (charged at a discount). Report all of
+        // them as input and keep the cached parts apart so pricing can charge each at its rate.
+        let to_i32 = |tokens: u64| tokens.min(i32::MAX as u64) as i32;
+        let total_input_tokens = input_tokens + cache_creation_tokens + cache_read_tokens;
 
         Ok(Usage::new(
-            Some(effective_input_i32),
-            Some(output_tokens_i32),
-            Some(total_tokens_i32),
+            Some(to_i32(total_input_tokens)),
+            Some(to_i32(output_tokens)),
+            Some(to_i32(total_input_tokens + output_tokens)),
+        )
+        .with_cache_tokens(
+            Some(to_i32(cache_read_tokens)),
+            Some(to_i32(cache_creation_tokens)),
         ))
     } else {
         tracing::debug!(
@@ -302,6 +293,170 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// Reassembles a streamed Messages API response into the body `response_to_message` expects
+///
+/// See https://docs.anthropic.com/en/docs/build-with-claude/streaming for the event types.
+#[derive(Default)]
+pub struct AnthropicStreamAccumulator {
+    message: Option<Value>,
+    blocks: Vec<Value>,
+    partial_json: HashMap<usize, String>,
+}
+
+impl AnthropicStreamAccumulator {
+    fn block_mut(&mut self, index: usize) -> Result<&mut Value, ProviderError> {
+        self.blocks.get_mut(index).ok_or_else(|| {
+            ProviderError::RequestFailed(format!(
+                "Stream delta for unknown content block {}",
+                index
+            ))
+        })
+    }
+}
+
+impl StreamAccumulator for AnthropicStreamAccumulator {
+    fn push(&mut self, event: &SseEvent) -> Result<Vec<MessageDelta>, ProviderError> {
+        let data: Value = serde_json::from_str(&event.data).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid stream event: {}: {}", e, event.data))
+        })?;
+        let index = data.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+
+        let mut deltas = Vec::new();
+        match data.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                self.message = data.get("message").cloned();
+            }
+            Some("content_block_start") => {
+                let block = data.get("content_block").cloned().unwrap_or(json!({}));
+                if block.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
+                    deltas.push(MessageDelta::ToolCall {
+                        index,
+                        id: block.get("id").and_then(|i| i.as_str()).map(str::to_string),
+                        name: block
+                            .get("name")
+                            .and_then(|n| n.as_str())
+                            .map(str::to_string),
+                        arguments: String::new(),
+                    });
+                }
+                if self.blocks.len() <= index {
+                    self.blocks.resize(index + 1, json!({}));
+                }
+                self.blocks[index] = block;
+            }
+            Some("content_block_delta") => {
+                let delta = data.get("delta").cloned().unwrap_or(json!({}));
+                let block = self.block_mut(index)?;
+                let append = |block: &mut Value, field: &str, fragment: &str| {
+                    let existing = block.get(field).and_then(|v| v.as_str()).unwrap_or("");
+                    block[field] = json!(format!("{}{}", existing, fragment));
+                };
+                match delta.get("type").and_then(|t| t.as_str()) {
+                    Some("text_delta") => {
+                        let text = delta.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                        append(block, "text", text);
+                        deltas.push(MessageDelta::Text {
+                            text: text.to_string(),
+                        });
+                    }
+                    Some("thinking_delta") => {
+                        let thinking = delta.get("thinking").and_then(|t| t.as_str()).unwrap_or("");
+                        append(block, "thinking", thinking);
+                        deltas.push(MessageDelta::Thinking {
+                            thinking: thinking.to_string(),
+                        });
+                    }
+                    Some("signature_delta") => {
+                        let signature = delta
+                            .get("signature")
+                            .and_then(|s| s.as_str())
+                            .unwrap_or("");
+                        append(block, "signature", signature);
+                    }
+                    Some("input_json_delta") => {
+                        let id = block.get("id").and_then(|i| i.as_str()).map(str::to_string);
+                        let partial = delta
+                            .get("partial_json")
+                            .and_then(|p| p.as_str())
+                            .unwrap_or("");
+                        self.partial_json
+                            .entry(index)
+                            .or_default()
+                            .push_str(partial);
+                        deltas.push(MessageDelta::ToolCall {
+                            index,
+                            id,
+                            name: None,
+                            arguments: partial.to_string(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            Some("content_block_stop") => {
+                // Tool input arrives as JSON fragments and is only valid once the block ends
+                if let Some(partial) = self.partial_json.remove(&index) {
+                    let input = if partial.trim().is_empty() {
+                        json!({})
+                    } else {
+                        serde_json::from_str(&partial).map_err(|e| {
+                            ProviderError::RequestFailed(format!(
+                                "Invalid tool_use input in stream: {}",
+                                e
+                            ))
+                        })?
+                    };
+                    self.block_mut(index)?["input"] = input;
+                }
+            }
+            Some("message_delta") => {
+                let message = self.message.get_or_insert_with(|| json!({}));
+                if let Some(stop_reason) = data.get("delta").and_then(|d| d.get("stop_reason")) {
+                    message["stop_reason"] = stop_reason.clone();
+                }
+                // The final usage only reports output tokens, so merge rather than replace
+                if let Some(Value::Object(usage)) = data.get("usage") {
+                    if !message.get("usage").is_some_and(|u| u.is_object()) {
+                        message["usage"] = json!({});
+                    }
+                    for (key, value) in usage {
+                        message["usage"][key] = value.clone();
+                    }
+                }
+            }
+            Some("error") => {
+                let error = data.get("error").cloned().unwrap_or(json!({}));
+                let message = error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Unknown error")
+                    .to_string();
+                return Err(match error.get("type").and_then(|t| t.as_str()) {
+                    Some("rate_limit_error") => ProviderError::RateLimitExceeded {
+                        details: message,
+                        retry_after: None,
+                    },
+                    Some("overloaded_error") | Some("api_error") => ProviderError::ServerError {
+                        details: message,
+                        retry_after: None,
+                    },
+                    _ => ProviderError::RequestFailed(message),
+                });
+            }
+            _ => {}
+        }
+        Ok(deltas)
+    }
+
+    fn finish(self) -> Result<Value, ProviderError> {
+        let mut message = self.message.ok_or_else(|| {
+            ProviderError::RequestFailed("Stream ended before message_start".to_string())
+        })?;
+        message["content"] = Value::Array(self.blocks);
+        Ok(message)
+    }
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -319,8 +474,13 @@ pub fn create_request(
     }
 
     // https://docs.anthropic.com/en/docs/about-claude/models/all-models#model-comparison-table
-    // Claude 3.7 supports max output tokens up to 8192
-    let max_tokens = model_config.max_tokens.unwrap_or(8192);
+    let capabilities = model_config.capabilities();
+    let max_tokens = model_config
+        .max_tokens
+        .or(capabilities.max_output_tokens)
+        .unwrap_or(8192);
+    let is_thinking_enabled =
+        capabilities.is_reasoning() && std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
     let mut payload = json!({
         "model": model_config.model_name,
         "messages": anthropic_messages,
@@ -343,10 +503,10 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tool_specs));
     }
 
-    // Add temperature if specified and not using extended thinking model
+    // Add temperature if specified and not using extended thinking
     if let Some(temp) = model_config.temperature {
-        // Claude 3.7 models with thinking enabled don't support temperature
-        if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+        // Models with thinking enabled don't support temperature
+        if !is_thinking_enabled {
             payload
                 .as_object_mut()
                 .unwrap()
@@ -354,9 +514,8 @@ pub fn create_request(
         }
     }
 
-    // Add thinking parameters for claude-3-7-sonnet model
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
+    // Add thinking parameters for models the registry knows can reason
+    if is_thinking_enabled {
         // Minimum budget_tokens is 1024
         let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
             .unwrap_or_else(|_| "16000".to_string())
@@ -415,9 +574,9 @@ mod tests {
             panic!("Expected Text content");
         }
 
-        assert_eq!(usage.input_tokens, Some(27)); // 12 * 1.0 + 12 * 1.25 = 27 effective tokens
+        assert_eq!(usage.input_tokens, Some(24)); // 12 fresh + 12 written to the cache
         assert_eq!(usage.output_tokens, Some(15));
-        assert_eq!(usage.total_tokens, Some(42)); // 27 + 15
+        assert_eq!(usage.total_tokens, Some(39)); // 24 + 15
 
         Ok(())
     }
@@ -458,9 +617,9 @@ mod tests {
             panic!("Expected ToolRequest content");
         }
 
-        assert_eq!(usage.input_tokens, Some(34)); // 15 * 1.0 + 15 * 1.25 = 33.75 → 34 effective tokens
+        assert_eq!(usage.input_tokens, Some(30)); // 15 fresh + 15 written to the cache
         assert_eq!(usage.output_tokens, Some(20));
-        assert_eq!(usage.total_tokens, Some(54)); // 34 + 20
+        assert_eq!(usage.total_tokens, Some(50)); // 30 + 20
 
         Ok(())
     }
@@ -559,6 +718,30 @@ mod tests {
         assert_eq!(spec[2]["content"][0]["text"], "How are you?");
     }
 
+    #[test]
+    fn test_openai_reasoning_is_left_out() -> Result<()> {
+        let openai = crate::providers::formats::openai_responses::responses_to_message(&json!({
+            "output": [
+                {"type": "reasoning", "id": "rs_1", "summary": [{"type": "summary_text", "text": "Hmm"}]},
+                {"type": "reasoning", "id": "rs_2", "summary": [], "encrypted_content": "abc"},
+                {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Done."}]},
+            ],
+        }))?;
+        let messages = vec![
+            Message::user().with_text("Hello"),
+            openai,
+            Message::assistant().with_thinking("Let me think", "sig"),
+        ];
+
+        let spec = format_messages(&messages);
+        assert_eq!(
+            spec[1]["content"],
+            json!([{"type": "text", "text": "Done."}])
+        );
+        assert_eq!(spec[2]["content"][0]["signature"], "sig");
+        Ok(())
+    }
+
     #[test]
     fn test_tools_to_anthropic_spec() {
         let tools = vec![
@@ -661,7 +844,7 @@ mod tests {
     }
 
     #[test]
-    fn test_cache_pricing_calculation() -> Result<()> {
+    fn test_cache_token_usage() -> Result<()> {
         // Test realistic cache scenario: small fresh input, large cached content
         let response = json!({
             "id": "msg_cache_test",
@@ -684,12 +867,108 @@ mod tests {
 
         let usage = get_usage(&response)?;
 
-        // Effective input tokens should be:
-        // 7 * 1.0 + 10000 * 1.25 + 5000 * 0.10 = 7 + 12500 + 500 = 13007
-        assert_eq!(usage.input_tokens, Some(13007));
+        // Cached tokens count as input, and are also reported on their own
+        assert_eq!(usage.input_tokens, Some(15007));
         assert_eq!(usage.output_tokens, Some(50));
-        assert_eq!(usage.total_tokens, Some(13057)); // 13007 + 50
+        assert_eq!(usage.total_tokens, Some(15057));
+        assert_eq!(usage.cache_read_input_tokens, Some(5000));
+        assert_eq!(usage.cache_write_input_tokens, Some(10000));
+        assert_eq!(usage.uncached_input_tokens(), Some(7));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_accumulator_text_thinking_and_tool_use() -> Result<()> {
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "type": "message", "role": "assistant", "model": "claude-sonnet-4-20250514", "content": [], "usage": {"input_tokens": 25, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Need to list files."}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Sure"}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": ", one moment."}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "developer__shell", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "{\"command\": "}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "\"ls\"}"}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 40}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let mut accumulator = AnthropicStreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for event in events {
+            deltas.extend(accumulator.push(&SseEvent {
+                event: event["type"].as_str().map(str::to_string),
+                data: event.to_string(),
+            })?);
+        }
+
+        assert_eq!(
+            deltas,
+            vec![
+                MessageDelta::Thinking {
+                    thinking: "Need to list files.".to_string()
+                },
+                MessageDelta::Text {
+                    text: "Sure".to_string()
+                },
+                MessageDelta::Text {
+                    text: ", one moment.".to_string()
+                },
+                MessageDelta::ToolCall {
+                    index: 2,
+                    id: Some("toolu_1".to_string()),
+                    name: Some("developer__shell".to_string()),
+                    arguments: String::new(),
+                },
+                MessageDelta::ToolCall {
+                    index: 2,
+                    id: Some("toolu_1".to_string()),
+                    name: None,
+                    arguments: "{\"command\": ".to_string(),
+                },
+                MessageDelta::ToolCall {
+                    index: 2,
+                    id: Some("toolu_1".to_string()),
+                    name: None,
+                    arguments: "\"ls\"}".to_string(),
+                },
+            ]
+        );
+
+        let response = accumulator.finish()?;
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content.len(), 3);
+        let thinking = message.content[0].as_thinking().unwrap();
+        assert_eq!(thinking.thinking, "Need to list files.");
+        assert_eq!(thinking.signature, "sig");
+        assert_eq!(message.content[1].as_text(), Some("Sure, one moment."));
+        let tool_call = message.content[2]
+            .as_tool_request()
+            .unwrap()
+            .tool_call
+            .as_ref()
+            .unwrap();
+        assert_eq!(tool_call.arguments, json!({"command": "ls"}));
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(25));
+        assert_eq!(usage.output_tokens, Some(40));
 
         Ok(())
     }
+
+    #[test]
+    fn test_stream_accumulator_error_event() {
+        let mut accumulator = AnthropicStreamAccumulator::default();
+        let result = accumulator.push(&SseEvent {
+            event: Some("error".to_string()),
+            data: json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}).to_string(),
+        });
+        assert!(matches!(result, Err(ProviderError::ServerError { .. })));
+    }
 }
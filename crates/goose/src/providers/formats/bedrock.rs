@@ -11,20 +11,66 @@ use serde_json::Value;
 use super::super::base::Usage;
 use crate::message::{Message, MessageContent};
 
+fn cache_point() -> Result<bedrock::CachePointBlock> {
+    Ok(bedrock::CachePointBlock::builder()
+        .r#type(bedrock::CachePointType::Default)
+        .build()?)
+}
+
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
+    to_bedrock_message_with_cache_point(message, false)
+}
+
+fn to_bedrock_message_with_cache_point(
+    message: &Message,
+    cache_point_after: bool,
+) -> Result<bedrock::Message> {
+    let mut content = message
+        .content
+        .iter()
+        .map(to_bedrock_message_content)
+        .collect::<Result<Vec<_>>>()?;
+    if cache_point_after {
+        content.push(bedrock::ContentBlock::CachePoint(cache_point()?));
+    }
     bedrock::Message::builder()
         .role(to_bedrock_role(&message.role))
-        .set_content(Some(
-            message
-                .content
-                .iter()
-                .map(to_bedrock_message_content)
-                .collect::<Result<_>>()?,
-        ))
+        .set_content(Some(content))
         .build()
         .map_err(|err| anyhow!("Failed to construct Bedrock message: {}", err))
 }
 
+/// Convert the conversation, marking the last and second-to-last user messages as cache
+/// points if `cache` is set
+///
+/// Like the Anthropic format, the latest cache point extends the cache each turn while the
+/// one before it reads from the cache written on the previous turn.
+pub fn to_bedrock_messages(messages: &[Message], cache: bool) -> Result<Vec<bedrock::Message>> {
+    let mut cached_user_messages = 0;
+    let mut converted = messages
+        .iter()
+        .rev()
+        .map(|message| {
+            let cache_point_after = cache && message.role == Role::User && cached_user_messages < 2;
+            if cache_point_after {
+                cached_user_messages += 1;
+            }
+            to_bedrock_message_with_cache_point(message, cache_point_after)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    converted.reverse();
+    Ok(converted)
+}
+
+/// The system prompt, followed by a cache point if `cache` is set
+pub fn to_bedrock_system(system: &str, cache: bool) -> Result<Vec<bedrock::SystemContentBlock>> {
+    let mut blocks = vec![bedrock::SystemContentBlock::Text(system.to_string())];
+    if cache {
+        blocks.push(bedrock::SystemContentBlock::CachePoint(cache_point()?));
+    }
+    Ok(blocks)
+}
+
 pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::ContentBlock> {
     Ok(match content {
         MessageContent::Text(text) => bedrock::ContentBlock::Text(text.text.to_string()),
@@ -137,10 +183,24 @@ pub fn to_bedrock_role(role: &Role) -> bedrock::ConversationRole {
 }
 
 pub fn to_bedrock_tool_config(tools: &[Tool]) -> Result<bedrock::ToolConfiguration> {
+    to_bedrock_tool_config_with_cache(tools, false)
+}
+
+/// The tool configuration, with a cache point after the last tool if `cache` is set so all
+/// tool definitions are cached as one prefix
+pub fn to_bedrock_tool_config_with_cache(
+    tools: &[Tool],
+    cache: bool,
+) -> Result<bedrock::ToolConfiguration> {
+    let mut bedrock_tools = tools
+        .iter()
+        .map(to_bedrock_tool)
+        .collect::<Result<Vec<_>>>()?;
+    if cache && !bedrock_tools.is_empty() {
+        bedrock_tools.push(bedrock::Tool::CachePoint(cache_point()?));
+    }
     Ok(bedrock::ToolConfiguration::builder()
-        .set_tools(Some(
-            tools.iter().map(to_bedrock_tool).collect::<Result<_>>()?,
-        ))
+        .set_tools(Some(bedrock_tools))
         .build()?)
 }
 
@@ -286,11 +346,18 @@ pub fn from_bedrock_role(role: &bedrock::ConversationRole) -> Result<Role> {
 }
 
 pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
-    Usage {
-        input_tokens: Some(usage.input_tokens),
-        output_tokens: Some(usage.output_tokens),
-        total_tokens: Some(usage.total_tokens),
-    }
+    // Bedrock leaves cached tokens out of input_tokens but counts them in total_tokens
+    let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
+    let cache_write = usage.cache_write_input_tokens.unwrap_or(0);
+    Usage::new(
+        Some(usage.input_tokens + cache_read + cache_write),
+        Some(usage.output_tokens),
+        Some(usage.total_tokens),
+    )
+    .with_cache_tokens(
+        usage.cache_read_input_tokens,
+        usage.cache_write_input_tokens,
+    )
 }
 
 pub fn from_bedrock_json(document: &Document) -> Result<Value> {
@@ -315,3 +382,90 @@ pub fn from_bedrock_json(document: &Document) -> Result<Value> {
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use serde_json::json;
+
+    fn is_cache_point(message: &bedrock::Message) -> bool {
+        matches!(
+            message.content().last(),
+            Some(bedrock::ContentBlock::CachePoint(_))
+        )
+    }
+
+    #[test]
+    fn test_prompt_caching_models() {
+        let caches = |provider: &str, model: &str| {
+            ModelConfig::new(model.to_string())
+                .with_provider(provider)
+                .capabilities()
+                .supports_prompt_caching()
+        };
+        assert!(caches(
+            "aws_bedrock",
+            "us.anthropic.claude-sonnet-4-20250514-v1:0"
+        ));
+        assert!(caches(
+            "aws_bedrock",
+            "anthropic.claude-3-7-sonnet-20250219-v1:0"
+        ));
+        assert!(!caches(
+            "aws_bedrock",
+            "anthropic.claude-3-5-sonnet-20240620-v1:0"
+        ));
+        assert!(!caches("aws_bedrock", "meta.llama3-70b-instruct-v1:0"));
+        assert!(caches("anthropic", "claude-3-5-sonnet-latest"));
+    }
+
+    #[test]
+    fn test_cache_points_on_last_two_user_messages_system_and_tools() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("one"),
+            Message::user().with_text("second"),
+            Message::assistant().with_text("two"),
+            Message::user().with_text("third"),
+        ];
+        let converted = to_bedrock_messages(&messages, true)?;
+        let marked: Vec<bool> = converted.iter().map(is_cache_point).collect();
+        assert_eq!(marked, vec![false, false, true, false, true]);
+        assert!(!to_bedrock_messages(&messages, false)?
+            .iter()
+            .any(is_cache_point));
+
+        let system = to_bedrock_system("be helpful", true)?;
+        assert!(matches!(
+            system.last(),
+            Some(bedrock::SystemContentBlock::CachePoint(_))
+        ));
+
+        let tool = Tool::new("read", "Read a file", json!({"type": "object"}), None);
+        let config = to_bedrock_tool_config_with_cache(&[tool], true)?;
+        assert_eq!(config.tools().len(), 2);
+        assert!(matches!(
+            config.tools().last(),
+            Some(bedrock::Tool::CachePoint(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_counts_cached_tokens_as_input() -> Result<()> {
+        let usage = bedrock::TokenUsage::builder()
+            .input_tokens(10)
+            .output_tokens(5)
+            .total_tokens(1015)
+            .cache_read_input_tokens(800)
+            .cache_write_input_tokens(200)
+            .build()?;
+        let usage = from_bedrock_usage(&usage);
+        assert_eq!(usage.input_tokens, Some(1010));
+        assert_eq!(usage.cache_read_input_tokens, Some(800));
+        assert_eq!(usage.cache_write_input_tokens, Some(200));
+        assert_eq!(usage.uncached_input_tokens(), Some(10));
+        Ok(())
+    }
+}
@@ -2,6 +2,10 @@ use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai::{
+    check_tool_support, is_reasoning_model, messages_for_model, split_reasoning_effort,
+};
+use crate::providers::formats::openai_responses::is_responses_item;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
     sanitize_function_name, ImageFormat,
@@ -54,6 +58,8 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                         }
                     }
                 }
+                // Reasoning from the OpenAI Responses API isn't understood by other models
+                MessageContent::Thinking(content) if is_responses_item(&content.signature) => {}
                 MessageContent::Thinking(content) => {
                     has_multiple_content = true;
                     content_array.push(json!({
@@ -67,6 +73,7 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                         ]
                     }));
                 }
+                MessageContent::RedactedThinking(content) if is_responses_item(&content.data) => {}
                 MessageContent::RedactedThinking(content) => {
                     has_multiple_content = true;
                     content_array.push(json!({
@@ -445,44 +452,21 @@ pub fn create_request(
     tools: &[Tool],
     image_format: &ImageFormat,
 ) -> anyhow::Result<Value, Error> {
-    if model_config.model_name.starts_with("o1-mini") {
-        return Err(anyhow!(
-            "o1-mini model is not currently supported since Goose uses tool calling and o1-mini does not support it. Please use o1 or o3 models instead."
-        ));
-    }
+    check_tool_support(model_config, tools)?;
 
-    let model_name = model_config.model_name.to_string();
-    let is_o1 = model_name.starts_with("o1") || model_name.starts_with("goose-o1");
-    let is_o3 = model_name.starts_with("o3") || model_name.starts_with("goose-o3");
-    let is_claude_sonnet =
-        model_name.contains("claude-3-7-sonnet") || model_name.contains("claude-4-sonnet"); // can be goose- or databricks-
-
-    // Only extract reasoning effort for O1/O3 models
-    let (model_name, reasoning_effort) = if is_o1 || is_o3 {
-        let parts: Vec<&str> = model_config.model_name.split('-').collect();
-        let last_part = parts.last().unwrap();
-
-        match *last_part {
-            "low" | "medium" | "high" => {
-                let base_name = parts[..parts.len() - 1].join("-");
-                (base_name, Some(last_part.to_string()))
-            }
-            _ => (
-                model_config.model_name.to_string(),
-                Some("medium".to_string()),
-            ),
-        }
-    } else {
-        // For non-O family models, use the model name as is and no reasoning effort
-        (model_config.model_name.to_string(), None)
-    };
+    let capabilities = model_config.capabilities();
+    let is_ox_model = is_reasoning_model(model_config);
+    // can be goose- or databricks-
+    let is_claude_thinking = capabilities.is_reasoning() && capabilities.is_family("anthropic");
+    let (model_name, reasoning_effort) = split_reasoning_effort(model_config);
 
     let system_message = json!({
-        "role": if is_o1 || is_o3 { "developer" } else { "system" },
+        "role": if is_ox_model { "developer" } else { "system" },
         "content": system
     });
 
-    let messages_spec = format_messages(messages, image_format);
+    let messages = messages_for_model(model_config, messages);
+    let messages_spec = format_messages(&messages, image_format);
     let mut tools_spec = if !tools.is_empty() {
         format_tools(tools)?
     } else {
@@ -516,7 +500,7 @@ pub fn create_request(
 
     // Add thinking parameters for Claude 3.7 Sonnet model when requested
     let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if is_claude_sonnet && is_thinking_enabled {
+    if is_claude_thinking && is_thinking_enabled {
         // Minimum budget_tokens is 1024
         let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
             .unwrap_or_else(|_| "16000".to_string())
@@ -546,7 +530,7 @@ pub fn create_request(
             .insert("temperature".to_string(), json!(2));
     } else {
         // o1, o3 models currently don't support temperature
-        if !is_o1 && !is_o3 {
+        if !is_ox_model {
             if let Some(temp) = model_config.temperature {
                 payload
                     .as_object_mut()
@@ -557,7 +541,7 @@ pub fn create_request(
 
         // o1 models use max_completion_tokens instead of max_tokens
         if let Some(tokens) = model_config.max_tokens {
-            let key = if is_o1 || is_o3 {
+            let key = if is_ox_model {
                 "max_completion_tokens"
             } else {
                 "max_tokens"
@@ -980,6 +964,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1011,6 +997,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1043,6 +1031,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -230,6 +230,10 @@ impl ModelProvider {
 
 /// Creates an Anthropic-specific Vertex AI request payload.
 ///
+/// Vertex accepts the same `cache_control` breakpoints as the Anthropic API, so the system
+/// prompt, tools and latest user messages are cached the same way, and cache reads and writes
+/// are reported in the usage.
+///
 /// # Arguments
 /// * `model_config` - Configuration for the model
 /// * `system` - System prompt
@@ -443,4 +447,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_claude_request_keeps_cache_breakpoints() -> Result<()> {
+        let model_config = ModelConfig::new("claude-sonnet-4@20250514".to_string());
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("one"),
+            Message::user().with_text("second"),
+        ];
+        let tool = Tool::new(
+            "read",
+            "Read a file",
+            serde_json::json!({"type": "object"}),
+            None,
+        );
+        let (request, _) = create_request(&model_config, "be helpful", &messages, &[tool])?;
+
+        assert!(request.get("model").is_none());
+        assert!(request["system"][0].get("cache_control").is_some());
+        assert!(request["tools"][0].get("cache_control").is_some());
+        let cached_messages = request["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|m| m["content"][0].get("cache_control").is_some())
+            .count();
+        assert_eq!(cached_messages, 2);
+        Ok(())
+    }
 }
@@ -1,8 +1,11 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
-use crate::providers::utils::{is_valid_function_name, sanitize_function_name};
+use crate::providers::streaming::{SseEvent, StreamAccumulator};
+use crate::providers::utils::{
+    is_valid_function_name, sanitize_function_name, unescape_json_values,
+};
 use anyhow::Result;
 use mcp_core::content::Content;
 use mcp_core::role::Role;
@@ -274,7 +277,13 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .get("totalTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        // Part of promptTokenCount, served from implicit or explicit context caching
+        let cached_tokens = usage_meta_data
+            .get("cachedContentTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
+        Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cached_tokens, None))
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -285,6 +294,99 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// Reassembles a `streamGenerateContent` response into the body `response_to_message` expects
+///
+/// Each event is a partial `GenerateContentResponse`: text parts arrive in pieces while
+/// function calls always arrive whole.
+#[derive(Default)]
+pub struct GoogleStreamAccumulator {
+    parts: Vec<Value>,
+    usage_metadata: Option<Value>,
+    model_version: Option<Value>,
+}
+
+impl StreamAccumulator for GoogleStreamAccumulator {
+    fn push(&mut self, event: &SseEvent) -> Result<Vec<MessageDelta>, ProviderError> {
+        let data: Value = serde_json::from_str(&event.data).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid stream chunk: {}: {}", e, event.data))
+        })?;
+        if let Some(error) = data.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("Unknown error");
+            return Err(ProviderError::RequestFailed(message.to_string()));
+        }
+
+        if let Some(usage_metadata) = data.get("usageMetadata") {
+            self.usage_metadata = Some(usage_metadata.clone());
+        }
+        if let Some(model_version) = data.get("modelVersion") {
+            self.model_version = Some(model_version.clone());
+        }
+
+        let parts = data
+            .get("candidates")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("content"))
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut deltas = Vec::new();
+        for part in parts {
+            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                // Match the unescaping `complete` applies to the whole response
+                let unescaped = unescape_json_values(&json!(text));
+                deltas.push(MessageDelta::Text {
+                    text: unescaped.as_str().unwrap_or(text).to_string(),
+                });
+                match self.parts.last_mut() {
+                    Some(last) if last.get("text").is_some() => {
+                        let existing = last["text"].as_str().unwrap_or("");
+                        last["text"] = json!(format!("{}{}", existing, text));
+                    }
+                    _ => self.parts.push(json!({"text": text})),
+                }
+            } else if let Some(function_call) = part.get("functionCall") {
+                deltas.push(MessageDelta::ToolCall {
+                    index: self.parts.len(),
+                    id: None,
+                    name: function_call
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .map(str::to_string),
+                    arguments: function_call
+                        .get("args")
+                        .map(|a| a.to_string())
+                        .unwrap_or_default(),
+                });
+                self.parts.push(part);
+            }
+        }
+        Ok(deltas)
+    }
+
+    fn finish(self) -> Result<Value, ProviderError> {
+        let mut response = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": self.parts,
+                }
+            }]
+        });
+        if let Some(usage_metadata) = self.usage_metadata {
+            response["usageMetadata"] = usage_metadata;
+        }
+        if let Some(model_version) = self.model_version {
+            response["modelVersion"] = model_version;
+        }
+        Ok(response)
+    }
+}
+
 /// Create a complete request payload for Google's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -628,4 +730,42 @@ mod tests {
 
         assert_eq!(payload, expected_payload);
     }
+
+    #[test]
+    fn test_stream_accumulator_merges_text_and_keeps_function_calls() -> Result<()> {
+        let events = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Checking"}]}}], "modelVersion": "gemini-2.5-flash"}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": " now."}]}}]}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"functionCall": {"name": "developer__shell", "args": {"command": "ls"}}}]}}], "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}}),
+        ];
+
+        let mut accumulator = GoogleStreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for event in events {
+            deltas.extend(accumulator.push(&SseEvent {
+                event: None,
+                data: event.to_string(),
+            })?);
+        }
+        assert_eq!(deltas.len(), 3);
+        assert_eq!(
+            deltas[2],
+            MessageDelta::ToolCall {
+                index: 1,
+                id: None,
+                name: Some("developer__shell".to_string()),
+                arguments: json!({"command": "ls"}).to_string(),
+            }
+        );
+
+        let response = accumulator.finish()?;
+        assert_eq!(response["modelVersion"], "gemini-2.5-flash");
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.content[0].as_text(), Some("Checking now."));
+        assert!(message.content[1].as_tool_request().is_some());
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.total_tokens, Some(15));
+        Ok(())
+    }
 }
@@ -4,4 +4,5 @@ pub mod databricks;
 pub mod gcpvertexai;
 pub mod google;
 pub mod openai;
+pub mod openai_responses;
 pub mod snowflake;
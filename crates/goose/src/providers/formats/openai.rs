@@ -1,15 +1,19 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
-use crate::providers::base::Usage;
+use crate::providers::base::{MessageDelta, Usage};
 use crate::providers::errors::ProviderError;
+use crate::providers::streaming::{SseEvent, StreamAccumulator};
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
     sanitize_function_name, ImageFormat,
 };
+use crate::providers::utils_universal_openai_stream::{OAIStreamChunk, OAIStreamCollector};
 use anyhow::{anyhow, Error};
 use mcp_core::ToolError;
 use mcp_core::{Content, Role, Tool, ToolCall};
 use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 /// Convert internal Message format to OpenAI's API message specification
 ///   some openai compatible endpoints use the anthropic image spec at the content level
@@ -233,43 +237,16 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
             for tool_call in tool_calls_array {
-                let id = tool_call["id"].as_str().unwrap_or_default().to_string();
-                let function_name = tool_call["function"]["name"]
+                let id = tool_call["id"].as_str().unwrap_or_default();
+                let function_name = tool_call["function"]["name"].as_str().unwrap_or_default();
+                let arguments = tool_call["function"]["arguments"]
                     .as_str()
-                    .unwrap_or_default()
-                    .to_string();
-                let mut arguments = tool_call["function"]["arguments"]
-                    .as_str()
-                    .unwrap_or_default()
-                    .to_string();
-                // If arguments is empty, we will have invalid json parsing error later.
-                if arguments.is_empty() {
-                    arguments = "{}".to_string();
-                }
-
-                if !is_valid_function_name(&function_name) {
-                    let error = ToolError::NotFound(format!(
-                        "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
-                        function_name
-                    ));
-                    content.push(MessageContent::tool_request(id, Err(error)));
-                } else {
-                    match serde_json::from_str::<Value>(&arguments) {
-                        Ok(params) => {
-                            content.push(MessageContent::tool_request(
-                                id,
-                                Ok(ToolCall::new(&function_name, params)),
-                            ));
-                        }
-                        Err(e) => {
-                            let error = ToolError::InvalidParameters(format!(
-                                "Could not interpret tool use parameters for id {}: {}",
-                                id, e
-                            ));
-                            content.push(MessageContent::tool_request(id, Err(error)));
-                        }
-                    }
-                }
+                    .unwrap_or_default();
+                content.push(tool_request_from_function_call(
+                    id,
+                    function_name,
+                    arguments,
+                ));
             }
         }
     }
@@ -281,6 +258,39 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     })
 }
 
+/// Convert a function call the model made into a tool request, or the error to show it
+pub fn tool_request_from_function_call(
+    id: &str,
+    function_name: &str,
+    arguments: &str,
+) -> MessageContent {
+    let id = id.to_string();
+    if !is_valid_function_name(function_name) {
+        let error = ToolError::NotFound(format!(
+            "The provided function name '{}' had invalid characters, it must match this regex [a-zA-Z0-9_-]+",
+            function_name
+        ));
+        return MessageContent::tool_request(id, Err(error));
+    }
+
+    // If arguments is empty, we will have invalid json parsing error later.
+    let arguments = if arguments.is_empty() {
+        "{}"
+    } else {
+        arguments
+    };
+    match serde_json::from_str::<Value>(arguments) {
+        Ok(params) => MessageContent::tool_request(id, Ok(ToolCall::new(function_name, params))),
+        Err(e) => {
+            let error = ToolError::InvalidParameters(format!(
+                "Could not interpret tool use parameters for id {}: {}",
+                id, e
+            ));
+            MessageContent::tool_request(id, Err(error))
+        }
+    }
+}
+
 pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
     let usage = data
         .get("usage")
@@ -305,7 +315,78 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    // Prompt tokens include the cached ones, OpenAI doesn't charge for writing to the cache
+    let cache_read_tokens = usage
+        .get("prompt_tokens_details")
+        .and_then(|details| details.get("cached_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+        .with_cache_tokens(cache_read_tokens, None))
+}
+
+/// Reassembles a streamed chat completion into the body `response_to_message` expects
+#[derive(Default)]
+pub struct OpenAiStreamAccumulator {
+    collector: OAIStreamCollector,
+    tool_call_ids: HashMap<usize, String>,
+}
+
+impl StreamAccumulator for OpenAiStreamAccumulator {
+    fn push(&mut self, event: &SseEvent) -> Result<Vec<MessageDelta>, ProviderError> {
+        if event.data == "[DONE]" {
+            return Ok(vec![]);
+        }
+
+        let value: Value = serde_json::from_str(&event.data).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid stream chunk: {}: {}", e, event.data))
+        })?;
+        if let Some(error) = value.get("error") {
+            return Err(ProviderError::ServerError {
+                details: error.to_string(),
+                retry_after: None,
+            });
+        }
+        let chunk: OAIStreamChunk = serde_json::from_value(value).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid stream chunk: {}: {}", e, event.data))
+        })?;
+
+        // Only the first choice becomes the message, so only surface its deltas
+        let mut deltas = Vec::new();
+        for choice in chunk.choices.iter().filter(|choice| choice.index == 0) {
+            if let Some(text) = choice.delta.content.as_ref().filter(|t| !t.is_empty()) {
+                deltas.push(MessageDelta::Text { text: text.clone() });
+            }
+            for tool_call in &choice.delta.tool_calls {
+                if let Some(id) = tool_call.id.as_ref().filter(|id| !id.is_empty()) {
+                    self.tool_call_ids.insert(tool_call.index, id.clone());
+                }
+                deltas.push(MessageDelta::ToolCall {
+                    index: tool_call.index,
+                    id: self.tool_call_ids.get(&tool_call.index).cloned(),
+                    name: tool_call.function.name.clone(),
+                    arguments: tool_call.function.arguments.clone(),
+                });
+            }
+        }
+
+        self.collector.add_chunk(&chunk);
+        Ok(deltas)
+    }
+
+    fn finish(self) -> Result<Value, ProviderError> {
+        serde_json::to_value(self.collector.build_response())
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))
+    }
+}
+
+/// Ask an OpenAI compatible endpoint to stream its response, including usage on the last chunk
+pub fn enable_streaming(payload: &mut Value) {
+    if let Some(obj) = payload.as_object_mut() {
+        obj.insert("stream".to_string(), json!(true));
+        obj.insert("stream_options".to_string(), json!({"include_usage": true}));
+    }
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -354,6 +435,84 @@ fn ensure_valid_json_schema(schema: &mut Value) {
     }
 }
 
+/// Whether the model is one of OpenAI's reasoning models, per the model registry
+///
+/// These take a reasoning effort and a developer message instead of a system one.
+pub fn is_reasoning_model(model_config: &ModelConfig) -> bool {
+    let capabilities = model_config.capabilities();
+    capabilities.is_reasoning() && capabilities.is_family("openai")
+}
+
+/// Errors when tools are sent to a model the registry knows can't call them
+pub fn check_tool_support(model_config: &ModelConfig, tools: &[Tool]) -> anyhow::Result<()> {
+    if !tools.is_empty() && !model_config.capabilities().supports_tools() {
+        return Err(anyhow!(
+            "{} does not support tool calling, which Goose uses. Set GOOSE_TOOLSHIM to interpret tool calls from its text instead.",
+            model_config.model_name
+        ));
+    }
+    Ok(())
+}
+
+/// The messages with images, including those in tool results, left out
+pub fn without_images(messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            message.content.retain_mut(|content| match content {
+                MessageContent::Image(_) => false,
+                MessageContent::ToolResponse(response) => {
+                    if let Ok(contents) = &mut response.tool_result {
+                        contents.retain(|content| !matches!(content, Content::Image(_)));
+                    }
+                    true
+                }
+                _ => true,
+            });
+            message
+        })
+        .collect()
+}
+
+/// The messages as they can be sent to the model, without images if it can't see
+pub fn messages_for_model<'a>(
+    model_config: &ModelConfig,
+    messages: &'a [Message],
+) -> Cow<'a, [Message]> {
+    if model_config.capabilities().supports_vision() {
+        Cow::Borrowed(messages)
+    } else {
+        Cow::Owned(without_images(messages))
+    }
+}
+
+/// The model name to send and the reasoning effort to ask for
+///
+/// A reasoning model name can end in the effort, like `o3-mini-high`, which takes precedence
+/// over the configured `reasoning_effort`. Reasoning models default to medium effort; other
+/// models never get one, since the API rejects it for them.
+pub fn split_reasoning_effort(model_config: &ModelConfig) -> (String, Option<String>) {
+    if !is_reasoning_model(model_config) {
+        return (model_config.model_name.clone(), None);
+    }
+
+    match model_config.model_name.rsplit_once('-') {
+        Some((base_name, effort @ ("low" | "medium" | "high"))) => {
+            (base_name.to_string(), Some(effort.to_string()))
+        }
+        _ => (
+            model_config.model_name.clone(),
+            Some(
+                model_config
+                    .reasoning_effort
+                    .clone()
+                    .unwrap_or_else(|| "medium".to_string()),
+            ),
+        ),
+    }
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
@@ -361,40 +520,18 @@ pub fn create_request(
     tools: &[Tool],
     image_format: &ImageFormat,
 ) -> anyhow::Result<Value, Error> {
-    if model_config.model_name.starts_with("o1-mini") {
-        return Err(anyhow!(
-            "o1-mini model is not currently supported since Goose uses tool calling and o1-mini does not support it. Please use o1 or o3 models instead."
-        ));
-    }
-
-    let is_ox_model = model_config.model_name.starts_with("o");
-
-    // Only extract reasoning effort for O1/O3 models
-    let (model_name, reasoning_effort) = if is_ox_model {
-        let parts: Vec<&str> = model_config.model_name.split('-').collect();
-        let last_part = parts.last().unwrap();
+    check_tool_support(model_config, tools)?;
 
-        match *last_part {
-            "low" | "medium" | "high" => {
-                let base_name = parts[..parts.len() - 1].join("-");
-                (base_name, Some(last_part.to_string()))
-            }
-            _ => (
-                model_config.model_name.to_string(),
-                Some("medium".to_string()),
-            ),
-        }
-    } else {
-        // For non-O family models, use the model name as is and no reasoning effort
-        (model_config.model_name.to_string(), None)
-    };
+    let is_ox_model = is_reasoning_model(model_config);
+    let (model_name, reasoning_effort) = split_reasoning_effort(model_config);
 
     let system_message = json!({
         "role": if is_ox_model { "developer" } else { "system" },
         "content": system
     });
 
-    let messages_spec = format_messages(messages, image_format);
+    let messages = messages_for_model(model_config, messages);
+    let messages_spec = format_messages(&messages, image_format);
     let mut tools_spec = if !tools.is_empty() {
         format_tools(tools)?
     } else {
@@ -858,6 +995,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -889,6 +1028,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -921,6 +1062,8 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            provider: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -942,4 +1085,92 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_reasoning_effort_only_for_reasoning_models() {
+        let config =
+            ModelConfig::new("gpt-4o".to_string()).with_reasoning_effort(Some("high".to_string()));
+        assert_eq!(
+            split_reasoning_effort(&config),
+            ("gpt-4o".to_string(), None)
+        );
+
+        let config =
+            ModelConfig::new("o3".to_string()).with_reasoning_effort(Some("low".to_string()));
+        assert_eq!(
+            split_reasoning_effort(&config),
+            ("o3".to_string(), Some("low".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_stream_accumulator_text_and_tool_call() -> anyhow::Result<()> {
+        let events = [
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Let me"}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"content":" check."}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"developer__shell","arguments":""}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"command\":\"ls\"}"}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":8,"total_tokens":20}}"#,
+            "[DONE]",
+        ];
+
+        let mut accumulator = OpenAiStreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for data in events {
+            deltas.extend(accumulator.push(&SseEvent {
+                event: None,
+                data: data.to_string(),
+            })?);
+        }
+
+        assert_eq!(
+            deltas[0],
+            MessageDelta::Text {
+                text: "Let me".to_string()
+            }
+        );
+        assert_eq!(
+            deltas[3],
+            MessageDelta::ToolCall {
+                index: 0,
+                id: Some("call_1".to_string()),
+                name: None,
+                arguments: r#"{"command":"ls"}"#.to_string(),
+            }
+        );
+
+        let response = accumulator.finish()?;
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.as_concat_text(), "Let me check.");
+        let tool_call = message.content[1]
+            .as_tool_request()
+            .unwrap()
+            .tool_call
+            .as_ref()
+            .unwrap();
+        assert_eq!(tool_call.name, "developer__shell");
+        assert_eq!(tool_call.arguments, json!({"command": "ls"}));
+
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(12));
+        assert_eq!(usage.output_tokens, Some(8));
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_images() {
+        let image = Content::image("aGk=", "image/png");
+        let messages = vec![
+            Message::user()
+                .with_text("look")
+                .with_image("aGk=", "image/png"),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("done"), image])),
+        ];
+
+        let messages = without_images(&messages);
+        assert_eq!(messages[0].content.len(), 1);
+        let response = messages[1].content[0].as_tool_response().unwrap();
+        assert_eq!(response.tool_result.as_ref().unwrap().len(), 1);
+    }
 }
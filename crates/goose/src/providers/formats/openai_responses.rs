@@ -0,0 +1,568 @@
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::providers::base::{MessageDelta, Usage};
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::openai::{
+    check_tool_support, format_tools, is_reasoning_model, messages_for_model,
+    split_reasoning_effort, tool_request_from_function_call, validate_tool_schemas,
+};
+use crate::providers::streaming::{SseEvent, StreamAccumulator};
+use crate::providers::utils::{convert_image, sanitize_function_name, ImageFormat};
+use anyhow::Error;
+use mcp_core::content::ImageContent;
+use mcp_core::{Content, Role, Tool, ToolCall, ToolResult};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Convert internal messages to input items for OpenAI's Responses API
+///
+/// Reasoning and built-in tool call items the model returned are kept in the message as
+/// thinking content holding the item's JSON, and are sent back as they were so the model
+/// can pick up its reasoning on the next turn.
+pub fn format_input(messages: &[Message]) -> Vec<Value> {
+    let mut input = Vec::new();
+    let mut skipped_calls = HashSet::new();
+
+    for message in messages {
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        let mut parts = Vec::new();
+
+        for content in &message.content {
+            match content {
+                MessageContent::Text(text) => {
+                    if !text.text.is_empty() {
+                        parts.push(text_part(&message.role, &text.text));
+                    }
+                }
+                MessageContent::Image(image) => {
+                    if message.role == Role::User {
+                        parts.push(image_part(image));
+                    }
+                }
+                MessageContent::Thinking(thinking) => {
+                    if let Some(item) = output_item(&thinking.signature) {
+                        flush_message(&mut input, role, &mut parts);
+                        input.push(item);
+                    }
+                }
+                MessageContent::RedactedThinking(redacted) => {
+                    if let Some(item) = output_item(&redacted.data) {
+                        flush_message(&mut input, role, &mut parts);
+                        input.push(item);
+                    }
+                }
+                MessageContent::ToolRequest(request) => {
+                    flush_message(&mut input, role, &mut parts);
+                    push_function_call(
+                        &mut input,
+                        &mut skipped_calls,
+                        &request.id,
+                        &request.tool_call,
+                    );
+                }
+                MessageContent::FrontendToolRequest(request) => {
+                    flush_message(&mut input, role, &mut parts);
+                    push_function_call(
+                        &mut input,
+                        &mut skipped_calls,
+                        &request.id,
+                        &request.tool_call,
+                    );
+                }
+                MessageContent::ToolResponse(response) => {
+                    flush_message(&mut input, role, &mut parts);
+                    let (output, images) = match &response.tool_result {
+                        Ok(contents) => tool_output(contents),
+                        // A tool result error is shown as output so the model can interpret the error message
+                        Err(e) => (
+                            format!("The tool call returned the following error:\n{}", e),
+                            vec![],
+                        ),
+                    };
+                    if skipped_calls.contains(&response.id) {
+                        // There is no function call for the output to answer, so tell the model in a message
+                        input.push(json!({
+                            "type": "message",
+                            "role": "user",
+                            "content": [{"type": "input_text", "text": output}],
+                        }));
+                    } else {
+                        input.push(json!({
+                            "type": "function_call_output",
+                            "call_id": response.id,
+                            "output": output,
+                        }));
+                    }
+                    if !images.is_empty() {
+                        input.push(json!({
+                            "type": "message",
+                            "role": "user",
+                            "content": images.iter().map(image_part).collect::<Vec<_>>(),
+                        }));
+                    }
+                }
+                MessageContent::ToolConfirmationRequest(_)
+                | MessageContent::ContextLengthExceeded(_)
+                | MessageContent::SummarizationRequested(_) => {}
+            }
+        }
+
+        flush_message(&mut input, role, &mut parts);
+    }
+
+    input
+}
+
+fn text_part(role: &Role, text: &str) -> Value {
+    let part_type = match role {
+        Role::User => "input_text",
+        Role::Assistant => "output_text",
+    };
+    json!({"type": part_type, "text": text})
+}
+
+fn image_part(image: &ImageContent) -> Value {
+    json!({
+        "type": "input_image",
+        "image_url": convert_image(image, &ImageFormat::OpenAi)["image_url"]["url"],
+    })
+}
+
+fn flush_message(input: &mut Vec<Value>, role: &str, parts: &mut Vec<Value>) {
+    if !parts.is_empty() {
+        input.push(json!({
+            "type": "message",
+            "role": role,
+            "content": std::mem::take(parts),
+        }));
+    }
+}
+
+fn push_function_call(
+    input: &mut Vec<Value>,
+    skipped_calls: &mut HashSet<String>,
+    id: &str,
+    tool_call: &ToolResult<ToolCall>,
+) {
+    match tool_call {
+        Ok(tool_call) => input.push(json!({
+            "type": "function_call",
+            "call_id": id,
+            "name": sanitize_function_name(&tool_call.name),
+            "arguments": tool_call.arguments.to_string(),
+        })),
+        // The model's call couldn't be read, so there is nothing valid to send back
+        Err(_) => {
+            skipped_calls.insert(id.to_string());
+        }
+    }
+}
+
+/// Marks the thinking signatures and data that hold a Responses API output item
+const RESPONSES_ITEM_PREFIX: &str = "openai-responses:";
+
+/// An output item to keep in a thinking block's signature or data, marked as ours
+fn responses_item_data(item: &Value) -> String {
+    format!("{}{}", RESPONSES_ITEM_PREFIX, item)
+}
+
+/// Whether a thinking block's signature or data holds a Responses API output item, which
+/// other providers can't read and should leave out
+pub fn is_responses_item(data: &str) -> bool {
+    data.starts_with(RESPONSES_ITEM_PREFIX)
+}
+
+/// The output item kept in a thinking block's signature or data, if it holds one
+///
+/// Thinking from other providers, like Anthropic's signatures, isn't an item and is left out.
+fn output_item(data: &str) -> Option<Value> {
+    serde_json::from_str::<Value>(data.strip_prefix(RESPONSES_ITEM_PREFIX)?)
+        .ok()
+        .filter(|item| item.get("type").and_then(|t| t.as_str()).is_some())
+}
+
+/// The text of a tool result for the model, and the images it included
+fn tool_output(contents: &[Content]) -> (String, Vec<ImageContent>) {
+    let mut texts = Vec::new();
+    let mut images = Vec::new();
+    // Send only contents with no audience or with Assistant in the audience
+    for content in contents.iter().filter(|content| {
+        content
+            .audience()
+            .is_none_or(|audience| audience.contains(&Role::Assistant))
+    }) {
+        match content.unannotated() {
+            Content::Text(text) => texts.push(text.text),
+            Content::Image(image) => {
+                texts.push(
+                    "This tool result included an image that is uploaded in the next message."
+                        .to_string(),
+                );
+                images.push(image);
+            }
+            Content::Resource(resource) => texts.push(resource.get_text()),
+        }
+    }
+    (texts.join(" "), images)
+}
+
+/// Convert internal tools to Responses API function tools
+pub fn format_responses_tools(tools: &[Tool]) -> anyhow::Result<Vec<Value>> {
+    let mut tools_spec = format_tools(tools)?;
+    validate_tool_schemas(&mut tools_spec);
+    Ok(tools_spec
+        .into_iter()
+        .map(|mut tool| {
+            let mut function = tool["function"].take();
+            function["type"] = json!("function");
+            function
+        })
+        .collect())
+}
+
+/// Convert a Responses API response to internal Message format
+pub fn responses_to_message(response: &Value) -> anyhow::Result<Message> {
+    let mut content = Vec::new();
+
+    for item in response["output"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    let text = match part["type"].as_str() {
+                        Some("output_text") => part["text"].as_str(),
+                        Some("refusal") => part["refusal"].as_str(),
+                        _ => None,
+                    };
+                    if let Some(text) = text {
+                        content.push(MessageContent::text(text));
+                    }
+                }
+            }
+            Some("function_call") => {
+                content.push(tool_request_from_function_call(
+                    item["call_id"].as_str().unwrap_or_default(),
+                    item["name"].as_str().unwrap_or_default(),
+                    item["arguments"].as_str().unwrap_or_default(),
+                ));
+            }
+            Some("reasoning") => {
+                let summary: Vec<&str> = item["summary"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| part["text"].as_str())
+                    .collect();
+                if summary.is_empty() {
+                    content.push(MessageContent::redacted_thinking(responses_item_data(item)));
+                } else {
+                    content.push(MessageContent::thinking(
+                        summary.join("\n\n"),
+                        responses_item_data(item),
+                    ));
+                }
+            }
+            // Built-in tool calls like web_search_call ran on OpenAI's side, keep them so
+            // they go back with the conversation
+            Some(_) => content.push(MessageContent::redacted_thinking(responses_item_data(item))),
+            None => {}
+        }
+    }
+
+    Ok(Message {
+        role: Role::Assistant,
+        created: chrono::Utc::now().timestamp(),
+        content,
+    })
+}
+
+pub fn get_responses_usage(data: &Value) -> Result<Usage, ProviderError> {
+    let usage = data
+        .get("usage")
+        .filter(|usage| !usage.is_null())
+        .ok_or_else(|| ProviderError::UsageError("No usage data in response".to_string()))?;
+
+    let input_tokens = usage
+        .get("input_tokens")
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    let output_tokens = usage
+        .get("output_tokens")
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    let total_tokens = usage
+        .get("total_tokens")
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32)
+        .or_else(|| match (input_tokens, output_tokens) {
+            (Some(input), Some(output)) => Some(input + output),
+            _ => None,
+        });
+
+    let cache_read_tokens = usage
+        .get("input_tokens_details")
+        .and_then(|details| details.get("cached_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+        .with_cache_tokens(cache_read_tokens, None))
+}
+
+/// Create a Responses API request
+///
+/// Nothing is stored on OpenAI's side; reasoning comes back encrypted so it can be sent
+/// with the next request instead.
+pub fn create_responses_request(
+    model_config: &ModelConfig,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> anyhow::Result<Value, Error> {
+    check_tool_support(model_config, tools)?;
+    let (model_name, reasoning_effort) = split_reasoning_effort(model_config);
+    let messages = messages_for_model(model_config, messages);
+
+    let mut payload = json!({
+        "model": model_name,
+        "instructions": system,
+        "input": format_input(&messages),
+        "store": false,
+    });
+    let obj = payload.as_object_mut().unwrap();
+
+    let tools_spec = format_responses_tools(tools)?;
+    if !tools_spec.is_empty() {
+        obj.insert("tools".to_string(), json!(tools_spec));
+    }
+
+    if let Some(effort) = reasoning_effort {
+        obj.insert(
+            "reasoning".to_string(),
+            json!({"effort": effort, "summary": "auto"}),
+        );
+        obj.insert(
+            "include".to_string(),
+            json!(["reasoning.encrypted_content"]),
+        );
+    }
+
+    // Reasoning models don't support temperature
+    if !is_reasoning_model(model_config) {
+        if let Some(temp) = model_config.temperature {
+            obj.insert("temperature".to_string(), json!(temp));
+        }
+    }
+
+    if let Some(tokens) = model_config.max_tokens {
+        obj.insert("max_output_tokens".to_string(), json!(tokens));
+    }
+    Ok(payload)
+}
+
+/// Collects a streamed Responses API response
+///
+/// The final `response.completed` event carries the whole response, so it is returned as
+/// is; the other events are only turned into deltas.
+#[derive(Default)]
+pub struct ResponsesStreamAccumulator {
+    response: Option<Value>,
+    call_ids: HashMap<usize, String>,
+}
+
+impl StreamAccumulator for ResponsesStreamAccumulator {
+    fn push(&mut self, event: &SseEvent) -> Result<Vec<MessageDelta>, ProviderError> {
+        let value: Value = serde_json::from_str(&event.data).map_err(|e| {
+            ProviderError::RequestFailed(format!("Invalid stream event: {}: {}", e, event.data))
+        })?;
+        let output_index = value["output_index"].as_u64().unwrap_or_default() as usize;
+        let delta = value["delta"].as_str().unwrap_or_default().to_string();
+
+        let deltas = match value["type"].as_str().unwrap_or_default() {
+            "response.output_text.delta" if !delta.is_empty() => {
+                vec![MessageDelta::Text { text: delta }]
+            }
+            "response.reasoning_summary_text.delta" if !delta.is_empty() => {
+                vec![MessageDelta::Thinking { thinking: delta }]
+            }
+            "response.output_item.added" if value["item"]["type"] == "function_call" => {
+                let item = &value["item"];
+                let call_id = item["call_id"].as_str().unwrap_or_default().to_string();
+                self.call_ids.insert(output_index, call_id.clone());
+                vec![MessageDelta::ToolCall {
+                    index: output_index,
+                    id: Some(call_id),
+                    name: item["name"].as_str().map(str::to_string),
+                    arguments: item["arguments"].as_str().unwrap_or_default().to_string(),
+                }]
+            }
+            "response.function_call_arguments.delta" => vec![MessageDelta::ToolCall {
+                index: output_index,
+                id: self.call_ids.get(&output_index).cloned(),
+                name: None,
+                arguments: delta,
+            }],
+            "response.completed" | "response.incomplete" => {
+                self.response = Some(value["response"].clone());
+                vec![]
+            }
+            "response.failed" => {
+                return Err(ProviderError::ServerError {
+                    details: value["response"]["error"].to_string(),
+                    retry_after: None,
+                })
+            }
+            "error" => {
+                return Err(ProviderError::ServerError {
+                    details: value.to_string(),
+                    retry_after: None,
+                })
+            }
+            _ => vec![],
+        };
+        Ok(deltas)
+    }
+
+    fn finish(self) -> Result<Value, ProviderError> {
+        self.response.ok_or_else(|| {
+            ProviderError::RequestFailed("The stream ended before the response completed".into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_input() {
+        let reasoning = json!({
+            "type": "reasoning",
+            "id": "rs_1",
+            "summary": [{"type": "summary_text", "text": "Need the weather"}],
+            "encrypted_content": "abc",
+        });
+        let messages = vec![
+            Message::user().with_text("What's the weather?"),
+            Message::assistant()
+                .with_thinking("Need the weather", responses_item_data(&reasoning))
+                .with_tool_request(
+                    "call_1",
+                    Ok(ToolCall::new("weather", json!({"city": "Paris"}))),
+                ),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("Sunny")])),
+            Message::assistant()
+                .with_thinking("Anthropic thinking", "not-an-item")
+                .with_text("It's sunny."),
+        ];
+
+        let input = format_input(&messages);
+        assert_eq!(
+            input,
+            vec![
+                json!({"type": "message", "role": "user", "content": [{"type": "input_text", "text": "What's the weather?"}]}),
+                reasoning,
+                json!({"type": "function_call", "call_id": "call_1", "name": "weather", "arguments": "{\"city\":\"Paris\"}"}),
+                json!({"type": "function_call_output", "call_id": "call_1", "output": "Sunny"}),
+                json!({"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "It's sunny."}]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_responses_to_message_round_trips_reasoning() -> anyhow::Result<()> {
+        let response = json!({
+            "model": "o3-2025-04-16",
+            "output": [
+                {"type": "reasoning", "id": "rs_1", "summary": [], "encrypted_content": "abc"},
+                {"type": "web_search_call", "id": "ws_1", "status": "completed"},
+                {"type": "message", "role": "assistant", "content": [
+                    {"type": "output_text", "text": "Checking.", "annotations": []}
+                ]},
+                {"type": "function_call", "call_id": "call_1", "name": "weather", "arguments": "{\"city\":\"Paris\"}"},
+            ],
+            "usage": {
+                "input_tokens": 100,
+                "input_tokens_details": {"cached_tokens": 40},
+                "output_tokens": 20,
+                "total_tokens": 120,
+            },
+        });
+
+        let message = responses_to_message(&response)?;
+        assert_eq!(message.content.len(), 4);
+        assert!(message.content[0].as_redacted_thinking().is_some());
+        assert_eq!(message.as_concat_text(), "Checking.");
+        let request = message.content[3].as_tool_request().unwrap();
+        assert_eq!(request.id, "call_1");
+        assert_eq!(request.tool_call.as_ref().unwrap().name, "weather");
+
+        // The reasoning and built-in tool call go back as they came
+        let input = format_input(&[message]);
+        assert_eq!(input[0], response["output"][0]);
+        assert_eq!(input[1], response["output"][1]);
+
+        let usage = get_responses_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(100));
+        assert_eq!(usage.output_tokens, Some(20));
+        assert_eq!(usage.cache_read_input_tokens, Some(40));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_responses_request() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("o3".to_string())
+            .with_reasoning_effort(Some("high".to_string()))
+            .with_max_tokens(Some(1024));
+        let tool = Tool::new(
+            "weather",
+            "Get the weather",
+            json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+            None,
+        );
+
+        let request = create_responses_request(&model_config, "system", &[], &[tool])?;
+        assert_eq!(request["model"], "o3");
+        assert_eq!(request["instructions"], "system");
+        assert_eq!(request["store"], false);
+        assert_eq!(request["reasoning"]["effort"], "high");
+        assert_eq!(request["include"], json!(["reasoning.encrypted_content"]));
+        assert_eq!(request["max_output_tokens"], 1024);
+        assert_eq!(request["tools"][0]["type"], "function");
+        assert_eq!(request["tools"][0]["name"], "weather");
+        assert_eq!(request["tools"][0]["parameters"]["required"], json!([]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_accumulator() -> anyhow::Result<()> {
+        let events = [
+            json!({"type": "response.reasoning_summary_text.delta", "output_index": 0, "delta": "Hmm"}),
+            json!({"type": "response.output_item.added", "output_index": 1, "item": {"type": "function_call", "call_id": "call_1", "name": "weather", "arguments": ""}}),
+            json!({"type": "response.function_call_arguments.delta", "output_index": 1, "delta": "{}"}),
+            json!({"type": "response.completed", "response": {"output": [], "usage": null}}),
+        ];
+        let mut accumulator = ResponsesStreamAccumulator::default();
+        let mut deltas = Vec::new();
+        for event in events {
+            deltas.extend(accumulator.push(&SseEvent {
+                event: None,
+                data: event.to_string(),
+            })?);
+        }
+
+        assert_eq!(deltas.len(), 3);
+        assert!(matches!(&deltas[0], MessageDelta::Thinking { thinking } if thinking == "Hmm"));
+        assert!(matches!(
+            &deltas[2],
+            MessageDelta::ToolCall { index: 1, id: Some(id), arguments, .. }
+                if id == "call_1" && arguments == "{}"
+        ));
+        assert_eq!(accumulator.finish()?, json!({"output": [], "usage": null}));
+        Ok(())
+    }
+}
@@ -297,7 +297,10 @@ impl GcpVertexAIProvider {
                     self.retry_config.max_retries
                 );
                 tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
+                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded {
+                    details: error_msg,
+                    retry_after: None,
+                }));
             }
 
             // Get a fresh auth token for each attempt
@@ -366,7 +369,10 @@ impl GcpVertexAIProvider {
             );
 
             // Store the error in case we need to return it after max retries
-            last_error = Some(ProviderError::RateLimitExceeded(quota_error));
+            last_error = Some(ProviderError::RateLimitExceeded {
+                details: quota_error,
+                retry_after: None,
+            });
 
             // Calculate and apply the backoff delay
             let delay = self.retry_config.delay_for_attempt(attempts);
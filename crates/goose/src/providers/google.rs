@@ -1,10 +1,14 @@
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
-use crate::providers::formats::google::{create_request, get_usage, response_to_message};
+use crate::model_registry::{self, ModelCapabilities};
+use crate::providers::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::formats::google::{
+    create_request, get_usage, response_to_message, GoogleStreamAccumulator,
+};
+use crate::providers::streaming::{check_stream_response, stream_response};
 use crate::providers::utils::{
-    emit_debug_trace, handle_response_google_compat, unescape_json_values,
+    finish_response, handle_response_google_compat, unescape_json_values,
 };
 use anyhow::Result;
 use async_trait::async_trait;
@@ -86,18 +90,22 @@ impl GoogleProvider {
         })
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    fn url(&self, method: &str) -> Result<Url, ProviderError> {
         let base_url = Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
 
-        let url = base_url
+        base_url
             .join(&format!(
-                "v1beta/models/{}:generateContent",
-                self.model.model_name
+                "v1beta/models/{}:{}",
+                self.model.model_name, method
             ))
             .map_err(|e| {
                 ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
-            })?;
+            })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let url = self.url("generateContent")?;
 
         let max_retries = 3;
         let mut retries = 0;
@@ -115,12 +123,14 @@ impl GoogleProvider {
                 Ok(res) => {
                     match handle_response_google_compat(res).await {
                         Ok(result) => return Ok(result),
-                        Err(ProviderError::RateLimitExceeded(_)) => {
+                        Err(ProviderError::RateLimitExceeded { .. }) => {
                             retries += 1;
                             if retries > max_retries {
-                                return Err(ProviderError::RateLimitExceeded(
-                                    "Max retries exceeded for rate limit error".to_string(),
-                                ));
+                                return Err(ProviderError::RateLimitExceeded {
+                                    details: "Max retries exceeded for rate limit error"
+                                        .to_string(),
+                                    retry_after: None,
+                                });
                             }
 
                             let delay = 2u64.pow(retries);
@@ -142,6 +152,32 @@ impl GoogleProvider {
             }
         }
     }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = create_request(model, system, messages, tools)?;
+
+        // Make request
+        let response = self.post(payload.clone()).await?;
+
+        // Parse response
+        finish_response(
+            model,
+            &payload,
+            response,
+            |response| response_to_message(unescape_json_values(&response)),
+            get_usage,
+        )
+    }
 }
 
 #[async_trait]
@@ -165,31 +201,62 @@ impl Provider for GoogleProvider {
         self.model.clone()
     }
 
-    #[tracing::instrument(
-        skip(self, system, messages, tools),
-        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
-    )]
     async fn complete(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.model.capped_at(max_tokens);
+        self.complete_with_model(&model, system, messages, tools)
+            .await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
         let payload = create_request(&self.model, system, messages, tools)?;
 
-        // Make request
-        let response = self.post(payload.clone()).await?;
+        let response = self
+            .client
+            .post(self.url("streamGenerateContent?alt=sse")?)
+            .json(&payload)
+            .send()
+            .await?;
+        let response = check_stream_response(response, handle_response_google_compat).await?;
 
-        // Parse response
-        let message = response_to_message(unescape_json_values(&response))?;
-        let usage = get_usage(&response)?;
-        let model = match response.get("modelVersion") {
-            Some(model_version) => model_version.as_str().unwrap_or_default().to_string(),
-            None => self.model.model_name.clone(),
-        };
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        let provider_usage = ProviderUsage::new(model, usage);
-        Ok((message, provider_usage))
+        let model_config = self.model.clone();
+        Ok(stream_response(
+            response,
+            GoogleStreamAccumulator::default(),
+            move |response| {
+                finish_response(
+                    &model_config,
+                    &payload,
+                    response,
+                    |response| response_to_message(unescape_json_values(&response)),
+                    get_usage,
+                )
+            },
+        ))
     }
 
     /// Fetch supported models from Google Generative Language API; returns Err on failure, Ok(None) if not present
@@ -205,8 +272,21 @@ impl Provider for GoogleProvider {
         };
         let mut models: Vec<String> = arr
             .iter()
-            .filter_map(|m| m.get("name").and_then(|v| v.as_str()))
-            .map(|name| name.split('/').next_back().unwrap_or(name).to_string())
+            .filter_map(|m| {
+                let name = m.get("name").and_then(|v| v.as_str())?;
+                let name = name.split('/').next_back().unwrap_or(name).to_string();
+                model_registry::refine(
+                    "google",
+                    &name,
+                    ModelCapabilities {
+                        context_limit: m["inputTokenLimit"].as_u64().map(|t| t as usize),
+                        max_output_tokens: m["outputTokenLimit"].as_i64().map(|t| t as i32),
+                        reasoning: m["thinking"].as_bool(),
+                        ..Default::default()
+                    },
+                );
+                Some(name)
+            })
             .collect();
         models.sort();
         Ok(Some(models))
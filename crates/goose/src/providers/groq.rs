@@ -1,9 +1,10 @@
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::model_registry::{self, ModelCapabilities};
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
-use crate::providers::utils::get_model;
+use crate::providers::utils::{get_model, retry_after};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::Tool;
@@ -70,6 +71,7 @@ impl GroqProvider {
             .await?;
 
         let status = response.status();
+        let retry_after = retry_after(&response);
         let payload: Option<Value> = response.json().await.ok();
 
         match status {
@@ -82,10 +84,16 @@ impl GroqProvider {
                 Err(ProviderError::ContextLengthExceeded(format!("{:?}", payload)))
             }
             StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+                Err(ProviderError::RateLimitExceeded {
+                    details: format!("{:?}", payload),
+                    retry_after,
+                })
             }
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-                Err(ProviderError::ServerError(format!("{:?}", payload)))
+                Err(ProviderError::ServerError {
+                    details: format!("{:?}", payload),
+                    retry_after,
+                })
             }
             _ => {
                 tracing::debug!(
@@ -195,7 +203,18 @@ impl Provider for GroqProvider {
 
             let mut model_names: Vec<String> = data
                 .iter()
-                .filter_map(|m| m.get("id").and_then(Value::as_str).map(String::from))
+                .filter_map(|m| {
+                    let id = m.get("id").and_then(Value::as_str)?;
+                    model_registry::refine(
+                        "groq",
+                        id,
+                        ModelCapabilities {
+                            context_limit: m["context_window"].as_u64().map(|t| t as usize),
+                            ..Default::default()
+                        },
+                    );
+                    Some(id.to_string())
+                })
                 .collect();
             model_names.sort();
             Ok(Some(model_names))
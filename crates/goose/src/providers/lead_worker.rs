@@ -407,6 +407,20 @@ impl Provider for LeadWorkerProvider {
         final_result
     }
 
+    /// Uses whichever model is active without counting the request as a turn
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.get_active_provider()
+            .await
+            .complete_with_max_tokens(system, messages, tools, max_tokens)
+            .await
+    }
+
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
         // Combine models from both providers
         let lead_models = self.lead_provider.fetch_supported_models_async().await?;
@@ -443,6 +457,14 @@ impl Provider for LeadWorkerProvider {
         }
     }
 
+    fn usage_provider(&self, usage: &ProviderUsage) -> Option<String> {
+        if usage.model == self.worker_provider.get_model_config().model_name {
+            self.worker_provider.usage_provider(usage)
+        } else {
+            self.lead_provider.usage_provider(usage)
+        }
+    }
+
     /// Check if this provider is a LeadWorkerProvider
     fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
         Some(self)
@@ -533,6 +555,27 @@ mod tests {
         assert_eq!(usage.model, "lead");
     }
 
+    #[test]
+    fn test_usage_provider_names_the_serving_provider() {
+        let lead_provider = Arc::new(MockProvider {
+            name: "lead".to_string(),
+            model_config: ModelConfig::new("lead-model".to_string()).with_provider("anthropic"),
+        });
+        let worker_provider = Arc::new(MockProvider {
+            name: "worker".to_string(),
+            model_config: ModelConfig::new("worker-model".to_string()).with_provider("openai"),
+        });
+        let provider = LeadWorkerProvider::new(lead_provider, worker_provider, Some(3));
+
+        let usage = ProviderUsage::new("worker-model".to_string(), Usage::default());
+        assert_eq!(provider.usage_provider(&usage).as_deref(), Some("openai"));
+        let usage = ProviderUsage::new("lead-model".to_string(), Usage::default());
+        assert_eq!(
+            provider.usage_provider(&usage).as_deref(),
+            Some("anthropic")
+        );
+    }
+
     #[tokio::test]
     async fn test_technical_failure_retry() {
         let lead_provider = Arc::new(MockFailureProvider {
@@ -4,10 +4,12 @@ pub mod azureauth;
 pub mod base;
 pub mod bedrock;
 pub mod claude_code;
+pub mod custom;
 pub mod databricks;
 pub mod embedding;
 pub mod errors;
 mod factory;
+pub mod fallback;
 pub mod formats;
 mod gcpauth;
 pub mod gcpvertexai;
@@ -21,8 +23,13 @@ pub mod ollama;
 pub mod openai;
 pub mod openrouter;
 pub mod pricing;
+pub mod replay;
+pub mod retry;
+pub mod routing;
 pub mod sagemaker_tgi;
+pub mod scripted;
 pub mod snowflake;
+pub mod streaming;
 pub mod toolshim;
 pub mod utils;
 pub mod utils_universal_openai_stream;
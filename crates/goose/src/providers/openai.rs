@@ -5,11 +5,17 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::time::Duration;
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::formats::openai::{
+    create_request, enable_streaming, get_usage, response_to_message, OpenAiStreamAccumulator,
+};
+use super::formats::openai_responses::{
+    create_responses_request, get_responses_usage, responses_to_message, ResponsesStreamAccumulator,
+};
+use super::streaming::{check_stream_response, stream_response};
+use super::utils::{finish_response, handle_response_openai_compat, ImageFormat};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -38,6 +44,7 @@ pub struct OpenAiProvider {
     project: Option<String>,
     model: ModelConfig,
     custom_headers: Option<HashMap<String, String>>,
+    responses_models: Vec<String>,
 }
 
 impl Default for OpenAiProvider {
@@ -64,6 +71,10 @@ impl OpenAiProvider {
             .or_else(|_| config.get_param("OPENAI_CUSTOM_HEADERS"))
             .ok()
             .map(parse_custom_headers);
+        let responses_models = config
+            .get_param::<String>("OPENAI_RESPONSES_MODELS")
+            .map(|models| parse_model_list(&models))
+            .unwrap_or_default();
         let timeout_secs: u64 = config.get_param("OPENAI_TIMEOUT").unwrap_or(600);
         let client = Client::builder()
             .timeout(Duration::from_secs(timeout_secs))
@@ -78,9 +89,33 @@ impl OpenAiProvider {
             project,
             model,
             custom_headers,
+            responses_models,
         })
     }
 
+    /// Whether the configured model is listed in `OPENAI_RESPONSES_MODELS`, so requests go
+    /// to the Responses API instead of Chat Completions
+    ///
+    /// An entry matches the model of the same name and its dated snapshots, so `o3` also
+    /// covers `o3-2025-04-16`.
+    fn uses_responses_api(&self) -> bool {
+        let model_name = &self.model.model_name;
+        self.responses_models.iter().any(|entry| {
+            model_name == entry
+                || model_name
+                    .strip_prefix(entry.as_str())
+                    .is_some_and(|rest| rest.starts_with('-'))
+        })
+    }
+
+    /// The Responses API path next to the configured Chat Completions one
+    fn responses_path(&self) -> String {
+        match self.base_path.strip_suffix("chat/completions") {
+            Some(prefix) => format!("{}responses", prefix),
+            None => "v1/responses".to_string(),
+        }
+    }
+
     /// Helper function to add OpenAI-specific headers to a request
     fn add_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
         // Add organization header if present
@@ -103,10 +138,10 @@ impl OpenAiProvider {
         request
     }
 
-    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+    async fn send(&self, path: &str, payload: &Value) -> Result<reqwest::Response, ProviderError> {
         let base_url = url::Url::parse(&self.host)
             .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-        let url = base_url.join(&self.base_path).map_err(|e| {
+        let url = base_url.join(path).map_err(|e| {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
@@ -117,10 +152,46 @@ impl OpenAiProvider {
 
         let request = self.add_headers(request);
 
-        let response = request.json(&payload).send().await?;
+        Ok(request.json(payload).send().await?)
+    }
+
+    async fn post(&self, path: &str, payload: Value) -> Result<Value, ProviderError> {
+        let response = self.send(path, &payload).await?;
 
         handle_response_openai_compat(response).await
     }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_model(
+        &self,
+        model: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        if self.uses_responses_api() {
+            let payload = create_responses_request(model, system, messages, tools)?;
+            let response = self.post(&self.responses_path(), payload.clone()).await?;
+            return finish_response(
+                model,
+                &payload,
+                response,
+                |response| responses_to_message(&response),
+                get_responses_usage,
+            );
+        }
+
+        let payload = create_request(model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        // Make request
+        let response = self.post(&self.base_path, payload.clone()).await?;
+
+        // Parse response
+        finish_response(model, &payload, response, response_to_message, get_usage)
+    }
 }
 
 #[async_trait]
@@ -149,6 +220,7 @@ impl Provider for OpenAiProvider {
                 ConfigKey::new("OPENAI_PROJECT", false, false, None),
                 ConfigKey::new("OPENAI_CUSTOM_HEADERS", false, true, None),
                 ConfigKey::new("OPENAI_TIMEOUT", false, false, Some("600")),
+                ConfigKey::new("OPENAI_RESPONSES_MODELS", false, false, None),
             ],
         )
     }
@@ -157,34 +229,82 @@ impl Provider for OpenAiProvider {
         self.model.clone()
     }
 
-    #[tracing::instrument(
-        skip(self, system, messages, tools),
-        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
-    )]
     async fn complete(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<(Message, ProviderUsage), ProviderError> {
-        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        self.complete_with_model(&self.model, system, messages, tools)
+            .await
+    }
 
-        // Make request
-        let response = self.post(payload.clone()).await?;
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.model.capped_at(max_tokens);
+        self.complete_with_model(&model, system, messages, tools)
+            .await
+    }
 
-        // Parse response
-        let message = response_to_message(response.clone())?;
-        let usage = match get_usage(&response) {
-            Ok(usage) => usage,
-            Err(ProviderError::UsageError(e)) => {
-                tracing::debug!("Failed to get usage data: {}", e);
-                Usage::default()
-            }
-            Err(e) => return Err(e),
-        };
-        let model = get_model(&response);
-        emit_debug_trace(&self.model, &payload, &response, &usage);
-        Ok((message, ProviderUsage::new(model, usage)))
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        if self.uses_responses_api() {
+            let mut payload = create_responses_request(&self.model, system, messages, tools)?;
+            payload["stream"] = Value::Bool(true);
+
+            let response = self.send(&self.responses_path(), &payload).await?;
+            let response = check_stream_response(response, handle_response_openai_compat).await?;
+
+            let model_config = self.model.clone();
+            return Ok(stream_response(
+                response,
+                ResponsesStreamAccumulator::default(),
+                move |response| {
+                    finish_response(
+                        &model_config,
+                        &payload,
+                        response,
+                        |response| responses_to_message(&response),
+                        get_responses_usage,
+                    )
+                },
+            ));
+        }
+
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        enable_streaming(&mut payload);
+
+        let response = self.send(&self.base_path, &payload).await?;
+        let response = check_stream_response(response, handle_response_openai_compat).await?;
+
+        let model_config = self.model.clone();
+        Ok(stream_response(
+            response,
+            OpenAiStreamAccumulator::default(),
+            move |response| {
+                finish_response(
+                    &model_config,
+                    &payload,
+                    response,
+                    response_to_message,
+                    get_usage,
+                )
+            },
+        ))
     }
 
     /// Fetch supported models from OpenAI; returns Err on any failure, Ok(None) if no data
@@ -238,6 +358,13 @@ impl Provider for OpenAiProvider {
     }
 }
 
+fn parse_model_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty())
+        .collect()
+}
+
 fn parse_custom_headers(s: String) -> HashMap<String, String> {
     s.split(',')
         .filter_map(|header| {
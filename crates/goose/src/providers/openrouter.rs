@@ -114,8 +114,18 @@ impl OpenRouterProvider {
             // Return appropriate error based on the OpenRouter error code
             match error_code {
                 401 | 403 => return Err(ProviderError::Authentication(error_message.to_string())),
-                429 => return Err(ProviderError::RateLimitExceeded(error_message.to_string())),
-                500 | 503 => return Err(ProviderError::ServerError(error_message.to_string())),
+                429 => {
+                    return Err(ProviderError::RateLimitExceeded {
+                        details: error_message.to_string(),
+                        retry_after: None,
+                    })
+                }
+                500 | 503 => {
+                    return Err(ProviderError::ServerError {
+                        details: error_message.to_string(),
+                        retry_after: None,
+                    })
+                }
                 _ => return Err(ProviderError::RequestFailed(error_message.to_string())),
             }
         }
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -7,6 +9,9 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+use super::base::{ProviderUsage, Usage};
+use crate::config::Config;
+
 /// Disk cache configuration
 const CACHE_FILE_NAME: &str = "pricing_cache.json";
 const CACHE_TTL_DAYS: u64 = 7; // Cache for 7 days
@@ -38,6 +43,25 @@ pub struct PricingInfo {
     pub input_cost: f64,  // Cost per token
     pub output_cost: f64, // Cost per token
     pub context_length: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_cost: Option<f64>, // Cost per token read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_cost: Option<f64>, // Cost per token written to the prompt cache
+}
+
+impl PricingInfo {
+    /// The cost of `usage`, charging cached input at the cache rates
+    ///
+    /// Cached tokens fall back to the regular input rate when the model has no cache pricing.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let tokens = |count: Option<i32>| count.unwrap_or(0).max(0) as f64;
+        tokens(usage.uncached_input_tokens()) * self.input_cost
+            + tokens(usage.cache_read_input_tokens)
+                * self.cache_read_cost.unwrap_or(self.input_cost)
+            + tokens(usage.cache_write_input_tokens)
+                * self.cache_write_cost.unwrap_or(self.input_cost)
+            + tokens(usage.output_tokens) * self.output_cost
+    }
 }
 
 /// Cache for OpenRouter pricing data with disk persistence
@@ -160,6 +184,16 @@ impl PricingCache {
                             input_cost,
                             output_cost,
                             context_length: model.context_length,
+                            cache_read_cost: model
+                                .pricing
+                                .input_cache_read
+                                .as_deref()
+                                .and_then(convert_pricing),
+                            cache_write_cost: model
+                                .pricing
+                                .input_cache_write
+                                .as_deref()
+                                .and_then(convert_pricing),
                         },
                     );
                 }
@@ -253,6 +287,10 @@ pub struct OpenRouterModel {
 pub struct OpenRouterPricing {
     pub prompt: String,     // Cost per token for input (in USD)
     pub completion: String, // Cost per token for output (in USD)
+    #[serde(default)]
+    pub input_cache_read: Option<String>, // Cost per token read from the prompt cache
+    #[serde(default)]
+    pub input_cache_write: Option<String>, // Cost per token written to the prompt cache
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -327,6 +365,83 @@ pub async fn get_all_pricing() -> HashMap<String, HashMap<String, PricingInfo>>
     }
 }
 
+/// Pricing for `model`, from the `model_pricing` config overrides or else the pricing cache
+///
+/// Overrides map model names to costs per token, for models the cache doesn't know or when
+/// it can't be fetched. Cached models under `provider` are preferred over other providers'.
+/// Names match exactly, or as a prefix of a dated or versioned model name.
+pub async fn find_model_pricing(provider: Option<&str>, model: &str) -> Option<PricingInfo> {
+    let overrides: HashMap<String, PricingInfo> = Config::global()
+        .get_param("model_pricing")
+        .unwrap_or_default();
+    if let Some(pricing) = best_pricing_match(&overrides, model) {
+        return Some(pricing.clone());
+    }
+
+    let all_pricing = get_all_pricing().await;
+    provider
+        .and_then(|provider| all_pricing.get(&provider.to_lowercase()))
+        .and_then(|models| best_pricing_match(models, model))
+        .or_else(|| {
+            all_pricing
+                .values()
+                .filter_map(|models| best_pricing_match(models, model))
+                .next()
+        })
+        .cloned()
+}
+
+/// The cost of one provider call, if the model's pricing is known
+///
+/// `provider` is the one that served the call, see [`Provider::usage_provider`]. Without it
+/// the configured `GOOSE_PROVIDER` is assumed.
+///
+/// [`Provider::usage_provider`]: crate::providers::base::Provider::usage_provider
+pub async fn usage_cost(provider: Option<&str>, usage: &ProviderUsage) -> Option<f64> {
+    let provider: Option<String> = provider
+        .map(str::to_string)
+        .or_else(|| Config::global().get_param("GOOSE_PROVIDER").ok());
+    let pricing = find_model_pricing(provider.as_deref(), &usage.model).await?;
+    Some(pricing.cost(&usage.usage))
+}
+
+/// A Bedrock model id's region and vendor prefix, such as `us.anthropic.`, and its version
+/// suffix, such as `-v1:0`
+static BEDROCK_MODEL_ID_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"^(?:(?:us|eu|apac|us-gov|global)\.)?",
+        r"(?:anthropic|meta|amazon|mistral|cohere|ai21|deepseek)\.",
+        r"(.+?)(?:-v\d+(?::\d+)?)?$"
+    ))
+    .unwrap()
+});
+
+fn normalize_model_name(name: &str) -> String {
+    let name = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+    let name = match BEDROCK_MODEL_ID_RE.captures(&name) {
+        Some(captures) => captures[1].to_string(),
+        None => name,
+    };
+    name.replace('.', "-")
+}
+
+/// The pricing whose model name matches `model` most closely
+fn best_pricing_match<'a>(
+    models: &'a HashMap<String, PricingInfo>,
+    model: &str,
+) -> Option<&'a PricingInfo> {
+    let model = normalize_model_name(model);
+    models
+        .iter()
+        .filter_map(|(name, pricing)| {
+            let name = normalize_model_name(name);
+            let matches = model == name || model.starts_with(&format!("{}-", name));
+            matches.then_some((name.len(), pricing))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, pricing)| pricing)
+}
+
 /// Convert OpenRouter model ID to provider/model format
 /// e.g., "anthropic/claude-3.5-sonnet" -> ("anthropic", "claude-3.5-sonnet")
 pub fn parse_model_id(model_id: &str) -> Option<(String, String)> {
@@ -384,6 +499,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cost_charges_cached_tokens_at_cache_rates() {
+        let usage =
+            Usage::new(Some(1000), Some(100), Some(1100)).with_cache_tokens(Some(600), Some(300));
+        let pricing = PricingInfo {
+            input_cost: 0.000003,
+            output_cost: 0.000015,
+            context_length: None,
+            cache_read_cost: Some(0.0000003),
+            cache_write_cost: Some(0.00000375),
+        };
+        let expected = 100.0 * 0.000003 + 600.0 * 0.0000003 + 300.0 * 0.00000375 + 100.0 * 0.000015;
+        assert!((pricing.cost(&usage) - expected).abs() < 1e-12);
+
+        // Without cache prices every input token is charged at the input rate
+        let pricing = PricingInfo {
+            cache_read_cost: None,
+            cache_write_cost: None,
+            ..pricing
+        };
+        let expected = 1000.0 * 0.000003 + 100.0 * 0.000015;
+        assert!((pricing.cost(&usage) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_best_pricing_match_prefers_longest_name() {
+        let pricing = |input_cost| PricingInfo {
+            input_cost,
+            output_cost: 0.0,
+            context_length: None,
+            cache_read_cost: None,
+            cache_write_cost: None,
+        };
+        let models = HashMap::from([
+            ("claude-sonnet-4".to_string(), pricing(1.0)),
+            ("claude-3.5-sonnet".to_string(), pricing(2.0)),
+            ("gpt-4o".to_string(), pricing(3.0)),
+            ("gpt-4o-mini".to_string(), pricing(4.0)),
+        ]);
+        let input_cost =
+            |model: &str| best_pricing_match(&models, model).map(|pricing| pricing.input_cost);
+
+        assert_eq!(input_cost("claude-sonnet-4-20250514"), Some(1.0));
+        assert_eq!(input_cost("claude-3-5-sonnet-latest"), Some(2.0));
+        assert_eq!(input_cost("openai/gpt-4o-mini-2024-07-18"), Some(4.0));
+        assert_eq!(input_cost("gpt-4o"), Some(3.0));
+        assert_eq!(input_cost("gpt-4"), None);
+
+        // Bedrock model ids carry a region, a vendor and a version
+        assert_eq!(
+            input_cost("us.anthropic.claude-sonnet-4-20250514-v1:0"),
+            Some(1.0)
+        );
+        assert_eq!(
+            input_cost("anthropic.claude-3-5-sonnet-20240620-v1:0"),
+            Some(2.0)
+        );
+    }
+
     #[test]
     fn test_convert_pricing() {
         assert_eq!(convert_pricing("0.000003"), Some(0.000003));
@@ -0,0 +1,478 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use mcp_core::tool::Tool;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::base::{
+    ConfigKey, LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+    StreamChunk,
+};
+use super::errors::ProviderError;
+use crate::config::Budget;
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+pub const REPLAY_PROVIDER_NAME: &str = "replay";
+
+/// One `complete()` call saved in a cassette, a JSON Lines file with one call per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub hash: String,
+    pub system: String,
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+    pub response: Message,
+    pub usage: ProviderUsage,
+}
+
+/// Matches the date and time the system prompt is rendered with
+static PROMPT_TIMESTAMP_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}").unwrap());
+
+/// The hash a request is recorded and looked up by
+///
+/// Message timestamps and the current time in the system prompt are left out, so the same
+/// conversation replayed later matches.
+pub fn request_hash(system: &str, messages: &[Message], tools: &[Tool]) -> String {
+    let system = PROMPT_TIMESTAMP_RE.replace_all(system, "");
+    let messages: Vec<Value> = messages
+        .iter()
+        .map(|message| {
+            let mut value = serde_json::to_value(message).unwrap_or_default();
+            if let Some(object) = value.as_object_mut() {
+                object.remove("created");
+            }
+            value
+        })
+        .collect();
+    let request = serde_json::json!({
+        "system": system,
+        "messages": messages,
+        "tools": tools,
+    });
+    let mut hasher = Sha256::new();
+    hasher.update(request.to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// A cassette file being appended to
+struct Cassette {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl Cassette {
+    /// Append `interaction`, logging rather than failing the call if it can't be written
+    fn record(&self, interaction: &Interaction) {
+        if let Err(e) = self.append(interaction) {
+            tracing::warn!(
+                "Failed to record to cassette {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn append(&self, interaction: &Interaction) -> Result<()> {
+        let line = serde_json::to_string(interaction)?;
+        let _guard = self.write_lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// A provider that passes every call to another provider and appends each successful
+/// request and response to a cassette for [`ReplayProvider`]
+///
+/// A streamed response is recorded once its stream completes.
+pub struct RecordingProvider {
+    inner: Arc<dyn Provider>,
+    cassette: Arc<Cassette>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn Provider>, cassette: impl Into<PathBuf>) -> Result<Self> {
+        let path = cassette.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            inner,
+            cassette: Arc::new(Cassette {
+                path,
+                write_lock: Mutex::new(()),
+            }),
+        })
+    }
+
+    fn record(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        (response, usage): (Message, ProviderUsage),
+    ) -> (Message, ProviderUsage) {
+        let interaction = Interaction {
+            hash: request_hash(system, messages, tools),
+            system: system.to_string(),
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            response,
+            usage,
+        };
+        self.cassette.record(&interaction);
+        (interaction.response, interaction.usage)
+    }
+}
+
+#[async_trait]
+impl Provider for RecordingProvider {
+    fn metadata() -> ProviderMetadata {
+        ReplayProvider::metadata()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let result = self.inner.complete(system, messages, tools).await?;
+        Ok(self.record(system, messages, tools, result))
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let result = self
+            .inner
+            .complete_with_max_tokens(system, messages, tools, max_tokens)
+            .await?;
+        Ok(self.record(system, messages, tools, result))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let stream = self.inner.stream(system, messages, tools).await?;
+        let cassette = self.cassette.clone();
+        let hash = request_hash(system, messages, tools);
+        let (system, messages, tools) = (system.to_string(), messages.to_vec(), tools.to_vec());
+        Ok(Box::pin(stream.inspect(move |chunk| {
+            if let Ok(StreamChunk::Complete(response, usage)) = chunk {
+                cassette.record(&Interaction {
+                    hash: hash.clone(),
+                    system: system.clone(),
+                    messages: messages.clone(),
+                    tools: tools.clone(),
+                    response: response.clone(),
+                    usage: usage.clone(),
+                });
+            }
+        })))
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.inner.create_embeddings(texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+
+    fn set_session_budget(&self, budget: Option<Budget>, spent: Option<f64>) {
+        self.inner.set_session_budget(budget, spent)
+    }
+
+    fn usage_provider(&self, usage: &ProviderUsage) -> Option<String> {
+        self.inner.usage_provider(usage)
+    }
+}
+
+/// A provider that answers from a cassette written by [`RecordingProvider`], without network
+///
+/// Each call is served the first unused recorded response with the same request hash. When
+/// there is none, a strict replay fails; a lenient one serves the next unused response in
+/// recorded order instead, so runs whose tool output differs slightly can still be replayed.
+pub struct ReplayProvider {
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+    strict: bool,
+    model: ModelConfig,
+}
+
+impl ReplayProvider {
+    pub fn new(interactions: Vec<Interaction>, strict: bool, model: ModelConfig) -> Self {
+        Self {
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+            strict,
+            model,
+        }
+    }
+
+    pub fn from_cassette(cassette: &Path, strict: bool, model: ModelConfig) -> Result<Self> {
+        let content = fs::read_to_string(cassette).map_err(|e| {
+            anyhow::anyhow!("Failed to read cassette {}: {}", cassette.display(), e)
+        })?;
+        let mut interactions = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let interaction = serde_json::from_str(line).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid interaction on line {} of cassette {}: {}",
+                    index + 1,
+                    cassette.display(),
+                    e
+                )
+            })?;
+            interactions.push(interaction);
+        }
+        Ok(Self::new(interactions, strict, model))
+    }
+
+    /// Create the provider `GOOSE_PROVIDER=replay` stands for
+    ///
+    /// `GOOSE_REPLAY_CASSETTE` is the cassette file. With `GOOSE_REPLAY_MODE=record`, calls go
+    /// to the `GOOSE_REPLAY_PROVIDER` provider and are recorded; otherwise they are replayed,
+    /// strictly by hash unless `GOOSE_REPLAY_STRICT` is false.
+    pub fn from_env(
+        model: ModelConfig,
+        create_inner: impl FnOnce(&str, ModelConfig) -> Result<Arc<dyn Provider>>,
+    ) -> Result<Arc<dyn Provider>> {
+        let config = crate::config::Config::global();
+        let cassette: String = config.get_param("GOOSE_REPLAY_CASSETTE")?;
+        let mode: String = config
+            .get_param("GOOSE_REPLAY_MODE")
+            .unwrap_or_else(|_| "replay".to_string());
+        match mode.as_str() {
+            "record" => {
+                let inner_name: String = config.get_param("GOOSE_REPLAY_PROVIDER")?;
+                if inner_name == REPLAY_PROVIDER_NAME {
+                    return Err(anyhow::anyhow!(
+                        "GOOSE_REPLAY_PROVIDER must be a provider other than replay"
+                    ));
+                }
+                let inner = create_inner(&inner_name, model)?;
+                Ok(Arc::new(RecordingProvider::new(inner, cassette)?))
+            }
+            "replay" => {
+                let strict = config.get_param("GOOSE_REPLAY_STRICT").unwrap_or(true);
+                Ok(Arc::new(Self::from_cassette(
+                    Path::new(&cassette),
+                    strict,
+                    model,
+                )?))
+            }
+            other => Err(anyhow::anyhow!(
+                "Unknown GOOSE_REPLAY_MODE '{}', use record or replay",
+                other
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            REPLAY_PROVIDER_NAME,
+            "Replay",
+            "Records another provider's responses to a cassette, or replays them offline",
+            "",
+            vec![],
+            "",
+            vec![
+                ConfigKey::new("GOOSE_REPLAY_CASSETTE", true, false, None),
+                ConfigKey::new("GOOSE_REPLAY_MODE", false, false, Some("replay")),
+                ConfigKey::new("GOOSE_REPLAY_PROVIDER", false, false, None),
+                ConfigKey::new("GOOSE_REPLAY_STRICT", false, false, Some("true")),
+            ],
+        )
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = request_hash(system, messages, tools);
+        let mut interactions = self.interactions.lock().unwrap();
+        let position = interactions
+            .iter()
+            .position(|(interaction, used)| !used && interaction.hash == hash);
+        let position = match position {
+            Some(position) => position,
+            None if self.strict => {
+                return Err(ProviderError::ExecutionError(format!(
+                    "No recorded response for request {} in the cassette",
+                    hash
+                )))
+            }
+            None => {
+                let position =
+                    interactions
+                        .iter()
+                        .position(|(_, used)| !used)
+                        .ok_or_else(|| {
+                            ProviderError::ExecutionError(
+                                "The cassette has no recorded response left".to_string(),
+                            )
+                        })?;
+                tracing::warn!(
+                    "No recorded response for request {}, replaying the next one in order",
+                    hash
+                );
+                position
+            }
+        };
+        let (interaction, used) = &mut interactions[position];
+        *used = true;
+        Ok((interaction.response.clone(), interaction.usage.clone()))
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::prompt_manager::PromptManager;
+    use crate::providers::base::Usage;
+    use crate::providers::scripted::ScriptedProvider;
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let cassette = dir.path().join("cassettes/run.jsonl");
+        let recorder = RecordingProvider::new(
+            Arc::new(ScriptedProvider::new(vec![
+                Message::assistant().with_text("first"),
+                Message::assistant().with_text("second"),
+            ])),
+            &cassette,
+        )
+        .unwrap();
+        let hello = [Message::user().with_text("hello")];
+        let bye = [Message::user().with_text("bye")];
+        recorder.complete("system", &hello, &[]).await.unwrap();
+        recorder.complete("system", &bye, &[]).await.unwrap();
+
+        let model = ModelConfig::new("gpt-4o".to_string());
+        let replay = ReplayProvider::from_cassette(&cassette, true, model.clone()).unwrap();
+        // Served by hash, not in recorded order, with fresh timestamps
+        let bye = [Message::user().with_text("bye")];
+        let (message, usage) = replay.complete("system", &bye, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "second");
+        assert_eq!(usage.model, "scripted");
+        let (message, _) = replay.complete("system", &hello, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "first");
+
+        let err = replay.complete("system", &hello, &[]).await.unwrap_err();
+        assert!(err.to_string().contains("No recorded response"));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_responses_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cassette = dir.path().join("run.jsonl");
+        let recorder = RecordingProvider::new(
+            Arc::new(ScriptedProvider::new(vec![
+                Message::assistant().with_text("streamed")
+            ])),
+            &cassette,
+        )
+        .unwrap();
+        let hello = [Message::user().with_text("hello")];
+        let chunks: Vec<_> = recorder
+            .stream("system", &hello, &[])
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 1);
+
+        let model = ModelConfig::new("gpt-4o".to_string());
+        let replay = ReplayProvider::from_cassette(&cassette, true, model).unwrap();
+        let (message, _) = replay.complete("system", &hello, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "streamed");
+    }
+
+    #[test]
+    fn test_hash_ignores_prompt_time() {
+        let prompt =
+            PromptManager::new().build_system_prompt(vec![], None, Value::Null, None, None);
+        let later = PROMPT_TIMESTAMP_RE.replace_all(&prompt, "2031-01-02 03:04:05");
+        assert_ne!(prompt, later);
+
+        let hello = [Message::user().with_text("hello")];
+        assert_eq!(
+            request_hash(&prompt, &hello, &[]),
+            request_hash(&later, &hello, &[])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lenient_replay_falls_back_to_recorded_order() {
+        let interaction = |text: &str| Interaction {
+            hash: request_hash("", &[Message::user().with_text(text)], &[]),
+            system: String::new(),
+            messages: vec![Message::user().with_text(text)],
+            tools: vec![],
+            response: Message::assistant().with_text(format!("re: {}", text)),
+            usage: ProviderUsage::new("m".to_string(), Usage::default()),
+        };
+        let replay = ReplayProvider::new(
+            vec![interaction("a"), interaction("b")],
+            false,
+            ModelConfig::new("m".to_string()),
+        );
+
+        let changed = [Message::user().with_text("a, but different")];
+        let (message, _) = replay.complete("", &changed, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "re: a");
+        let (message, _) = replay.complete("", &changed, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "re: b");
+        assert!(replay.complete("", &changed, &[]).await.is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use super::errors::ProviderError;
+use crate::config::{Budget, Config};
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+fn default_max_attempts() -> usize {
+    3
+}
+fn default_initial_interval_ms() -> u64 {
+    1000
+}
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+fn default_max_interval_ms() -> u64 {
+    30_000
+}
+fn default_jitter() -> f64 {
+    0.2
+}
+
+/// How often and how patiently a failed provider call is sent again
+///
+/// Read from the `provider_retry` config key, or the provider's own defaults when it isn't
+/// set. Only rate limit and server errors are retried.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Most calls made for one request, the first included. 1 turns retries off.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+    /// Wait before the first retry
+    #[serde(default = "default_initial_interval_ms")]
+    pub initial_interval_ms: u64,
+    /// What each wait is multiplied by for the next retry
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    /// Longest wait between retries, also for a provider asking to wait longer
+    #[serde(default = "default_max_interval_ms")]
+    pub max_interval_ms: u64,
+    /// Fraction each wait is randomly shortened or lengthened by, so clients that failed
+    /// together don't all retry together
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_interval_ms: default_initial_interval_ms(),
+            backoff_multiplier: default_backoff_multiplier(),
+            max_interval_ms: default_max_interval_ms(),
+            jitter: default_jitter(),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// How calls to the named provider are retried, or None if it retries them itself
+    ///
+    /// The `provider_retry` key in the global config applies to every provider it wraps.
+    pub fn for_provider(name: &str) -> Option<Self> {
+        match name {
+            // Rate limits are retried inside these, with their own settings
+            "databricks" | "gcp_vertex_ai" => None,
+            _ => Some(
+                Config::global()
+                    .get_param("provider_retry")
+                    .unwrap_or_else(|_| Self::default_for(name)),
+            ),
+        }
+    }
+
+    /// The defaults for the named provider
+    ///
+    /// Bedrock throttles for minutes at a time, so it keeps trying for much longer.
+    fn default_for(name: &str) -> Self {
+        match name {
+            "aws_bedrock" => Self {
+                max_attempts: 11,
+                initial_interval_ms: 20_000,
+                max_interval_ms: 120_000,
+                ..Self::default()
+            },
+            "azure_openai" => Self {
+                max_attempts: 6,
+                max_interval_ms: 32_000,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// How long to wait before retry number `retry`, counting from 1, without jitter
+    fn backoff(&self, retry: usize) -> Duration {
+        let interval = self.initial_interval_ms as f64
+            * self.backoff_multiplier.powi(retry.saturating_sub(1) as i32);
+        Duration::from_millis(interval.min(self.max_interval_ms as f64) as u64)
+    }
+
+    /// How long to wait after `error` before retry number `retry`, or None to give up
+    fn delay(&self, retry: usize, error: &ProviderError) -> Option<Duration> {
+        if retry >= self.max_attempts || !error.is_retryable() {
+            return None;
+        }
+        let max_interval = Duration::from_millis(self.max_interval_ms);
+        if let Some(retry_after) = error.retry_after() {
+            return Some(retry_after.min(max_interval));
+        }
+        let backoff = self.backoff(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return Some(backoff);
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+        Some(backoff.mul_f64(factor).min(max_interval))
+    }
+}
+
+/// A provider that sends a call to another provider again when it fails with a rate limit
+/// or server error, backing off between attempts
+///
+/// A `Retry-After` the provider sent is waited for instead of the backoff. Streams are
+/// retried only while being opened; an error part way through a stream is passed on.
+pub struct RetryingProvider {
+    inner: Arc<dyn Provider>,
+    config: RetryConfig,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Arc<dyn Provider>, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+
+    async fn wait_before_retry(&self, retry: usize, error: &ProviderError) -> bool {
+        let Some(delay) = self.config.delay(retry, error) else {
+            return false;
+        };
+        tracing::warn!(
+            "Provider call failed ({}), retry {} of {} in {:?}",
+            error,
+            retry,
+            self.config.max_attempts - 1,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        true
+    }
+}
+
+#[async_trait]
+impl Provider for RetryingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut retry = 1;
+        loop {
+            match self.inner.complete(system, messages, tools).await {
+                Ok(result) => return Ok(result),
+                Err(e) if self.wait_before_retry(retry, &e).await => retry += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut retry = 1;
+        loop {
+            match self
+                .inner
+                .complete_with_max_tokens(system, messages, tools, max_tokens)
+                .await
+            {
+                Ok(result) => return Ok(result),
+                Err(e) if self.wait_before_retry(retry, &e).await => retry += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut retry = 1;
+        loop {
+            match self.inner.stream(system, messages, tools).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) if self.wait_before_retry(retry, &e).await => retry += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let mut retry = 1;
+        loop {
+            match self.inner.create_embeddings(texts.clone()).await {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(e) if self.wait_before_retry(retry, &e).await => retry += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+
+    fn set_session_budget(&self, budget: Option<Budget>, spent: Option<f64>) {
+        self.inner.set_session_budget(budget, spent)
+    }
+
+    fn usage_provider(&self, usage: &ProviderUsage) -> Option<String> {
+        self.inner.usage_provider(usage)
+    }
+
+    fn get_active_model_name(&self) -> String {
+        self.inner.get_active_model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::Mutex;
+
+    /// Fails with the queued errors in order, then succeeds
+    struct FlakyProvider {
+        errors: Mutex<Vec<ProviderError>>,
+        calls: Mutex<usize>,
+    }
+
+    impl FlakyProvider {
+        fn new(mut errors: Vec<ProviderError>) -> Self {
+            errors.reverse();
+            Self {
+                errors: Mutex::new(errors),
+                calls: Mutex::new(0),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            *self.calls.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl Provider for FlakyProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            *self.calls.lock().unwrap() += 1;
+            match self.errors.lock().unwrap().pop() {
+                Some(error) => Err(error),
+                None => Ok((
+                    Message::assistant().with_text("done"),
+                    ProviderUsage::new("flaky".to_string(), Usage::default()),
+                )),
+            }
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("flaky".to_string())
+        }
+    }
+
+    fn fast_config(max_attempts: usize) -> RetryConfig {
+        RetryConfig {
+            max_attempts,
+            initial_interval_ms: 1,
+            max_interval_ms: 10,
+            ..RetryConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limit_and_server_errors() {
+        let inner = Arc::new(FlakyProvider::new(vec![
+            ProviderError::RateLimitExceeded {
+                details: "slow down".to_string(),
+                retry_after: None,
+            },
+            ProviderError::ServerError {
+                details: "overloaded".to_string(),
+                retry_after: None,
+            },
+        ]));
+        let provider = RetryingProvider::new(inner.clone(), fast_config(3));
+
+        let (message, _) = provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "done");
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let inner = Arc::new(FlakyProvider::new(vec![
+            ProviderError::ServerError {
+                details: "1".to_string(),
+                retry_after: None,
+            },
+            ProviderError::ServerError {
+                details: "2".to_string(),
+                retry_after: None,
+            },
+            ProviderError::ServerError {
+                details: "3".to_string(),
+                retry_after: None,
+            },
+        ]));
+        let provider = RetryingProvider::new(inner.clone(), fast_config(2));
+
+        let err = provider.complete("", &[], &[]).await.unwrap_err();
+        assert_eq!(err.to_string(), "Server error: 2");
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let inner = Arc::new(FlakyProvider::new(vec![
+            ProviderError::ContextLengthExceeded("too long".to_string()),
+        ]));
+        let provider = RetryingProvider::new(inner.clone(), fast_config(3));
+
+        let err = provider.complete("", &[], &[]).await.unwrap_err();
+        assert!(matches!(err, ProviderError::ContextLengthExceeded(_)));
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn test_delay() {
+        let config = RetryConfig {
+            jitter: 0.0,
+            ..RetryConfig::default()
+        };
+        let server_error = ProviderError::ServerError {
+            details: "overloaded".to_string(),
+            retry_after: None,
+        };
+        assert_eq!(config.delay(1, &server_error), Some(Duration::from_secs(1)));
+        assert_eq!(config.delay(2, &server_error), Some(Duration::from_secs(2)));
+        assert_eq!(config.delay(3, &server_error), None);
+
+        let long_backoff = RetryConfig {
+            max_attempts: 10,
+            ..config.clone()
+        };
+        assert_eq!(
+            long_backoff.delay(9, &server_error),
+            Some(Duration::from_secs(30))
+        );
+
+        let retry_after = ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_after: Some(Duration::from_secs(7)),
+        };
+        assert_eq!(config.delay(1, &retry_after), Some(Duration::from_secs(7)));
+        let too_long = ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_after: Some(Duration::from_secs(3600)),
+        };
+        assert_eq!(config.delay(1, &too_long), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_provider_defaults() {
+        let bedrock = RetryConfig::default_for("aws_bedrock");
+        assert_eq!(bedrock.max_attempts, 11);
+        assert_eq!(bedrock.backoff(1), Duration::from_secs(20));
+        assert_eq!(bedrock.backoff(4), Duration::from_secs(120));
+        assert_eq!(RetryConfig::default_for("openai"), RetryConfig::default());
+        assert_eq!(RetryConfig::for_provider("databricks"), None);
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let config = RetryConfig::default();
+        let error = ProviderError::ServerError {
+            details: String::new(),
+            retry_after: None,
+        };
+        for _ in 0..100 {
+            let delay = config.delay(1, &error).unwrap();
+            assert!(delay >= Duration::from_millis(800) && delay <= Duration::from_millis(1200));
+        }
+    }
+}
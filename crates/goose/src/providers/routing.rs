@@ -0,0 +1,651 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::StreamExt;
+use mcp_core::tool::Tool;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use utoipa::ToSchema;
+
+use super::base::{MessageStream, Provider, ProviderMetadata, ProviderUsage, StreamChunk};
+use super::errors::ProviderError;
+use super::pricing::{find_model_pricing, PricingInfo};
+use crate::config::{Budget, Config, ConfigError};
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+fn default_true() -> bool {
+    true
+}
+fn default_budget_reserve() -> f64 {
+    0.2
+}
+
+/// Rough tokens an image takes up, whatever its size
+///
+/// Providers scale images down to about a megapixel, which costs roughly this much.
+const IMAGE_TOKENS: usize = 1_600;
+
+/// A provider and model the router can send a turn to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoutingTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+/// How turns are routed among models, from the `model_routing` config key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoutingConfig {
+    /// The models to choose from, listed from the one to use by default to the most capable
+    pub models: Vec<RoutingTarget>,
+    /// Conversations estimated at this many tokens or more go to the most capable model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalate_after_tokens: Option<usize>,
+    /// Whether a turn following a failed tool call goes to the most capable model
+    #[serde(default = "default_true")]
+    pub escalate_on_tool_error: bool,
+    /// Fraction of the `session_budget` cost left below which turns are no longer escalated
+    #[serde(default = "default_budget_reserve")]
+    pub budget_reserve: f64,
+}
+
+impl RoutingConfig {
+    /// The routing under `model_routing` in the global config, if any models are listed
+    ///
+    /// Routing that can't be read is logged and left off, so turns go to the configured model.
+    pub fn from_config() -> Option<Self> {
+        match Config::global().get_param::<Self>("model_routing") {
+            Ok(config) => Some(config).filter(|config| !config.models.is_empty()),
+            Err(ConfigError::NotFound(_)) => None,
+            Err(e) => {
+                tracing::warn!("Ignoring model_routing, which can't be read: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Which model a turn was sent to and why, kept in the session metadata for auditing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RoutingDecision {
+    pub provider: String,
+    pub model: String,
+    /// The signals that decided the choice, empty when the default model was used
+    pub reasons: Vec<String>,
+    /// Rough size of the conversation sent, in tokens
+    pub estimated_tokens: usize,
+    /// Unix timestamp of the decision
+    pub timestamp: i64,
+}
+
+/// What a turn asks of the model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurnSignals {
+    pub estimated_tokens: usize,
+    /// Whether the latest user message holds images
+    pub needs_vision: bool,
+    pub needs_tools: bool,
+    /// Whether the latest message reports a failed tool call
+    pub last_tool_failed: bool,
+}
+
+impl TurnSignals {
+    pub fn from_turn(system: &str, messages: &[Message], tools: &[Tool]) -> Self {
+        let contents = messages.iter().flat_map(|message| &message.content);
+        let images = contents
+            .clone()
+            .filter(|content| matches!(content, MessageContent::Image(_)))
+            .count();
+        // About four characters per token is close enough to compare against context limits
+        let characters = system.len()
+            + contents
+                .map(|content| match content {
+                    MessageContent::Text(text) => text.text.len(),
+                    MessageContent::Image(_) => 0,
+                    other => serde_json::to_string(other).map_or(0, |json| json.len()),
+                })
+                .sum::<usize>()
+            + tools
+                .iter()
+                .map(|tool| tool.description.len() + tool.input_schema.to_string().len())
+                .sum::<usize>();
+        let last = messages.last();
+
+        Self {
+            estimated_tokens: characters / 4 + images * IMAGE_TOKENS,
+            needs_vision: messages
+                .iter()
+                .rev()
+                .find(|message| message.role == mcp_core::Role::User)
+                .is_some_and(|message| {
+                    message
+                        .content
+                        .iter()
+                        .any(|content| matches!(content, MessageContent::Image(_)))
+                }),
+            needs_tools: !tools.is_empty(),
+            last_tool_failed: last.is_some_and(|message| {
+                message.content.iter().any(|content| {
+                    content
+                        .as_tool_response()
+                        .is_some_and(|response| response.tool_result.is_err())
+                })
+            }),
+        }
+    }
+}
+
+/// What the router knows about one of its models when choosing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteProfile {
+    pub context_limit: usize,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+    /// Cost per input token, if the model's pricing is known
+    pub input_cost: Option<f64>,
+}
+
+/// The route to take for a turn and the reasons for it
+///
+/// Routes that can't hold the conversation or lack a capability it needs are passed over,
+/// as are those whose input alone would cost more than the budget left. The first
+/// remaining route is used, or the last one when the turn is escalated, unless little of
+/// the budget is left.
+pub fn choose_route(
+    config: &RoutingConfig,
+    routes: &[RouteProfile],
+    signals: &TurnSignals,
+    budget: Option<(f64, f64)>,
+) -> (usize, Vec<String>) {
+    let mut reasons = Vec::new();
+    let fits = |route: &RouteProfile| route.context_limit >= signals.estimated_tokens;
+    let capable: Vec<usize> = (0..routes.len())
+        .filter(|&i| {
+            (!signals.needs_vision || routes[i].supports_vision)
+                && (!signals.needs_tools || routes[i].supports_tools)
+        })
+        .collect();
+
+    let mut eligible: Vec<usize> = capable
+        .iter()
+        .copied()
+        .filter(|&i| fits(&routes[i]))
+        .collect();
+    if signals.needs_vision && routes.iter().any(|route| !route.supports_vision) {
+        reasons.push("the latest message has images".to_string());
+    }
+    if eligible.is_empty() {
+        reasons.push("no model meets every requirement".to_string());
+        // The largest context gives the conversation the best chance of fitting
+        let largest = (0..routes.len())
+            .max_by_key(|&i| routes[i].context_limit)
+            .unwrap_or(0);
+        eligible = vec![largest];
+    }
+
+    let index = pick_route(config, routes, signals, budget, eligible, &mut reasons);
+    // The conversation's size is only a reason when a model it doesn't fit would have
+    // been chosen otherwise
+    if capable.iter().any(|&i| !fits(&routes[i]))
+        && pick_route(config, routes, signals, budget, capable, &mut Vec::new()) != index
+    {
+        reasons.insert(
+            0,
+            format!(
+                "needs a context of about {} tokens",
+                signals.estimated_tokens
+            ),
+        );
+    }
+    (index, reasons)
+}
+
+/// The first of the `eligible` routes, or the last one when the turn is escalated, once
+/// those the budget left can't pay for are passed over
+fn pick_route(
+    config: &RoutingConfig,
+    routes: &[RouteProfile],
+    signals: &TurnSignals,
+    budget: Option<(f64, f64)>,
+    mut eligible: Vec<usize>,
+    reasons: &mut Vec<String>,
+) -> usize {
+    let mut low_budget = false;
+    if let Some((spent, max_cost)) = budget {
+        let remaining = (max_cost - spent).max(0.0);
+        let affordable: Vec<usize> = eligible
+            .iter()
+            .copied()
+            .filter(|&i| {
+                routes[i]
+                    .input_cost
+                    .is_none_or(|cost| cost * signals.estimated_tokens as f64 <= remaining)
+            })
+            .collect();
+        if !affordable.is_empty() && affordable.len() < eligible.len() {
+            reasons.push(format!(
+                "passed over models costing more than the ${:.4} budget left",
+                remaining
+            ));
+            eligible = affordable;
+        }
+        low_budget = remaining < max_cost * config.budget_reserve;
+    }
+
+    let mut escalate = Vec::new();
+    if config.escalate_on_tool_error && signals.last_tool_failed {
+        escalate.push("the last tool call failed".to_string());
+    }
+    if let Some(threshold) = config.escalate_after_tokens {
+        if signals.estimated_tokens >= threshold {
+            escalate.push(format!("the conversation is over {} tokens", threshold));
+        }
+    }
+
+    if escalate.is_empty() {
+        eligible[0]
+    } else if low_budget {
+        reasons.push("not escalating as the budget is running low".to_string());
+        eligible[0]
+    } else {
+        reasons.extend(escalate);
+        eligible[eligible.len() - 1]
+    }
+}
+
+struct Route {
+    target: RoutingTarget,
+    provider: Arc<dyn Provider>,
+}
+
+/// A provider that picks one of several models for each turn
+///
+/// The choice weighs the conversation's length, the capabilities the turn needs, whether
+/// the last tool call failed and the budget cost left. The agent passes in the session's
+/// budget and cost before each turn, and in between the router adds what the turns it
+/// routed cost. Each decision is logged in a `model_routing` tracing span and returned in
+/// the response's usage for the agent to record in the session metadata.
+pub struct RoutingProvider {
+    routes: Vec<Route>,
+    config: RoutingConfig,
+    budget: Mutex<Option<Budget>>,
+    spent: Arc<Mutex<f64>>,
+    active: AtomicUsize,
+}
+
+/// The route chosen for a turn
+struct RoutedTurn {
+    index: usize,
+    pricing: Option<PricingInfo>,
+    decision: RoutingDecision,
+    span: tracing::Span,
+}
+
+impl RoutingProvider {
+    pub fn new(
+        routes: Vec<(RoutingTarget, Arc<dyn Provider>)>,
+        config: RoutingConfig,
+        budget: Option<Budget>,
+    ) -> Self {
+        assert!(!routes.is_empty(), "RoutingProvider needs a route");
+        Self {
+            routes: routes
+                .into_iter()
+                .map(|(target, provider)| Route { target, provider })
+                .collect(),
+            config,
+            budget: Mutex::new(budget),
+            spent: Arc::new(Mutex::new(0.0)),
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    async fn pricing(&self, route: &Route) -> Option<PricingInfo> {
+        find_model_pricing(Some(&route.target.provider), &route.target.model).await
+    }
+
+    /// Choose the route for a turn
+    async fn route(&self, system: &str, messages: &[Message], tools: &[Tool]) -> RoutedTurn {
+        let signals = TurnSignals::from_turn(system, messages, tools);
+        let mut pricing = Vec::with_capacity(self.routes.len());
+        let mut profiles = Vec::with_capacity(self.routes.len());
+        for route in &self.routes {
+            let model_config = route.provider.get_model_config();
+            let capabilities = model_config.capabilities();
+            let price = self.pricing(route).await;
+            profiles.push(RouteProfile {
+                context_limit: model_config.context_limit(),
+                supports_vision: capabilities.supports_vision(),
+                supports_tools: capabilities.supports_tools() || model_config.toolshim,
+                input_cost: price.as_ref().map(|p| p.input_cost),
+            });
+            pricing.push(price);
+        }
+        let budget = self
+            .budget
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|budget| budget.max_cost)
+            .map(|max_cost| (*self.spent.lock().unwrap(), max_cost));
+
+        let (index, reasons) = choose_route(&self.config, &profiles, &signals, budget);
+        let target = &self.routes[index].target;
+        let span = tracing::info_span!(
+            "model_routing",
+            provider = %target.provider,
+            model = %target.model,
+            estimated_tokens = signals.estimated_tokens,
+            reasons = ?reasons,
+        );
+        span.in_scope(|| {
+            tracing::info!(
+                "Routing turn to {} on {}: {}",
+                target.model,
+                target.provider,
+                if reasons.is_empty() {
+                    "default model".to_string()
+                } else {
+                    reasons.join(", ")
+                }
+            )
+        });
+
+        self.active.store(index, Ordering::Relaxed);
+        RoutedTurn {
+            index,
+            decision: RoutingDecision {
+                provider: target.provider.clone(),
+                model: target.model.clone(),
+                reasons,
+                estimated_tokens: signals.estimated_tokens,
+                timestamp: Utc::now().timestamp(),
+            },
+            pricing: pricing.swap_remove(index),
+            span,
+        }
+    }
+
+    fn active(&self) -> &Arc<dyn Provider> {
+        &self.routes[self.active.load(Ordering::Relaxed)].provider
+    }
+}
+
+/// Add what `usage` cost to `spent`, if the model's pricing is known, and note the decision
+/// that led to it
+fn record_turn(
+    spent: &Mutex<f64>,
+    pricing: Option<&PricingInfo>,
+    decision: RoutingDecision,
+    usage: &mut ProviderUsage,
+) {
+    if let Some(pricing) = pricing {
+        *spent.lock().unwrap() += pricing.cost(&usage.usage);
+    }
+    usage.routing = Some(decision);
+}
+
+#[async_trait]
+impl Provider for RoutingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let turn = self.route(system, messages, tools).await;
+        let (message, mut usage) = self.routes[turn.index]
+            .provider
+            .complete(system, messages, tools)
+            .instrument(turn.span)
+            .await?;
+        record_turn(
+            &self.spent,
+            turn.pricing.as_ref(),
+            turn.decision,
+            &mut usage,
+        );
+        Ok((message, usage))
+    }
+
+    async fn complete_with_max_tokens(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        max_tokens: i32,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let turn = self.route(system, messages, tools).await;
+        let (message, mut usage) = self.routes[turn.index]
+            .provider
+            .complete_with_max_tokens(system, messages, tools, max_tokens)
+            .instrument(turn.span)
+            .await?;
+        record_turn(
+            &self.spent,
+            turn.pricing.as_ref(),
+            turn.decision,
+            &mut usage,
+        );
+        Ok((message, usage))
+    }
+
+    /// Whether every route streams, so the turn streams whichever is chosen
+    fn supports_streaming(&self) -> bool {
+        self.routes
+            .iter()
+            .all(|route| route.provider.supports_streaming())
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let turn = self.route(system, messages, tools).await;
+        let stream = self.routes[turn.index]
+            .provider
+            .stream(system, messages, tools)
+            .instrument(turn.span)
+            .await?;
+        let spent = Arc::clone(&self.spent);
+        let (pricing, decision) = (turn.pricing, turn.decision);
+        Ok(Box::pin(stream.map(move |mut chunk| {
+            if let Ok(StreamChunk::Complete(_, usage)) = &mut chunk {
+                record_turn(&spent, pricing.as_ref(), decision.clone(), usage);
+            }
+            chunk
+        })))
+    }
+
+    /// The model config of the route taken last, the first route's until then
+    fn get_model_config(&self) -> ModelConfig {
+        self.active().get_model_config()
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.routes[0].provider.fetch_supported_models_async().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.routes[0].provider.supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.routes[0].provider.create_embeddings(texts).await
+    }
+
+    fn get_active_model_name(&self) -> String {
+        self.active().get_active_model_name()
+    }
+
+    /// Use the session's budget over `session_budget`, and its cost so far over the cost
+    /// the router counted, which misses earlier runs of the session
+    fn set_session_budget(&self, budget: Option<Budget>, spent: Option<f64>) {
+        if budget.is_some() {
+            *self.budget.lock().unwrap() = budget;
+        }
+        if let Some(spent) = spent {
+            *self.spent.lock().unwrap() = spent;
+        }
+    }
+
+    /// The provider of the route the usage's decision names
+    fn usage_provider(&self, usage: &ProviderUsage) -> Option<String> {
+        let Some(decision) = &usage.routing else {
+            return self.active().usage_provider(usage);
+        };
+        self.routes
+            .iter()
+            .find(|route| {
+                route.target.provider == decision.provider && route.target.model == decision.model
+            })
+            .map_or(Some(decision.provider.clone()), |route| {
+                route.provider.usage_provider(usage)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RoutingConfig {
+        serde_yaml::from_str(
+            r#"
+models:
+  - provider: openai
+    model: gpt-4o-mini
+  - provider: anthropic
+    model: claude-sonnet-4
+escalate_after_tokens: 50000
+"#,
+        )
+        .unwrap()
+    }
+
+    fn profile(context_limit: usize, supports_vision: bool, input_cost: f64) -> RouteProfile {
+        RouteProfile {
+            context_limit,
+            supports_vision,
+            supports_tools: true,
+            input_cost: Some(input_cost),
+        }
+    }
+
+    fn signals(estimated_tokens: usize) -> TurnSignals {
+        TurnSignals {
+            estimated_tokens,
+            needs_tools: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_escalation() {
+        let config = config();
+        assert!(config.escalate_on_tool_error);
+        let routes = [profile(128_000, true, 1e-7), profile(200_000, true, 3e-6)];
+
+        assert_eq!(
+            choose_route(&config, &routes, &signals(1000), None),
+            (0, vec![])
+        );
+
+        let failed = TurnSignals {
+            last_tool_failed: true,
+            ..signals(1000)
+        };
+        let (index, reasons) = choose_route(&config, &routes, &failed, None);
+        assert_eq!(index, 1);
+        assert_eq!(reasons, vec!["the last tool call failed"]);
+
+        let (index, _) = choose_route(&config, &routes, &signals(60_000), None);
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn test_requirements() {
+        let config = RoutingConfig {
+            escalate_after_tokens: None,
+            ..config()
+        };
+        let routes = [profile(128_000, false, 1e-7), profile(200_000, true, 3e-6)];
+
+        let (index, reasons) = choose_route(&config, &routes, &signals(150_000), None);
+        assert_eq!(index, 1);
+        assert_eq!(reasons, vec!["needs a context of about 150000 tokens"]);
+
+        // A model too small for the conversation that wouldn't be chosen anyway isn't a reason
+        let larger_first = [routes[1].clone(), routes[0].clone()];
+        assert_eq!(
+            choose_route(&config, &larger_first, &signals(150_000), None),
+            (0, vec![])
+        );
+
+        let images = TurnSignals {
+            needs_vision: true,
+            ..signals(1000)
+        };
+        let (index, reasons) = choose_route(&config, &routes, &images, None);
+        assert_eq!(index, 1);
+        assert_eq!(reasons, vec!["the latest message has images"]);
+
+        let (index, reasons) = choose_route(&config, &routes, &signals(500_000), None);
+        assert_eq!(index, 1);
+        assert!(reasons.contains(&"no model meets every requirement".to_string()));
+    }
+
+    #[test]
+    fn test_budget() {
+        let config = config();
+        let routes = [profile(128_000, true, 1e-7), profile(200_000, true, 3e-6)];
+        let failed = TurnSignals {
+            last_tool_failed: true,
+            ..signals(10_000)
+        };
+
+        // 10k tokens cost $0.03 on the second route, more than is left
+        let (index, reasons) = choose_route(&config, &routes, &failed, Some((0.98, 1.0)));
+        assert_eq!(index, 0);
+        assert!(reasons[0].starts_with("passed over models"));
+
+        // Enough is left for the turn, but not above the reserve
+        let (index, reasons) = choose_route(&config, &routes, &failed, Some((0.9, 1.0)));
+        assert_eq!(index, 0);
+        assert_eq!(reasons, vec!["not escalating as the budget is running low"]);
+
+        let (index, _) = choose_route(&config, &routes, &failed, Some((0.1, 1.0)));
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn test_turn_signals() {
+        let messages = vec![
+            Message::user()
+                .with_text("what is this?")
+                .with_image("aGk=", "image/png"),
+            Message::assistant().with_text("a picture"),
+            Message::user().with_tool_response(
+                "1",
+                Err(mcp_core::ToolError::ExecutionError("failed".to_string())),
+            ),
+        ];
+        let signals = TurnSignals::from_turn("system", &messages, &[]);
+        assert!(!signals.needs_vision);
+        assert!(!signals.needs_tools);
+        assert!(signals.last_tool_failed);
+        assert!(signals.estimated_tokens >= IMAGE_TOKENS);
+
+        let signals = TurnSignals::from_turn("system", &messages[..1], &[]);
+        assert!(signals.needs_vision);
+        assert!(!signals.last_tool_failed);
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+
+use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+pub const SCRIPTED_MODEL: &str = "scripted";
+
+/// A provider that answers with a fixed list of assistant messages, one per call, for
+/// running recipes and agents without a model
+///
+/// Tool requests in the messages are executed by the agent as usual. A call after the
+/// script has run out fails, so a conversation that goes on longer than expected is
+/// reported rather than hanging.
+pub struct ScriptedProvider {
+    responses: Mutex<std::vec::IntoIter<Message>>,
+    requests: Mutex<Vec<Vec<Message>>>,
+    model: ModelConfig,
+}
+
+impl ScriptedProvider {
+    pub fn new(responses: Vec<Message>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter()),
+            requests: Mutex::new(Vec::new()),
+            model: ModelConfig::new(SCRIPTED_MODEL.to_string()),
+        }
+    }
+
+    /// The conversation sent with each call so far
+    pub fn requests(&self) -> Vec<Vec<Message>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Provider for ScriptedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "scripted",
+            "Scripted",
+            "Answers with a fixed list of messages, for tests",
+            SCRIPTED_MODEL,
+            vec![SCRIPTED_MODEL],
+            "",
+            vec![],
+        )
+    }
+
+    async fn complete(
+        &self,
+        _system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let call = {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(messages.to_vec());
+            requests.len()
+        };
+        let message = self.responses.lock().unwrap().next().ok_or_else(|| {
+            ProviderError::ExecutionError(format!(
+                "The script has no response left for model call {}",
+                call
+            ))
+        })?;
+        Ok((
+            message,
+            ProviderUsage::new(SCRIPTED_MODEL.to_string(), Usage::default()),
+        ))
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripted_responses_in_order_then_error() {
+        let provider = ScriptedProvider::new(vec![
+            Message::assistant().with_text("first"),
+            Message::assistant().with_text("second"),
+        ]);
+        let prompt = [Message::user().with_text("hi")];
+
+        let (first, _) = provider.complete("", &prompt, &[]).await.unwrap();
+        assert_eq!(first.as_concat_text(), "first");
+        let (second, _) = provider.complete("", &prompt, &[]).await.unwrap();
+        assert_eq!(second.as_concat_text(), "second");
+
+        let err = provider.complete("", &prompt, &[]).await.unwrap_err();
+        assert!(err.to_string().contains("model call 3"));
+        assert_eq!(provider.requests().len(), 3);
+    }
+}
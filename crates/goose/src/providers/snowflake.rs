@@ -369,14 +369,17 @@ impl SnowflakeProvider {
                     error_msg
                 )))
             }
-            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded(
-                "Rate limit exceeded. Please try again later.".to_string(),
-            )),
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimitExceeded {
+                details: "Rate limit exceeded. Please try again later.".to_string(),
+                retry_after: None,
+            }),
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-                Err(ProviderError::ServerError(
-                    "Snowflake service is temporarily unavailable. Please try again later."
-                        .to_string(),
-                ))
+                Err(ProviderError::ServerError {
+                    details:
+                        "Snowflake service is temporarily unavailable. Please try again later."
+                            .to_string(),
+                    retry_after: None,
+                })
             }
             _ => {
                 tracing::debug!(
@@ -0,0 +1,200 @@
+use std::future::Future;
+
+use futures::StreamExt;
+use reqwest::Response;
+use serde_json::Value;
+
+use super::base::{MessageDelta, MessageStream, ProviderUsage, StreamChunk};
+use super::errors::ProviderError;
+use crate::message::Message;
+
+/// A single server-sent event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for a `text/event-stream` body
+///
+/// Network chunks can split lines (and multi-byte characters) anywhere, so bytes are
+/// buffered until a full line is available.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed a chunk of the body, returning every event it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.process_line(line.trim_end_matches(['\n', '\r'])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flush any event left over when the body ends without a trailing blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string();
+            if let Some(event) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // Lines starting with a colon are comments, often used as keep-alives
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+/// Reassembles a provider's streamed events into the body its non-streaming endpoint returns
+///
+/// This lets each format reuse its existing `response_to_message` and `get_usage` on the
+/// final result, so a streamed message is identical to one from `complete`.
+pub trait StreamAccumulator: Send + 'static {
+    /// Consume one event, returning any deltas to surface to the caller
+    fn push(&mut self, event: &SseEvent) -> Result<Vec<MessageDelta>, ProviderError>;
+
+    /// Build the complete response body once the stream has ended
+    fn finish(self) -> Result<Value, ProviderError>;
+}
+
+/// Return the response if it succeeded, otherwise let `handle_error` read the error body
+/// so streaming requests surface the same errors as their non-streaming counterparts
+pub async fn check_stream_response<F, Fut>(
+    response: Response,
+    handle_error: F,
+) -> Result<Response, ProviderError>
+where
+    F: FnOnce(Response) -> Fut,
+    Fut: Future<Output = Result<Value, ProviderError>>,
+{
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    match handle_error(response).await {
+        Err(e) => Err(e),
+        Ok(_) => Err(ProviderError::RequestFailed(format!(
+            "Request failed with status: {}",
+            status
+        ))),
+    }
+}
+
+/// Drive an event-stream response through an accumulator
+///
+/// Deltas are yielded as they arrive. Once the body ends, `complete` converts the
+/// reassembled response into the final message and usage.
+pub fn stream_response<A, F>(response: Response, mut accumulator: A, complete: F) -> MessageStream
+where
+    A: StreamAccumulator,
+    F: FnOnce(Value) -> Result<(Message, ProviderUsage), ProviderError> + Send + 'static,
+{
+    Box::pin(async_stream::try_stream! {
+        let mut parser = SseParser::default();
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
+            for event in parser.push(&chunk) {
+                for delta in accumulator.push(&event)? {
+                    yield StreamChunk::Delta(delta);
+                }
+            }
+        }
+        if let Some(event) = parser.finish() {
+            for delta in accumulator.push(&event)? {
+                yield StreamChunk::Delta(delta);
+            }
+        }
+
+        let (message, usage) = complete(accumulator.finish()?)?;
+        yield StreamChunk::Complete(message, usage);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: message_start\nda").is_empty());
+        let events = parser.push(b"ta: {\"a\":1}\r\n\r\ndata: [DONE]\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message_start".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "[DONE]".to_string(),
+                },
+            ]
+        );
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_utf8_and_comments() {
+        let mut parser = SseParser::default();
+        let bytes = "data: 🌍\n\n".as_bytes();
+        assert!(parser.push(&bytes[..8]).is_empty());
+        let events = parser.push(&bytes[8..]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "🌍");
+
+        assert!(parser.push(b": keep-alive\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_sse_parser_joins_multiline_data_and_flushes() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: first\ndata: second").is_empty());
+        let event = parser.finish().unwrap();
+        assert_eq!(event.data, "first\nsecond");
+    }
+}
@@ -1,6 +1,8 @@
-use super::base::Usage;
+use super::base::{ProviderUsage, Usage};
 use super::errors::GoogleErrorCode;
+use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::model_registry;
 use anyhow::Result;
 use base64::Engine;
 use regex::Regex;
@@ -9,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 use crate::providers::errors::{OpenAIError, ProviderError};
 use mcp_core::content::ImageContent;
@@ -44,11 +47,35 @@ pub fn convert_image(image: &ImageContent, image_format: &ImageFormat) -> Value
     }
 }
 
+/// How long the response's `Retry-After` header asks to wait before retrying
+///
+/// The header is either a number of seconds or an HTTP date. The retry layer waits as long
+/// as the provider asked.
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after)
+        .map(Duration::from_secs)
+}
+
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds);
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(seconds.max(0) as u64)
+}
+
 /// Handle response from OpenAI compatible endpoints
 /// Error codes: https://platform.openai.com/docs/guides/error-codes
 /// Context window exceeded: https://community.openai.com/t/help-needed-tackling-context-length-limits-in-openai-models/617543
 pub async fn handle_response_openai_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_after = retry_after(&response);
     // Try to parse the response body as JSON (if applicable)
     let payload = match response.json::<Value>().await {
         Ok(json) => json,
@@ -75,10 +102,16 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Unknown error (status {})", status)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-            Err(ProviderError::ServerError(format!("{:?}", payload)))
+            Err(ProviderError::ServerError {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
         }
         _ => {
             tracing::debug!(
@@ -89,7 +122,7 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
     }
 }
 
-/// Check if the model is a Google model based on the "model" field in the payload.
+/// Check if the model in the payload's "model" field is one of Google's, per the model registry.
 ///
 /// ### Arguments
 /// - `payload`: The JSON payload as a `serde_json::Value`.
@@ -98,8 +131,7 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
 /// - `bool`: Returns `true` if the model is a Google model, otherwise `false`.
 pub fn is_google_model(payload: &Value) -> bool {
     if let Some(model) = payload.get("model").and_then(|m| m.as_str()) {
-        // Check if the model name contains "google"
-        return model.to_lowercase().contains("google");
+        return model_registry::capabilities(None, model).is_family("google");
     }
     false
 }
@@ -140,6 +172,7 @@ fn get_google_final_status(status: StatusCode, payload: Option<&Value>) -> Statu
 /// - `Err(ProviderError)`: Describes the failure reason.
 pub async fn handle_response_google_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_after = retry_after(&response);
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
 
@@ -166,10 +199,16 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", final_status, error_msg)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-            Err(ProviderError::ServerError(format!("{:?}", payload)))
+            Err(ProviderError::ServerError {
+                details: format!("{:?}", payload),
+                retry_after,
+            })
         }
         _ => {
             tracing::debug!(
@@ -319,6 +358,41 @@ pub fn unescape_json_values(value: &Value) -> Value {
     }
 }
 
+/// Convert a complete response into the message and usage for the agent, for both the
+/// complete and streamed paths of a provider
+///
+/// `to_message` and `get_usage` read the provider's response format. Usage the response
+/// doesn't report is left empty rather than failing the completion, and the model is the one
+/// the response names, falling back to the configured one.
+pub fn finish_response<M, U, E1, E2>(
+    model_config: &ModelConfig,
+    payload: &Value,
+    response: Value,
+    to_message: M,
+    get_usage: U,
+) -> Result<(Message, ProviderUsage), ProviderError>
+where
+    M: FnOnce(Value) -> Result<Message, E1>,
+    U: FnOnce(&Value) -> Result<Usage, E2>,
+    ProviderError: From<E1> + From<E2>,
+{
+    let message = to_message(response.clone())?;
+    let usage = match get_usage(&response).map_err(ProviderError::from) {
+        Ok(usage) => usage,
+        Err(ProviderError::UsageError(e)) => {
+            tracing::debug!("Failed to get usage data: {}", e);
+            Usage::default()
+        }
+        Err(e) => return Err(e),
+    };
+    let model = ["model", "modelVersion"]
+        .iter()
+        .find_map(|key| response.get(key).and_then(Value::as_str))
+        .map_or_else(|| model_config.model_name.clone(), str::to_string);
+    emit_debug_trace(model_config, payload, &response, &usage);
+    Ok((message, ProviderUsage::new(model, usage)))
+}
+
 pub fn emit_debug_trace(
     model_config: &ModelConfig,
     payload: &Value,
@@ -340,6 +414,47 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_finish_response_tolerates_missing_usage() {
+        let model_config = ModelConfig::new("gpt-4o".to_string());
+        let to_message = |_: Value| -> Result<Message, ProviderError> {
+            Ok(Message::assistant().with_text("done"))
+        };
+
+        let (message, usage) = finish_response(
+            &model_config,
+            &json!({}),
+            json!({"model": "gpt-4o-2024-08-06"}),
+            to_message,
+            |_| Err(ProviderError::UsageError("no usage".to_string())),
+        )
+        .unwrap();
+        assert_eq!(message.as_concat_text(), "done");
+        assert_eq!(usage.model, "gpt-4o-2024-08-06");
+        assert_eq!(usage.usage.total_tokens, None);
+
+        let (_, usage) = finish_response(&model_config, &json!({}), json!({}), to_message, |_| {
+            Ok::<_, ProviderError>(Usage::new(Some(1), Some(2), Some(3)))
+        })
+        .unwrap();
+        assert_eq!(usage.model, "gpt-4o");
+        assert_eq!(usage.usage.total_tokens, Some(3));
+
+        let result = finish_response(&model_config, &json!({}), json!({}), to_message, |_| {
+            Err(ProviderError::ExecutionError("bad usage".to_string()))
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("30"), Some(30));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        assert!(matches!(parse_retry_after(&later), Some(115..=120)));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
     #[test]
     fn test_detect_image_path() {
         // Create a temporary PNG file with valid PNG magic numbers
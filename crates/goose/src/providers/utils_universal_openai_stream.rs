@@ -58,6 +58,7 @@ pub struct OAIStreamChunk {
     pub created: Option<i64>,
     pub model: Option<String>,
     pub system_fingerprint: Option<String>,
+    #[serde(default)]
     pub choices: Vec<OAIStreamChoice>,
     pub usage: Option<OAIUsage>,
     pub prompt_filter_results: Option<Vec<OAIPromptFilterResult>>,
@@ -136,6 +137,30 @@ impl OAIStreamCollector {
     }
 
     pub fn add_chunk(&mut self, chunk: &OAIStreamChunk) {
+        // Top-level fields are repeated on every chunk (sometimes empty on the first one),
+        // so keep the first real value. Usage only arrives on the last chunk.
+        if let Some(id) = chunk.id.as_ref().filter(|id| !id.is_empty()) {
+            self.id.get_or_insert_with(|| id.clone());
+        }
+        if let Some(model) = chunk.model.as_ref().filter(|model| !model.is_empty()) {
+            self.model.get_or_insert_with(|| model.clone());
+        }
+        if let Some(created) = chunk.created.filter(|created| *created != 0) {
+            self.created.get_or_insert(created);
+        }
+        if self.object.is_none() {
+            self.object = chunk.object.clone();
+        }
+        if self.system_fingerprint.is_none() {
+            self.system_fingerprint = chunk.system_fingerprint.clone();
+        }
+        if chunk.prompt_filter_results.is_some() {
+            self.prompt_filter_results = chunk.prompt_filter_results.clone();
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage.clone();
+        }
+
         for ch in chunk.choices.iter() {
             // Always ensure choice exists, even if all fields are absent!
             let idx = ch.index;
@@ -298,6 +323,13 @@ data: [DONE]
         assert_eq!(tc.function.name.as_deref(), Some("get_weather"));
         assert_eq!(tc.function.arguments, r#"{"location":"San Francisco"}"#);
         assert_eq!(choice.finish_reason, "tool_calls");
+        assert_eq!(resp.id, "chatcmpl-BYcbLSepxSXIxgUX2WZCFZrjqjp0l");
+        assert_eq!(resp.model, "gpt-4o-2024-11-20");
+        let usage = resp
+            .usage
+            .expect("usage should be collected from the last chunk");
+        assert_eq!(usage.prompt_tokens, Some(73));
+        assert_eq!(usage.completion_tokens, Some(16));
     }
 
     const TEXT_STREAM: &str = r#"
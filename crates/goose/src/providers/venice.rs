@@ -10,6 +10,7 @@ use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
+use crate::model_registry::{self, ModelCapabilities};
 use mcp_core::{tool::Tool, Role, ToolCall, ToolResult};
 
 // ---------- Capability Flags ----------
@@ -39,6 +40,16 @@ impl CapabilityFlags {
         flag!("supportsReasoning", 'r');
         CapabilityFlags(s)
     }
+
+    /// What the flags tell the model registry
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities {
+            tool_calling: Some(self.0.contains('f')),
+            vision: Some(self.0.contains('v')),
+            reasoning: Some(self.0.contains('r')),
+            ..Default::default()
+        }
+    }
 }
 
 impl std::fmt::Display for CapabilityFlags {
@@ -286,6 +297,16 @@ impl Provider for VeniceProvider {
                 let id = model["id"].as_str()?.to_owned();
                 // Build flags from capabilities
                 let flags = CapabilityFlags::from_json(model);
+                model_registry::refine(
+                    "venice",
+                    &id,
+                    ModelCapabilities {
+                        context_limit: model["model_spec"]["availableContextTokens"]
+                            .as_u64()
+                            .map(|tokens| tokens as usize),
+                        ..flags.capabilities()
+                    },
+                );
                 // Only include models that support function calling (have 'f' flag)
                 if flags.0.contains('f') {
                     Some(format!("{id} {flags}"))
@@ -550,11 +571,11 @@ impl Provider for VeniceProvider {
 
         // Extract usage
         let usage_data = &response_json["usage"];
-        let usage = Usage {
-            input_tokens: usage_data["prompt_tokens"].as_i64().map(|v| v as i32),
-            output_tokens: usage_data["completion_tokens"].as_i64().map(|v| v as i32),
-            total_tokens: usage_data["total_tokens"].as_i64().map(|v| v as i32),
-        };
+        let usage = Usage::new(
+            usage_data["prompt_tokens"].as_i64().map(|v| v as i32),
+            usage_data["completion_tokens"].as_i64().map(|v| v as i32),
+            usage_data["total_tokens"].as_i64().map(|v| v as i32),
+        );
 
         Ok((
             Message {
@@ -3,7 +3,7 @@ use crate::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
-use crate::providers::utils::get_model;
+use crate::providers::utils::{get_model, retry_after};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::Tool;
@@ -97,6 +97,7 @@ impl XaiProvider {
             .await?;
 
         let status = response.status();
+        let retry_after = retry_after(&response);
         let payload: Option<Value> = response.json().await.ok();
 
         match status {
@@ -109,10 +110,16 @@ impl XaiProvider {
                 Err(ProviderError::ContextLengthExceeded(format!("{:?}", payload)))
             }
             StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+                Err(ProviderError::RateLimitExceeded {
+                    details: format!("{:?}", payload),
+                    retry_after,
+                })
             }
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
-                Err(ProviderError::ServerError(format!("{:?}", payload)))
+                Err(ProviderError::ServerError {
+                    details: format!("{:?}", payload),
+                    retry_after,
+                })
             }
             _ => {
                 tracing::debug!(
@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::Recipe;
+
+const MAX_EXTENDS_DEPTH: usize = 10;
+
+static EXTENDS_KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^\s*"?extends"?\s*:"#).unwrap());
+static RECIPE_DIR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*recipe_dir\s*\}\}").unwrap());
+
+/// Merge the base recipes that a recipe `extends` into it, before any template is rendered
+///
+/// `content` is the unrendered recipe loaded from `path`. `load_base` is given each base's
+/// name and the directory of the recipe extending it, and returns the base's unrendered
+/// content and path. Bases may extend other bases in turn.
+///
+/// Template variables are kept as they are, so the merged recipe is rendered once with the
+/// parameters of every recipe, except that each base's `{{ recipe_dir }}` is filled in with
+/// the base's own directory. Returns none when the recipe extends nothing.
+pub fn resolve_extends<F>(content: &str, path: &Path, mut load_base: F) -> Result<Option<Recipe>>
+where
+    F: FnMut(&str, &Path) -> Result<(String, PathBuf)>,
+{
+    let recipe = match Recipe::from_content(content) {
+        Ok(recipe) => recipe,
+        Err(_) if !mentions_extends(content) => return Ok(None),
+        Err(e) => {
+            return Err(anyhow!(
+                "Recipe {} extends other recipes, so it must be valid YAML or JSON before its \
+                 parameters are filled in: {}",
+                path.display(),
+                e
+            ))
+        }
+    };
+    if recipe.extends.is_none() {
+        return Ok(None);
+    }
+    let mut chain = vec![path.to_path_buf()];
+    extend_with_bases(recipe, path, &mut chain, &mut load_base).map(Some)
+}
+
+fn extend_with_bases<F>(
+    recipe: Recipe,
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    load_base: &mut F,
+) -> Result<Recipe>
+where
+    F: FnMut(&str, &Path) -> Result<(String, PathBuf)>,
+{
+    let Some(extends) = recipe.extends.clone() else {
+        return Ok(recipe);
+    };
+    if chain.len() > MAX_EXTENDS_DEPTH {
+        return Err(anyhow!(
+            "Recipe {} extends more than {} levels of base recipes",
+            path.display(),
+            MAX_EXTENDS_DEPTH
+        ));
+    }
+
+    let recipe_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut merged_base: Option<Recipe> = None;
+    for base_name in &extends {
+        let (content, base_path) = load_base(base_name, recipe_dir)?;
+        if chain.contains(&base_path) {
+            return Err(anyhow!(
+                "Recipe {} extends itself through {}",
+                base_path.display(),
+                path.display()
+            ));
+        }
+        let base_dir = base_path.parent().unwrap_or_else(|| Path::new(""));
+        let base = with_recipe_dir(&content, base_dir)
+            .map_err(|e| anyhow!("Failed to load base recipe {}: {}", base_name, e))?;
+
+        chain.push(base_path.clone());
+        let base = extend_with_bases(base, &base_path, chain, load_base)?;
+        chain.pop();
+
+        merged_base = Some(match merged_base {
+            Some(earlier) => base.extend(earlier),
+            None => base,
+        });
+    }
+
+    Ok(match merged_base {
+        Some(base) => recipe.extend(base),
+        None => Recipe {
+            extends: None,
+            ..recipe
+        },
+    })
+}
+
+/// Load a base recipe from a file, relative to the directory of the recipe extending it
+pub fn read_base_recipe_file(base_name: &str, recipe_dir: &Path) -> Result<(String, PathBuf)> {
+    let path = recipe_dir
+        .join(base_name)
+        .canonicalize()
+        .map_err(|e| anyhow!("Failed to find base recipe {}: {}", base_name, e))?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read base recipe {}: {}", path.display(), e))?;
+    Ok((content, path))
+}
+
+/// Whether unparsable content looks like it has an `extends` key
+fn mentions_extends(content: &str) -> bool {
+    EXTENDS_KEY_RE.is_match(content)
+}
+
+/// Parse a base recipe, filling in `{{ recipe_dir }}` without rendering anything else
+///
+/// The directory goes into the parsed strings, so characters in it that mean something
+/// in YAML can't change the recipe.
+fn with_recipe_dir(content: &str, dir: &Path) -> Result<Recipe> {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    fill_in_recipe_dir(&mut value, &dir.to_string_lossy());
+    Ok(serde_yaml::from_value(value)?)
+}
+
+fn fill_in_recipe_dir(value: &mut serde_yaml::Value, dir: &str) {
+    match value {
+        serde_yaml::Value::String(text) => {
+            if RECIPE_DIR_RE.is_match(text) {
+                *text = RECIPE_DIR_RE
+                    .replace_all(text, regex::NoExpand(dir))
+                    .into_owned();
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                fill_in_recipe_dir(item, dir);
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                fill_in_recipe_dir(item, dir);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_are_merged_unrendered() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("base.yaml"),
+            r#"
+version: 1.0.0
+title: Base
+description: Shared setup
+instructions: "Read {{ recipe_dir }}/style.md.{% if verbose %} Explain each step.{% endif %}"
+"#,
+        )
+        .unwrap();
+        let child_path = dir.path().join("child.yaml");
+        let child = r#"
+version: 1.0.0
+title: Child
+description: Reviews code
+extends: base.yaml
+instructions: Review {{ repo | upper }}.
+"#;
+
+        let recipe = resolve_extends(child, &child_path, read_base_recipe_file)
+            .unwrap()
+            .unwrap();
+        let base_dir = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            format!(
+                "Read {}/style.md.{{% if verbose %}} Explain each step.{{% endif %}}\n\nReview {{{{ repo | upper }}}}.",
+                base_dir.display()
+            )
+        );
+        assert!(recipe.extends.is_none());
+
+        let plain = "version: 1.0.0\ntitle: t\ndescription: d\ninstructions: {{ x }}\n";
+        assert!(resolve_extends(plain, &child_path, read_base_recipe_file)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_recipe_dir_is_not_read_as_yaml() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join(r#"team "a": #shared"#);
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("base.yaml"),
+            "version: 1.0.0\ntitle: Base\ndescription: d\ninstructions: \"Read {{ recipe_dir }}/style.md\"\n",
+        )
+        .unwrap();
+        let child = "version: 1.0.0\ntitle: Child\ndescription: d\nextends: base.yaml\n";
+
+        let recipe = resolve_extends(child, &dir.join("child.yaml"), read_base_recipe_file)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            format!("Read {}/style.md", dir.canonicalize().unwrap().display())
+        );
+    }
+}
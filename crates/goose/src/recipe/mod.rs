@@ -4,9 +4,25 @@ use std::collections::HashMap;
 use std::fmt;
 
 use crate::agents::extension::ExtensionConfig;
+use crate::config::{Budget, ShellSandbox};
+use crate::context_mgmt::compaction::CompactionSettings;
 use serde::de::Deserializer;
 use serde::{Deserialize, Serialize};
 
+mod extends;
+mod parameters;
+mod steps;
+
+pub use extends::{read_base_recipe_file, resolve_extends};
+pub use parameters::{
+    validate_parameter_definitions, validate_parameter_values,
+    validate_parameter_values_without_files, BUILT_IN_RECIPE_DIR_PARAM,
+};
+pub use steps::{
+    step_parameter_references, validate_steps, RecipeStep, StepContext, STEP_STATUS_FAILED,
+    STEP_STATUS_SKIPPED, STEP_STATUS_SUCCESS,
+};
+
 fn default_version() -> String {
     "1.0.0".to_string()
 }
@@ -30,6 +46,8 @@ fn default_version() -> String {
 /// * `author` - Information about the Recipe's creator and metadata
 /// * `parameters` - Additional parameters for the Recipe
 /// * `response` - Response configuration including JSON schema validation
+/// * `extends` - Base recipes this Recipe builds on, see [`Recipe::extend`]
+/// * `steps` - Stages to run in order before the prompt, see [`RecipeStep`]
 ///
 /// # Example
 ///
@@ -59,6 +77,8 @@ fn default_version() -> String {
 ///     parameters: None,
 ///     response: None,
 ///     sub_recipes: None,
+///     extends: None,
+///     steps: None,
 /// };
 ///
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -102,6 +122,16 @@ pub struct Recipe {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sub_recipes: Option<Vec<SubRecipe>>, // sub-recipes for the recipe
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_one_or_many",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub extends: Option<Vec<String>>, // paths or names of base recipes, applied in order
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<RecipeStep>>, // stages to run in order before the prompt
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -123,6 +153,65 @@ pub struct Settings {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<ShellSandbox>, // restrictions on the developer shell
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<CompactionSettings>, // how to compact the conversation as it grows
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>, // most tokens or money a session of this recipe may use
+}
+
+impl Settings {
+    /// Each setting from this recipe if set, otherwise from `base`
+    pub fn extend(self, base: Settings) -> Settings {
+        Settings {
+            goose_provider: self.goose_provider.or(base.goose_provider),
+            goose_model: self.goose_model.or(base.goose_model),
+            temperature: self.temperature.or(base.temperature),
+            sandbox: self.sandbox.or(base.sandbox),
+            compaction: self.compaction.or(base.compaction),
+            budget: self.budget.or(base.budget),
+        }
+    }
+}
+
+/// The base's items followed by the recipe's own, where an item of the recipe replaces the
+/// base's item with the same key in place
+fn merge_by_key<T>(
+    base: Option<Vec<T>>,
+    own: Option<Vec<T>>,
+    key: impl Fn(&T) -> String,
+) -> Option<Vec<T>> {
+    let (mut merged, own) = match (base, own) {
+        (Some(base), Some(own)) => (base, own),
+        (base, own) => return own.or(base),
+    };
+    for item in own {
+        match merged
+            .iter()
+            .position(|existing| key(existing) == key(&item))
+        {
+            Some(index) => merged[index] = item,
+            None => merged.push(item),
+        }
+    }
+    Some(merged)
+}
+
+fn merge_unique(base: Option<Vec<String>>, own: Option<Vec<String>>) -> Option<Vec<String>> {
+    let (mut merged, own) = match (base, own) {
+        (Some(base), Some(own)) => (base, own),
+        (base, own) => return own.or(base),
+    };
+    for item in own {
+        if !merged.contains(&item) {
+            merged.push(item);
+        }
+    }
+    Some(merged)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -139,6 +228,25 @@ pub struct SubRecipe {
     pub values: Option<HashMap<String, String>>,
 }
 
+fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(
+        Option::<OneOrMany>::deserialize(deserializer)?.map(|value| match value {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        }),
+    )
+}
+
 fn deserialize_value_map_as_string<'de, D>(
     deserializer: D,
 ) -> Result<Option<HashMap<String, String>>, D::Error>
@@ -189,7 +297,12 @@ pub enum RecipeParameterInputType {
     Number,
     Boolean,
     Date,
+    /// A path whose file content is put in the template
     File,
+    /// One of the parameter's `options`
+    Enum,
+    /// Comma separated values
+    List,
 }
 
 impl fmt::Display for RecipeParameterInputType {
@@ -210,6 +323,18 @@ pub struct RecipeParameter {
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
+    /// The allowed values of an `enum`, or of each item of a `list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    /// A regex the whole value of a `string`, or each item of a `list`, must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The smallest `number`, or the fewest characters of a `string` or items of a `list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// The largest `number`, or the most characters of a `string` or items of a `list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
 }
 
 /// Builder for creating Recipe instances
@@ -230,6 +355,7 @@ pub struct RecipeBuilder {
     parameters: Option<Vec<RecipeParameter>>,
     response: Option<Response>,
     sub_recipes: Option<Vec<SubRecipe>>,
+    steps: Option<Vec<RecipeStep>>,
 }
 
 impl Recipe {
@@ -262,6 +388,43 @@ impl Recipe {
             parameters: None,
             response: None,
             sub_recipes: None,
+            steps: None,
+        }
+    }
+
+    /// Apply this recipe on top of `base`, as a recipe that `extends` it
+    ///
+    /// * `instructions` are joined, the base's first
+    /// * `extensions`, `parameters`, `sub_recipes` and `steps` are merged by name or key, and
+    ///   this recipe's entry replaces a base entry with the same name
+    /// * `context` and `activities` are joined, leaving out duplicates
+    /// * each of the `settings` is taken from this recipe if set, otherwise from the base
+    /// * everything else is taken from this recipe if set, otherwise from the base
+    ///
+    /// The result has no `extends`, since its bases have been applied.
+    pub fn extend(self, base: Recipe) -> Recipe {
+        Recipe {
+            version: self.version,
+            title: self.title,
+            description: self.description,
+            instructions: match (base.instructions, self.instructions) {
+                (Some(base), Some(own)) => Some(format!("{}\n\n{}", base.trim_end(), own)),
+                (base, own) => own.or(base),
+            },
+            prompt: self.prompt.or(base.prompt),
+            extensions: merge_by_key(base.extensions, self.extensions, |e| e.name()),
+            context: merge_unique(base.context, self.context),
+            settings: match (base.settings, self.settings) {
+                (Some(base), Some(own)) => Some(own.extend(base)),
+                (base, own) => own.or(base),
+            },
+            activities: merge_unique(base.activities, self.activities),
+            author: self.author.or(base.author),
+            parameters: merge_by_key(base.parameters, self.parameters, |p| p.key.clone()),
+            response: self.response.or(base.response),
+            sub_recipes: merge_by_key(base.sub_recipes, self.sub_recipes, |s| s.name.clone()),
+            extends: None,
+            steps: merge_by_key(base.steps, self.steps, |s| s.name.clone()),
         }
     }
     pub fn from_content(content: &str) -> Result<Self> {
@@ -352,6 +515,12 @@ impl RecipeBuilder {
         self
     }
 
+    /// Sets the steps to run before the prompt
+    pub fn steps(mut self, steps: Vec<RecipeStep>) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
     /// Builds the Recipe instance
     ///
     /// Returns an error if any required fields are missing
@@ -377,6 +546,8 @@ impl RecipeBuilder {
             parameters: self.parameters,
             response: self.response,
             sub_recipes: self.sub_recipes,
+            extends: None,
+            steps: self.steps,
         })
     }
 }
@@ -621,4 +792,84 @@ sub_recipes:
         let activities = recipe.activities.unwrap();
         assert_eq!(activities, vec!["activity1", "activity2"]);
     }
+
+    #[test]
+    fn test_extend_merges_base_recipe() {
+        let base = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Base
+description: Shared setup
+instructions: Follow the team style guide.
+activities: [review, test]
+extensions:
+  - type: builtin
+    name: developer
+    timeout: 300
+  - type: builtin
+    name: memory
+parameters:
+  - key: language
+    input_type: string
+    requirement: optional
+    default: rust
+    description: The language
+settings:
+  goose_model: base-model
+  temperature: 0.2
+"#,
+        )
+        .unwrap();
+        let child = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Child
+description: Reviews code
+instructions: Review the open pull request.
+extends: base.yaml
+activities: [test, ship]
+extensions:
+  - type: builtin
+    name: developer
+    timeout: 60
+parameters:
+  - key: language
+    input_type: string
+    requirement: required
+    description: The language
+settings:
+  temperature: 0.7
+"#,
+        )
+        .unwrap();
+        assert_eq!(child.extends, Some(vec!["base.yaml".to_string()]));
+
+        let merged = child.extend(base);
+        assert_eq!(merged.title, "Child");
+        assert_eq!(
+            merged.instructions.as_deref(),
+            Some("Follow the team style guide.\n\nReview the open pull request.")
+        );
+        assert_eq!(merged.activities.unwrap(), vec!["review", "test", "ship"]);
+
+        let extensions = merged.extensions.unwrap();
+        let names: Vec<String> = extensions.iter().map(|e| e.name()).collect();
+        assert_eq!(names, vec!["developer", "memory"]);
+        match &extensions[0] {
+            ExtensionConfig::Builtin { timeout, .. } => assert_eq!(*timeout, Some(60)),
+            other => panic!("expected a builtin extension, got {:?}", other),
+        }
+
+        let parameters = merged.parameters.unwrap();
+        assert_eq!(parameters.len(), 1);
+        assert!(matches!(
+            parameters[0].requirement,
+            RecipeParameterRequirement::Required
+        ));
+
+        let settings = merged.settings.unwrap();
+        assert_eq!(settings.goose_model.as_deref(), Some("base-model"));
+        assert_eq!(settings.temperature, Some(0.7));
+        assert!(merged.extends.is_none());
+    }
 }
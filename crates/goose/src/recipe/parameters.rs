@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use regex::Regex;
+
+use super::{RecipeParameter, RecipeParameterInputType};
+
+/// The built-in parameter holding the directory of the recipe file
+pub const BUILT_IN_RECIPE_DIR_PARAM: &str = "recipe_dir";
+
+/// Check that each parameter's constraints make sense for its type
+pub fn validate_parameter_definitions(parameters: &[RecipeParameter]) -> Result<()> {
+    let mut errors = Vec::new();
+    for param in parameters {
+        if let Err(error) = check_definition(param) {
+            errors.push(format!("'{}': {}", param.key, error));
+        }
+    }
+    into_result(errors, "Invalid parameter definitions in the recipe")
+}
+
+/// Check the values given for `parameters` against their types and constraints
+///
+/// Returns the values to render the recipe with: booleans become `true` or `false`, list
+/// items are trimmed, and a `file` parameter's path is replaced with the file's content.
+/// Relative paths are taken from the recipe's directory when `values` has the built-in
+/// `recipe_dir`. Values without a matching parameter, such as built-in ones, are kept as they are. Every
+/// invalid value is reported, not just the first.
+pub fn validate_parameter_values(
+    parameters: &[RecipeParameter],
+    values: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    check_values(parameters, values, true)
+}
+
+/// Check values like [`validate_parameter_values`], without reading any files
+///
+/// A `file` parameter only has to name an existing file, and its path is kept as the value.
+pub fn validate_parameter_values_without_files(
+    parameters: &[RecipeParameter],
+    values: &HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    check_values(parameters, values, false)
+}
+
+fn check_values(
+    parameters: &[RecipeParameter],
+    values: &HashMap<String, String>,
+    read_files: bool,
+) -> Result<HashMap<String, String>> {
+    let mut validated = values.clone();
+    let mut errors = Vec::new();
+    let recipe_dir = values.get(BUILT_IN_RECIPE_DIR_PARAM).map(Path::new);
+    for param in parameters {
+        let Some(value) = values.get(&param.key) else {
+            continue;
+        };
+        match check_value(param, value, read_files, recipe_dir) {
+            Ok(value) => {
+                validated.insert(param.key.clone(), value);
+            }
+            Err(error) => errors.push(format!("'{}': {}", param.key, error)),
+        }
+    }
+    into_result(errors, "Invalid recipe parameter values")?;
+    Ok(validated)
+}
+
+pub(super) fn into_result(errors: Vec<String>, heading: &str) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "{}:\n  - {}",
+        heading,
+        errors.join("\n  - ")
+    ))
+}
+
+fn check_definition(param: &RecipeParameter) -> Result<(), String> {
+    use RecipeParameterInputType::{Enum, List, Number};
+
+    let input_type = &param.input_type;
+    match (input_type, &param.options) {
+        (Enum, None) => return Err("an enum needs a list of options".to_string()),
+        (Enum, Some(options)) if options.is_empty() => {
+            return Err("an enum needs a list of options".to_string())
+        }
+        (Enum | List, _) | (_, None) => {}
+        (_, Some(_)) => return Err(format!("options don't apply to a {}", input_type)),
+    }
+    if let Some(pattern) = &param.pattern {
+        if !matches!(input_type, RecipeParameterInputType::String | List) {
+            return Err(format!("a pattern doesn't apply to a {}", input_type));
+        }
+        anchored_regex(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+    }
+    if (param.min.is_some() || param.max.is_some())
+        && !matches!(input_type, RecipeParameterInputType::String | Number | List)
+    {
+        return Err(format!("min and max don't apply to a {}", input_type));
+    }
+    if let (Some(min), Some(max)) = (param.min, param.max) {
+        if min > max {
+            return Err(format!("min {} is more than max {}", min, max));
+        }
+    }
+    Ok(())
+}
+
+fn check_value(
+    param: &RecipeParameter,
+    value: &str,
+    read_files: bool,
+    recipe_dir: Option<&Path>,
+) -> Result<String, String> {
+    match param.input_type {
+        RecipeParameterInputType::String => {
+            check_pattern(param, value)?;
+            check_range(param, value.chars().count() as f64, "characters")?;
+            Ok(value.to_string())
+        }
+        RecipeParameterInputType::Number => {
+            let number: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", value))?;
+            check_range(param, number, "")?;
+            Ok(value.trim().to_string())
+        }
+        RecipeParameterInputType::Boolean => match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "y" | "1" => Ok("true".to_string()),
+            "false" | "no" | "n" | "0" => Ok("false".to_string()),
+            _ => Err(format!("'{}' is not true or false", value)),
+        },
+        RecipeParameterInputType::Date => {
+            let value = value.trim();
+            if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_err()
+                && DateTime::parse_from_rfc3339(value).is_err()
+            {
+                return Err(format!(
+                    "'{}' is not a date, use YYYY-MM-DD or RFC 3339",
+                    value
+                ));
+            }
+            Ok(value.to_string())
+        }
+        RecipeParameterInputType::File if read_files => {
+            let path = file_path(value, recipe_dir);
+            std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read file {}: {}", path.display(), e))
+        }
+        RecipeParameterInputType::File => {
+            let path = file_path(value, recipe_dir);
+            if path.is_file() {
+                Ok(value.trim().to_string())
+            } else {
+                Err(format!("{} is not a file", path.display()))
+            }
+        }
+        RecipeParameterInputType::Enum => {
+            check_option(param, value.trim())?;
+            Ok(value.trim().to_string())
+        }
+        RecipeParameterInputType::List => {
+            let items: Vec<&str> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect();
+            for item in &items {
+                check_option(param, item)?;
+                check_pattern(param, item)?;
+            }
+            check_range(param, items.len() as f64, "items")?;
+            Ok(items.join(", "))
+        }
+    }
+}
+
+fn check_option(param: &RecipeParameter, value: &str) -> Result<(), String> {
+    match &param.options {
+        Some(options) if !options.iter().any(|option| option == value) => {
+            Err(format!("'{}' is not one of: {}", value, options.join(", ")))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_pattern(param: &RecipeParameter, value: &str) -> Result<(), String> {
+    let Some(pattern) = &param.pattern else {
+        return Ok(());
+    };
+    let regex =
+        anchored_regex(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+    if regex.is_match(value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' does not match the pattern {}",
+            value, pattern
+        ))
+    }
+}
+
+/// `size` is the number itself for a number, with an empty `unit`
+fn check_range(param: &RecipeParameter, size: f64, unit: &str) -> Result<(), String> {
+    let describe = |limit: f64| {
+        if unit.is_empty() {
+            limit.to_string()
+        } else {
+            format!("{} {}", limit, unit)
+        }
+    };
+    if let Some(min) = param.min {
+        if size < min {
+            return Err(format!("must be at least {}", describe(min)));
+        }
+    }
+    if let Some(max) = param.max {
+        if size > max {
+            return Err(format!("must be at most {}", describe(max)));
+        }
+    }
+    Ok(())
+}
+
+fn anchored_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The file a `file` parameter names, relative to the recipe's directory when it's known
+fn file_path(value: &str, recipe_dir: Option<&Path>) -> PathBuf {
+    let path = expand_home(value.trim());
+    match recipe_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::Recipe;
+
+    fn parameters(yaml: &str) -> Vec<RecipeParameter> {
+        let recipe = Recipe::from_content(&format!(
+            "version: 1.0.0\ntitle: t\ndescription: d\ninstructions: i\nparameters:\n{}",
+            yaml
+        ))
+        .unwrap();
+        recipe.parameters.unwrap()
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    const PARAMETERS: &str = r#"
+  - key: env
+    input_type: enum
+    requirement: required
+    description: Where to deploy
+    options: [dev, prod]
+  - key: count
+    input_type: number
+    requirement: required
+    description: How many
+    min: 1
+    max: 10
+  - key: ticket
+    input_type: string
+    requirement: required
+    description: Ticket id
+    pattern: "[A-Z]+-[0-9]+"
+  - key: dry_run
+    input_type: boolean
+    requirement: required
+    description: Skip changes
+  - key: regions
+    input_type: list
+    requirement: required
+    description: Regions
+    options: [us, eu, ap]
+    max: 2
+"#;
+
+    #[test]
+    fn test_valid_values_are_normalized() {
+        let params = parameters(PARAMETERS);
+        validate_parameter_definitions(&params).unwrap();
+        let validated = validate_parameter_values(
+            &params,
+            &values(&[
+                ("env", "prod"),
+                ("count", " 3 "),
+                ("ticket", "OPS-42"),
+                ("dry_run", "Yes"),
+                ("regions", "us,  eu"),
+                ("recipe_dir", "/recipes"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(validated["count"], "3");
+        assert_eq!(validated["dry_run"], "true");
+        assert_eq!(validated["regions"], "us, eu");
+        assert_eq!(validated["recipe_dir"], "/recipes");
+    }
+
+    #[test]
+    fn test_every_invalid_value_is_reported() {
+        let params = parameters(PARAMETERS);
+        let err = validate_parameter_values(
+            &params,
+            &values(&[
+                ("env", "staging"),
+                ("count", "11"),
+                ("ticket", "ops-42 and more"),
+                ("dry_run", "maybe"),
+                ("regions", "us, eu, ap"),
+            ]),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("'env': 'staging' is not one of: dev, prod"));
+        assert!(err.contains("'count': must be at most 10"));
+        assert!(err.contains("does not match the pattern [A-Z]+-[0-9]+"));
+        assert!(err.contains("'dry_run': 'maybe' is not true or false"));
+        assert!(err.contains("'regions': must be at most 2 items"));
+    }
+
+    #[test]
+    fn test_file_parameter_inlines_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "release notes").unwrap();
+        let params = parameters(
+            "  - key: notes\n    input_type: file\n    requirement: required\n    description: Notes\n",
+        );
+
+        let validated =
+            validate_parameter_values(&params, &values(&[("notes", path.to_str().unwrap())]))
+                .unwrap();
+        assert_eq!(validated["notes"], "release notes");
+
+        let missing = dir.path().join("missing.md");
+        let err =
+            validate_parameter_values(&params, &values(&[("notes", missing.to_str().unwrap())]))
+                .unwrap_err();
+        assert!(err.to_string().contains("could not read file"));
+
+        let checked = validate_parameter_values_without_files(
+            &params,
+            &values(&[("notes", path.to_str().unwrap())]),
+        )
+        .unwrap();
+        assert_eq!(checked["notes"], path.to_str().unwrap());
+        assert!(validate_parameter_values_without_files(
+            &params,
+            &values(&[("notes", missing.to_str().unwrap())])
+        )
+        .is_err());
+
+        let validated = validate_parameter_values(
+            &params,
+            &values(&[
+                ("notes", "notes.md"),
+                (BUILT_IN_RECIPE_DIR_PARAM, dir.path().to_str().unwrap()),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(validated["notes"], "release notes");
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let params = parameters(
+            r#"
+  - key: env
+    input_type: enum
+    requirement: required
+    description: No options
+  - key: flag
+    input_type: boolean
+    requirement: required
+    description: Has a pattern
+    pattern: "x"
+  - key: count
+    input_type: number
+    requirement: required
+    description: Bad range
+    min: 5
+    max: 1
+"#,
+        );
+        let err = validate_parameter_definitions(&params)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'env': an enum needs a list of options"));
+        assert!(err.contains("'flag': a pattern doesn't apply to a boolean"));
+        assert!(err.contains("'count': min 5 is more than max 1"));
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::parameters::into_result;
+use super::{deserialize_value_map_as_string, Recipe, RecipeParameterInputType};
+use crate::agents::extension::ExtensionConfig;
+
+pub const STEP_STATUS_SUCCESS: &str = "success";
+pub const STEP_STATUS_FAILED: &str = "failed";
+pub const STEP_STATUS_SKIPPED: &str = "skipped";
+
+/// One stage of a recipe's `steps`, run in order without the model having to decide to call it
+///
+/// A step runs one of the recipe's `sub_recipes`, or a fresh session with `instructions`.
+/// `when` is a condition on parameters and earlier steps, such as
+/// `env == 'prod' and steps.test.status == 'success'`, and `for_each` names a `list`
+/// parameter to run the step once per item. `values` and `instructions` may refer to
+/// `${item}`, `${steps.<name>.output}`, `${steps.<name>.status}` or a parameter key.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecipeStep {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_recipe: Option<String>, // name of the sub-recipe to run
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>, // text to run when there is no sub-recipe
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_value_map_as_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub values: Option<HashMap<String, String>>, // parameters for the sub-recipe
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>, // run the step only if this holds
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub for_each: Option<String>, // key of a list parameter to run the step for each item of
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parallel: bool, // run the `for_each` items at the same time
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_error: bool, // keep going with the next steps if this one fails
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<ExtensionConfig>>, // extensions to add for this step only
+}
+
+/// Check that the recipe's `steps` only refer to sub-recipes, parameters and earlier steps
+/// that exist, and that their conditions parse
+pub fn validate_steps(recipe: &Recipe) -> Result<()> {
+    let Some(steps) = &recipe.steps else {
+        return Ok(());
+    };
+    let parameters = recipe.parameters.as_deref().unwrap_or_default();
+    let sub_recipes: HashSet<&str> = recipe
+        .sub_recipes
+        .iter()
+        .flatten()
+        .map(|s| s.name.as_str())
+        .collect();
+
+    let mut errors = Vec::new();
+    let mut known: HashSet<String> = parameters.iter().map(|p| p.key.clone()).collect();
+    for step in steps {
+        let mut error = |message: String| errors.push(format!("'{}': {}", step.name, message));
+
+        if step.name.is_empty() || step.name.contains(|c: char| c == '.' || c.is_whitespace()) {
+            error("a step name can't be empty or contain dots or spaces".to_string());
+        }
+        if known.contains(&step_reference(&step.name, "status")) {
+            error("another step has the same name".to_string());
+        }
+        match (&step.sub_recipe, &step.instructions) {
+            (Some(name), None) if !sub_recipes.contains(name.as_str()) => {
+                error(format!("there is no sub-recipe named '{}'", name))
+            }
+            (Some(_), None) => {}
+            (None, Some(_)) if step.values.is_some() => {
+                error("values only apply to a sub_recipe step".to_string())
+            }
+            (None, Some(_)) => {}
+            _ => error("a step needs either a sub_recipe or instructions".to_string()),
+        }
+        if let Some(for_each) = &step.for_each {
+            let is_list = parameters.iter().any(|p| {
+                &p.key == for_each && matches!(p.input_type, RecipeParameterInputType::List)
+            });
+            if !is_list {
+                error(format!("for_each '{}' is not a list parameter", for_each));
+            }
+        } else if step.parallel {
+            error("parallel only applies to a for_each step".to_string());
+        }
+        for extension in step.extensions.iter().flatten() {
+            if !matches!(
+                extension,
+                ExtensionConfig::Builtin { .. }
+                    | ExtensionConfig::Stdio { .. }
+                    | ExtensionConfig::Sse { .. }
+            ) {
+                error(format!(
+                    "extension '{}' can't be added to a step, use builtin, stdio or sse",
+                    extension.name()
+                ));
+            }
+        }
+
+        let mut references = Vec::new();
+        if let Some(when) = &step.when {
+            match parse_condition(when) {
+                Ok(condition) => condition.references(&mut references),
+                Err(e) => error(format!("invalid when '{}': {}", when, e)),
+            }
+        }
+        for text in step.values.iter().flat_map(|v| v.values()) {
+            references.extend(placeholders(text));
+        }
+        references.extend(step.instructions.iter().flat_map(|text| placeholders(text)));
+        for reference in references {
+            let is_item = reference == ITEM && step.for_each.is_some();
+            if !is_item && !known.contains(&reference) {
+                error(format!("unknown reference '{}'", reference));
+            }
+        }
+
+        known.insert(step_reference(&step.name, "output"));
+        known.insert(step_reference(&step.name, "status"));
+    }
+    into_result(errors, "Invalid recipe steps")
+}
+
+/// The parameter keys that the recipe's `steps` refer to, which don't appear in its template
+pub fn step_parameter_references(steps: &[RecipeStep]) -> HashSet<String> {
+    let mut references = Vec::new();
+    for step in steps {
+        references.extend(step.for_each.clone());
+        if let Some(Ok(condition)) = step.when.as_deref().map(parse_condition) {
+            condition.references(&mut references);
+        }
+        for text in step.values.iter().flat_map(|v| v.values()) {
+            references.extend(placeholders(text));
+        }
+        references.extend(step.instructions.iter().flat_map(|text| placeholders(text)));
+    }
+    references
+        .into_iter()
+        .filter(|r| r != ITEM && !r.starts_with("steps."))
+        .collect()
+}
+
+const ITEM: &str = "item";
+
+fn step_reference(step: &str, field: &str) -> String {
+    format!("steps.{}.{}", step, field)
+}
+
+/// The parameters, outputs of earlier steps and current `for_each` item a step can refer to
+#[derive(Debug, Clone, Default)]
+pub struct StepContext {
+    values: HashMap<String, String>,
+}
+
+impl StepContext {
+    pub fn new(params: &HashMap<String, String>) -> Self {
+        Self {
+            values: params.clone(),
+        }
+    }
+
+    /// The context for running the recipe's steps with the parameter values `params`
+    ///
+    /// Optional parameters left without a value are empty, so conditions on them are false
+    /// and they substitute as blank instead of failing the step.
+    pub fn for_recipe(recipe: &Recipe, params: &HashMap<String, String>) -> Self {
+        let mut values: HashMap<String, String> = recipe
+            .parameters
+            .iter()
+            .flatten()
+            .map(|p| (p.key.clone(), String::new()))
+            .collect();
+        values.extend(params.clone());
+        Self { values }
+    }
+
+    /// Make a finished step's status and output available to the steps after it
+    pub fn record(&mut self, step: &str, status: &str, output: &str) {
+        self.values
+            .insert(step_reference(step, "status"), status.to_string());
+        self.values
+            .insert(step_reference(step, "output"), output.to_string());
+    }
+
+    pub fn with_item(&self, item: &str) -> Self {
+        let mut context = self.clone();
+        context.values.insert(ITEM.to_string(), item.to_string());
+        context
+    }
+
+    fn resolve(&self, reference: &str) -> Result<&str> {
+        self.values
+            .get(reference)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not defined", reference))
+    }
+
+    pub fn evaluate(&self, condition: &str) -> Result<bool> {
+        parse_condition(condition)?.evaluate(self)
+    }
+
+    /// The trimmed, non-empty items of the comma separated list parameter `key`
+    pub fn items(&self, key: &str) -> Result<Vec<String>> {
+        Ok(self
+            .resolve(key)?
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Replace each `${reference}` in `text` with its value, leaving unknown ones as they are
+    /// so that text such as `${HOME}` in a shell command survives
+    pub fn substitute(&self, text: &str) -> String {
+        PLACEHOLDER_RE
+            .replace_all(text, |captures: &regex::Captures| {
+                match self.values.get(&captures[1]) {
+                    Some(value) => value.clone(),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+static PLACEHOLDER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$\{\s*([A-Za-z0-9_.-]+)\s*\}").unwrap());
+
+fn placeholders(text: &str) -> Vec<String> {
+    PLACEHOLDER_RE
+        .captures_iter(text)
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Word(String),
+    Equal,
+    NotEqual,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(other) => literal.push(other),
+                        None => return Err(anyhow::anyhow!("unterminated quote")),
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(anyhow::anyhow!("expected '{}='", c));
+                }
+                tokens.push(if c == '=' {
+                    Token::Equal
+                } else {
+                    Token::NotEqual
+                });
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '\'' | '"' | '=' | '!') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Operand {
+    Literal(String),
+    Reference(String),
+}
+
+impl Operand {
+    fn value<'a>(&'a self, context: &'a StepContext) -> Result<&'a str> {
+        match self {
+            Operand::Literal(literal) => Ok(literal),
+            Operand::Reference(reference) => context.resolve(reference),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Condition {
+    Truthy(Operand),
+    Equal(Operand, Operand),
+    NotEqual(Operand, Operand),
+    Contains(Operand, Operand),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    fn evaluate(&self, context: &StepContext) -> Result<bool> {
+        Ok(match self {
+            Condition::Truthy(operand) => {
+                let value = operand.value(context)?.trim();
+                !(value.is_empty()
+                    || value == "0"
+                    || value.eq_ignore_ascii_case("false")
+                    || value.eq_ignore_ascii_case("no"))
+            }
+            Condition::Equal(left, right) => left.value(context)? == right.value(context)?,
+            Condition::NotEqual(left, right) => left.value(context)? != right.value(context)?,
+            Condition::Contains(left, right) => {
+                left.value(context)?.contains(right.value(context)?)
+            }
+            Condition::Not(inner) => !inner.evaluate(context)?,
+            Condition::And(left, right) => left.evaluate(context)? && right.evaluate(context)?,
+            Condition::Or(left, right) => left.evaluate(context)? || right.evaluate(context)?,
+        })
+    }
+
+    fn references(&self, references: &mut Vec<String>) {
+        let mut add = |operand: &Operand| {
+            if let Operand::Reference(reference) = operand {
+                references.push(reference.clone());
+            }
+        };
+        match self {
+            Condition::Truthy(operand) => add(operand),
+            Condition::Equal(left, right)
+            | Condition::NotEqual(left, right)
+            | Condition::Contains(left, right) => {
+                add(left);
+                add(right);
+            }
+            Condition::Not(inner) => inner.references(references),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.references(references);
+                right.references(references);
+            }
+        }
+    }
+}
+
+/// `or` binds loosest, then `and`, then `not`, then `==`, `!=` and `contains`
+fn parse_condition(text: &str) -> Result<Condition> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, next: 0 };
+    let condition = parser.or()?;
+    match parser.tokens.get(parser.next) {
+        None => Ok(condition),
+        Some(token) => Err(anyhow::anyhow!("unexpected {:?}", token)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.tokens.get(self.next), Some(Token::Word(word)) if word == keyword) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition> {
+        let mut condition = self.and()?;
+        while self.keyword("or") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition> {
+        let mut condition = self.not()?;
+        while self.keyword("and") {
+            condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<Condition> {
+        if self.keyword("not") {
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Condition> {
+        let left = self.operand()?;
+        let condition = match self.tokens.get(self.next) {
+            Some(Token::Equal) => {
+                self.next += 1;
+                Condition::Equal(left, self.operand()?)
+            }
+            Some(Token::NotEqual) => {
+                self.next += 1;
+                Condition::NotEqual(left, self.operand()?)
+            }
+            Some(Token::Word(word)) if word == "contains" => {
+                self.next += 1;
+                Condition::Contains(left, self.operand()?)
+            }
+            _ => Condition::Truthy(left),
+        };
+        Ok(condition)
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of condition"))?;
+        self.next += 1;
+        match token {
+            Token::Literal(literal) => Ok(Operand::Literal(literal)),
+            Token::Word(word) if matches!(word.as_str(), "and" | "or" | "not" | "contains") => {
+                Err(anyhow::anyhow!("expected a value before '{}'", word))
+            }
+            Token::Word(word)
+                if word.parse::<f64>().is_ok() || word == "true" || word == "false" =>
+            {
+                Ok(Operand::Literal(word))
+            }
+            Token::Word(word) => Ok(Operand::Reference(word)),
+            other => Err(anyhow::anyhow!("expected a value, found {:?}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> StepContext {
+        let mut context = StepContext::new(&HashMap::from([
+            ("env".to_string(), "prod".to_string()),
+            ("regions".to_string(), "us, eu".to_string()),
+            ("dry_run".to_string(), "false".to_string()),
+        ]));
+        context.record("test", STEP_STATUS_SUCCESS, "all 12 tests passed");
+        context
+    }
+
+    #[test]
+    fn test_conditions() {
+        let context = context();
+        assert!(context.evaluate("env == 'prod'").unwrap());
+        assert!(context.evaluate("env != \"dev\"").unwrap());
+        assert!(!context.evaluate("dry_run").unwrap());
+        assert!(context.evaluate("not dry_run and env == 'prod'").unwrap());
+        assert!(context
+            .evaluate("env == 'dev' or steps.test.status == 'success'")
+            .unwrap());
+        assert!(context.evaluate("regions contains 'eu'").unwrap());
+        assert!(context
+            .evaluate("steps.test.output contains 'passed'")
+            .unwrap());
+
+        assert!(context
+            .evaluate("steps.deploy.status == 'success'")
+            .is_err());
+        assert!(context.evaluate("env == 'prod").is_err());
+        assert!(context.evaluate("env = 'prod'").is_err());
+        assert!(context.evaluate("env == and").is_err());
+    }
+
+    #[test]
+    fn test_substitute_and_items() {
+        let context = context().with_item("eu");
+        assert_eq!(
+            context.substitute("deploy ${item} to ${env}: ${ steps.test.output }"),
+            "deploy eu to prod: all 12 tests passed"
+        );
+        assert_eq!(context.substitute("cd ${HOME}"), "cd ${HOME}");
+        assert_eq!(context.items("regions").unwrap(), vec!["us", "eu"]);
+    }
+
+    #[test]
+    fn test_unset_optional_parameters_are_empty() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Notify
+description: Notify
+instructions: Notify
+parameters:
+  - key: channel
+    input_type: string
+    requirement: optional
+    description: Channel
+  - key: env
+    input_type: string
+    requirement: required
+    description: Environment
+"#,
+        )
+        .unwrap();
+        let params = HashMap::from([("env".to_string(), "prod".to_string())]);
+        let context = StepContext::for_recipe(&recipe, &params);
+        assert!(!context.evaluate("channel").unwrap());
+        assert!(context.evaluate("channel == ''").unwrap());
+        assert_eq!(
+            context.substitute("post to ${channel} in ${env}"),
+            "post to  in prod"
+        );
+    }
+
+    #[test]
+    fn test_validate_steps() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Release
+description: Release to each region
+instructions: Release
+parameters:
+  - key: regions
+    input_type: list
+    requirement: required
+    description: Regions
+  - key: env
+    input_type: string
+    requirement: required
+    description: Environment
+sub_recipes:
+  - name: deploy
+    path: deploy.yaml
+steps:
+  - name: test
+    instructions: Run the tests in ${env}
+  - name: deploy
+    sub_recipe: deploy
+    when: steps.test.status == 'success' and steps.notify.status == 'success'
+    for_each: regions
+    values:
+      region: ${item}
+  - name: notify
+    sub_recipe: missing
+    for_each: env
+  - name: test
+    parallel: true
+    instructions: Report ${item}
+"#,
+        )
+        .unwrap();
+        let err = validate_steps(&recipe).unwrap_err().to_string();
+        assert!(err.contains("'deploy': unknown reference 'steps.notify.status'"));
+        assert!(err.contains("'notify': there is no sub-recipe named 'missing'"));
+        assert!(err.contains("'notify': for_each 'env' is not a list parameter"));
+        assert!(err.contains("'test': another step has the same name"));
+        assert!(err.contains("'test': parallel only applies to a for_each step"));
+        assert!(err.contains("'test': unknown reference 'item'"));
+        assert!(!err.contains("'test': unknown reference 'env'"));
+
+        let references = step_parameter_references(recipe.steps.as_ref().unwrap());
+        assert_eq!(
+            references,
+            HashSet::from(["env".to_string(), "regions".to_string()])
+        );
+    }
+}
@@ -12,13 +12,14 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tokio_cron_scheduler::{job::JobId, Job, JobScheduler as TokioJobScheduler};
 
+use crate::agents::sub_recipe_execution_tool::steps::run_recipe_steps;
 use crate::agents::AgentEvent;
 use crate::agents::{Agent, SessionConfig};
 use crate::config::{self, Config};
 use crate::message::Message;
 use crate::providers::base::Provider as GooseProvider; // Alias to avoid conflict in test section
 use crate::providers::create;
-use crate::recipe::Recipe;
+use crate::recipe::{read_base_recipe_file, resolve_extends, Recipe};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::session;
 use crate::session::storage::SessionMetadata;
@@ -1083,7 +1084,20 @@ async fn run_scheduled_job_internal(
         }
     };
 
-    let recipe: Recipe = {
+    let extended =
+        resolve_extends(&recipe_content, recipe_path, read_base_recipe_file).map_err(|e| {
+            JobExecutionError {
+                job_id: job.id.clone(),
+                error: format!(
+                    "Failed to resolve the recipes '{}' extends: {}",
+                    job.source, e
+                ),
+            }
+        })?;
+
+    let mut recipe: Recipe = if let Some(recipe) = extended {
+        recipe
+    } else {
         let extension = recipe_path
             .extension()
             .and_then(|os_str| os_str.to_str())
@@ -1110,10 +1124,40 @@ async fn run_scheduled_job_internal(
                     extension, job.source
                 ),
             }),
-        }
-    }?;
+        }?
+    };
+
+    // Run the recipe's steps first, with its parameter defaults as there is no one to ask
+    if recipe.steps.is_some() {
+        let params: HashMap<String, String> = recipe
+            .parameters
+            .iter()
+            .flatten()
+            .filter_map(|param| Some((param.key.clone(), param.default.clone()?)))
+            .collect();
+        let run = run_recipe_steps(&recipe, &params)
+            .await
+            .and_then(|run| match run.stop_error() {
+                Some(e) => Err(e),
+                None => Ok(run),
+            })
+            .map_err(|e| JobExecutionError {
+                job_id: job.id.clone(),
+                error: format!("Failed to run the steps of recipe '{}': {}", job.source, e),
+            })?;
+        recipe.prompt = recipe.prompt.map(|prompt| run.context.substitute(&prompt));
+    }
 
     let agent: Agent = Agent::new();
+    agent
+        .set_shell_sandbox(recipe.settings.as_ref().and_then(|s| s.sandbox.clone()))
+        .await;
+    agent
+        .set_compaction(recipe.settings.as_ref().and_then(|s| s.compaction.clone()))
+        .await;
+    agent
+        .set_budget(recipe.settings.as_ref().and_then(|s| s.budget.clone()))
+        .await;
 
     let agent_provider: Arc<dyn GooseProvider>; // Use the aliased GooseProvider
 
@@ -1224,12 +1268,18 @@ async fn run_scheduled_job_internal(
                             }
                             all_session_messages.push(msg);
                         }
+                        Ok(AgentEvent::MessageDelta(_)) => {
+                            // The complete message follows the deltas
+                        }
                         Ok(AgentEvent::McpNotification(_)) => {
                             // Handle notifications if needed
                         }
                         Ok(AgentEvent::ModelChange { .. }) => {
                             // Model change events are informational, just continue
                         }
+                        Ok(AgentEvent::HistoryReplaced(messages)) => {
+                            all_session_messages = messages;
+                        }
 
                         Err(e) => {
                             tracing::error!(
@@ -1274,6 +1324,15 @@ async fn run_scheduled_job_internal(
                             accumulated_total_tokens: None,
                             accumulated_input_tokens: None,
                             accumulated_output_tokens: None,
+                            cache_read_input_tokens: None,
+                            cache_write_input_tokens: None,
+                            accumulated_cache_read_input_tokens: None,
+                            accumulated_cache_write_input_tokens: None,
+                            cost: None,
+                            accumulated_cost: None,
+                            fork: None,
+                            branches: Vec::new(),
+                            routing: Vec::new(),
                         };
                         if let Err(e_fb) = crate::session::storage::save_messages_with_metadata(
                             &session_file_path,
@@ -1420,6 +1479,8 @@ mod tests {
             settings: None,
             response: None,
             sub_recipes: None,
+            extends: None,
+            steps: None,
         };
         let mut recipe_file = File::create(&recipe_filename)?;
         writeln!(
@@ -1456,8 +1517,8 @@ mod tests {
         let expected_session_path = session_dir.join(format!("{}.jsonl", created_session_id));
 
         assert!(
-            expected_session_path.exists(),
-            "Expected session file {} was not created",
+            session::storage::session_exists(&expected_session_path),
+            "Expected session {} was not created",
             expected_session_path.display()
         );
 
@@ -0,0 +1,284 @@
+//! Forking a session from an earlier message, so several continuations of one conversation
+//! can be kept side by side
+//!
+//! Each branch is a session file of its own, so usage is tracked per branch like any other
+//! session. The session that was first forked is the root of the tree and records every
+//! branch in its metadata.
+
+use crate::message::Message;
+use crate::session::storage::{self, Identifier, SessionMetadata};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+/// Where a forked session split off from the session it was forked from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ForkOrigin {
+    /// The session at the root of the branch tree
+    pub root_id: String,
+    /// The session this one was forked from
+    pub parent_id: String,
+    /// How many of the parent's messages this session started with
+    pub message_index: usize,
+}
+
+/// A fork recorded in the branch tree of the root session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SessionBranch {
+    pub session_id: String,
+    pub parent_id: String,
+    pub message_index: usize,
+}
+
+/// One session in a branch tree, with its own metadata and usage totals
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BranchInfo {
+    pub session_id: String,
+    pub path: String,
+    /// The session this one was forked from, none for the root
+    pub parent_id: Option<String>,
+    /// How many of the parent's messages this branch started with
+    pub message_index: Option<usize>,
+    /// How far below the root this branch is
+    pub depth: usize,
+    pub metadata: SessionMetadata,
+}
+
+fn session_id(session: &Path) -> Result<String> {
+    session
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("Invalid session file {}", session.display()))
+}
+
+/// The file of session `id`, next to `sibling`
+fn sibling_path(sibling: &Path, id: &str) -> Result<PathBuf> {
+    if id.is_empty() || id.contains("..") || id.contains('/') || id.contains('\\') {
+        return Err(anyhow::anyhow!("Invalid session name '{}'", id));
+    }
+    storage::get_path(Identifier::Path(
+        sibling.with_file_name(format!("{}.jsonl", id)),
+    ))
+}
+
+fn root_of(session: &Path, metadata: &SessionMetadata) -> Result<(String, PathBuf)> {
+    match &metadata.fork {
+        Some(origin) => Ok((
+            origin.root_id.clone(),
+            sibling_path(session, &origin.root_id)?,
+        )),
+        None => Ok((session_id(session)?, session.to_path_buf())),
+    }
+}
+
+/// Copy the first `message_index` messages of `session` into a new session and record it
+/// in the branch tree, returning the new session's file
+///
+/// The new session is named `name`, or after the root session and its number of branches.
+/// The original session is left as it was.
+pub async fn fork_session(
+    session: &Path,
+    message_index: usize,
+    name: Option<&str>,
+) -> Result<PathBuf> {
+    let session = storage::get_path(Identifier::Path(session.to_path_buf()))?;
+    if !storage::session_exists(&session) {
+        return Err(anyhow::anyhow!(
+            "Session {} does not exist",
+            session.display()
+        ));
+    }
+    let metadata = storage::read_metadata(&session)?;
+    let messages = storage::read_messages(&session)?;
+    check_fork_point(&messages, message_index)?;
+
+    let (root_id, root_path) = root_of(&session, &metadata)?;
+    let mut root_metadata = if root_path == session {
+        metadata.clone()
+    } else {
+        storage::read_metadata(&root_path)?
+    };
+
+    let (branch_id, branch_path) = match name {
+        Some(name) => {
+            let path = sibling_path(&session, name)?;
+            if storage::session_exists(&path) {
+                return Err(anyhow::anyhow!("A session named '{}' already exists", name));
+            }
+            (name.to_string(), path)
+        }
+        None => {
+            let mut number = root_metadata.branches.len() + 1;
+            loop {
+                let id = format!("{}-branch-{}", root_id, number);
+                let path = sibling_path(&session, &id)?;
+                if !storage::session_exists(&path) {
+                    break (id, path);
+                }
+                number += 1;
+            }
+        }
+    };
+
+    let parent_id = session_id(&session)?;
+    let mut branch_metadata = SessionMetadata::new(metadata.working_dir.clone());
+    branch_metadata.description = metadata.description.clone();
+    branch_metadata.message_count = message_index;
+    branch_metadata.fork = Some(ForkOrigin {
+        root_id,
+        parent_id: parent_id.clone(),
+        message_index,
+    });
+    storage::save_messages_with_metadata(
+        &branch_path,
+        &branch_metadata,
+        &messages[..message_index],
+    )?;
+
+    root_metadata.branches.push(SessionBranch {
+        session_id: branch_id,
+        parent_id,
+        message_index,
+    });
+    storage::update_metadata(&root_path, &root_metadata).await?;
+
+    Ok(branch_path)
+}
+
+/// A fork can't separate a tool call from its result, since providers reject a
+/// conversation that ends in an unanswered tool call
+fn check_fork_point(messages: &[Message], message_index: usize) -> Result<()> {
+    if message_index > messages.len() {
+        return Err(anyhow::anyhow!(
+            "Message index {} is past the end of the session, which has {} messages",
+            message_index,
+            messages.len()
+        ));
+    }
+    if message_index > 0 && messages[message_index - 1].is_tool_call() {
+        return Err(anyhow::anyhow!(
+            "Message {} is a tool call, fork before it or after its result instead",
+            message_index - 1
+        ));
+    }
+    Ok(())
+}
+
+/// Every session in the branch tree that `session` belongs to, root first and each branch
+/// after the session it was forked from
+///
+/// Branches whose files were removed are left out, along with anything forked from them.
+pub fn list_branches(session: &Path) -> Result<Vec<BranchInfo>> {
+    let session = storage::get_path(Identifier::Path(session.to_path_buf()))?;
+    let metadata = storage::read_metadata(&session)?;
+    let (root_id, root_path) = root_of(&session, &metadata)?;
+    let root_metadata = storage::read_metadata(&root_path)?;
+
+    let mut branches = vec![BranchInfo {
+        session_id: root_id.clone(),
+        path: root_path.to_string_lossy().into_owned(),
+        parent_id: None,
+        message_index: None,
+        depth: 0,
+        metadata: root_metadata.clone(),
+    }];
+    add_children(
+        &root_path,
+        &root_metadata.branches,
+        &root_id,
+        1,
+        &mut branches,
+    )?;
+    Ok(branches)
+}
+
+fn add_children(
+    root_path: &Path,
+    tree: &[SessionBranch],
+    parent_id: &str,
+    depth: usize,
+    branches: &mut Vec<BranchInfo>,
+) -> Result<()> {
+    for branch in tree.iter().filter(|b| b.parent_id == parent_id) {
+        let path = sibling_path(root_path, &branch.session_id)?;
+        if !storage::session_exists(&path) {
+            continue;
+        }
+        branches.push(BranchInfo {
+            session_id: branch.session_id.clone(),
+            path: path.to_string_lossy().into_owned(),
+            parent_id: Some(branch.parent_id.clone()),
+            message_index: Some(branch.message_index),
+            depth,
+            metadata: storage::read_metadata(&path)?,
+        });
+        add_children(root_path, tree, &branch.session_id, depth + 1, branches)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::user().with_text("write a haiku"),
+            Message::assistant().with_text("an old silent pond"),
+            Message::user().with_text("now a limerick"),
+            Message::assistant().with_text("there once was a goose"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_fork_builds_a_branch_tree() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("root.jsonl");
+        let mut metadata = SessionMetadata::new(dir.path().to_path_buf());
+        metadata.accumulated_total_tokens = Some(500);
+        storage::save_messages_with_metadata(&root, &metadata, &conversation())?;
+
+        let first = fork_session(&root, 2, None).await?;
+        assert_eq!(first, dir.path().join("root-branch-1.jsonl"));
+        assert_eq!(
+            storage::read_messages(&first)?,
+            conversation()[..2].to_vec()
+        );
+        let first_metadata = storage::read_metadata(&first)?;
+        assert_eq!(first_metadata.accumulated_total_tokens, None);
+        assert_eq!(first_metadata.fork.unwrap().parent_id, "root");
+
+        // Forking a branch still records it on the root
+        let nested = fork_session(&first, 0, Some("fresh")).await?;
+        assert!(storage::read_messages(&nested)?.is_empty());
+        let root_metadata = storage::read_metadata(&root)?;
+        assert_eq!(root_metadata.branches.len(), 2);
+        assert_eq!(root_metadata.accumulated_total_tokens, Some(500));
+        assert_eq!(storage::read_messages(&root)?, conversation());
+
+        let branches = list_branches(&nested)?;
+        let tree: Vec<(&str, usize)> = branches
+            .iter()
+            .map(|b| (b.session_id.as_str(), b.depth))
+            .collect();
+        assert_eq!(tree, vec![("root", 0), ("root-branch-1", 1), ("fresh", 2)]);
+
+        assert!(fork_session(&root, 2, Some("fresh")).await.is_err());
+        assert!(fork_session(&root, 2, Some("../escape")).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fork_point_must_be_in_range_and_not_split_tool_calls() {
+        let mut messages = conversation();
+        messages.push(
+            Message::assistant().with_tool_request("call-1", Ok(ToolCall::new("shell", json!({})))),
+        );
+        assert!(check_fork_point(&messages, 4).is_ok());
+        assert!(check_fork_point(&messages, 5).is_err());
+        assert!(check_fork_point(&messages, 6).is_err());
+    }
+}
@@ -29,15 +29,9 @@ pub fn get_valid_sorted_sessions(sort_order: SortOrder) -> Result<Vec<SessionInf
     let mut session_infos: Vec<SessionInfo> = sessions
         .into_iter()
         .filter_map(|(id, path)| {
-            let modified = path
-                .metadata()
-                .and_then(|m| m.modified())
-                .map(|time| {
-                    chrono::DateTime::<chrono::Utc>::from(time)
-                        .format("%Y-%m-%d %H:%M:%S UTC")
-                        .to_string()
-                })
-                .ok()?;
+            let modified = session::last_modified(&path)?
+                .format("%Y-%m-%d %H:%M:%S UTC")
+                .to_string();
 
             let metadata = session::read_metadata(&path).ok()?;
 
@@ -1,12 +1,20 @@
+pub mod branch;
 pub mod info;
 pub mod storage;
+pub mod store;
 
 // Re-export common session types and functions
 pub use storage::{
     ensure_session_dir, generate_description, generate_description_with_schedule_id,
-    generate_session_id, get_most_recent_session, get_path, list_sessions, persist_messages,
-    persist_messages_with_schedule_id, read_messages, read_metadata, update_metadata, Identifier,
-    SessionMetadata,
+    generate_session_id, get_most_recent_session, get_path, last_modified, list_sessions,
+    persist_messages, persist_messages_with_schedule_id, read_messages, read_metadata,
+    remove_session, session_exists, update_metadata, Identifier, SessionMetadata,
 };
 
+pub use store::{
+    search_sessions, SessionSearchResult, SessionStore, SqliteSessionStore, StoredSession,
+};
+
+pub use branch::{fork_session, list_branches, BranchInfo, ForkOrigin, SessionBranch};
+
 pub use info::{get_valid_sorted_sessions, SessionInfo};
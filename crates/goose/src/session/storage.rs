@@ -7,8 +7,11 @@
 
 use crate::message::Message;
 use crate::providers::base::Provider;
+use crate::providers::routing::RoutingDecision;
+use crate::session::branch::{ForkOrigin, SessionBranch};
+use crate::session::store::{self, SessionStore};
 use anyhow::Result;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -23,6 +26,9 @@ const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB
 const MAX_MESSAGE_COUNT: usize = 5000;
 const MAX_LINE_LENGTH: usize = 1024 * 1024; // 1MB per line
 
+/// How many routing decisions a session keeps, dropping the oldest beyond this
+pub const MAX_ROUTING_DECISIONS: usize = 100;
+
 fn get_home_dir() -> PathBuf {
     choose_app_strategy(crate::config::APP_STRATEGY.clone())
         .expect("goose requires a home dir")
@@ -54,6 +60,35 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// How many of the last usage's input tokens were read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+    /// How many of the last usage's input tokens were written to the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_input_tokens: Option<i32>,
+    /// Input tokens read from the prompt cache, accumulated across all messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulated_cache_read_input_tokens: Option<i32>,
+    /// Input tokens written to the prompt cache, accumulated across all messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulated_cache_write_input_tokens: Option<i32>,
+    /// The cost of the provider's last usage, if the model's pricing is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// The cost of the session so far, accumulated across every usage that could be priced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulated_cost: Option<f64>,
+    /// Where this session was forked from, if it is a branch of another session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<ForkOrigin>,
+    /// Every session forked from this one or its branches, if this is the root of a branch tree
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<SessionBranch>,
+    /// The model the latest turns were routed to and why, when `model_routing` is configured
+    ///
+    /// Only the last [`MAX_ROUTING_DECISIONS`] turns are kept.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing: Vec<RoutingDecision>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -73,7 +108,25 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: Option<i32>,
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
+            #[serde(default)]
+            cache_read_input_tokens: Option<i32>,
+            #[serde(default)]
+            cache_write_input_tokens: Option<i32>,
+            #[serde(default)]
+            accumulated_cache_read_input_tokens: Option<i32>,
+            #[serde(default)]
+            accumulated_cache_write_input_tokens: Option<i32>,
+            #[serde(default)]
+            cost: Option<f64>,
+            #[serde(default)]
+            accumulated_cost: Option<f64>,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            fork: Option<ForkOrigin>,
+            #[serde(default)]
+            branches: Vec<SessionBranch>,
+            #[serde(default)]
+            routing: Vec<RoutingDecision>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -94,7 +147,16 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: helper.accumulated_total_tokens,
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
+            cache_read_input_tokens: helper.cache_read_input_tokens,
+            cache_write_input_tokens: helper.cache_write_input_tokens,
+            accumulated_cache_read_input_tokens: helper.accumulated_cache_read_input_tokens,
+            accumulated_cache_write_input_tokens: helper.accumulated_cache_write_input_tokens,
+            cost: helper.cost,
+            accumulated_cost: helper.accumulated_cost,
             working_dir,
+            fork: helper.fork,
+            branches: helper.branches,
+            routing: helper.routing,
         })
     }
 }
@@ -119,6 +181,15 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            cache_read_input_tokens: None,
+            cache_write_input_tokens: None,
+            accumulated_cache_read_input_tokens: None,
+            accumulated_cache_write_input_tokens: None,
+            cost: None,
+            accumulated_cost: None,
+            fork: None,
+            branches: Vec::new(),
+            routing: Vec::new(),
         }
     }
 }
@@ -326,6 +397,15 @@ pub fn ensure_session_dir() -> Result<PathBuf> {
 
 /// Get the path to the most recently modified session file
 pub fn get_most_recent_session() -> Result<PathBuf> {
+    if let Some(store) = store::global() {
+        return store
+            .list()?
+            .into_iter()
+            .next()
+            .map(|session| session.path)
+            .ok_or_else(|| anyhow::anyhow!("No session files found"));
+    }
+
     let session_dir = ensure_session_dir()?;
     let mut entries = fs::read_dir(&session_dir)?
         .filter_map(|entry| entry.ok())
@@ -353,6 +433,14 @@ pub fn get_most_recent_session() -> Result<PathBuf> {
 
 /// List all available session files
 pub fn list_sessions() -> Result<Vec<(String, PathBuf)>> {
+    if let Some(store) = store::global() {
+        return Ok(store
+            .list()?
+            .into_iter()
+            .map(|session| (session.id, session.path))
+            .collect());
+    }
+
     let session_dir = ensure_session_dir()?;
     let entries = fs::read_dir(&session_dir)?
         .filter_map(|entry| {
@@ -371,11 +459,55 @@ pub fn list_sessions() -> Result<Vec<(String, PathBuf)>> {
     Ok(entries)
 }
 
+/// Whether the session has been saved
+pub fn session_exists(session_file: &Path) -> bool {
+    match store::for_session(session_file) {
+        Some(store) => store.modified(session_file).ok().flatten().is_some(),
+        None => session_file.exists(),
+    }
+}
+
+/// When the session was last saved
+pub fn last_modified(session_file: &Path) -> Option<DateTime<Utc>> {
+    match store::for_session(session_file) {
+        Some(store) => DateTime::from_timestamp_millis(store.modified(session_file).ok()??),
+        None => session_file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from),
+    }
+}
+
+/// Delete a session, along with the file it was stored in before the session store
+pub fn remove_session(session_file: &Path) -> Result<()> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    if let Some(store) = store::for_session(&secure_path) {
+        store.remove(&secure_path)?;
+    }
+    if secure_path.exists() {
+        fs::remove_file(&secure_path)?;
+    }
+    Ok(())
+}
+
 /// Generate a session ID using timestamp format (yyyymmdd_hhmmss)
 pub fn generate_session_id() -> String {
     Local::now().format("%Y%m%d_%H%M%S").to_string()
 }
 
+/// Read the messages of a session
+///
+/// Sessions in the session directory are read from the session store, others from their
+/// file as [`read_jsonl_messages`] does.
+pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    match store::for_session(&secure_path) {
+        Some(store) => store.read_messages(&secure_path),
+        None => read_jsonl_messages(&secure_path),
+    }
+}
+
 /// Read messages from a session file with corruption recovery
 ///
 /// Creates the file if it doesn't exist, reads and deserializes all messages if it does.
@@ -386,7 +518,7 @@ pub fn generate_session_id() -> String {
 /// Security features:
 /// - Validates file paths to prevent directory traversal
 /// - Includes all security limits from read_messages_with_truncation
-pub fn read_messages(session_file: &Path) -> Result<Vec<Message>> {
+pub fn read_jsonl_messages(session_file: &Path) -> Result<Vec<Message>> {
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
 
@@ -980,11 +1112,24 @@ fn truncate_json_string(json_str: &str, max_content_size: usize) -> String {
     result
 }
 
+/// Read the metadata of a session
+///
+/// Returns default empty metadata if the session hasn't been saved. Sessions in the session
+/// directory are read from the session store, others from their file as
+/// [`read_jsonl_metadata`] does.
+pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    match store::for_session(&secure_path) {
+        Some(store) => Ok(store.read_metadata(&secure_path)?.unwrap_or_default()),
+        None => read_jsonl_metadata(&secure_path),
+    }
+}
+
 /// Read session metadata from a session file with security validation
 ///
 /// Returns default empty metadata if the file doesn't exist or has no metadata.
 /// Includes security checks for file access and content validation.
-pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
+pub fn read_jsonl_metadata(session_file: &Path) -> Result<SessionMetadata> {
     // Validate the path for security
     let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
 
@@ -1095,9 +1240,13 @@ pub async fn persist_messages_with_schedule_id(
     }
 }
 
-/// Write messages to a session file with the provided metadata using secure atomic operations
+/// Save the messages of a session with the provided metadata
+///
+/// Sessions in the session directory are saved to the session store, which only writes the
+/// messages it doesn't have yet. Others are written to their file using secure atomic
+/// operations.
 ///
-/// This function uses atomic file operations to prevent corruption:
+/// The file is written using atomic file operations to prevent corruption:
 /// 1. Writes to a temporary file first with secure permissions
 /// 2. Uses fs2 file locking to prevent concurrent writes
 /// 3. Atomically moves the temp file to the final location
@@ -1127,6 +1276,10 @@ pub fn save_messages_with_metadata(
         return Err(anyhow::anyhow!("Too many messages to save"));
     }
 
+    if let Some(store) = store::for_session(&secure_path) {
+        return store.save(&secure_path, metadata, messages);
+    }
+
     // Create a temporary file in the same directory to ensure atomic move
     let temp_file = secure_path.with_extension("tmp");
 
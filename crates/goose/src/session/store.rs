@@ -0,0 +1,666 @@
+//! Session history in an embedded database, so sessions can be appended to and searched
+//! without rewriting or reading every `.jsonl` file
+//!
+//! Sessions in the session directory are stored in a SQLite database next to them, which is
+//! their source of truth. Sessions are still named by the path of their `.jsonl` file, and
+//! files from before the database existed are imported when it is first opened.
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::{self, SessionMetadata};
+use anyhow::{Context, Result};
+use mcp_core::role::Role;
+use once_cell::sync::OnceCell;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+const DATABASE_FILE: &str = "sessions.db";
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    path TEXT PRIMARY KEY,
+    id TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    message_count INTEGER NOT NULL DEFAULT 0,
+    history_hash TEXT NOT NULL DEFAULT '',
+    modified INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS sessions_by_modified ON sessions(modified);
+CREATE TABLE IF NOT EXISTS messages (
+    path TEXT NOT NULL REFERENCES sessions(path) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    created INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    PRIMARY KEY (path, seq)
+);
+CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+    path UNINDEXED,
+    seq UNINDEXED,
+    content,
+    tokenize = 'unicode61'
+);
+";
+
+// The search_index row holding a session's description rather than a message
+const DESCRIPTION_SEQ: i64 = -1;
+
+/// A message or session description matching a search
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSearchResult {
+    pub session_id: String,
+    pub path: String,
+    pub description: String,
+    /// Position of the matching message in the session, or none when the description matched
+    pub message_index: Option<usize>,
+    pub role: Option<String>,
+    /// When the matching message was created, as a unix timestamp
+    pub created: Option<i64>,
+    /// The matching text, with matched terms wrapped in `[` and `]`
+    pub snippet: String,
+}
+
+/// A stored session and when it was last written
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredSession {
+    pub id: String,
+    pub path: PathBuf,
+    /// Unix timestamp in milliseconds
+    pub modified: i64,
+}
+
+/// Storage for session history that supports appending and full-text search
+///
+/// Sessions are keyed by the path of their `.jsonl` file.
+pub trait SessionStore: Send + Sync {
+    /// Store the session, appending only the messages that weren't stored before
+    ///
+    /// If the stored history is no longer a prefix of `messages`, e.g. after the context
+    /// was summarized, the stored messages are replaced.
+    fn save(&self, session: &Path, metadata: &SessionMetadata, messages: &[Message]) -> Result<()>;
+
+    /// Add messages to the end of a session that was already saved
+    fn append_messages(&self, session: &Path, messages: &[Message]) -> Result<()>;
+
+    fn read_metadata(&self, session: &Path) -> Result<Option<SessionMetadata>>;
+
+    fn read_messages(&self, session: &Path) -> Result<Vec<Message>>;
+
+    /// When the session was last written, or none if it isn't stored
+    fn modified(&self, session: &Path) -> Result<Option<i64>>;
+
+    /// Every stored session, most recently written first
+    fn list(&self) -> Result<Vec<StoredSession>>;
+
+    fn remove(&self, session: &Path) -> Result<()>;
+
+    /// The best matches for `query` across message text, tool calls and descriptions
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SessionSearchResult>>;
+}
+
+/// A [`SessionStore`] in an embedded SQLite database
+pub struct SqliteSessionStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteSessionStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open session index {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        // The CLI and the server can write to the same sessions
+        conn.busy_timeout(Duration::from_secs(5))?;
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            anyhow::bail!(
+                "Session index was created by a newer version of goose (schema {})",
+                version
+            );
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Import the `.jsonl` sessions in `dir` that aren't stored yet
+    ///
+    /// Returns how many sessions were imported.
+    pub fn migrate_jsonl(&self, dir: &Path) -> Result<usize> {
+        let mut imported = 0;
+        for path in jsonl_files(dir)? {
+            if self.modified(&path)?.is_some() {
+                continue;
+            }
+            let metadata = storage::read_jsonl_metadata(&path)?;
+            let messages = match storage::read_jsonl_messages(&path) {
+                Ok(messages) => messages,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable session {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            self.save(&path, &metadata, &messages)?;
+            self.set_modified(&path, modified_millis(&path))?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Record when the session was written, for imports that keep the file's time
+    fn set_modified(&self, session: &Path, modified: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sessions SET modified = ?2 WHERE path = ?1",
+            params![key(session), modified],
+        )?;
+        Ok(())
+    }
+}
+
+impl SessionStore for SqliteSessionStore {
+    fn save(&self, session: &Path, metadata: &SessionMetadata, messages: &[Message]) -> Result<()> {
+        let path = key(session);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let stored: Option<(usize, String)> = tx
+            .query_row(
+                "SELECT message_count, history_hash FROM sessions WHERE path = ?1",
+                [&path],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        tx.execute(
+            "INSERT INTO sessions (path, id, metadata, modified) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(path) DO UPDATE SET metadata = excluded.metadata,
+                                             modified = excluded.modified",
+            params![
+                path,
+                session_id(session),
+                serde_json::to_string(metadata)?,
+                now_millis()
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM search_index WHERE path = ?1 AND seq = ?2",
+            params![path, DESCRIPTION_SEQ],
+        )?;
+        if !metadata.description.is_empty() {
+            tx.execute(
+                "INSERT INTO search_index (path, seq, content) VALUES (?1, ?2, ?3)",
+                params![path, DESCRIPTION_SEQ, metadata.description],
+            )?;
+        }
+
+        // Only the new tail needs writing when the stored history is a prefix of this one
+        let (stored, stored_hash) = stored.unwrap_or_default();
+        let is_prefix = stored <= messages.len()
+            && history_hash(String::new(), &messages[..stored])? == stored_hash;
+        let (start, hash) = if is_prefix {
+            (stored, stored_hash)
+        } else {
+            tx.execute("DELETE FROM messages WHERE path = ?1", [&path])?;
+            tx.execute(
+                "DELETE FROM search_index WHERE path = ?1 AND seq != ?2",
+                params![path, DESCRIPTION_SEQ],
+            )?;
+            (0, String::new())
+        };
+        insert_messages(&tx, &path, start, hash, &messages[start..])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn append_messages(&self, session: &Path, messages: &[Message]) -> Result<()> {
+        let path = key(session);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let (stored, hash): (usize, String) = tx
+            .query_row(
+                "SELECT message_count, history_hash FROM sessions WHERE path = ?1",
+                [&path],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Session {} has not been saved", path))?;
+        insert_messages(&tx, &path, stored, hash, messages)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn read_metadata(&self, session: &Path) -> Result<Option<SessionMetadata>> {
+        let conn = self.conn.lock().unwrap();
+        let metadata: Option<String> = conn
+            .query_row(
+                "SELECT metadata FROM sessions WHERE path = ?1",
+                [key(session)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(metadata
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?)
+    }
+
+    fn read_messages(&self, session: &Path) -> Result<Vec<Message>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT message FROM messages WHERE path = ?1 ORDER BY seq")?;
+        let rows = statement.query_map([key(session)], |row| row.get::<_, String>(0))?;
+        rows.map(|json| Ok(serde_json::from_str(&json?)?)).collect()
+    }
+
+    fn modified(&self, session: &Path) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row(
+                "SELECT modified FROM sessions WHERE path = ?1",
+                [key(session)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn list(&self) -> Result<Vec<StoredSession>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT id, path, modified FROM sessions ORDER BY modified DESC")?;
+        let rows = statement.query_map([], |row| {
+            Ok(StoredSession {
+                id: row.get(0)?,
+                path: PathBuf::from(row.get::<_, String>(1)?),
+                modified: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn remove(&self, session: &Path) -> Result<()> {
+        let path = key(session);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM search_index WHERE path = ?1", [&path])?;
+        tx.execute("DELETE FROM sessions WHERE path = ?1", [&path])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SessionSearchResult>> {
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT s.path, s.id, s.metadata, i.seq, m.role, m.created,
+                    snippet(search_index, 2, '[', ']', '...', 16)
+             FROM search_index i
+             JOIN sessions s ON s.path = i.path
+             LEFT JOIN messages m ON m.path = i.path AND m.seq = i.seq
+             WHERE search_index MATCH ?1
+             ORDER BY rank
+             LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![query, limit as i64], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (path, session_id, metadata, seq, role, created, snippet) = row?;
+            let metadata: SessionMetadata = serde_json::from_str(&metadata)?;
+            Ok(SessionSearchResult {
+                session_id,
+                path,
+                description: metadata.description,
+                message_index: usize::try_from(seq).ok(),
+                role,
+                created,
+                snippet,
+            })
+        })
+        .collect()
+    }
+}
+
+/// A hash of a whole history, continuing from `hash` of the messages before `messages`
+///
+/// Comparing it tells whether a stored history is still a prefix of a new one without
+/// reading the stored messages back.
+fn history_hash(hash: String, messages: &[Message]) -> Result<String> {
+    messages.iter().try_fold(hash, |hash, message| {
+        let mut hasher = Sha256::new();
+        hasher.update(hash.as_bytes());
+        hasher.update(serde_json::to_string(message)?.as_bytes());
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+}
+
+/// Store `messages` from position `start`, after a history whose hash is `hash`
+fn insert_messages(
+    tx: &rusqlite::Transaction,
+    path: &str,
+    start: usize,
+    hash: String,
+    messages: &[Message],
+) -> Result<()> {
+    let mut insert_message = tx.prepare(
+        "INSERT INTO messages (path, seq, role, created, message) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut insert_index =
+        tx.prepare("INSERT INTO search_index (path, seq, content) VALUES (?1, ?2, ?3)")?;
+    for (i, message) in messages.iter().enumerate() {
+        let seq = (start + i) as i64;
+        insert_message.execute(params![
+            path,
+            seq,
+            role_name(&message.role),
+            message.created,
+            serde_json::to_string(message)?
+        ])?;
+        let text = searchable_text(message);
+        if !text.trim().is_empty() {
+            insert_index.execute(params![path, seq, text])?;
+        }
+    }
+    tx.execute(
+        "UPDATE sessions SET message_count = ?2, history_hash = ?3, modified = ?4 WHERE path = ?1",
+        params![
+            path,
+            (start + messages.len()) as i64,
+            history_hash(hash, messages)?,
+            now_millis()
+        ],
+    )?;
+    Ok(())
+}
+
+/// The text of a message worth searching: what was said, and which tools were called with
+/// what arguments and returned what
+fn searchable_text(message: &Message) -> String {
+    let mut parts = Vec::new();
+    for content in &message.content {
+        match content {
+            MessageContent::Text(text) => parts.push(text.text.clone()),
+            MessageContent::ToolRequest(request) => {
+                if let Ok(call) = &request.tool_call {
+                    parts.push(format!("{} {}", call.name, call.arguments));
+                }
+            }
+            MessageContent::FrontendToolRequest(request) => {
+                if let Ok(call) = &request.tool_call {
+                    parts.push(format!("{} {}", call.name, call.arguments));
+                }
+            }
+            MessageContent::ToolResponse(response) => {
+                if let Ok(contents) = &response.tool_result {
+                    parts.extend(
+                        contents
+                            .iter()
+                            .filter_map(|c| c.as_text())
+                            .map(String::from),
+                    );
+                }
+            }
+            MessageContent::Thinking(thinking) => parts.push(thinking.thinking.clone()),
+            _ => {}
+        }
+    }
+    parts.join("\n")
+}
+
+/// Turn free text into an FTS5 query matching every word, so punctuation in the query
+/// isn't read as query syntax
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+fn key(session: &Path) -> String {
+    session.to_string_lossy().into_owned()
+}
+
+fn session_id(session: &Path) -> String {
+    session
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn modified_millis(path: &Path) -> i64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn jsonl_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            path.extension()
+                .is_some_and(|ext| ext == "jsonl")
+                .then_some(path)
+        })
+        .collect())
+}
+
+static GLOBAL_STORE: OnceCell<Option<Arc<SqliteSessionStore>>> = OnceCell::new();
+
+/// The store for the session directory, or none if it couldn't be opened
+///
+/// Opening it the first time imports any `.jsonl` sessions it doesn't have yet.
+pub fn global() -> Option<Arc<SqliteSessionStore>> {
+    GLOBAL_STORE
+        .get_or_init(|| {
+            let dir = storage::ensure_session_dir().ok()?;
+            let store = match SqliteSessionStore::open(&dir.join(DATABASE_FILE)) {
+                Ok(store) => store,
+                Err(e) => {
+                    tracing::warn!("Session store unavailable, using session files: {}", e);
+                    return None;
+                }
+            };
+            match store.migrate_jsonl(&dir) {
+                Ok(0) => {}
+                Ok(imported) => tracing::info!("Imported {} session files", imported),
+                Err(e) => tracing::warn!("Failed to import session files: {}", e),
+            }
+            Some(Arc::new(store))
+        })
+        .clone()
+}
+
+/// The store holding `session`, or none if the session is a file outside the session
+/// directory, or the store is unavailable
+pub(crate) fn for_session(session: &Path) -> Option<Arc<SqliteSessionStore>> {
+    let in_session_dir = storage::ensure_session_dir()
+        .is_ok_and(|dir| session.parent().is_some_and(|parent| parent == dir));
+    if !in_session_dir {
+        return None;
+    }
+    global()
+}
+
+/// Search every session in the session directory
+///
+/// This blocks on the database, so async callers should run it with `spawn_blocking`.
+pub fn search_sessions(query: &str, limit: usize) -> Result<Vec<SessionSearchResult>> {
+    let store = global().ok_or_else(|| anyhow::anyhow!("Session store is unavailable"))?;
+    store.search(query, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    fn metadata(description: &str) -> SessionMetadata {
+        SessionMetadata {
+            description: description.to_string(),
+            ..SessionMetadata::default()
+        }
+    }
+
+    #[test]
+    fn test_save_appends_and_replaces_rewritten_history() -> Result<()> {
+        let store = SqliteSessionStore::open_in_memory()?;
+        let path = Path::new("/sessions/one.jsonl");
+        let mut messages = vec![Message::user().with_text("first")];
+        store.save(path, &metadata(""), &messages)?;
+
+        messages.push(Message::assistant().with_text("second"));
+        store.save(path, &metadata("two turns"), &messages)?;
+        assert_eq!(store.read_messages(path)?, messages);
+        assert_eq!(store.read_metadata(path)?.unwrap().description, "two turns");
+
+        store.append_messages(path, &[Message::user().with_text("third")])?;
+        assert_eq!(store.read_messages(path)?.len(), 3);
+
+        let summarized = vec![Message::assistant().with_text("summary")];
+        store.save(path, &metadata("two turns"), &summarized)?;
+        assert_eq!(store.read_messages(path)?, summarized);
+        assert!(store.search("first", 10)?.is_empty());
+
+        // Only an earlier message changed, so the history is rewritten rather than appended to
+        let question = Message::user().with_text("question");
+        store.save(path, &metadata(""), &[question, summarized[0].clone()])?;
+        let edited = vec![
+            Message::user().with_text("edited question"),
+            summarized[0].clone(),
+            Message::user().with_text("next"),
+        ];
+        store.save(path, &metadata(""), &edited)?;
+        assert_eq!(store.read_messages(path)?, edited);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_covers_text_tool_calls_and_descriptions() -> Result<()> {
+        let store = SqliteSessionStore::open_in_memory()?;
+        let path = Path::new("/sessions/20250101_120000.jsonl");
+        let messages = vec![
+            Message::user().with_text("Why does the parser panic?"),
+            Message::assistant().with_tool_request(
+                "call-1",
+                Ok(ToolCall::new(
+                    "developer__shell",
+                    json!({"command": "cargo test parser"}),
+                )),
+            ),
+        ];
+        store.save(path, &metadata("Debugging tokenizer"), &messages)?;
+
+        let results = store.search("parser panic", 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].session_id, "20250101_120000");
+        assert_eq!(results[0].message_index, Some(0));
+        assert_eq!(results[0].role.as_deref(), Some("user"));
+        assert!(results[0].snippet.contains("[parser]"));
+
+        let results = store.search("developer__shell", 10)?;
+        assert_eq!(results[0].message_index, Some(1));
+
+        let results = store.search("tokenizer", 10)?;
+        assert_eq!(results[0].message_index, None);
+        assert_eq!(results[0].description, "Debugging tokenizer");
+
+        // Query syntax characters are searched as text
+        assert!(store.search("\"unbalanced (", 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_orders_by_last_write() -> Result<()> {
+        let store = SqliteSessionStore::open_in_memory()?;
+        let first = Path::new("/sessions/first.jsonl");
+        let second = Path::new("/sessions/second.jsonl");
+        store.save(first, &metadata(""), &[Message::user().with_text("one")])?;
+        store.set_modified(first, 1)?;
+        store.save(second, &metadata(""), &[Message::user().with_text("two")])?;
+        store.set_modified(second, 2)?;
+
+        let ids = |store: &SqliteSessionStore| -> Result<Vec<String>> {
+            Ok(store.list()?.into_iter().map(|s| s.id).collect())
+        };
+        assert_eq!(ids(&store)?, vec!["second", "first"]);
+
+        store.append_messages(first, &[Message::assistant().with_text("reply")])?;
+        assert_eq!(ids(&store)?, vec!["first", "second"]);
+
+        store.remove(first)?;
+        assert_eq!(ids(&store)?, vec!["second"]);
+        assert!(store.modified(first)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_jsonl_imports_new_files_once() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = SqliteSessionStore::open_in_memory()?;
+        let first = dir.path().join("first.jsonl");
+        let second = dir.path().join("second.jsonl");
+        storage::save_messages_with_metadata(
+            &first,
+            &metadata("first session"),
+            &[Message::user().with_text("hello kestrel")],
+        )?;
+        storage::save_messages_with_metadata(
+            &second,
+            &metadata("second session"),
+            &[Message::user().with_text("hello osprey")],
+        )?;
+
+        assert_eq!(store.migrate_jsonl(dir.path())?, 2);
+        assert_eq!(store.migrate_jsonl(dir.path())?, 0);
+        assert_eq!(store.search("kestrel", 10)?[0].session_id, "first");
+        assert_eq!(store.modified(&first)?, Some(modified_millis(&first)));
+
+        // Once imported, the stored copy is the one that counts
+        store.append_messages(&second, &[Message::assistant().with_text("hello again")])?;
+        assert_eq!(store.migrate_jsonl(dir.path())?, 0);
+        assert_eq!(store.read_messages(&second)?.len(), 2);
+        Ok(())
+    }
+}
@@ -136,12 +136,14 @@ async fn run_truncate_test(
     while let Some(response_result) = reply_stream.next().await {
         match response_result {
             Ok(AgentEvent::Message(response)) => responses.push(response),
+            Ok(AgentEvent::MessageDelta(_)) => {}
             Ok(AgentEvent::McpNotification(n)) => {
                 println!("MCP Notification: {n:?}");
             }
             Ok(AgentEvent::ModelChange { .. }) => {
                 // Model change events are informational, just continue
             }
+            Ok(AgentEvent::HistoryReplaced(_)) => {}
 
             Err(e) => {
                 println!("Error: {:?}", e);
@@ -732,8 +734,10 @@ mod max_turns_tests {
                     }
                     responses.push(response);
                 }
+                Ok(AgentEvent::MessageDelta(_)) => {}
                 Ok(AgentEvent::McpNotification(_)) => {}
                 Ok(AgentEvent::ModelChange { .. }) => {}
+                Ok(AgentEvent::HistoryReplaced(_)) => {}
                 Err(e) => {
                     return Err(e);
                 }
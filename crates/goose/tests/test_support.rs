@@ -410,5 +410,14 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         accumulated_total_tokens: Some(100),
         accumulated_input_tokens: Some(50),
         accumulated_output_tokens: Some(50),
+        cache_read_input_tokens: None,
+        cache_write_input_tokens: None,
+        accumulated_cache_read_input_tokens: None,
+        accumulated_cache_write_input_tokens: None,
+        cost: None,
+        accumulated_cost: None,
+        fork: None,
+        branches: Vec::new(),
+        routing: Vec::new(),
     }
 }
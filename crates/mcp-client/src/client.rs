@@ -1,7 +1,8 @@
 use mcp_core::protocol::{
-    CallToolResult, GetPromptResult, Implementation, InitializeResult, JsonRpcError,
-    JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, ListPromptsResult,
-    ListResourcesResult, ListToolsResult, ReadResourceResult, ServerCapabilities, METHOD_NOT_FOUND,
+    CallToolResult, CreateMessageParams, CreateMessageResult, ErrorData, GetPromptResult,
+    Implementation, InitializeResult, JsonRpcError, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, ListPromptsResult, ListResourcesResult, ListRootsResult,
+    ListToolsResult, ReadResourceResult, ServerCapabilities, INVALID_PARAMS, METHOD_NOT_FOUND,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -10,7 +11,7 @@ use std::sync::{
     Arc,
 };
 use thiserror::Error;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tower::{timeout::TimeoutLayer, Layer, Service, ServiceExt};
 
 use crate::{McpService, TransportHandle};
@@ -68,7 +69,22 @@ pub struct ClientInfo {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
-    // Add fields as needed. For now, empty capabilities are fine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<RootsCapability>,
+}
+
+/// Declares that the client can handle `sampling/createMessage` requests
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct SamplingCapability {}
+
+/// Declares that the client can answer `roots/list` requests
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct RootsCapability {
+    /// Whether the client sends `notifications/roots/list_changed`
+    #[serde(rename = "listChanged", skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -80,6 +96,83 @@ pub struct InitializeParams {
     pub client_info: ClientInfo,
 }
 
+/// Handles requests the server sends to the client
+///
+/// Every method defaults to rejecting the request, so handlers only implement what they
+/// advertise in `ClientCapabilities`.
+#[async_trait::async_trait]
+pub trait ClientRequestHandler: Send + Sync {
+    async fn create_message(
+        &self,
+        _params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        Err(method_not_found("sampling/createMessage"))
+    }
+
+    async fn list_roots(&self) -> Result<ListRootsResult, ErrorData> {
+        Err(method_not_found("roots/list"))
+    }
+}
+
+fn method_not_found(method: &str) -> ErrorData {
+    ErrorData {
+        code: METHOD_NOT_FOUND,
+        message: format!("Client does not support '{}'", method),
+        data: None,
+    }
+}
+
+fn parse_params<P>(params: Option<Value>) -> Result<P, ErrorData>
+where
+    P: for<'de> Deserialize<'de>,
+{
+    serde_json::from_value(params.unwrap_or(Value::Null)).map_err(|e| ErrorData {
+        code: INVALID_PARAMS,
+        message: format!("Invalid params: {}", e),
+        data: None,
+    })
+}
+
+fn to_result<R: Serialize>(result: Result<R, ErrorData>) -> Result<Value, ErrorData> {
+    result.and_then(|r| {
+        serde_json::to_value(r).map_err(|e| ErrorData {
+            code: mcp_core::protocol::INTERNAL_ERROR,
+            message: e.to_string(),
+            data: None,
+        })
+    })
+}
+
+/// Route a server request to the matching handler method and build the reply
+async fn handle_server_request(
+    handler: Option<Arc<dyn ClientRequestHandler>>,
+    request: JsonRpcRequest,
+) -> JsonRpcMessage {
+    let result = match (handler, request.method.as_str()) {
+        (Some(handler), "sampling/createMessage") => match parse_params(request.params) {
+            Ok(params) => to_result(handler.create_message(params).await),
+            Err(e) => Err(e),
+        },
+        (Some(handler), "roots/list") => to_result(handler.list_roots().await),
+        (_, "ping") => Ok(json!({})),
+        (_, method) => Err(method_not_found(method)),
+    };
+
+    match result {
+        Ok(result) => JsonRpcMessage::Response(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result: Some(result),
+            error: None,
+        }),
+        Err(error) => JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            error,
+        }),
+    }
+}
+
 #[async_trait::async_trait]
 pub trait McpClientTrait: Send + Sync {
     async fn initialize(
@@ -104,6 +197,44 @@ pub trait McpClientTrait: Send + Sync {
     async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error>;
 
     async fn subscribe(&self) -> mpsc::Receiver<JsonRpcMessage>;
+
+    /// Set the handler for requests the server sends to the client, such as sampling
+    async fn set_request_handler(&self, handler: Arc<dyn ClientRequestHandler>);
+
+    /// Tell the server that the roots returned by `roots/list` have changed
+    async fn notify_roots_list_changed(&self) -> Result<(), Error>;
+}
+
+/// Sends `notifications/cancelled` for a request when dropped while still armed
+struct CancelOnDrop<T>
+where
+    T: TransportHandle + Send + Sync + 'static,
+{
+    id: u64,
+    service: McpService<T>,
+    armed: bool,
+}
+
+impl<T> Drop for CancelOnDrop<T>
+where
+    T: TransportHandle + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let service = self.service.clone();
+        let id = self.id;
+        runtime.spawn(async move {
+            let reason = Some("The client stopped waiting for the request".to_string());
+            if let Err(e) = service.cancel(id, reason).await {
+                tracing::warn!("Failed to cancel request {}: {}", id, e);
+            }
+        });
+    }
 }
 
 /// The MCP client is the interface for MCP operations.
@@ -116,6 +247,7 @@ where
     server_capabilities: Option<ServerCapabilities>,
     server_info: Option<Implementation>,
     notification_subscribers: Arc<Mutex<Vec<mpsc::Sender<JsonRpcMessage>>>>,
+    request_handler: Arc<RwLock<Option<Arc<dyn ClientRequestHandler>>>>,
 }
 
 impl<T> McpClient<T>
@@ -128,6 +260,9 @@ where
         let notification_subscribers =
             Arc::new(Mutex::new(Vec::<mpsc::Sender<JsonRpcMessage>>::new()));
         let subscribers_ptr = notification_subscribers.clone();
+        let request_handler: Arc<RwLock<Option<Arc<dyn ClientRequestHandler>>>> =
+            Arc::new(RwLock::new(None));
+        let handler_ptr = request_handler.clone();
 
         tokio::spawn(async move {
             loop {
@@ -139,6 +274,21 @@ where
                             | JsonRpcMessage::Error(JsonRpcError { id: Some(id), .. }) => {
                                 service_ptr.respond(&id.to_string(), Ok(message)).await;
                             }
+                            JsonRpcMessage::Request(request) if request.id.is_some() => {
+                                // Handle server requests off the receive loop, since they can
+                                // take as long as an LLM call
+                                let handler = handler_ptr.read().await.clone();
+                                let transport = transport.clone();
+                                tokio::spawn(async move {
+                                    let response = handle_server_request(handler, request).await;
+                                    if let Err(e) = transport.send(response).await {
+                                        tracing::error!(
+                                            "Failed to respond to server request: {}",
+                                            e
+                                        );
+                                    }
+                                });
+                            }
                             _ => {
                                 let mut subs = subscribers_ptr.lock().await;
                                 subs.retain(|sub| sub.try_send(message.clone()).is_ok());
@@ -162,6 +312,7 @@ where
             server_capabilities: None,
             server_info: None,
             notification_subscribers,
+            request_handler,
         })
     }
 
@@ -186,19 +337,29 @@ where
             params: Some(params),
         });
 
-        let response_msg = service
-            .call(request)
-            .await
-            .map_err(|e| Error::McpServerError {
-                server: self
-                    .server_info
-                    .as_ref()
-                    .map(|s| s.name.clone())
-                    .unwrap_or("".to_string()),
-                method: method.to_string(),
-                // we don't need include params because it can be really large
-                source: Box::<Error>::new(e.into()),
-            })?;
+        // If we stop waiting before the server answers, because the caller dropped this
+        // future or the request timed out, tell the server so it can stop working on it.
+        // The spec doesn't allow cancelling initialization.
+        let mut cancel_guard = CancelOnDrop {
+            id,
+            service: service.get_ref().clone(),
+            armed: method != "initialize",
+        };
+        let response_msg = service.call(request).await;
+        if response_msg.is_ok() {
+            cancel_guard.armed = false;
+        }
+
+        let response_msg = response_msg.map_err(|e| Error::McpServerError {
+            server: self
+                .server_info
+                .as_ref()
+                .map(|s| s.name.clone())
+                .unwrap_or("".to_string()),
+            method: method.to_string(),
+            // we don't need include params because it can be really large
+            source: Box::<Error>::new(e.into()),
+        })?;
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse {
@@ -435,4 +596,145 @@ where
         self.notification_subscribers.lock().await.push(tx);
         rx
     }
+
+    async fn set_request_handler(&self, handler: Arc<dyn ClientRequestHandler>) {
+        *self.request_handler.write().await = Some(handler);
+    }
+
+    async fn notify_roots_list_changed(&self) -> Result<(), Error> {
+        self.send_notification("notifications/roots/list_changed", json!({}))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{protocol::Root, Content, Role};
+
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl ClientRequestHandler for EchoHandler {
+        async fn create_message(
+            &self,
+            params: CreateMessageParams,
+        ) -> Result<CreateMessageResult, ErrorData> {
+            Ok(CreateMessageResult {
+                role: Role::Assistant,
+                content: params.messages[0].content.clone(),
+                model: "echo".to_string(),
+                stop_reason: Some("endTurn".to_string()),
+            })
+        }
+
+        async fn list_roots(&self) -> Result<ListRootsResult, ErrorData> {
+            Ok(ListRootsResult {
+                roots: vec![Root {
+                    uri: "file:///workspace".to_string(),
+                    name: None,
+                }],
+            })
+        }
+    }
+
+    fn sampling_request() -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(7),
+            method: "sampling/createMessage".to_string(),
+            params: Some(json!({
+                "messages": [{"role": "user", "content": {"type": "text", "text": "hi"}}],
+                "maxTokens": 10
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_request_routed_to_handler() {
+        let response = handle_server_request(Some(Arc::new(EchoHandler)), sampling_request()).await;
+        match response {
+            JsonRpcMessage::Response(JsonRpcResponse {
+                id,
+                result: Some(result),
+                ..
+            }) => {
+                assert_eq!(id, Some(7));
+                let result: CreateMessageResult = serde_json::from_value(result).unwrap();
+                assert_eq!(result.content, Content::text("hi"));
+                assert_eq!(result.model, "echo");
+            }
+            other => panic!("Expected response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_request_without_handler_is_rejected() {
+        let response = handle_server_request(None, sampling_request()).await;
+        match response {
+            JsonRpcMessage::Error(JsonRpcError { id, error, .. }) => {
+                assert_eq!(id, Some(7));
+                assert_eq!(error.code, METHOD_NOT_FOUND);
+            }
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots_list_routed_to_handler() {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(3),
+            method: "roots/list".to_string(),
+            params: None,
+        };
+        let response = handle_server_request(Some(Arc::new(EchoHandler)), request).await;
+        let JsonRpcMessage::Response(JsonRpcResponse {
+            result: Some(result),
+            ..
+        }) = response
+        else {
+            panic!("Expected response, got {:?}", response);
+        };
+        let result: ListRootsResult = serde_json::from_value(result).unwrap();
+        assert_eq!(result.roots[0].uri, "file:///workspace");
+    }
+
+    /// Records what the client sends and never answers
+    #[derive(Clone, Default)]
+    struct SilentTransport {
+        sent: Arc<Mutex<Vec<JsonRpcMessage>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TransportHandle for SilentTransport {
+        async fn send(&self, message: JsonRpcMessage) -> Result<(), crate::transport::Error> {
+            self.sent.lock().await.push(message);
+            Ok(())
+        }
+
+        async fn receive(&self) -> Result<JsonRpcMessage, crate::transport::Error> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_request_is_cancelled() {
+        let transport = SilentTransport::default();
+        let client = McpClient::connect(transport.clone(), std::time::Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        assert!(client.call_tool("slow", json!({})).await.is_err());
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let sent = transport.sent.lock().await;
+        let cancelled = sent.iter().find_map(|message| match message {
+            JsonRpcMessage::Notification(n) if n.method == "notifications/cancelled" => {
+                n.params.clone()
+            }
+            _ => None,
+        });
+        assert_eq!(cancelled.unwrap()["requestId"], json!(1));
+    }
 }
@@ -6,7 +6,10 @@ pub mod transport;
 #[cfg(test)]
 mod oauth_tests;
 
-pub use client::{ClientCapabilities, ClientInfo, Error, McpClient, McpClientTrait};
+pub use client::{
+    ClientCapabilities, ClientInfo, ClientRequestHandler, Error, McpClient, McpClientTrait,
+    RootsCapability, SamplingCapability,
+};
 pub use oauth::{authenticate_service, ServiceConfig};
 pub use service::McpService;
 pub use transport::{
@@ -1,5 +1,7 @@
 use futures::future::BoxFuture;
-use mcp_core::protocol::{JsonRpcMessage, JsonRpcRequest};
+use mcp_core::protocol::{
+    CancelledNotificationParams, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::{Context, Poll};
@@ -30,6 +32,22 @@ impl<T: TransportHandle> McpService<T> {
     pub async fn hangup(&self, error: Error) {
         self.pending_requests.broadcast_close(error).await
     }
+
+    /// Stop waiting for a request and tell the server it was cancelled
+    pub async fn cancel(&self, id: u64, reason: Option<String>) -> Result<(), Error> {
+        self.pending_requests.remove(&id.to_string()).await;
+        let params = CancelledNotificationParams {
+            request_id: id,
+            reason,
+        };
+        self.inner
+            .send(JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/cancelled".to_string(),
+                params: Some(serde_json::to_value(params)?),
+            }))
+            .await
+    }
 }
 
 impl<T> Service<JsonRpcMessage> for McpService<T>
@@ -109,6 +127,10 @@ impl PendingRequests {
         self.requests.write().await.insert(id, sender);
     }
 
+    pub async fn remove(&self, id: &str) {
+        self.requests.write().await.remove(id);
+    }
+
     pub async fn respond(&self, id: &str, response: Result<JsonRpcMessage, Error>) {
         if let Some(tx) = self.requests.write().await.remove(id) {
             let _ = tx.send(response);
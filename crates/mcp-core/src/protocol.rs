@@ -4,6 +4,7 @@ use crate::{
     prompt::{Prompt, PromptMessage},
     resource::Resource,
     resource::ResourceContents,
+    role::Role,
     tool::Tool,
 };
 use serde::{Deserialize, Serialize};
@@ -237,6 +238,102 @@ pub struct GetPromptResult {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmptyResult {}
 
+/// A message in a `sampling/createMessage` request or result
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+/// A hint used by the client to pick a model for sampling
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ModelHint {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The server's preferences for model selection, each priority ranging from 0 to 1
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Vec<ModelHint>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_priority: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_priority: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intelligence_priority: Option<f32>,
+}
+
+/// Parameters of a `sampling/createMessage` request sent from a server to the client
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// One of "none", "thisServer" or "allServers"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+    /// The name of the model that generated the message
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Parameters of `notifications/cancelled`, sent by either side to abandon a request it made
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledNotificationParams {
+    pub request_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Parameters of `notifications/progress`, reported against the token the requester sent
+/// in its request's `_meta.progressToken`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressNotificationParams {
+    pub progress_token: Value,
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A directory or file the client is working in, exposed to servers through `roots/list`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Root {
+    /// Must be a `file://` URI
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ListRootsResult {
+    pub roots: Vec<Root>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +361,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_message_params_serialization() {
+        let params: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [{"role": "user", "content": {"type": "text", "text": "Summarize"}}],
+            "systemPrompt": "Be brief",
+            "maxTokens": 100
+        }))
+        .unwrap();
+        assert_eq!(params.messages[0].role, Role::User);
+        assert_eq!(params.messages[0].content.as_text(), Some("Summarize"));
+        assert_eq!(params.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(params.max_tokens, 100);
+
+        let result = CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text("Done"),
+            model: "gpt-4o".to_string(),
+            stop_reason: Some("endTurn".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({
+                "role": "assistant",
+                "content": {"type": "text", "text": "Done"},
+                "model": "gpt-4o",
+                "stopReason": "endTurn"
+            })
+        );
+    }
+
+    #[test]
+    fn test_list_roots_result_serialization() {
+        let result = ListRootsResult {
+            roots: vec![Root {
+                uri: "file:///home/user/project".to_string(),
+                name: None,
+            }],
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json,
+            json!({"roots": [{"uri": "file:///home/user/project"}]})
+        );
+    }
+
     #[test]
     fn test_request_conversion() {
         let raw = JsonRpcRaw {
@@ -0,0 +1,8 @@
+//! Environment variables that ask the developer extension to sandbox its shell commands
+
+/// Set to `true` to run shell commands in the sandbox
+pub const SANDBOX_ENV: &str = "GOOSE_SHELL_SANDBOX";
+/// Set to `true` to let sandboxed commands use the network
+pub const SANDBOX_NETWORK_ENV: &str = "GOOSE_SHELL_SANDBOX_NETWORK";
+/// Extra writable directories, separated like `PATH`
+pub const SANDBOX_WRITABLE_ENV: &str = "GOOSE_SHELL_SANDBOX_WRITABLE";
@@ -1,10 +1,14 @@
 use std::{
+    collections::VecDeque,
     pin::Pin,
     task::{Context, Poll},
 };
 
 use futures::{Future, Stream};
-use mcp_core::protocol::{JsonRpcError, JsonRpcMessage, JsonRpcResponse};
+use mcp_core::protocol::{
+    CancelledNotificationParams, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse,
+};
 use pin_project::pin_project;
 use router::McpRequest;
 use tokio::{
@@ -16,9 +20,42 @@ use tower_service::Service;
 mod errors;
 pub use errors::{BoxError, RouterError, ServerError, TransportError};
 
+mod peer;
+use peer::PendingRequests;
+pub use peer::{Peer, PeerError};
+
 pub mod router;
 pub use router::Router;
 
+/// Client notifications after which the router is given the client's current roots
+const ROOTS_NOTIFICATIONS: [&str; 2] = [
+    "notifications/initialized",
+    "notifications/roots/list_changed",
+];
+
+/// A notification that means the client's roots are available or changed, as a request without
+/// an id, so the router can fetch the roots with its peer
+fn roots_request(notification: &JsonRpcNotification) -> Option<JsonRpcRequest> {
+    ROOTS_NOTIFICATIONS
+        .contains(&notification.method.as_str())
+        .then(|| JsonRpcRequest {
+            jsonrpc: notification.jsonrpc.clone(),
+            id: None,
+            method: notification.method.clone(),
+            params: notification.params.clone(),
+        })
+}
+
+/// The id of the request a `notifications/cancelled` message abandons
+fn cancelled_request_id(notification: &JsonRpcNotification) -> Option<u64> {
+    if notification.method != "notifications/cancelled" {
+        return None;
+    }
+    let params: CancelledNotificationParams =
+        serde_json::from_value(notification.params.clone()?).ok()?;
+    Some(params.request_id)
+}
+
 /// A transport layer that handles JSON-RPC messages over byte
 #[pin_project]
 pub struct ByteTransport<R, W> {
@@ -144,13 +181,47 @@ where
         use futures::StreamExt;
         let mut service = self.service;
 
+        // Requests the router sends to the client, and client requests that arrived while
+        // another request was in flight
+        let pending = PendingRequests::default();
+        let mut deferred: VecDeque<JsonRpcRequest> = VecDeque::new();
+        // Whether the client answers `roots/list`, as declared when it initialized
+        let mut client_has_roots = false;
+
         tracing::info!("Server started");
-        while let Some(msg_result) = transport.next().await {
+        loop {
+            let msg_result = match deferred.pop_front() {
+                Some(request) => Ok(JsonRpcMessage::Request(request)),
+                None => match transport.next().await {
+                    Some(msg_result) => msg_result,
+                    None => break,
+                },
+            };
             let _span = tracing::span!(tracing::Level::INFO, "message_processing").entered();
             match msg_result {
                 Ok(msg) => {
+                    // Notifications that mean the client's roots are available or changed are
+                    // passed to the router like a request
+                    let msg = match msg {
+                        JsonRpcMessage::Notification(notification) if client_has_roots => {
+                            match roots_request(&notification) {
+                                Some(request) => JsonRpcMessage::Request(request),
+                                None => JsonRpcMessage::Notification(notification),
+                            }
+                        }
+                        msg => msg,
+                    };
                     match msg {
                         JsonRpcMessage::Request(request) => {
+                            if request.method == "initialize" {
+                                client_has_roots = request
+                                    .params
+                                    .as_ref()
+                                    .and_then(|p| p.get("capabilities"))
+                                    .and_then(|c| c.get("roots"))
+                                    .is_some_and(|r| !r.is_null());
+                            }
+
                             // Serialize request for logging
                             let id = request.id;
                             let request_json = serde_json::to_string(&request)
@@ -164,22 +235,80 @@ where
                             );
 
                             // Process the request using our service
+                            let progress_token = request
+                                .params
+                                .as_ref()
+                                .and_then(|p| p.get("_meta"))
+                                .and_then(|m| m.get("progressToken"))
+                                .cloned();
                             let (notify_tx, mut notify_rx) = mpsc::channel(256);
                             let mcp_request = McpRequest {
                                 request,
-                                notifier: notify_tx,
+                                notifier: notify_tx.clone(),
+                                peer: Peer::new(notify_tx, pending.clone())
+                                    .with_progress_token(progress_token),
                             };
 
-                            let transport_fut = tokio::spawn(async move {
-                                while let Some(notification) = notify_rx.recv().await {
-                                    if transport.write_message(notification).await.is_err() {
-                                        break;
+                            // Keep reading while the request runs, so responses to requests the
+                            // router sends to the client (e.g. sampling) can be delivered and the
+                            // client can cancel it
+                            let call = service.call(mcp_request);
+                            tokio::pin!(call);
+                            let result = loop {
+                                tokio::select! {
+                                    result = &mut call => break Some(result),
+                                    Some(outgoing) = notify_rx.recv() => {
+                                        transport
+                                            .write_message(outgoing)
+                                            .await
+                                            .map_err(|e| ServerError::Transport(TransportError::Io(e)))?;
+                                    }
+                                    Some(incoming) = transport.next() => {
+                                        match incoming {
+                                            Ok(message @ (JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_))) => {
+                                                if !pending.respond(message).await {
+                                                    tracing::warn!("Received a response for an unknown request");
+                                                }
+                                            }
+                                            Ok(JsonRpcMessage::Request(request)) => {
+                                                deferred.push_back(request);
+                                            }
+                                            Ok(JsonRpcMessage::Notification(notification)) => {
+                                                match cancelled_request_id(&notification) {
+                                                    Some(cancelled) if id == Some(cancelled) => break None,
+                                                    Some(cancelled) => deferred.retain(|r| r.id != Some(cancelled)),
+                                                    // Roots that change during the request are
+                                                    // fetched once it's done
+                                                    None if client_has_roots => {
+                                                        deferred.extend(roots_request(&notification));
+                                                    }
+                                                    None => {}
+                                                }
+                                            }
+                                            Ok(_) => {}
+                                            Err(e) => {
+                                                tracing::error!(error = %e, "Failed to read message while handling request");
+                                            }
+                                        }
                                     }
                                 }
+                            };
+                            // Flush notifications sent right before the request finished
+                            while let Ok(outgoing) = notify_rx.try_recv() {
                                 transport
-                            });
+                                    .write_message(outgoing)
+                                    .await
+                                    .map_err(|e| ServerError::Transport(TransportError::Io(e)))?;
+                            }
+
+                            // Dropping the call stops the router's work, and a cancelled request
+                            // gets no response
+                            let Some(result) = result else {
+                                tracing::info!(request_id = ?id, "Request cancelled by client");
+                                continue;
+                            };
 
-                            let response = match service.call(mcp_request).await {
+                            let response = match result {
                                 Ok(resp) => resp,
                                 Err(e) => {
                                     let error_msg = e.into().to_string();
@@ -197,15 +326,10 @@ where
                                 }
                             };
 
-                            transport = match transport_fut.await {
-                                Ok(transport) => transport,
-                                Err(e) => {
-                                    tracing::error!(error = %e, "Failed to spawn transport task");
-                                    return Err(ServerError::Transport(TransportError::Io(
-                                        e.into(),
-                                    )));
-                                }
-                            };
+                            // Requests without an id are notifications and get no response
+                            if id.is_none() {
+                                continue;
+                            }
 
                             // Serialize response for logging
                             let response_json = serde_json::to_string(&response)
@@ -224,13 +348,19 @@ where
                                 return Err(ServerError::Transport(TransportError::Io(e)));
                             }
                         }
-                        JsonRpcMessage::Response(_)
-                        | JsonRpcMessage::Notification(_)
-                        | JsonRpcMessage::Nil
-                        | JsonRpcMessage::Error(_) => {
-                            // Ignore responses, notifications and nil messages for now
+                        JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_) => {
+                            // A late response to a request the router sent to the client
+                            pending.respond(msg).await;
+                        }
+                        JsonRpcMessage::Notification(notification) => {
+                            // Drop cancelled requests that haven't started yet, and ignore other
+                            // notifications for now
+                            if let Some(cancelled) = cancelled_request_id(&notification) {
+                                deferred.retain(|r| r.id != Some(cancelled));
+                            }
                             continue;
                         }
+                        JsonRpcMessage::Nil => continue,
                     }
                 }
                 Err(e) => {
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use mcp_core::protocol::{
+    CreateMessageParams, CreateMessageResult, ErrorData, JsonRpcError, JsonRpcMessage,
+    JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, ListRootsResult,
+    ProgressNotificationParams,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+#[derive(Error, Debug)]
+pub enum PeerError {
+    #[error("Connection to the client is closed")]
+    Closed,
+
+    #[error("Client returned an error: {}", .0.message)]
+    Client(ErrorData),
+
+    #[error("Invalid response from client: {0}")]
+    InvalidResponse(String),
+}
+
+type PendingResponses = Arc<Mutex<HashMap<u64, oneshot::Sender<JsonRpcMessage>>>>;
+
+/// Requests sent by the server that are waiting for the client to answer
+#[derive(Clone, Default)]
+pub(crate) struct PendingRequests {
+    next_id: Arc<AtomicU64>,
+    responses: PendingResponses,
+}
+
+impl PendingRequests {
+    /// Deliver a response from the client, returning false if no request is waiting on it
+    pub(crate) async fn respond(&self, message: JsonRpcMessage) -> bool {
+        let id = match &message {
+            JsonRpcMessage::Response(JsonRpcResponse { id: Some(id), .. })
+            | JsonRpcMessage::Error(JsonRpcError { id: Some(id), .. }) => *id,
+            _ => return false,
+        };
+        match self.responses.lock().await.remove(&id) {
+            Some(tx) => tx.send(message).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// A handle a router uses to send requests to the connected client, such as
+/// asking it to sample from its LLM
+#[derive(Clone)]
+pub struct Peer {
+    sender: mpsc::Sender<JsonRpcMessage>,
+    pending: PendingRequests,
+    progress_token: Option<Value>,
+}
+
+impl Peer {
+    pub(crate) fn new(sender: mpsc::Sender<JsonRpcMessage>, pending: PendingRequests) -> Self {
+        Self {
+            sender,
+            pending,
+            progress_token: None,
+        }
+    }
+
+    /// Report progress against the token the client sent with the current request
+    pub(crate) fn with_progress_token(mut self, progress_token: Option<Value>) -> Self {
+        self.progress_token = progress_token;
+        self
+    }
+
+    /// Tell the client how far the current request has got
+    ///
+    /// Does nothing if the client didn't ask for progress on this request.
+    pub async fn notify_progress(
+        &self,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) -> Result<(), PeerError> {
+        let Some(progress_token) = self.progress_token.clone() else {
+            return Ok(());
+        };
+        let params = ProgressNotificationParams {
+            progress_token,
+            progress,
+            total,
+            message,
+        };
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/progress".to_string(),
+            params: Some(
+                serde_json::to_value(params)
+                    .map_err(|e| PeerError::InvalidResponse(e.to_string()))?,
+            ),
+        });
+        self.sender
+            .send(notification)
+            .await
+            .map_err(|_| PeerError::Closed)
+    }
+
+    /// Ask the client to run a completion with its own model
+    pub async fn create_message(
+        &self,
+        params: CreateMessageParams,
+    ) -> Result<CreateMessageResult, PeerError> {
+        let params =
+            serde_json::to_value(params).map_err(|e| PeerError::InvalidResponse(e.to_string()))?;
+        self.request("sampling/createMessage", params).await
+    }
+
+    /// Ask the client for the directories it is working in
+    pub async fn list_roots(&self) -> Result<ListRootsResult, PeerError> {
+        self.request("roots/list", Value::Object(Default::default()))
+            .await
+    }
+
+    /// Send a request to the client and wait for its result
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, PeerError> {
+        // Server-initiated ids are independent of the client's, since each side only
+        // matches responses against the requests it sent
+        let id = self.pending.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let (tx, rx) = oneshot::channel();
+        self.pending.responses.lock().await.insert(id, tx);
+
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: method.to_string(),
+            params: Some(params),
+        });
+        if self.sender.send(request).await.is_err() {
+            self.pending.responses.lock().await.remove(&id);
+            return Err(PeerError::Closed);
+        }
+
+        match rx.await.map_err(|_| PeerError::Closed)? {
+            JsonRpcMessage::Response(JsonRpcResponse {
+                error: Some(error), ..
+            })
+            | JsonRpcMessage::Error(JsonRpcError { error, .. }) => Err(PeerError::Client(error)),
+            JsonRpcMessage::Response(JsonRpcResponse {
+                result: Some(result),
+                ..
+            }) => serde_json::from_value(result)
+                .map_err(|e| PeerError::InvalidResponse(e.to_string())),
+            _ => Err(PeerError::InvalidResponse("missing result".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::{protocol::SamplingMessage, Content, Role};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_create_message_round_trip() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let pending = PendingRequests::default();
+        let peer = Peer::new(tx, pending.clone());
+
+        let client = tokio::spawn(async move {
+            let Some(JsonRpcMessage::Request(request)) = rx.recv().await else {
+                panic!("Expected a request");
+            };
+            assert_eq!(request.method, "sampling/createMessage");
+            assert!(
+                pending
+                    .respond(JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: Some(json!({
+                            "role": "assistant",
+                            "content": {"type": "text", "text": "summary"},
+                            "model": "test-model"
+                        })),
+                        error: None,
+                    }))
+                    .await
+            );
+        });
+
+        let result = peer
+            .create_message(CreateMessageParams {
+                messages: vec![SamplingMessage {
+                    role: Role::User,
+                    content: Content::text("summarize this"),
+                }],
+                model_preferences: None,
+                system_prompt: None,
+                include_context: None,
+                temperature: None,
+                max_tokens: 50,
+                stop_sequences: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        client.await.unwrap();
+
+        assert_eq!(result.content, Content::text("summary"));
+        assert_eq!(result.model, "test-model");
+    }
+
+    #[tokio::test]
+    async fn test_request_surfaces_client_error() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let pending = PendingRequests::default();
+        let peer = Peer::new(tx, pending.clone());
+
+        tokio::spawn(async move {
+            if let Some(JsonRpcMessage::Request(request)) = rx.recv().await {
+                pending
+                    .respond(JsonRpcMessage::Error(JsonRpcError {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        error: ErrorData {
+                            code: -1,
+                            message: "declined".to_string(),
+                            data: None,
+                        },
+                    }))
+                    .await;
+            }
+        });
+
+        let result: Result<Value, _> = peer.request("sampling/createMessage", json!({})).await;
+        assert!(matches!(result, Err(PeerError::Client(e)) if e.message == "declined"));
+    }
+
+    #[tokio::test]
+    async fn test_progress_uses_request_token() {
+        let (tx, mut rx) = mpsc::channel(2);
+        let peer = Peer::new(tx.clone(), PendingRequests::default());
+        peer.notify_progress(1.0, None, None).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        let peer = peer.with_progress_token(Some(json!("prog-4")));
+        peer.notify_progress(3.0, Some(10.0), Some("indexing".to_string()))
+            .await
+            .unwrap();
+        let Some(JsonRpcMessage::Notification(notification)) = rx.recv().await else {
+            panic!("Expected a notification");
+        };
+        assert_eq!(notification.method, "notifications/progress");
+        assert_eq!(
+            notification.params.unwrap(),
+            json!({"progressToken": "prog-4", "progress": 3.0, "total": 10.0, "message": "indexing"})
+        );
+    }
+}
@@ -13,7 +13,7 @@ use mcp_core::{
     protocol::{
         CallToolResult, GetPromptResult, Implementation, InitializeResult, JsonRpcMessage,
         JsonRpcRequest, JsonRpcResponse, ListPromptsResult, ListResourcesResult, ListToolsResult,
-        PromptsCapability, ReadResourceResult, ResourcesCapability, ServerCapabilities,
+        PromptsCapability, ReadResourceResult, ResourcesCapability, Root, ServerCapabilities,
         ToolsCapability,
     },
     ResourceContents,
@@ -22,7 +22,7 @@ use serde_json::Value;
 use tokio::sync::mpsc;
 use tower_service::Service;
 
-use crate::{BoxError, RouterError};
+use crate::{BoxError, Peer, RouterError};
 
 /// Builder for configuring and constructing capabilities
 pub struct CapabilitiesBuilder {
@@ -94,6 +94,17 @@ pub trait Router: Send + Sync + 'static {
         arguments: Value,
         notifier: mpsc::Sender<JsonRpcMessage>,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>>;
+    /// Call a tool with a handle for sending requests back to the client, e.g. for sampling
+    /// Routers whose tools talk to the client override this; by default the peer is unused.
+    fn call_tool_with_peer(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        notifier: mpsc::Sender<JsonRpcMessage>,
+        _peer: Peer,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        self.call_tool(tool_name, arguments, notifier)
+    }
     fn list_resources(&self) -> Vec<mcp_core::resource::Resource>;
     fn read_resource(
         &self,
@@ -101,6 +112,8 @@ pub trait Router: Send + Sync + 'static {
     ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>>;
     fn list_prompts(&self) -> Vec<Prompt>;
     fn get_prompt(&self, prompt_name: &str) -> PromptFuture;
+    /// Called with the client's roots after initialization and whenever they change
+    fn roots_changed(&self, _roots: Vec<Root>) {}
 
     // Helper method to create base response
     fn create_response(&self, id: Option<u64>) -> JsonRpcResponse {
@@ -162,6 +175,7 @@ pub trait Router: Send + Sync + 'static {
         &self,
         req: JsonRpcRequest,
         notifier: mpsc::Sender<JsonRpcMessage>,
+        peer: Peer,
     ) -> impl Future<Output = Result<JsonRpcResponse, RouterError>> + Send {
         async move {
             let params = req
@@ -175,7 +189,10 @@ pub trait Router: Send + Sync + 'static {
 
             let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
 
-            let result = match self.call_tool(name, arguments, notifier).await {
+            let result = match self
+                .call_tool_with_peer(name, arguments, notifier, peer)
+                .await
+            {
                 Ok(result) => CallToolResult {
                     content: result,
                     is_error: None,
@@ -270,6 +287,21 @@ pub trait Router: Send + Sync + 'static {
         }
     }
 
+    fn handle_roots_changed(
+        &self,
+        req: JsonRpcRequest,
+        peer: Peer,
+    ) -> impl Future<Output = Result<JsonRpcResponse, RouterError>> + Send {
+        async move {
+            // A client without roots answers with an error, which leaves the router as it was
+            match peer.list_roots().await {
+                Ok(result) => self.roots_changed(result.roots),
+                Err(e) => tracing::debug!(error = %e, "Failed to list client roots"),
+            }
+            Ok(self.create_response(req.id))
+        }
+    }
+
     fn handle_prompts_get(
         &self,
         req: JsonRpcRequest,
@@ -400,6 +432,7 @@ pub struct RouterService<T>(pub T);
 pub struct McpRequest {
     pub request: JsonRpcRequest,
     pub notifier: mpsc::Sender<JsonRpcMessage>,
+    pub peer: Peer,
 }
 
 impl<T> Service<McpRequest> for RouterService<T>
@@ -421,11 +454,17 @@ where
             let result = match req.request.method.as_str() {
                 "initialize" => this.handle_initialize(req.request).await,
                 "tools/list" => this.handle_tools_list(req.request).await,
-                "tools/call" => this.handle_tools_call(req.request, req.notifier).await,
+                "tools/call" => {
+                    this.handle_tools_call(req.request, req.notifier, req.peer)
+                        .await
+                }
                 "resources/list" => this.handle_resources_list(req.request).await,
                 "resources/read" => this.handle_resources_read(req.request).await,
                 "prompts/list" => this.handle_prompts_list(req.request).await,
                 "prompts/get" => this.handle_prompts_get(req.request).await,
+                "notifications/initialized" | "notifications/roots/list_changed" => {
+                    this.handle_roots_changed(req.request, req.peer).await
+                }
                 _ => {
                     let mut response = this.create_response(req.request.id);
                     response.error = Some(RouterError::MethodNotFound(req.request.method).into());